@@ -44,6 +44,8 @@ pub enum QueryError {
     #[error("Function call returned an error: {error_message}")]
     ContractExecutionError {
         error_message: String,
+        /// Gas used by the call until it failed, if it was requested.
+        gas_usage: Option<near_primitives::views::CallGasUsageView>,
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
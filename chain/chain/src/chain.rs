@@ -9,6 +9,7 @@ use crate::migrations::check_if_block_is_first_with_chunk_of_version;
 use crate::missing_chunks::MissingChunksPool;
 use crate::orphan::{Orphan, OrphanBlockPool};
 use crate::rayon_spawner::RayonAsyncComputationSpawner;
+use crate::shard_scheduler::{ShardJob, ShardPriority, ShardScheduler};
 use crate::sharding::shuffle_receipt_proofs;
 use crate::state_request_tracker::StateRequestTracker;
 use crate::state_snapshot_actor::SnapshotCallbacks;
@@ -41,6 +42,7 @@ use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
 use near_async::time::{Clock, Duration, Instant};
 use near_chain_configs::{
     MutableConfigValue, MutableValidatorSigner, ReshardingConfig, ReshardingHandle,
+    ShardSchedulerConfig,
 };
 #[cfg(feature = "new_epoch_sync")]
 use near_chain_primitives::error::epoch_sync::EpochSyncInfoError;
@@ -255,6 +257,8 @@ pub struct Chain {
     apply_chunks_receiver: Receiver<BlockApplyChunksResult>,
     /// Used to spawn the apply chunks jobs.
     apply_chunks_spawner: Arc<dyn AsyncComputationSpawner>,
+    /// Runs the jobs applying the chunks of a block.
+    shard_scheduler: Arc<ShardScheduler>,
     /// Time when head was updated most recently.
     last_time_head_updated: Instant,
     /// Prevents re-application of known-to-be-invalid blocks, so that in case of a
@@ -298,8 +302,11 @@ impl Drop for Chain {
 
 /// UpdateShardJob is a closure that is responsible for updating a shard for a single block.
 /// Execution context (latest blocks/chunks details) are already captured within.
-type UpdateShardJob =
-    (ShardId, Box<dyn FnOnce(&Span) -> Result<ShardUpdateResult, Error> + Send + Sync + 'static>);
+type UpdateShardJob = (
+    ShardId,
+    ShardPriority,
+    Box<dyn FnOnce(&Span) -> Result<ShardUpdateResult, Error> + Send + Sync + 'static>,
+);
 
 /// PreprocessBlockResult is a tuple where the first element is a vector of jobs
 /// to update shards, the second element is BlockPreprocessInfo
@@ -382,6 +389,10 @@ impl Chain {
             apply_chunks_sender: sc,
             apply_chunks_receiver: rc,
             apply_chunks_spawner: Arc::new(RayonAsyncComputationSpawner),
+            shard_scheduler: Arc::new(
+                ShardScheduler::new(&ShardSchedulerConfig::default())
+                    .map_err(|err| Error::Other(err.to_string()))?,
+            ),
             last_time_head_updated: clock.now(),
             invalid_blocks: LruCache::new(NonZeroUsize::new(INVALID_CHUNKS_POOL_SIZE).unwrap()),
             pending_state_patch: Default::default(),
@@ -443,6 +454,7 @@ impl Chain {
             chain_genesis.height,
             chain_config.save_trie_changes,
         );
+        chain_store.set_save_account_activity(chain_config.save_account_activity);
 
         // Check if we have a head in the store, otherwise pick genesis block.
         let mut store_update = chain_store.store_update();
@@ -596,6 +608,10 @@ impl Chain {
             apply_chunks_sender: sc,
             apply_chunks_receiver: rc,
             apply_chunks_spawner,
+            shard_scheduler: Arc::new(
+                ShardScheduler::new(&chain_config.shard_scheduler)
+                    .map_err(|err| Error::Other(err.to_string()))?,
+            ),
             last_time_head_updated: clock.now(),
             pending_state_patch: Default::default(),
             requested_state_parts: StateRequestTracker::new(),
@@ -1822,9 +1838,12 @@ impl Chain {
         apply_chunks_done_sender: Option<near_async::messaging::Sender<ApplyChunksDoneMessage>>,
     ) {
         let sc = self.apply_chunks_sender.clone();
+        let shard_scheduler = self.shard_scheduler.clone();
         self.apply_chunks_spawner.spawn("apply_chunks", move || {
-            // do_apply_chunks runs `work` in parallel, but still waits for all of them to finish
-            let res = do_apply_chunks(block_hash, block_height, work);
+            // do_apply_chunks_with_scheduler runs `work` in parallel, but still waits for all of
+            // them to finish
+            let res =
+                do_apply_chunks_with_scheduler(&shard_scheduler, block_hash, block_height, work);
             // If we encounter error here, that means the receiver is deallocated and the client
             // thread is already shut down. The node is already crashed, so we can unwrap here
             sc.send((block_hash, res)).unwrap();
@@ -3254,7 +3273,7 @@ impl Chain {
     }
 
     /// Returns execution status based on the list of currently existing outcomes
-    fn get_execution_status(
+    pub fn get_execution_status(
         &self,
         outcomes: &[ExecutionOutcomeWithIdView],
         transaction_hash: &CryptoHash,
@@ -3649,6 +3668,22 @@ impl Chain {
         })
     }
 
+    /// Returns whether the node validates the shard or only tracks it.
+    fn get_shard_priority(
+        &self,
+        me: &Option<AccountId>,
+        prev_hash: &CryptoHash,
+        shard_id: ShardId,
+    ) -> ShardPriority {
+        let Some(me) = me else { return ShardPriority::Tracked };
+        // With `is_me` unset, only the validation duties of the account are checked.
+        if self.shard_tracker.care_about_shard(Some(me), prev_hash, shard_id, false) {
+            ShardPriority::Validated
+        } else {
+            ShardPriority::Tracked
+        }
+    }
+
     /// This method returns the closure that is responsible for updating a shard.
     fn get_update_shard_job(
         &self,
@@ -3794,10 +3829,12 @@ impl Chain {
             return Ok(None);
         };
 
+        let priority = self.get_shard_priority(me, prev_hash, shard_id);
         let runtime = self.runtime_adapter.clone();
         let epoch_manager = self.epoch_manager.clone();
         Ok(Some((
             shard_id,
+            priority,
             Box::new(move |parent_span| -> Result<ShardUpdateResult, Error> {
                 Ok(process_shard_update(
                     parent_span,
@@ -4617,6 +4654,34 @@ impl Chain {
     pub fn patch_state_in_progress(&self) -> bool {
         !self.pending_state_patch.is_empty()
     }
+
+    /// Reverts the database to the snapshot, see `Store::snapshot`, and makes
+    /// the chain pick up its contents.
+    ///
+    /// Orphans and pending state patches are dropped and the epoch manager,
+    /// flat storages and memtries are reloaded.  Fails if blocks are being
+    /// processed.
+    #[cfg(feature = "sandbox")]
+    pub fn revert_to_store_snapshot(
+        &mut self,
+        snapshot: &near_store::StoreSnapshot,
+    ) -> Result<(), Error> {
+        if self.blocks_in_processing.len() > 0 {
+            return Err(Error::Other(
+                "cannot revert the chain while blocks are being processed".to_owned(),
+            ));
+        }
+        self.chain_store.store().restore_snapshot(snapshot)?;
+        self.chain_store.clear_caches();
+        self.orphans = OrphanBlockPool::new();
+        self.blocks_with_missing_chunks = MissingChunksPool::new();
+        self.invalid_blocks.clear();
+        self.pending_state_patch = Default::default();
+        self.epoch_manager.reload_from_store()?;
+        self.runtime_adapter.get_flat_storage_manager().reload_flat_storages()?;
+        self.runtime_adapter.get_tries().reload_mem_tries()?;
+        Ok(())
+    }
 }
 
 /// Epoch sync specific functions.
@@ -4722,7 +4787,7 @@ pub fn do_apply_chunks(
         tracing::debug_span!(target: "chain", "do_apply_chunks", block_height, %block_hash)
             .entered();
     work.into_par_iter()
-        .map(|(shard_id, task)| {
+        .map(|(shard_id, _, task)| {
             // As chunks can be processed in parallel, make sure they are all tracked as children of
             // a single span.
             (shard_id, task(&parent_span))
@@ -4730,6 +4795,27 @@ pub fn do_apply_chunks(
         .collect()
 }
 
+/// Same as `do_apply_chunks`, but the jobs are run by the scheduler, which
+/// decides on the thread pool and the order of the jobs of each shard.
+fn do_apply_chunks_with_scheduler(
+    shard_scheduler: &ShardScheduler,
+    block_hash: CryptoHash,
+    block_height: BlockHeight,
+    work: Vec<UpdateShardJob>,
+) -> Vec<(ShardId, Result<ShardUpdateResult, Error>)> {
+    let parent_span =
+        tracing::debug_span!(target: "chain", "do_apply_chunks", block_height, %block_hash)
+            .entered();
+    let jobs = work
+        .into_iter()
+        .map(|(shard_id, priority, task)| -> ShardJob<Result<ShardUpdateResult, Error>> {
+            let parent_span = Span::clone(&parent_span);
+            (shard_id, priority, Box::new(move || task(&parent_span)))
+        })
+        .collect();
+    shard_scheduler.run(jobs)
+}
+
 pub fn collect_receipts<'a, T>(receipt_proofs: T) -> Vec<Receipt>
 where
     T: IntoIterator<Item = &'a ReceiptProof>,
@@ -567,6 +567,8 @@ impl<'a> ChainUpdate<'a> {
         if last_final_block_header.height() > final_head.height {
             let tip = Tip::from_header(&last_final_block_header);
             self.chain_store_update.save_final_head(&tip)?;
+            self.chain_store_update
+                .save_account_activity(last_final_block_header.hash(), final_head.height)?;
             Ok(Some(tip))
         } else {
             Ok(None)
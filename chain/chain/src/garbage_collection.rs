@@ -2,9 +2,12 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::{fmt, io};
 
-use near_chain_configs::GCConfig;
+use near_chain_configs::{GCConfig, GCRetainedData};
 use near_chain_primitives::Error;
 use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::account_activity::{
+    account_activity_key_from_height_key, get_account_activity_height_prefix,
+};
 use near_primitives::block::Block;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::get_block_shard_uid;
@@ -14,7 +17,7 @@ use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, inde
 use near_store::flat::store_helper;
 use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId};
 
-use crate::types::RuntimeAdapter;
+use crate::types::{RuntimeAdapter, Tip};
 use crate::{metrics, Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate};
 
 #[derive(Clone)]
@@ -24,6 +27,12 @@ pub enum GCMode {
     StateSync { clear_block_info: bool },
 }
 
+/// Key in `DBCol::BlockMisc` of the tail of data kept for longer than blocks,
+/// see `ChainStore::clear_extended_retention_data`.
+pub(crate) fn extended_tail_key(data: GCRetainedData) -> Vec<u8> {
+    format!("GC_EXTENDED_TAIL:{}", data.as_str()).into_bytes()
+}
+
 impl fmt::Debug for GCMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -132,6 +141,15 @@ impl ChainStore {
     //    and the Trie is updated with having only Genesis data.
     // 4. State Sync Clearing happens in `reset_data_pre_state_sync()`.
     //
+    // Retention Policies:
+    // 1. GC Stop Height is the highest height GC may clear up to, `GCConfig` may make it keep more.
+    // 2. With `gc_hot_db_size_budget`, Canonical Chain Clearing doesn't run while the database
+    //    is smaller than the budget.
+    // 3. With `gc_keep_duration`, Canonical Chain Clearing stops at the first block which
+    //    isn't older than that.
+    // 4. With `gc_extended_retention`, Canonical Chain Clearing leaves the retained data
+    //    in place and `clear_extended_retention_data()` clears it later.
+    //
     pub fn clear_data(
         &mut self,
         gc_config: &GCConfig,
@@ -163,6 +181,7 @@ impl ChainStore {
             fork_tail = gc_stop_height;
         }
         let mut gc_blocks_remaining = gc_config.gc_blocks_limit;
+        self.clear_extended_retention_data(gc_config, &head, &mut gc_blocks_remaining)?;
 
         // Forks Cleaning
         let gc_fork_clean_step = gc_config.gc_fork_clean_step;
@@ -183,6 +202,15 @@ impl ChainStore {
         }
 
         // Canonical Chain Clearing
+        if self.is_within_size_budget(gc_config) {
+            return Ok(());
+        }
+        let keep_since = match gc_config.gc_keep_duration {
+            Some(keep_duration) => {
+                Some(self.get_block_header(&head.last_block_hash)?.timestamp() - keep_duration)
+            }
+            None => None,
+        };
         for height in tail + 1..gc_stop_height {
             if gc_blocks_remaining == 0 {
                 return Ok(());
@@ -199,6 +227,12 @@ impl ChainStore {
             let mut chain_store_update = self.store_update();
             if let Some(block_hash) = blocks_current_height.first() {
                 let prev_hash = *chain_store_update.get_block_header(block_hash)?.prev_hash();
+                if let Some(keep_since) = keep_since {
+                    if chain_store_update.get_block_header(&prev_hash)?.timestamp() >= keep_since {
+                        // Block of `prev_hash` is to be kept, stopping
+                        break;
+                    }
+                }
                 let prev_block_refcount = chain_store_update.get_block_refcount(&prev_hash)?;
                 if prev_block_refcount > 1 {
                     // Block of `prev_hash` starts a Fork, stopping
@@ -228,6 +262,91 @@ impl ChainStore {
         Ok(())
     }
 
+    /// Returns whether the blocks are kept because the database is smaller
+    /// than `GCConfig::gc_hot_db_size_budget`.
+    fn is_within_size_budget(&self, gc_config: &GCConfig) -> bool {
+        let Some(budget) = gc_config.gc_hot_db_size_budget else {
+            return false;
+        };
+        match self.store().get_live_data_size() {
+            Some(size) => size < budget.as_u64(),
+            None => {
+                tracing::debug!(target: "garbage_collection", "Database size is unknown, ignoring gc_hot_db_size_budget");
+                false
+            }
+        }
+    }
+
+    /// Garbage collects data kept for longer than the blocks it belongs to,
+    /// see `GCConfig::gc_extended_retention`.
+    ///
+    /// While data is kept for longer than blocks, Canonical Chain Clearing
+    /// leaves it in place and its own tail, below which it has been cleared,
+    /// is stored in `DBCol::BlockMisc`.  Once the retention is removed from
+    /// the config, the data is cleared up to the block tail and the tail is
+    /// deleted.
+    ///
+    /// Blocks whose data is cleared count towards `gc_blocks_remaining`, which
+    /// is shared with the rest of the garbage collection.
+    fn clear_extended_retention_data(
+        &mut self,
+        gc_config: &GCConfig,
+        head: &Tip,
+        gc_blocks_remaining: &mut NumBlocks,
+    ) -> Result<(), Error> {
+        let tail = self.tail()?;
+        for data in GCRetainedData::ALL {
+            let key = extended_tail_key(data);
+            let retention = gc_config.extended_retention(data);
+            let mut height = match self.store().get_ser::<BlockHeight>(DBCol::BlockMisc, &key)? {
+                Some(height) => height,
+                // Data of the blocks below the tail has been cleared with them.
+                None if retention.is_some() => tail,
+                None => continue,
+            };
+            let keep_since = match retention {
+                Some(retention) => Some(
+                    self.get_block_header(&head.last_block_hash)?.timestamp()
+                        - retention.keep_duration,
+                ),
+                None => None,
+            };
+            let mut chain_store_update = self.store_update();
+            // The block at the tail has been garbage collected as well.
+            while height <= tail && *gc_blocks_remaining > 0 {
+                // Only data of blocks on the canonical chain is kept.
+                if let Ok(block_hash) = chain_store_update.get_block_hash_by_height(height) {
+                    if let Some(keep_since) = keep_since {
+                        let header = chain_store_update.get_block_header(&block_hash)?;
+                        if header.timestamp() >= keep_since {
+                            break;
+                        }
+                    }
+                    match data {
+                        GCRetainedData::TransactionResults => {
+                            chain_store_update.gc_retained_outcomes(&block_hash)?
+                        }
+                        GCRetainedData::StateChanges => {
+                            chain_store_update.gc_state_changes(&block_hash)?
+                        }
+                    }
+                    *gc_blocks_remaining -= 1;
+                }
+                height += 1;
+            }
+            let mut store_update = chain_store_update.store().store_update();
+            if retention.is_none() && height == tail + 1 {
+                store_update.delete(DBCol::BlockMisc, &key);
+            } else {
+                store_update.set_ser(DBCol::BlockMisc, &key, &height)?;
+            }
+            chain_store_update.merge(store_update);
+            chain_store_update.commit()?;
+            metrics::GC_EXTENDED_TAIL_HEIGHT.with_label_values(&[data.as_str()]).set(height as i64);
+        }
+        Ok(())
+    }
+
     /// Garbage collect data which archival node doesn’t need to keep.
     ///
     /// Normally, archival nodes keep all the data from the genesis block and
@@ -674,17 +793,18 @@ impl<'a> ChainStoreUpdate<'a> {
         self.gc_col(DBCol::NextBlockHashes, block_hash.as_bytes());
         self.gc_col(DBCol::ChallengedBlocks, block_hash.as_bytes());
         self.gc_col(DBCol::BlocksToCatchup, block_hash.as_bytes());
-        let storage_key = KeyForStateChanges::for_block(&block_hash);
-        let stored_state_changes: Vec<Box<[u8]>> = self
-            .store()
-            .iter_prefix(DBCol::StateChanges, storage_key.as_ref())
-            .map(|item| item.map(|(key, _)| key))
-            .collect::<io::Result<Vec<_>>>()?;
-        for key in stored_state_changes {
-            self.gc_col(DBCol::StateChanges, &key);
+        if !self.is_retained(GCRetainedData::StateChanges, &gc_mode)? {
+            self.gc_state_changes(&block_hash)?;
         }
         self.gc_col(DBCol::BlockRefCount, block_hash.as_bytes());
-        self.gc_outcomes(&block)?;
+        if !self.is_retained(GCRetainedData::TransactionResults, &gc_mode)? {
+            self.gc_outcomes(&block)?;
+        }
+        // Account activity is only saved for final blocks, which are never
+        // cleared as forks.
+        if !matches!(gc_mode, GCMode::Fork(_)) {
+            self.gc_account_activity(height)?;
+        }
         match gc_mode {
             GCMode::StateSync { clear_block_info: false } => {}
             _ => self.gc_col(DBCol::BlockInfo, block_hash.as_bytes()),
@@ -775,15 +895,7 @@ impl<'a> ChainStoreUpdate<'a> {
         self.gc_col(DBCol::NextBlockHashes, block_hash.as_bytes());
         self.gc_col(DBCol::ChallengedBlocks, block_hash.as_bytes());
         self.gc_col(DBCol::BlocksToCatchup, block_hash.as_bytes());
-        let storage_key = KeyForStateChanges::for_block(&block_hash);
-        let stored_state_changes: Vec<Box<[u8]>> = self
-            .store()
-            .iter_prefix(DBCol::StateChanges, storage_key.as_ref())
-            .map(|item| item.map(|(key, _)| key))
-            .collect::<io::Result<Vec<_>>>()?;
-        for key in stored_state_changes {
-            self.gc_col(DBCol::StateChanges, &key);
-        }
+        self.gc_state_changes(&block_hash)?;
         self.gc_col(DBCol::BlockRefCount, block_hash.as_bytes());
         self.gc_outcomes(&block)?;
         self.gc_col(DBCol::BlockInfo, block_hash.as_bytes());
@@ -903,7 +1015,72 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
+    /// Deletes the account activity records of the block at `height`.  The
+    /// records are looked up by height rather than recomputed from the block,
+    /// so that they are cleared even if the data they were computed from is
+    /// missing or the index has been disabled since.
+    fn gc_account_activity(&mut self, height: BlockHeight) -> Result<(), Error> {
+        let height_keys = self
+            .store()
+            .iter_prefix(
+                DBCol::AccountActivityByHeight,
+                &get_account_activity_height_prefix(height),
+            )
+            .map(|item| item.map(|(key, _)| key))
+            .collect::<io::Result<Vec<_>>>()?;
+        for height_key in height_keys {
+            self.gc_col(
+                DBCol::AccountActivity,
+                &account_activity_key_from_height_key(&height_key)?,
+            );
+            self.gc_col(DBCol::AccountActivityByHeight, &height_key);
+        }
+        Ok(())
+    }
+
+    /// Returns whether Canonical Chain Clearing leaves `data` in place, see
+    /// `ChainStore::clear_extended_retention_data`.
+    fn is_retained(&self, data: GCRetainedData, gc_mode: &GCMode) -> Result<bool, Error> {
+        if !matches!(gc_mode, GCMode::Canonical(_)) {
+            return Ok(false);
+        }
+        Ok(self.store().exists(DBCol::BlockMisc, &extended_tail_key(data))?)
+    }
+
+    fn gc_state_changes(&mut self, block_hash: &CryptoHash) -> Result<(), Error> {
+        let storage_key = KeyForStateChanges::for_block(block_hash);
+        let stored_state_changes: Vec<Box<[u8]>> = self
+            .store()
+            .iter_prefix(DBCol::StateChanges, storage_key.as_ref())
+            .map(|item| item.map(|(key, _)| key))
+            .collect::<io::Result<Vec<_>>>()?;
+        for key in stored_state_changes {
+            self.gc_col(DBCol::StateChanges, &key);
+        }
+        Ok(())
+    }
+
+    /// Deletes the outcomes of a block which has already been garbage
+    /// collected, finding them in `DBCol::OutcomeIds` alone.
+    fn gc_retained_outcomes(&mut self, block_hash: &CryptoHash) -> Result<(), Error> {
+        let outcome_ids: Vec<(Box<[u8]>, Vec<CryptoHash>)> = self
+            .store()
+            .iter_prefix_ser(DBCol::OutcomeIds, block_hash.as_ref())
+            .collect::<io::Result<Vec<_>>>()?;
+        for (key, outcome_ids) in outcome_ids {
+            for outcome_id in outcome_ids {
+                self.gc_col(
+                    DBCol::TransactionResultForBlock,
+                    &get_outcome_id_block_hash(&outcome_id, block_hash),
+                );
+            }
+            self.gc_col(DBCol::OutcomeIds, &key);
+        }
+        Ok(())
+    }
+
     fn gc_col(&mut self, col: DBCol, key: &[u8]) {
+        metrics::GC_DELETED_KEYS.with_label_values(&[col.into()]).inc();
         let mut store_update = self.store().store_update();
         match col {
             DBCol::OutgoingReceipts => {
@@ -1017,6 +1194,9 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::LatestWitnessesByIndex => {
                 store_update.delete(col, key);
             }
+            DBCol::AccountActivity | DBCol::AccountActivityByHeight => {
+                store_update.delete(col, key);
+            }
             DBCol::DbVersion
             | DBCol::BlockMisc
             | DBCol::_GCCount
@@ -1047,6 +1227,7 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::FlatStorageStatus
             | DBCol::Misc
             | DBCol::_ReceiptIdToShardId
+            | DBCol::TransactionPool
             => unreachable!(),
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => unreachable!(),
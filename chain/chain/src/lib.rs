@@ -35,6 +35,7 @@ pub mod types;
 pub mod validate;
 
 pub mod rayon_spawner;
+pub mod shard_scheduler;
 pub mod sharding;
 #[cfg(test)]
 mod tests;
@@ -1,7 +1,8 @@
 use near_o11y::metrics::{
     exponential_buckets, processing_time_buckets, try_create_histogram, try_create_histogram_vec,
-    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_gauge,
-    try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec,
+    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec,
 };
 use once_cell::sync::Lazy;
 
@@ -73,6 +74,22 @@ pub static FORK_TAIL_HEIGHT: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_fork_tail_height", "Height of fork tail").unwrap());
 pub static GC_STOP_HEIGHT: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_gc_stop_height", "Target height of gc").unwrap());
+pub static GC_EXTENDED_TAIL_HEIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_gc_extended_tail_height",
+        "Height of the tail of data kept for longer than blocks",
+        &["data"],
+    )
+    .unwrap()
+});
+pub static GC_DELETED_KEYS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_gc_deleted_keys",
+        "Number of keys deleted by garbage collection",
+        &["column"],
+    )
+    .unwrap()
+});
 pub static CHUNK_RECEIVED_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_chunk_receive_delay_seconds",
@@ -238,3 +255,13 @@ pub(crate) static RESHARDING_STATUS: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub(crate) static APPLY_CHUNK_QUEUE_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_apply_chunk_queue_delay_seconds",
+        "Delay between scheduling the application of a chunk and its start.",
+        &["shard_id", "priority"],
+        Some(exponential_buckets(0.0001, 1.6, 20).unwrap()),
+    )
+    .unwrap()
+});
@@ -8,3 +8,27 @@ impl AsyncComputationSpawner for RayonAsyncComputationSpawner {
         rayon::spawn(move || tracing::dispatcher::with_default(&dispatcher, f))
     }
 }
+
+/// Spawns computations in a rayon thread pool of its own rather than in the
+/// global one.  Computations are started in the order they are spawned.
+pub struct RayonThreadPoolSpawner {
+    pool: rayon::ThreadPool,
+}
+
+impl RayonThreadPoolSpawner {
+    pub fn new(name: &str, num_threads: usize) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let name = name.to_string();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(move |index| format!("{name}_{index}"))
+            .build()?;
+        Ok(Self { pool })
+    }
+}
+
+impl AsyncComputationSpawner for RayonThreadPoolSpawner {
+    fn spawn_boxed(&self, _name: &str, f: Box<dyn FnOnce() + Send>) {
+        let dispatcher = tracing::dispatcher::get_default(|it| it.clone());
+        self.pool.spawn_fifo(move || tracing::dispatcher::with_default(&dispatcher, f))
+    }
+}
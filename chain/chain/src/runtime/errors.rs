@@ -2,8 +2,11 @@ use crate::near_chain_primitives::error::QueryError;
 
 #[easy_ext::ext(FromStateViewerErrors)]
 impl QueryError {
+    /// Converts the error of a view call.  The gas usage of the call is kept
+    /// if the execution of the contract failed.
     pub fn from_call_function_error(
         error: node_runtime::state_viewer::errors::CallFunctionError,
+        gas_usage: Option<near_primitives::views::CallGasUsageView>,
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     ) -> Self {
@@ -18,7 +21,7 @@ impl QueryError {
                 error_message,
             } => Self::InternalError { error_message, block_height, block_hash },
             node_runtime::state_viewer::errors::CallFunctionError::VMError { error_message } => {
-                Self::ContractExecutionError { error_message, block_height, block_hash }
+                Self::ContractExecutionError { error_message, gas_usage, block_height, block_hash }
             }
         }
     }
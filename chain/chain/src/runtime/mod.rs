@@ -16,6 +16,7 @@ use near_parameters::{ActionCosts, ExtCosts, RuntimeConfig, RuntimeConfigStore};
 use near_pool::types::TransactionGroupIterator;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::apply::ApplyChunkReason;
+use near_primitives::block::BlockHeader;
 use near_primitives::checked_feature;
 use near_primitives::congestion_info::{
     CongestionControl, ExtendedCongestionInfo, RejectTransactionReason, ShardAcceptsTransactions,
@@ -24,6 +25,7 @@ use near_primitives::errors::{InvalidTxError, RuntimeError, StorageError};
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::receipt::{DelayedReceiptIndices, Receipt};
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
+use near_primitives::sandbox::impersonation::SandboxImpersonatedAccounts;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::shard_layout::{
     account_id_to_shard_id, account_id_to_shard_uid, ShardLayout, ShardUId,
@@ -37,8 +39,8 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewStateResult,
+    AccessKeyInfoView, CallGasUsageView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
+    QueryResponseKind, StateOverride, ViewStateResult,
 };
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
@@ -50,6 +52,7 @@ use near_store::{
 use near_vm_runner::ContractCode;
 use near_vm_runner::{precompile_contract, ContractRuntimeCache, FilesystemContractRuntimeCache};
 use node_runtime::adapter::ViewRuntimeAdapter;
+use node_runtime::simulation::SimulationResult;
 use node_runtime::state_viewer::{TrieViewer, ViewApplyState};
 use node_runtime::{
     validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
@@ -57,7 +60,7 @@ use node_runtime::{
 };
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tracing::{debug, error, info, instrument};
 
 pub mod errors;
@@ -80,6 +83,7 @@ pub struct NightshadeRuntime {
     epoch_manager: Arc<EpochManagerHandle>,
     migration_data: Arc<MigrationData>,
     gc_num_epochs_to_keep: u64,
+    impersonated_accounts: RwLock<SandboxImpersonatedAccounts>,
 }
 
 impl NightshadeRuntime {
@@ -132,6 +136,7 @@ impl NightshadeRuntime {
             epoch_manager,
             migration_data,
             gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_GC_NUM_EPOCHS_TO_KEEP),
+            impersonated_accounts: Default::default(),
         })
     }
 
@@ -388,6 +393,7 @@ impl NightshadeRuntime {
                 is_first_block_with_chunk_of_version,
             },
             congestion_info,
+            impersonated_accounts: self.impersonated_accounts(),
         };
 
         let instant = Instant::now();
@@ -649,6 +655,14 @@ impl RuntimeAdapter for NightshadeRuntime {
         self.tries.get_flat_storage_manager()
     }
 
+    fn impersonated_accounts(&self) -> SandboxImpersonatedAccounts {
+        self.impersonated_accounts.read().unwrap().clone()
+    }
+
+    fn set_impersonated_accounts(&self, accounts: SandboxImpersonatedAccounts) {
+        *self.impersonated_accounts.write().unwrap() = accounts;
+    }
+
     fn validate_tx(
         &self,
         gas_price: Balance,
@@ -698,6 +712,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                 gas_price,
                 transaction,
                 verify_signature,
+                &self.impersonated_accounts.read().unwrap(),
                 // here we do not know which block the transaction will be included
                 // and therefore skip the check on the nonce upper bound.
                 None,
@@ -713,6 +728,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                 gas_price,
                 transaction,
                 verify_signature,
+                &self.impersonated_accounts.read().unwrap(),
                 current_protocol_version,
             ) {
                 Ok(_) => Ok(None),
@@ -781,6 +797,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         let mut total_size = 0u64;
 
         let runtime_config = self.runtime_config_store.get_config(protocol_version);
+        let impersonated_accounts = self.impersonated_accounts();
 
         let transactions_gas_limit =
             chunk_tx_gas_limit(protocol_version, runtime_config, &prev_block, shard_id, gas_limit);
@@ -900,6 +917,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     prev_block.next_gas_price,
                     &tx,
                     false,
+                    &impersonated_accounts,
                     Some(next_block_height),
                     protocol_version,
                 ) {
@@ -1066,8 +1084,20 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
-            QueryRequest::CallFunction { account_id, method_name, args } => {
+            QueryRequest::CallFunction {
+                account_id,
+                method_name,
+                args,
+                include_gas_usage,
+                include_function_profile,
+                state_overrides,
+            } => {
                 let mut logs = vec![];
+                let mut gas_usage =
+                    (include_gas_usage || include_function_profile).then(|| CallGasUsageView {
+                        function_profile: include_function_profile.then(String::new),
+                        ..Default::default()
+                    });
                 let (epoch_height, current_protocol_version) = {
                     let epoch_manager = self.epoch_manager.read();
                     let epoch_info = epoch_manager.get_epoch_info(epoch_id).map_err(|err| {
@@ -1093,13 +1123,16 @@ impl RuntimeAdapter for NightshadeRuntime {
                         account_id,
                         method_name,
                         args.as_ref(),
+                        state_overrides,
                         &mut logs,
+                        gas_usage.as_mut(),
                         self.epoch_manager.as_ref(),
                         current_protocol_version,
                     )
                     .map_err(|err| {
                         crate::near_chain_primitives::error::QueryError::from_call_function_error(
                             err,
+                            gas_usage.take(),
                             block_height,
                             *block_hash,
                         )
@@ -1108,6 +1141,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     kind: QueryResponseKind::CallResult(CallResult {
                         result: call_function_result,
                         logs,
+                        gas_usage,
                     }),
                     block_height,
                     block_hash: *block_hash,
@@ -1118,6 +1152,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     .view_state(
                         &shard_uid,
                         *state_root,
+                        block_hash,
                         account_id,
                         prefix.as_ref(),
                         *include_proof,
@@ -1177,6 +1212,60 @@ impl RuntimeAdapter for NightshadeRuntime {
         }
     }
 
+    fn simulate_transaction(
+        &self,
+        block_header: &BlockHeader,
+        state_roots: &HashMap<ShardUId, StateRoot>,
+        transaction: &SignedTransaction,
+        verify_signature: bool,
+    ) -> Result<Result<SimulationResult, InvalidTxError>, Error> {
+        let epoch_id = block_header.epoch_id();
+        let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
+        let epoch_height = self.epoch_manager.get_epoch_info(epoch_id)?.epoch_height();
+        let current_protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
+        let state_updates = state_roots
+            .iter()
+            .map(|(shard_uid, state_root)| {
+                (shard_uid.shard_id(), self.tries.new_trie_update_view(*shard_uid, *state_root))
+            })
+            .collect();
+        let mut apply_state = ApplyState {
+            apply_reason: None,
+            block_height: block_header.height(),
+            prev_block_hash: *block_header.prev_hash(),
+            block_hash: *block_header.hash(),
+            // Overwritten with the shard of every processed receipt.
+            shard_id: 0,
+            epoch_id: *epoch_id,
+            epoch_height,
+            gas_price: block_header.next_gas_price(),
+            block_timestamp: block_header.raw_timestamp(),
+            gas_limit: None,
+            random_seed: *block_header.random_value(),
+            current_protocol_version,
+            config: self.runtime_config_store.get_config(current_protocol_version).clone(),
+            cache: Some(self.compiled_contract_cache.handle()),
+            is_new_chunk: true,
+            migration_data: Arc::clone(&self.migration_data),
+            migration_flags: MigrationFlags::default(),
+            congestion_info: Default::default(),
+            impersonated_accounts: self.impersonated_accounts(),
+        };
+        match self.runtime.simulate_transaction(
+            state_updates,
+            &|account_id| account_id_to_shard_id(account_id, &shard_layout),
+            &mut apply_state,
+            transaction,
+            verify_signature,
+            self.epoch_manager.as_ref(),
+        ) {
+            Ok(result) => Ok(Ok(result)),
+            Err(RuntimeError::InvalidTxError(err)) => Ok(Err(err)),
+            Err(RuntimeError::StorageError(err)) => Err(Error::StorageError(err)),
+            Err(err) => Err(Error::Other(err.to_string())),
+        }
+    }
+
     // Wrapper to get the metrics.
     fn obtain_state_part(
         &self,
@@ -1527,7 +1616,9 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         contract_id: &AccountId,
         method_name: &str,
         args: &[u8],
+        state_overrides: &[StateOverride],
         logs: &mut Vec<String>,
+        gas_usage: Option<&mut CallGasUsageView>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
@@ -1549,7 +1640,9 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             contract_id,
             method_name,
             args,
+            state_overrides,
             logs,
+            gas_usage,
             epoch_info_provider,
         )
     }
@@ -1580,11 +1673,23 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
     ) -> Result<ViewStateResult, node_runtime::state_viewer::errors::ViewStateError> {
         let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
-        self.trie_viewer.view_state(&state_update, account_id, prefix, include_proof)
+        // Flat storage only serves the iteration over the contract data, the
+        // account is looked up in the trie which supports blocks before flat
+        // head as well.
+        let flat_storage_chunk_view =
+            self.tries.get_flat_storage_manager().chunk_view(*shard_uid, *block_hash);
+        self.trie_viewer.view_state_with_flat_storage(
+            &state_update,
+            flat_storage_chunk_view.as_ref(),
+            account_id,
+            prefix,
+            include_proof,
+        )
     }
 }
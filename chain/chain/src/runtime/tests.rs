@@ -1512,6 +1512,47 @@ fn test_trie_and_flat_state_equality() {
     assert_eq!(state_value, view_state_value);
 }
 
+/// Check that `view_state` works for blocks before flat head, whose contract
+/// data can't be iterated over in flat storage.
+#[test]
+fn test_view_state_before_flat_head() {
+    let account_id: AccountId = "test1".parse().unwrap();
+    let mut env = TestEnv::new(vec![vec![account_id.clone()]], 4, false);
+    env.step_default(vec![]);
+    let old_block_hash = env.head.last_block_hash;
+    let old_state_root = env.state_roots[0];
+    for _ in 0..3 {
+        env.step_default(vec![]);
+    }
+    let shard_uid = env.epoch_manager.shard_id_to_uid(0, &env.head.epoch_id).unwrap();
+    let flat_storage_manager = env.runtime.get_flat_storage_manager();
+    flat_storage_manager
+        .get_flat_storage_for_shard(shard_uid)
+        .unwrap()
+        .update_flat_head(&env.head.prev_block_hash)
+        .unwrap();
+    let chunk_view = flat_storage_manager.chunk_view(shard_uid, old_block_hash).unwrap();
+    assert!(chunk_view.iter_range(None, None).is_err());
+
+    let result = env
+        .runtime
+        .view_state(&shard_uid, old_state_root, &old_block_hash, &account_id, b"", false)
+        .unwrap();
+    assert!(result.values.is_empty());
+    let result = env
+        .runtime
+        .view_state(
+            &shard_uid,
+            env.state_roots[0],
+            &env.head.last_block_hash,
+            &account_id,
+            b"",
+            false,
+        )
+        .unwrap();
+    assert!(result.values.is_empty());
+}
+
 /// Check that mainnet genesis hash still matches, to make sure that we're still backwards compatible.
 #[test]
 fn test_genesis_hash() {
@@ -1579,7 +1620,7 @@ fn generate_transaction_pool(
     }
     transactions.shuffle(&mut rng);
 
-    let mut pool = TransactionPool::new(TEST_SEED, None, "");
+    let mut pool = TransactionPool::new(TEST_SEED, None, Default::default(), "");
     for transaction in transactions {
         assert_eq!(pool.insert_transaction(transaction), InsertTransactionResult::Success);
     }
@@ -0,0 +1,146 @@
+//! Scheduling of the application of the chunks of a block.
+//!
+//! Chunks of all shards of a block are applied in parallel.  By default they
+//! share the global rayon thread pool, but shards can be given thread pools of
+//! their own (see `ShardSchedulerConfig::dedicated_shards`) so that a busy
+//! shard doesn't hold back the others.  Within a pool, chunks of the shards the
+//! node validates are started before the chunks of the shards it only tracks.
+use crate::metrics;
+use crate::rayon_spawner::RayonThreadPoolSpawner;
+use near_async::futures::AsyncComputationSpawnerExt;
+use near_chain_configs::ShardSchedulerConfig;
+use near_primitives::types::ShardId;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Priority of applying the chunk of a shard.  Chunks with higher priority
+/// (lower in the order) are started first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShardPriority {
+    /// The node validates or produces chunks for the shard.
+    Validated,
+    /// The node only tracks the shard.
+    Tracked,
+}
+
+impl ShardPriority {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Validated => "validated",
+            Self::Tracked => "tracked",
+        }
+    }
+}
+
+/// Job applying the chunk of a shard, along with its priority.
+pub type ShardJob<T> = (ShardId, ShardPriority, Box<dyn FnOnce() -> T + Send + 'static>);
+
+pub struct ShardScheduler {
+    /// Thread pools of the shards which have a dedicated one.
+    pools: HashMap<ShardId, RayonThreadPoolSpawner>,
+    prioritize_validated_shards: bool,
+}
+
+impl ShardScheduler {
+    pub fn new(config: &ShardSchedulerConfig) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let mut pools = HashMap::new();
+        for &shard_id in &config.dedicated_shards {
+            let pool = RayonThreadPoolSpawner::new(
+                &format!("apply_shard_{shard_id}"),
+                config.dedicated_pool_threads,
+            )?;
+            pools.insert(shard_id, pool);
+        }
+        Ok(Self { pools, prioritize_validated_shards: config.prioritize_validated_shards })
+    }
+
+    /// Runs the jobs and waits for all of them to finish.  Results are
+    /// returned in the order of `jobs`, regardless of the order the jobs were
+    /// run in.
+    ///
+    /// Jobs of shards without a dedicated pool run in the current rayon
+    /// thread pool, in which the calling thread takes part while waiting.
+    pub fn run<T: Send + 'static>(&self, jobs: Vec<ShardJob<T>>) -> Vec<(ShardId, T)> {
+        let mut jobs = jobs.into_iter().enumerate().collect::<Vec<_>>();
+        if self.prioritize_validated_shards {
+            // The sort is stable so jobs with the same priority keep their order.
+            jobs.sort_by_key(|(_, (_, priority, _))| *priority);
+        }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        rayon::in_place_scope_fifo(|scope| {
+            for (index, (shard_id, priority, job)) in jobs {
+                let sender = sender.clone();
+                let scheduled = Instant::now();
+                let run = move || {
+                    metrics::APPLY_CHUNK_QUEUE_DELAY
+                        .with_label_values(&[&shard_id.to_string(), priority.as_str()])
+                        .observe(scheduled.elapsed().as_secs_f64());
+                    // The receiver is dropped only after all senders are.
+                    sender.send((index, shard_id, job())).unwrap();
+                };
+                match self.pools.get(&shard_id) {
+                    Some(pool) => pool.spawn("apply_chunk", run),
+                    None => scope.spawn_fifo(move |_| run()),
+                }
+            }
+        });
+        drop(sender);
+        let mut results = receiver.iter().collect::<Vec<_>>();
+        results.sort_by_key(|(index, _, _)| *index);
+        results.into_iter().map(|(_, shard_id, result)| (shard_id, result)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShardJob, ShardPriority, ShardScheduler};
+    use near_chain_configs::ShardSchedulerConfig;
+    use std::sync::{Arc, Mutex};
+
+    fn make_jobs(
+        priorities: &[ShardPriority],
+        started: &Arc<Mutex<Vec<u64>>>,
+    ) -> Vec<ShardJob<String>> {
+        priorities
+            .iter()
+            .enumerate()
+            .map(|(shard_id, &priority)| -> ShardJob<String> {
+                let shard_id = shard_id as u64;
+                let started = started.clone();
+                let job = move || {
+                    started.lock().unwrap().push(shard_id);
+                    std::thread::current().name().unwrap_or_default().to_string()
+                };
+                (shard_id, priority, Box::new(job))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_results_in_job_order() {
+        let config = ShardSchedulerConfig { dedicated_shards: vec![1], ..Default::default() };
+        let scheduler = ShardScheduler::new(&config).unwrap();
+        let started = Arc::new(Mutex::new(vec![]));
+        let priorities = [ShardPriority::Tracked, ShardPriority::Validated, ShardPriority::Tracked];
+        let results = scheduler.run(make_jobs(&priorities, &started));
+        assert_eq!(results.iter().map(|(shard_id, _)| *shard_id).collect::<Vec<_>>(), [0, 1, 2]);
+        // Shard 1 is applied in its own pool.
+        assert!(results[1].1.starts_with("apply_shard_1_"), "thread = {}", results[1].1);
+        assert!(!results[0].1.starts_with("apply_shard_"), "thread = {}", results[0].1);
+        assert_eq!(started.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_validated_shards_first() {
+        // With a single thread jobs are started one after the other.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let priorities = [ShardPriority::Tracked, ShardPriority::Validated, ShardPriority::Tracked];
+        for (prioritize_validated_shards, expected) in [(true, [1, 0, 2]), (false, [0, 1, 2])] {
+            let config = ShardSchedulerConfig { prioritize_validated_shards, ..Default::default() };
+            let scheduler = ShardScheduler::new(&config).unwrap();
+            let started = Arc::new(Mutex::new(vec![]));
+            pool.install(|| scheduler.run(make_jobs(&priorities, &started)));
+            assert_eq!(*started.lock().unwrap(), expected);
+        }
+    }
+}
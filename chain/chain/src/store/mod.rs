@@ -8,6 +8,10 @@ use near_cache::CellLruCache;
 
 use near_chain_primitives::error::Error;
 use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::account_activity::{
+    get_account_activity_key, get_account_activity_key_rev, get_account_activity_prefix,
+    get_block_account_activity, AccountActivity, AccountActivityKind, AccountActivityRecord,
+};
 use near_primitives::block::Tip;
 use near_primitives::checked_feature;
 #[cfg(feature = "new_epoch_sync")]
@@ -32,7 +36,7 @@ use near_primitives::transaction::{
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
-    BlockExtra, BlockHeight, EpochId, NumBlocks, ShardId, StateChanges, StateChangesExt,
+    AccountId, BlockExtra, BlockHeight, EpochId, NumBlocks, ShardId, StateChanges, StateChangesExt,
     StateChangesForResharding, StateChangesKinds, StateChangesKindsExt, StateChangesRequest,
 };
 use near_primitives::utils::{
@@ -55,6 +59,7 @@ use near_store::db::{StoreStatistics, STATE_SYNC_DUMP_KEY};
 use std::sync::Arc;
 
 mod latest_witnesses;
+mod transaction_pool;
 
 /// lru cache size
 #[cfg(not(feature = "no_cache"))]
@@ -451,6 +456,9 @@ pub struct ChainStore {
     /// - archive is true, cold_store is configured and migration to split_storage is finished - node
     /// working in split storage mode needs trie changes in order to do garbage collection on hot.
     save_trie_changes: bool,
+    /// Whether to index transactions and receipts of final blocks by account
+    /// in DBCol::AccountActivity.
+    pub(crate) save_account_activity: bool,
 }
 
 fn option_to_not_found<T, F>(res: io::Result<Option<T>>, field_name: F) -> Result<T, Error>
@@ -492,9 +500,22 @@ impl ChainStore {
             block_ordinal_to_hash: CellLruCache::new(CACHE_SIZE),
             processed_block_heights: CellLruCache::new(CACHE_SIZE),
             save_trie_changes,
+            save_account_activity: false,
         }
     }
 
+    pub fn set_save_account_activity(&mut self, save_account_activity: bool) {
+        self.save_account_activity = save_account_activity;
+    }
+
+    /// Drops all the cached data, e.g. after the contents of the database
+    /// were reverted to an earlier snapshot.
+    pub fn clear_caches(&mut self) {
+        let save_account_activity = self.save_account_activity;
+        *self = ChainStore::new(self.store.clone(), self.genesis_height, self.save_trie_changes);
+        self.save_account_activity = save_account_activity;
+    }
+
     pub fn new_read_only_chunks_store(&self) -> ReadOnlyChunksStore {
         ReadOnlyChunksStore::new(self.store.clone())
     }
@@ -503,6 +524,27 @@ impl ChainStore {
         ChainStoreUpdate::new(self)
     }
 
+    /// Returns up to `limit` activity records of the account starting at
+    /// `from`, a `(block height, index)` pair, in chronological order.
+    pub fn get_account_activity(
+        &self,
+        account_id: &AccountId,
+        from: (BlockHeight, u32),
+        limit: usize,
+    ) -> Result<Vec<((BlockHeight, u32), AccountActivity)>, Error> {
+        let lower_bound = get_account_activity_key(account_id, from.0, from.1);
+        let mut upper_bound = get_account_activity_prefix(account_id);
+        *upper_bound.last_mut().unwrap() += 1;
+        self.store
+            .iter_range(DBCol::AccountActivity, Some(&lower_bound), Some(&upper_bound))
+            .take(limit)
+            .map(|item| {
+                let (key, value) = item?;
+                Ok((get_account_activity_key_rev(&key)?, AccountActivity::try_from_slice(&value)?))
+            })
+            .collect()
+    }
+
     pub fn iterate_state_sync_infos(&self) -> Result<Vec<(CryptoHash, StateSyncInfo)>, Error> {
         self.store
             .iter(DBCol::StateDlInfos)
@@ -2050,6 +2092,114 @@ impl<'a> ChainStoreUpdate<'a> {
         self.chain_store_cache_update.outcome_ids.insert((*block_hash, shard_id), outcome_ids);
     }
 
+    /// Computes the account activity records of a block from the execution
+    /// outcomes of the transactions and receipts applied in it.  Shards which
+    /// aren't tracked by the node have no outcomes and are skipped.
+    pub(crate) fn get_block_account_activity(
+        &self,
+        block: &Block,
+    ) -> Result<Vec<AccountActivityRecord>, Error> {
+        let block_hash = block.hash();
+        let mut executed = vec![];
+        for chunk_header in block.chunks().iter() {
+            let shard_id = chunk_header.shard_id();
+            let outcome_ids =
+                self.chain_store().get_outcomes_by_block_hash_and_shard_id(block_hash, shard_id)?;
+            if outcome_ids.is_empty() {
+                continue;
+            }
+            // Predecessors of the applied receipts.  Receipts coming from other
+            // chunks are found among the incoming receipts.  Receipts created
+            // and applied within the chunk are never stored, their predecessor
+            // is the executor of the transaction or receipt creating them.
+            let mut predecessors: HashMap<CryptoHash, AccountId> = HashMap::new();
+            match self.get_incoming_receipts(block_hash, shard_id) {
+                Ok(receipt_proofs) => {
+                    for receipt in receipt_proofs.iter().flat_map(|proof| proof.0.iter()) {
+                        predecessors
+                            .insert(*receipt.receipt_id(), receipt.predecessor_id().clone());
+                    }
+                }
+                Err(Error::DBNotFoundErr(_)) => {}
+                Err(err) => return Err(err),
+            }
+            let mut outcomes = vec![];
+            for id in outcome_ids {
+                let Some(outcome) =
+                    self.chain_store().get_outcome_by_id_and_block_hash(&id, block_hash)?
+                else {
+                    continue;
+                };
+                let executor_id = outcome.outcome.executor_id;
+                for receipt_id in outcome.outcome.receipt_ids {
+                    predecessors.entry(receipt_id).or_insert_with(|| executor_id.clone());
+                }
+                outcomes.push((id, executor_id));
+            }
+            for (id, executor_id) in outcomes {
+                let (kind, accounts) = match self.get_transaction(&id)? {
+                    Some(transaction) => (
+                        AccountActivityKind::Transaction,
+                        vec![
+                            transaction.transaction.signer_id().clone(),
+                            transaction.transaction.receiver_id().clone(),
+                        ],
+                    ),
+                    None => {
+                        let predecessor_id = match predecessors.get(&id) {
+                            Some(predecessor_id) => Some(predecessor_id.clone()),
+                            // Delayed receipts created in earlier blocks.
+                            None => self
+                                .get_receipt(&id)?
+                                .map(|receipt| receipt.predecessor_id().clone()),
+                        };
+                        let accounts = predecessor_id.into_iter().chain([executor_id]).collect();
+                        (AccountActivityKind::Receipt, accounts)
+                    }
+                };
+                executed.push((
+                    AccountActivity { kind, id, block_hash: *block_hash, shard_id },
+                    accounts,
+                ));
+            }
+        }
+        Ok(get_block_account_activity(block.header().height(), executed))
+    }
+
+    /// Indexes transactions and receipts by account for the blocks which have
+    /// just become final, that is `last_final_block` and its ancestors above
+    /// `prev_final_height`.  Only final blocks are indexed so that the records
+    /// never have to be updated on chain reorganisations.
+    pub fn save_account_activity(
+        &mut self,
+        last_final_block: &CryptoHash,
+        prev_final_height: BlockHeight,
+    ) -> Result<(), Error> {
+        if !self.chain_store.save_account_activity {
+            return Ok(());
+        }
+        let mut store_update = self.store().store_update();
+        let mut block_hash = *last_final_block;
+        loop {
+            let block = match self.get_block(&block_hash) {
+                Ok(block) => block,
+                // Blocks preceding the state sync are not available.
+                Err(Error::DBNotFoundErr(_)) => break,
+                Err(err) => return Err(err),
+            };
+            if block.header().height() <= prev_final_height {
+                break;
+            }
+            for record in self.get_block_account_activity(&block)? {
+                store_update.set_ser(DBCol::AccountActivity, &record.key(), &record.activity)?;
+                store_update.set(DBCol::AccountActivityByHeight, &record.height_key(), &[]);
+            }
+            block_hash = *block.header().prev_hash();
+        }
+        self.merge(store_update);
+        Ok(())
+    }
+
     pub fn save_trie_changes(&mut self, trie_changes: WrappedTrieChanges) {
         self.trie_changes.push(trie_changes);
     }
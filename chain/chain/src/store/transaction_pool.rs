@@ -0,0 +1,90 @@
+//! Persistence of the transaction pool across restarts of the node.
+//!
+//! The pending transactions are written to `DBCol::TransactionPool`
+//! periodically and on shutdown, replacing the previous contents of the
+//! column.  On startup they are read back and re-validated before being
+//! inserted into the pool again.
+
+use near_primitives::transaction::SignedTransaction;
+use near_store::DBCol;
+
+use crate::ChainStoreAccess;
+
+use super::ChainStore;
+
+impl ChainStore {
+    /// Replaces the persisted transactions with the given ones.
+    pub fn save_transaction_pool<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a SignedTransaction>,
+    ) -> Result<usize, std::io::Error> {
+        let mut store_update = self.store().store_update();
+        store_update.delete_all(DBCol::TransactionPool);
+        let mut count = 0;
+        for transaction in transactions {
+            store_update.set_ser(
+                DBCol::TransactionPool,
+                transaction.get_hash().as_ref(),
+                transaction,
+            )?;
+            count += 1;
+        }
+        store_update.commit()?;
+        Ok(count)
+    }
+
+    /// Returns the persisted transactions.
+    pub fn load_transaction_pool(&self) -> Result<Vec<SignedTransaction>, std::io::Error> {
+        self.store()
+            .iter(DBCol::TransactionPool)
+            .map(|item| {
+                let (_, value) = item?;
+                borsh::from_slice(&value)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_async::time::Clock;
+
+    use crate::test_utils::get_chain;
+    use near_crypto::{InMemorySigner, KeyType, Signer};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::SignedTransaction;
+
+    #[test]
+    fn test_save_and_load_transaction_pool() {
+        let chain = get_chain(Clock::real());
+        let store = chain.chain_store();
+        assert!(store.load_transaction_pool().unwrap().is_empty());
+
+        let signer: Signer = InMemorySigner::from_seed(
+            "alice.near".parse().unwrap(),
+            KeyType::ED25519,
+            "alice.near",
+        )
+        .into();
+        let transactions: Vec<_> = (1..=3)
+            .map(|nonce| {
+                SignedTransaction::send_money(
+                    nonce,
+                    "alice.near".parse().unwrap(),
+                    "bob.near".parse().unwrap(),
+                    &signer,
+                    100,
+                    CryptoHash::default(),
+                )
+            })
+            .collect();
+        assert_eq!(store.save_transaction_pool(&transactions).unwrap(), 3);
+        let mut loaded = store.load_transaction_pool().unwrap();
+        loaded.sort_by_key(|tx| tx.transaction.nonce());
+        assert_eq!(loaded, transactions);
+
+        // Saving replaces the previously persisted transactions.
+        assert_eq!(store.save_transaction_pool(&transactions[2..]).unwrap(), 1);
+        assert_eq!(store.load_transaction_pool().unwrap(), transactions[2..]);
+    }
+}
@@ -664,11 +664,23 @@ pub(crate) fn outcome_by_outcome_id_exists(
     Ok(())
 }
 
+/// Returns whether the block is below the tail, in which case its outcomes may
+/// have been kept after it was garbage collected.
+fn is_block_below_tail(sv: &StoreValidator, block_hash: &CryptoHash) -> bool {
+    match sv.store.get_ser::<BlockHeader>(DBCol::BlockHeader, block_hash.as_ref()) {
+        Ok(Some(header)) => header.height() < sv.inner.tail,
+        _ => false,
+    }
+}
+
 pub(crate) fn outcome_id_block_exists(
     sv: &mut StoreValidator,
     block_hash: &CryptoHash,
     _outcome_ids: &[CryptoHash],
 ) -> Result<(), StoreValidatorError> {
+    if is_block_below_tail(sv, block_hash) {
+        return Ok(());
+    }
     unwrap_or_err_db!(
         sv.store.get_ser::<Block>(DBCol::Block, block_hash.as_ref()),
         "Can't get Block from DB - outcome_id_block_exists"
@@ -681,6 +693,9 @@ pub(crate) fn outcome_indexed_by_block_hash(
     (outcome_id, block_hash): &(CryptoHash, CryptoHash),
     _outcome: &ExecutionOutcomeWithProof,
 ) -> Result<(), StoreValidatorError> {
+    if is_block_below_tail(sv, block_hash) {
+        return Ok(());
+    }
     let block = unwrap_or_err_db!(
         sv.store.get_ser::<Block>(DBCol::Block, block_hash.as_ref()),
         "Can't get Block {} from DB",
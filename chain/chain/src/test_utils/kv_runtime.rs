@@ -28,6 +28,7 @@ use near_primitives::epoch_manager::ValidatorSelectionConfig;
 use near_primitives::errors::{EpochError, InvalidTxError};
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptV0};
+use near_primitives::sandbox::impersonation::SandboxImpersonatedAccounts;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
 use near_primitives::state_part::PartId;
@@ -54,6 +55,7 @@ use near_store::{
     set_genesis_hash, set_genesis_state_roots, DBCol, ShardTries, Store, StoreUpdate, Trie,
     TrieChanges, WrappedTrieChanges,
 };
+use node_runtime::simulation::SimulationResult;
 use num_rational::Ratio;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -1055,6 +1057,10 @@ impl EpochManagerAdapter for MockEpochManager {
     #[cfg(feature = "new_epoch_sync")]
     fn force_update_aggregator(&self, _epoch_id: &EpochId, _hash: &CryptoHash) {}
 
+    fn reload_from_store(&self) -> Result<(), EpochError> {
+        Ok(())
+    }
+
     fn get_epoch_all_validators(
         &self,
         _epoch_id: &EpochId,
@@ -1088,6 +1094,12 @@ impl RuntimeAdapter for KeyValueRuntime {
         self.tries.get_flat_storage_manager()
     }
 
+    fn impersonated_accounts(&self) -> SandboxImpersonatedAccounts {
+        Default::default()
+    }
+
+    fn set_impersonated_accounts(&self, _accounts: SandboxImpersonatedAccounts) {}
+
     fn get_view_trie_for_shard(
         &self,
         shard_id: ShardId,
@@ -1377,6 +1389,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                 kind: QueryResponseKind::CallResult(CallResult {
                     result: Default::default(),
                     logs: Default::default(),
+                    gas_usage: None,
                 }),
                 block_height,
                 block_hash: *block_hash,
@@ -1384,6 +1397,16 @@ impl RuntimeAdapter for KeyValueRuntime {
         }
     }
 
+    fn simulate_transaction(
+        &self,
+        _block_header: &BlockHeader,
+        _state_roots: &HashMap<ShardUId, StateRoot>,
+        _transaction: &SignedTransaction,
+        _verify_signature: bool,
+    ) -> Result<Result<SimulationResult, InvalidTxError>, Error> {
+        Err(Error::Other("simulate_transaction should not be used in KeyValueRuntime".into()))
+    }
+
     fn obtain_state_part(
         &self,
         _shard_id: ShardId,
@@ -1,10 +1,10 @@
-use near_async::time::Clock;
+use near_async::time::{Clock, Duration, FakeClock};
 use near_epoch_manager::types::BlockHeaderInfo;
 use rand::Rng;
 use std::sync::Arc;
 
 use crate::chain::Chain;
-use crate::garbage_collection::GCMode;
+use crate::garbage_collection::{extended_tail_key, GCMode};
 use crate::test_utils::{
     get_chain, get_chain_with_epoch_length, get_chain_with_epoch_length_and_num_shards,
     get_chain_with_num_shards,
@@ -12,17 +12,22 @@ use crate::test_utils::{
 use crate::types::Tip;
 use crate::{ChainStoreAccess, StoreValidator};
 
-use near_chain_configs::{GCConfig, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP};
+use bytesize::ByteSize;
+use near_chain_configs::{
+    GCConfig, GCExtendedRetention, GCRetainedData, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+};
 use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::Block;
 use near_primitives::epoch_manager::block_info::BlockInfo;
+use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::test_utils::{create_test_signer, TestBlockBuilder};
 use near_primitives::types::{BlockHeight, NumBlocks, StateRoot};
+use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_store::test_utils::gen_changes;
-use near_store::{DBCol, ShardTries, Trie, WrappedTrieChanges};
+use near_store::{DBCol, KeyForStateChanges, ShardTries, Trie, WrappedTrieChanges};
 
 // Build a chain of num_blocks on top of prev_block
 fn do_fork(
@@ -702,6 +707,7 @@ fn test_clear_old_data() {
     let mut blocks = vec![prev_block.clone()];
     for i in 1..=max_height {
         add_block(
+            Clock::real(),
             &mut chain,
             epoch_manager.as_ref(),
             &mut prev_block,
@@ -724,8 +730,169 @@ fn test_clear_old_data() {
     }
 }
 
+/// Builds a chain with epochs of one block and a block every second.
+fn make_chain_with_block_per_second(max_height: BlockHeight) -> (Chain, Vec<Block>) {
+    let clock = FakeClock::default();
+    let mut chain = get_chain_with_epoch_length(clock.clock(), 1);
+    let epoch_manager = chain.epoch_manager.clone();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = chain.get_block_by_height(0).unwrap();
+    let mut blocks = vec![prev_block.clone()];
+    for height in 1..=max_height {
+        clock.advance(Duration::seconds(1));
+        add_block(
+            clock.clock(),
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            height,
+        );
+    }
+    (chain, blocks)
+}
+
+/// Returns the heights of the blocks which have been garbage collected.
+fn removed_heights(chain: &Chain, blocks: &[Block]) -> Vec<BlockHeight> {
+    blocks
+        .iter()
+        .filter(|block| chain.get_block(block.hash()).is_err())
+        .map(|block| block.header().height())
+        .collect()
+}
+
+/// Blocks are kept for `gc_keep_duration` even if they are older than
+/// `gc_num_epochs_to_keep` epochs, but not the other way around.
+#[test]
+fn test_clear_old_data_keep_duration() {
+    let (mut chain, blocks) = make_chain_with_block_per_second(14);
+    let gc_config = GCConfig {
+        gc_blocks_limit: 100,
+        gc_keep_duration: Some(Duration::seconds(8)),
+        ..GCConfig::default()
+    };
+    chain.clear_data(&gc_config).unwrap();
+    assert_eq!(removed_heights(&chain, &blocks), (0..6).collect::<Vec<_>>());
+
+    let gc_config = GCConfig { gc_keep_duration: Some(Duration::seconds(1)), ..gc_config };
+    chain.clear_data(&gc_config).unwrap();
+    let expected_removed = 14 - DEFAULT_GC_NUM_EPOCHS_TO_KEEP;
+    assert_eq!(removed_heights(&chain, &blocks), (0..expected_removed).collect::<Vec<_>>());
+}
+
+/// Blocks aren't garbage collected while the database is within
+/// `gc_hot_db_size_budget`.
+#[test]
+fn test_clear_old_data_size_budget() {
+    let (mut chain, blocks) = make_chain_with_block_per_second(14);
+    let gc_config = GCConfig {
+        gc_blocks_limit: 100,
+        gc_hot_db_size_budget: Some(ByteSize::gib(1)),
+        ..GCConfig::default()
+    };
+    chain.clear_data(&gc_config).unwrap();
+    assert_eq!(removed_heights(&chain, &blocks), Vec::<BlockHeight>::new());
+
+    let gc_config = GCConfig { gc_hot_db_size_budget: Some(ByteSize::b(1)), ..gc_config };
+    chain.clear_data(&gc_config).unwrap();
+    let expected_removed = 14 - DEFAULT_GC_NUM_EPOCHS_TO_KEEP;
+    assert_eq!(removed_heights(&chain, &blocks), (0..expected_removed).collect::<Vec<_>>());
+}
+
+/// Saves a state change and an outcome for the block.
+fn save_block_data(chain: &mut Chain, block_hash: &CryptoHash) {
+    let outcome_id = CryptoHash::hash_bytes(block_hash.as_bytes());
+    let mut store_update = chain.mut_chain_store().store().store_update();
+    let mut state_changes_key = KeyForStateChanges::for_block(block_hash).as_ref().to_vec();
+    state_changes_key.extend(b"key");
+    store_update.set(DBCol::StateChanges, &state_changes_key, b"value");
+    store_update
+        .set_ser(DBCol::OutcomeIds, &get_block_shard_id(block_hash, 0), &vec![outcome_id])
+        .unwrap();
+    store_update.insert(
+        DBCol::TransactionResultForBlock,
+        get_outcome_id_block_hash(&outcome_id, block_hash),
+        b"outcome".to_vec(),
+    );
+    store_update.commit().unwrap();
+}
+
+/// Returns the heights of the blocks whose state changes and outcomes have
+/// been garbage collected, checking that they are collected together.
+fn removed_data_heights(chain: &Chain, blocks: &[Block]) -> Vec<BlockHeight> {
+    let store = chain.chain_store().store();
+    let mut heights = vec![];
+    for block in blocks {
+        let block_hash = block.hash();
+        let outcome_id = CryptoHash::hash_bytes(block_hash.as_bytes());
+        let has_state_changes = store
+            .iter_prefix(DBCol::StateChanges, KeyForStateChanges::for_block(block_hash).as_ref())
+            .next()
+            .is_some();
+        let has_outcome_ids =
+            store.exists(DBCol::OutcomeIds, &get_block_shard_id(block_hash, 0)).unwrap();
+        let has_outcome = store
+            .exists(
+                DBCol::TransactionResultForBlock,
+                &get_outcome_id_block_hash(&outcome_id, block_hash),
+            )
+            .unwrap();
+        assert_eq!(has_state_changes, has_outcome_ids);
+        assert_eq!(has_state_changes, has_outcome);
+        if !has_state_changes {
+            heights.push(block.header().height());
+        }
+    }
+    heights
+}
+
+/// Data with extended retention is kept for its own duration after its block
+/// is garbage collected, and is cleared once the retention is removed.
+#[test]
+fn test_clear_old_data_extended_retention() {
+    let (mut chain, blocks) = make_chain_with_block_per_second(14);
+    for block in &blocks {
+        save_block_data(&mut chain, block.hash());
+    }
+    let gc_config = GCConfig {
+        gc_blocks_limit: 100,
+        gc_extended_retention: vec![
+            GCExtendedRetention {
+                data: GCRetainedData::TransactionResults,
+                keep_duration: Duration::seconds(8),
+            },
+            GCExtendedRetention {
+                data: GCRetainedData::StateChanges,
+                keep_duration: Duration::seconds(8),
+            },
+        ],
+        ..GCConfig::default()
+    };
+    // The first run clears the blocks and the second one the data older than
+    // the retention.
+    chain.clear_data(&gc_config).unwrap();
+    chain.clear_data(&gc_config).unwrap();
+    let expected_removed = 14 - DEFAULT_GC_NUM_EPOCHS_TO_KEEP;
+    assert_eq!(removed_heights(&chain, &blocks), (0..expected_removed).collect::<Vec<_>>());
+    assert_eq!(removed_data_heights(&chain, &blocks), (0..6).collect::<Vec<_>>());
+
+    chain.clear_data(&GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }).unwrap();
+    assert_eq!(removed_data_heights(&chain, &blocks), (0..expected_removed).collect::<Vec<_>>());
+    // The data of the block at the tail is cleared as well, after which the
+    // tails of the data are deleted.
+    for data in [GCRetainedData::TransactionResults, GCRetainedData::StateChanges] {
+        assert!(!chain
+            .chain_store()
+            .store()
+            .exists(DBCol::BlockMisc, &extended_tail_key(data))
+            .unwrap());
+    }
+}
+
 // Adds block to the chain at given height after prev_block.
 fn add_block(
+    clock: Clock,
     chain: &mut Chain,
     epoch_manager: &dyn EpochManagerAdapter,
     prev_block: &mut Block,
@@ -739,13 +906,13 @@ fn add_block(
     let mut store_update = chain.mut_chain_store().store_update();
 
     let block = if next_epoch_id == *prev_block.header().next_epoch_id() {
-        TestBlockBuilder::new(Clock::real(), &prev_block, signer).height(height).build()
+        TestBlockBuilder::new(clock, &prev_block, signer).height(height).build()
     } else {
         let prev_hash = prev_block.hash();
         let epoch_id = *prev_block.header().next_epoch_id();
         let next_bp_hash =
             Chain::compute_bp_hash(epoch_manager, next_epoch_id, epoch_id, &prev_hash).unwrap();
-        TestBlockBuilder::new(Clock::real(), &prev_block, signer)
+        TestBlockBuilder::new(clock, &prev_block, signer)
             .height(height)
             .epoch_id(epoch_id)
             .next_epoch_id(next_epoch_id)
@@ -783,6 +950,7 @@ fn test_clear_old_data_fixed_height() {
     let mut blocks = vec![prev_block.clone()];
     for i in 1..10 {
         add_block(
+            Clock::real(),
             &mut chain,
             epoch_manager.as_ref(),
             &mut prev_block,
@@ -937,6 +1105,7 @@ fn test_fork_chunk_tail_updates() {
     let mut blocks = vec![prev_block.clone()];
     for i in 1..10 {
         add_block(
+            Clock::real(),
             &mut chain,
             epoch_manager.as_ref(),
             &mut prev_block,
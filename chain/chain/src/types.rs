@@ -4,6 +4,7 @@ use near_chain_configs::GenesisConfig;
 use near_chain_configs::MutableConfigValue;
 use near_chain_configs::ProtocolConfig;
 use near_chain_configs::ReshardingConfig;
+use near_chain_configs::ShardSchedulerConfig;
 use near_chain_primitives::Error;
 pub use near_epoch_manager::EpochManagerAdapter;
 use near_parameters::RuntimeConfig;
@@ -19,6 +20,7 @@ use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath};
 use near_primitives::receipt::{PromiseYieldTimeout, Receipt};
+use near_primitives::sandbox::impersonation::SandboxImpersonatedAccounts;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state_part::PartId;
@@ -36,6 +38,7 @@ use near_primitives::version::{
 use near_primitives::views::{QueryRequest, QueryResponse};
 use near_store::flat::FlatStorageManager;
 use near_store::{PartialStorage, ShardTries, Store, Trie, WrappedTrieChanges};
+use node_runtime::simulation::SimulationResult;
 use num_rational::Rational32;
 use std::collections::HashMap;
 use tracing::instrument;
@@ -229,6 +232,11 @@ pub struct ChainConfig {
     pub background_migration_threads: usize,
     /// The resharding configuration.
     pub resharding_config: MutableConfigValue<ReshardingConfig>,
+    /// Whether to index transactions and receipts by account in
+    /// `DBCol::AccountActivity`.
+    pub save_account_activity: bool,
+    /// Thread pools and priorities used to apply the chunks of a block.
+    pub shard_scheduler: ShardSchedulerConfig,
 }
 
 impl ChainConfig {
@@ -240,6 +248,8 @@ impl ChainConfig {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
+            shard_scheduler: ShardSchedulerConfig::default(),
         }
     }
 }
@@ -426,6 +436,14 @@ pub trait RuntimeAdapter: Send + Sync {
 
     fn get_flat_storage_manager(&self) -> FlatStorageManager;
 
+    /// Returns the accounts impersonated in sandbox.
+    fn impersonated_accounts(&self) -> SandboxImpersonatedAccounts;
+
+    /// Replaces the accounts impersonated in sandbox.  Transactions of these
+    /// accounts are validated and applied without checking their signature,
+    /// access key and nonce.
+    fn set_impersonated_accounts(&self, accounts: SandboxImpersonatedAccounts);
+
     /// Validates a given signed transaction.
     /// If the state root is given, then the verification will use the account. Otherwise it will
     /// only validate the transaction math, limits and signatures.
@@ -494,6 +512,20 @@ pub trait RuntimeAdapter: Send + Sync {
         request: &QueryRequest,
     ) -> Result<QueryResponse, near_chain_primitives::error::QueryError>;
 
+    /// Executes the transaction and all the receipts it produces on top of
+    /// `state_roots` without committing anything to storage.
+    /// `state_roots` must contain the post state roots of `block_header` for
+    /// every shard. The simulation sees the chain as of `block_header`, same
+    /// as view calls executed by `query`.
+    /// Returns `Ok(Err(InvalidTxError))` if the transaction is invalid.
+    fn simulate_transaction(
+        &self,
+        block_header: &BlockHeader,
+        state_roots: &HashMap<ShardUId, StateRoot>,
+        transaction: &SignedTransaction,
+        verify_signature: bool,
+    ) -> Result<Result<SimulationResult, InvalidTxError>, Error>;
+
     /// Get part of the state corresponding to the given state root.
     /// `prev_hash` is a block whose post state root is `state_root`.
     /// Returns error when storage is inconsistent.
@@ -599,7 +631,7 @@ mod tests {
         assert!(b1.header().verify_block_producer(&signer.public_key()));
         let other_signer = create_test_signer("other2");
         let approvals =
-            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).signature))];
+            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).unwrap().signature))];
         let b2 =
             TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).approvals(approvals).build();
         b2.header().verify_block_producer(&signer.public_key());
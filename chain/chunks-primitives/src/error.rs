@@ -1,6 +1,7 @@
 use std::fmt;
 
 use near_primitives::errors::EpochError;
+use near_primitives::validator_signer::ValidatorSignerError;
 
 #[derive(Debug)]
 pub enum Error {
@@ -16,6 +17,7 @@ pub enum Error {
     KnownPart,
     ChainError(near_chain_primitives::Error),
     IOError(std::io::Error),
+    SignerError(ValidatorSignerError),
 }
 
 impl std::error::Error for Error {}
@@ -32,6 +34,12 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<ValidatorSignerError> for Error {
+    fn from(err: ValidatorSignerError) -> Self {
+        Error::SignerError(err)
+    }
+}
+
 impl From<near_chain_primitives::Error> for Error {
     fn from(err: near_chain_primitives::Error) -> Self {
         Error::ChainError(err)
@@ -282,22 +282,25 @@ mod tests {
     fn create_chunk_header(height: u64, shard_id: u64) -> ShardChunkHeader {
         let signer =
             InMemoryValidatorSigner::from_random("test".parse().unwrap(), KeyType::ED25519);
-        ShardChunkHeader::V2(ShardChunkHeaderV2::new(
-            CryptoHash::default(),
-            CryptoHash::default(),
-            CryptoHash::default(),
-            CryptoHash::default(),
-            1,
-            height,
-            shard_id,
-            0,
-            0,
-            0,
-            CryptoHash::default(),
-            CryptoHash::default(),
-            vec![],
-            &signer.into(),
-        ))
+        ShardChunkHeader::V2(
+            ShardChunkHeaderV2::new(
+                CryptoHash::default(),
+                CryptoHash::default(),
+                CryptoHash::default(),
+                CryptoHash::default(),
+                1,
+                height,
+                shard_id,
+                0,
+                0,
+                0,
+                CryptoHash::default(),
+                CryptoHash::default(),
+                vec![],
+                &signer.into(),
+            )
+            .unwrap(),
+        )
     }
 
     #[test]
@@ -3,11 +3,13 @@ use std::collections::HashMap;
 use actix::Message;
 use itertools::Itertools;
 
+use near_chain_configs::TransactionPoolConfig;
 use near_pool::types::TransactionGroupIterator;
 use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
 use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
 use near_primitives::{
     epoch_manager::RngSeed,
+    hash::CryptoHash,
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
     transaction::SignedTransaction,
     types::{AccountId, ShardId},
@@ -43,15 +45,30 @@ pub struct ShardedTransactionPool {
     /// If set, new transactions that bring the size of the pool over this limit will be rejected.
     /// The size is tracked and enforced separately for each shard.
     pool_size_limit: Option<u64>,
+
+    /// Ordering, quotas and eviction policy of the pool of each shard.
+    pool_config: TransactionPoolConfig,
 }
 
 impl ShardedTransactionPool {
-    pub fn new(rng_seed: RngSeed, pool_size_limit: Option<u64>) -> Self {
-        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit }
+    pub fn new(
+        rng_seed: RngSeed,
+        pool_size_limit: Option<u64>,
+        pool_config: TransactionPoolConfig,
+    ) -> Self {
+        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit, pool_config }
     }
 
-    pub fn get_pool_iterator(&mut self, shard_uid: ShardUId) -> Option<PoolIteratorWrapper<'_>> {
-        self.tx_pools.get_mut(&shard_uid).map(|pool| pool.pool_iterator())
+    /// Returns an iterator over the transactions of the shard.  The shard layout is used to find
+    /// receiver shards when the pool is configured to be fair across them.
+    pub fn get_pool_iterator(
+        &mut self,
+        shard_uid: ShardUId,
+        shard_layout: &ShardLayout,
+    ) -> Option<PoolIteratorWrapper<'_>> {
+        self.tx_pools
+            .get_mut(&shard_uid)
+            .map(|pool| pool.pool_iterator_with_shard_layout(shard_layout))
     }
 
     /// Tries to insert the transaction into the pool for a given shard.
@@ -85,11 +102,39 @@ impl ShardedTransactionPool {
             TransactionPool::new(
                 Self::random_seed(&self.rng_seed, shard_uid.shard_id()),
                 self.pool_size_limit,
+                self.pool_config.clone(),
                 &shard_uid.to_string(),
             )
         })
     }
 
+    /// Returns the transactions of all shards, in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.tx_pools.values().flat_map(|pool| pool.transactions())
+    }
+
+    /// Returns the pools of all shards which have received transactions, in no particular order.
+    pub fn pools(&self) -> impl Iterator<Item = (ShardUId, &TransactionPool)> {
+        self.tx_pools.iter().map(|(shard_uid, pool)| (*shard_uid, pool))
+    }
+
+    /// Looks up the transaction in the pools of all shards.  Returns the shard it is pending in
+    /// and the number of transactions of the same access key which have to be included first.
+    pub fn get_transaction(
+        &self,
+        tx_hash: &CryptoHash,
+    ) -> Option<(ShardUId, &SignedTransaction, usize)> {
+        self.tx_pools.iter().find_map(|(shard_uid, pool)| {
+            let (tx, num_before) = pool.get_transaction(tx_hash)?;
+            Some((*shard_uid, tx, num_before))
+        })
+    }
+
+    /// Size limit of the pool of each shard in bytes.
+    pub fn pool_size_limit(&self) -> Option<u64> {
+        self.pool_size_limit
+    }
+
     pub fn debug_status(&self) -> String {
         self.tx_pools
             .iter()
@@ -110,7 +155,8 @@ impl ShardedTransactionPool {
         for tx in transactions {
             reintroduced_count += match pool.insert_transaction(tx.clone()) {
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate => 1,
-                InsertTransactionResult::NoSpaceLeft => 0,
+                InsertTransactionResult::NoSpaceLeft
+                | InsertTransactionResult::SignerQuotaExceeded => 0,
             }
         }
         reintroduced_count
@@ -133,7 +179,7 @@ impl ShardedTransactionPool {
         let mut transactions = vec![];
 
         for old_shard_uid in old_shard_layout.shard_uids() {
-            if let Some(mut iter) = self.get_pool_iterator(old_shard_uid) {
+            if let Some(mut iter) = self.get_pool_iterator(old_shard_uid, old_shard_layout) {
                 while let Some(group) = iter.next() {
                     while let Some(tx) = group.next() {
                         transactions.push(tx);
@@ -194,7 +240,7 @@ mod tests {
         let old_shard_layout = ShardLayout::get_simple_nightshade_layout();
         let new_shard_layout = ShardLayout::get_simple_nightshade_layout_v2();
 
-        let mut pool = ShardedTransactionPool::new(TEST_SEED, None);
+        let mut pool = ShardedTransactionPool::new(TEST_SEED, None, Default::default());
 
         let mut shard_id_to_accounts = HashMap::new();
         shard_id_to_accounts.insert(0, vec!["aaa", "abcd", "a-a-a-a-a"]);
@@ -262,7 +308,7 @@ mod tests {
             for shard_id in shard_ids {
                 let shard_id = shard_id as u32;
                 let shard_uid = ShardUId { shard_id, version: new_shard_layout.version() };
-                let mut pool_iter = pool.get_pool_iterator(shard_uid).unwrap();
+                let mut pool_iter = pool.get_pool_iterator(shard_uid, &new_shard_layout).unwrap();
                 while let Some(group) = pool_iter.next() {
                     while let Some(tx) = group.next() {
                         total += 1;
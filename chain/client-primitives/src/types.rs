@@ -10,10 +10,12 @@ use near_primitives::types::{
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, DownloadStatusView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    GasPriceView, LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView,
-    QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesRequestView, StateChangesView, SyncStatusView, TxStatusView,
+    AccountActivityView, BlockView, ChunkView, DownloadStatusView, EpochValidatorInfo,
+    ExecutionOutcomeWithIdView, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
+    MaintenanceWindowsView, MempoolShardStatsView, MempoolTransactionStatusView,
+    MempoolTransactionView, QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView,
+    SignedTransactionView, SplitStorageInfoView, StateChangesKindsView, StateChangesRequestView,
+    StateChangesView, SyncStatusView, TxStatusView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use std::collections::HashMap;
@@ -35,6 +37,8 @@ pub enum Error {
     BlockProducer(String),
     #[error("Chunk Producer: {0}")]
     ChunkProducer(String),
+    #[error("Validator signer: {0}")]
+    Signer(#[from] near_primitives::validator_signer::ValidatorSignerError),
     #[error("Other: {0}")]
     Other(String),
 }
@@ -544,6 +548,7 @@ pub enum QueryError {
     #[error("Function call returned an error: {vm_error}")]
     ContractExecutionError {
         vm_error: String,
+        gas_usage: Option<near_primitives::views::CallGasUsageView>,
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
@@ -751,6 +756,59 @@ impl Message for TxStatus {
     type Result = Result<TxStatusView, TxStatusError>;
 }
 
+/// Executes a transaction and all the receipts it produces on top of the
+/// latest final state without committing anything.
+#[derive(Debug)]
+pub struct SimulateTransaction {
+    pub signed_transaction: near_primitives::transaction::SignedTransaction,
+    pub verify_signature: bool,
+}
+
+impl Message for SimulateTransaction {
+    type Result = Result<SimulateTransactionResponse, SimulateTransactionError>;
+}
+
+#[derive(Debug)]
+pub struct SimulateTransactionResponse {
+    /// Block whose post state the transaction has been executed on.
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub final_execution_outcome: near_primitives::views::FinalExecutionOutcomeView,
+    /// Receipts left unexecuted because the simulation hit one of its limits.
+    pub pending_receipts: Vec<ReceiptView>,
+    pub state_changes: StateChangesView,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SimulateTransactionError {
+    #[error("There are no fully synchronized blocks on the node yet")]
+    NoSyncedBlocks,
+    #[error("The node does not track the shard ID {requested_shard_id}")]
+    UnavailableShard { requested_shard_id: ShardId },
+    #[error("Simulating transactions requires tracking all shards, the node does not track shards {untracked_shard_ids:?}")]
+    UntrackedShards { untracked_shard_ids: Vec<ShardId> },
+    #[error("Transaction is invalid: {0:?}")]
+    InvalidTransaction(near_primitives::errors::InvalidTxError),
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for SimulateTransactionError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            near_chain_primitives::Error::StorageError(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetValidatorInfo {
     pub epoch_reference: EpochReference,
@@ -1015,6 +1073,49 @@ impl Message for GetReceipt {
     type Result = Result<Option<ReceiptView>, GetReceiptError>;
 }
 
+/// Lists transactions and receipts the account took part in, starting at the
+/// given position (block height and index within the block) inclusive.
+#[derive(Debug)]
+pub struct GetAccountActivity {
+    pub account_id: AccountId,
+    pub from: (BlockHeight, u32),
+    pub limit: usize,
+}
+
+#[derive(Debug)]
+pub struct GetAccountActivityResponse {
+    pub activity: Vec<AccountActivityView>,
+    /// Position of the first record which didn't fit in the response.
+    pub next: Option<(BlockHeight, u32)>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetAccountActivityError {
+    #[error("Account activity is not indexed by this node")]
+    NotEnabled,
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetAccountActivityError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+impl Message for GetAccountActivity {
+    type Result = Result<GetAccountActivityResponse, GetAccountActivityError>;
+}
+
 #[derive(Debug)]
 pub struct GetProtocolConfig(pub BlockReference);
 
@@ -1100,6 +1201,74 @@ impl From<near_chain_primitives::Error> for GetClientConfigError {
     }
 }
 
+/// Lists the transactions pending in the pool of this node which match all
+/// the given filters.
+#[derive(Debug)]
+pub struct GetMempoolTransactions {
+    pub shard_id: Option<ShardId>,
+    pub signer_id: Option<AccountId>,
+    pub receiver_id: Option<AccountId>,
+    pub limit: usize,
+}
+
+#[derive(Debug)]
+pub struct GetMempoolTransactionsResponse {
+    /// Sorted by shard, signer and nonce.
+    pub transactions: Vec<MempoolTransactionView>,
+    /// Whether more transactions matched the filters than `limit`.
+    pub truncated: bool,
+}
+
+impl Message for GetMempoolTransactions {
+    type Result = Result<GetMempoolTransactionsResponse, GetMempoolError>;
+}
+
+/// Looks up a transaction in the pool of this node.
+#[derive(Debug)]
+pub struct GetMempoolTransaction {
+    pub tx_hash: CryptoHash,
+}
+
+#[derive(Debug)]
+pub struct GetMempoolTransactionResponse {
+    pub status: MempoolTransactionStatusView,
+    /// `None` if the transaction is not in the pool.
+    pub transaction: Option<SignedTransactionView>,
+}
+
+impl Message for GetMempoolTransaction {
+    type Result = Result<GetMempoolTransactionResponse, GetMempoolError>;
+}
+
+/// Returns the number and size of the pending transactions of every shard.
+#[derive(Debug)]
+pub struct GetMempoolStats {}
+
+impl Message for GetMempoolStats {
+    type Result = Result<Vec<MempoolShardStatsView>, GetMempoolError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetMempoolError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetMempoolError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetSplitStorageInfo {}
 
@@ -1141,6 +1310,12 @@ pub enum SandboxMessage {
     SandboxPatchStateStatus,
     SandboxFastForward(near_primitives::types::BlockHeightDelta),
     SandboxFastForwardStatus,
+    SandboxSnapshot,
+    SandboxRevert(u64),
+    /// Timestamp of the next block, in nanoseconds since the Unix epoch.
+    SandboxSetBlockTimestamp(u64),
+    SandboxImpersonateAccount(AccountId),
+    SandboxStopImpersonatingAccount(AccountId),
 }
 
 #[cfg(feature = "sandbox")]
@@ -1149,6 +1324,11 @@ pub enum SandboxResponse {
     SandboxPatchStateFinished(bool),
     SandboxFastForwardFinished(bool),
     SandboxFastForwardFailed(String),
+    SandboxSnapshotCreated(u64),
+    SandboxSnapshotFailed(String),
+    SandboxRevertFinished,
+    SandboxRevertFailed(String),
+    SandboxSetBlockTimestampFailed(String),
     SandboxNoResponse,
 }
 #[cfg(feature = "sandbox")]
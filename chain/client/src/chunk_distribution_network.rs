@@ -413,7 +413,7 @@ mod tests {
             prev_validator_proposals: Vec::new(),
             congestion_info: CongestionInfo::default(),
         });
-        let header = ShardChunkHeaderV3::from_inner(header_inner, &signer);
+        let header = ShardChunkHeaderV3::from_inner(header_inner, &signer).unwrap();
         PartialEncodedChunk::V2(PartialEncodedChunkV2 {
             header: ShardChunkHeader::V3(header),
             parts: Vec::new(),
@@ -118,6 +118,24 @@ pub enum AdvProduceBlocksMode {
     OnlyValid,
 }
 
+/// State of a sandbox node saved by `Client::sandbox_snapshot`.
+#[cfg(feature = "sandbox")]
+struct SandboxSnapshot {
+    store: near_store::StoreSnapshot,
+    accrued_fastforward_delta: near_primitives::types::BlockHeightDelta,
+    time_delta: Duration,
+    impersonated_accounts: near_primitives::sandbox::impersonation::SandboxImpersonatedAccounts,
+}
+
+/// Directory holding the database checkpoints of the sandbox snapshots, next
+/// to the hot database like the state snapshots.
+#[cfg(feature = "sandbox")]
+fn sandbox_snapshots_dir(runtime_adapter: &dyn RuntimeAdapter) -> std::path::PathBuf {
+    let tries = runtime_adapter.get_tries();
+    let config = tries.state_snapshot_config();
+    config.home_dir.join(&config.hot_store_path).join("sandbox_snapshots")
+}
+
 pub struct Client {
     /// Adversarial controls - should be enabled only to test disruptive
     /// behaviour on chain.
@@ -133,6 +151,13 @@ pub struct Client {
     /// Fast Forward accrued delta height used to calculate fast forwarded timestamps for each block.
     #[cfg(feature = "sandbox")]
     pub(crate) accrued_fastforward_delta: near_primitives::types::BlockHeightDelta,
+    /// Time added to the timestamps of produced blocks on top of the fast
+    /// forward delta, see `sandbox_set_block_timestamp`.
+    #[cfg(feature = "sandbox")]
+    sandbox_time_delta: Duration,
+    /// Snapshots taken with `sandbox_snapshot`, by id.
+    #[cfg(feature = "sandbox")]
+    sandbox_snapshots: std::collections::BTreeMap<u64, SandboxSnapshot>,
 
     pub clock: Clock,
     pub config: ClientConfig,
@@ -263,6 +288,8 @@ impl Client {
             save_trie_changes: config.save_trie_changes,
             background_migration_threads: config.client_background_migration_threads,
             resharding_config: config.resharding_config.clone(),
+            save_account_activity: config.save_account_activity,
+            shard_scheduler: config.shard_scheduler.clone(),
         };
         let chain = Chain::new(
             clock.clone(),
@@ -283,8 +310,11 @@ impl Client {
             chain.chain_store(),
             chain_config.background_migration_threads,
         )?;
-        let sharded_tx_pool =
-            ShardedTransactionPool::new(rng_seed, config.transaction_pool_size_limit);
+        let sharded_tx_pool = ShardedTransactionPool::new(
+            rng_seed,
+            config.transaction_pool_size_limit,
+            config.transaction_pool.clone(),
+        );
         let sync_status = SyncStatus::AwaitingPeers;
         let genesis_block = chain.genesis_block();
         let epoch_sync = EpochSync::new(
@@ -370,6 +400,15 @@ impl Client {
             panic_on_validation_error,
         );
         let chunk_distribution_network = ChunkDistributionNetwork::from_config(&config);
+        // Snapshots don't outlive the node, so the ones left over by a
+        // previous run are deleted.
+        #[cfg(feature = "sandbox")]
+        {
+            let dir = sandbox_snapshots_dir(runtime_adapter.as_ref());
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir).map_err(near_chain::Error::from)?;
+            }
+        }
         Ok(Self {
             #[cfg(feature = "test_features")]
             adv_produce_blocks: None,
@@ -381,6 +420,10 @@ impl Client {
             produce_invalid_tx_in_chunks: false,
             #[cfg(feature = "sandbox")]
             accrued_fastforward_delta: 0,
+            #[cfg(feature = "sandbox")]
+            sandbox_time_delta: Duration::ZERO,
+            #[cfg(feature = "sandbox")]
+            sandbox_snapshots: Default::default(),
             clock: clock.clone(),
             config,
             sync_status,
@@ -506,6 +549,89 @@ impl Client {
         Ok(())
     }
 
+    /// Saves the pending transactions to the database, if enabled with
+    /// `transaction_pool.persist`.
+    pub fn persist_transaction_pool(&self) -> Result<(), Error> {
+        if !self.config.transaction_pool.persist {
+            return Ok(());
+        }
+        let _span = debug_span!(target: "client", "persist_transaction_pool").entered();
+        let count =
+            self.chain.chain_store().save_transaction_pool(self.sharded_tx_pool.transactions())?;
+        debug!(target: "client", count, "Persisted the transaction pool");
+        Ok(())
+    }
+
+    /// Inserts the transactions saved by `persist_transaction_pool` back into the pool.
+    /// Transactions which have expired, come from another fork or are no longer valid against
+    /// the current state (e.g. because of their nonce or the balance of the signer) are dropped.
+    pub fn restore_transaction_pool(&mut self) -> Result<(), Error> {
+        if !self.config.transaction_pool.persist {
+            return Ok(());
+        }
+        let transactions = self.chain.chain_store().load_transaction_pool()?;
+        if transactions.is_empty() {
+            return Ok(());
+        }
+        let head = self.chain.head()?;
+        let head_block = self.chain.get_head_block()?;
+        let gas_price = head_block.header().next_gas_price();
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+        let mut num_restored = 0;
+        for tx in &transactions {
+            if let Err(err) = self.chain.chain_store().check_transaction_validity_period(
+                head_block.header(),
+                tx.transaction.block_hash(),
+                self.chain.transaction_validity_period,
+            ) {
+                debug!(target: "client", tx_hash = ?tx.get_hash(), ?err, "Dropping persisted transaction");
+                continue;
+            }
+            let shard_id =
+                self.epoch_manager.account_id_to_shard_id(tx.transaction.signer_id(), &epoch_id)?;
+            let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
+            let Ok(chunk_extra) = self.chain.get_chunk_extra(&head.last_block_hash, &shard_uid)
+            else {
+                // The state of the shard isn't tracked, so the transaction can't be validated.
+                continue;
+            };
+            let receiver_shard = self
+                .epoch_manager
+                .account_id_to_shard_id(tx.transaction.receiver_id(), &epoch_id)?;
+            let receiver_congestion_info =
+                head_block.block_congestion_info().get(&receiver_shard).copied();
+            // The column may have been tampered with, so signatures are checked again.
+            let validation_result = self.runtime_adapter.validate_tx(
+                gas_price,
+                Some(*chunk_extra.state_root()),
+                tx,
+                true,
+                &epoch_id,
+                protocol_version,
+                receiver_congestion_info,
+            );
+            match validation_result {
+                Ok(None) => {}
+                Ok(Some(err)) => {
+                    debug!(target: "client", tx_hash = ?tx.get_hash(), ?err, "Dropping persisted transaction");
+                    continue;
+                }
+                Err(err) => {
+                    warn!(target: "client", tx_hash = ?tx.get_hash(), ?err, "Failed to validate persisted transaction");
+                    continue;
+                }
+            }
+            if self.sharded_tx_pool.insert_transaction(shard_uid, tx.clone())
+                == InsertTransactionResult::Success
+            {
+                num_restored += 1;
+            }
+        }
+        info!(target: "client", num_restored, num_persisted = transactions.len(), "Restored the transaction pool");
+        Ok(())
+    }
+
     /// Checks couple conditions whether Client can produce new block on height
     /// `height` on top of block with `prev_header`.
     /// Needed to skip several checks in case of adversarial controls enabled.
@@ -815,7 +941,7 @@ impl Client {
             block_merkle_root,
             self.clock.clone(),
             sandbox_delta_time,
-        );
+        )?;
 
         // Update latest known even before returning block out, to prevent race conditions.
         self.chain
@@ -1039,8 +1165,10 @@ impl Client {
     ) -> Result<PreparedTransactions, Error> {
         let Self { chain, sharded_tx_pool, runtime_adapter: runtime, .. } = self;
         let shard_id = shard_uid.shard_id as ShardId;
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_block.hash())?;
+        let shard_layout = self.epoch_manager.get_shard_layout(&epoch_id)?;
         let prepared_transactions = if let Some(mut iter) =
-            sharded_tx_pool.get_pool_iterator(shard_uid)
+            sharded_tx_pool.get_pool_iterator(shard_uid, &shard_layout)
         {
             let storage_config = RuntimeStorageConfig {
                 state_root: *chunk_extra.state_root(),
@@ -1048,7 +1176,6 @@ impl Client {
                 source: StorageDataSource::Db,
                 state_patch: Default::default(),
             };
-            let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_block.hash())?;
             let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
             let last_chunk_transactions_size =
                 if checked_feature!("stable", WitnessTransactionLimits, protocol_version) {
@@ -1092,7 +1219,13 @@ impl Client {
     ) {
         if let Some(validator_signer) = &signer {
             for body in challenges {
-                let challenge = Challenge::produce(body, &**validator_signer);
+                let challenge = match Challenge::produce(body, &**validator_signer) {
+                    Ok(challenge) => challenge,
+                    Err(err) => {
+                        warn!(target: "client", ?err, "Failed to sign challenge");
+                        continue;
+                    }
+                };
                 self.challenges.insert(challenge.hash, challenge.clone());
                 self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                     NetworkRequests::Challenge(challenge),
@@ -1325,24 +1458,24 @@ impl Client {
         // Send out challenge if the block was found to be invalid.
         if let Some(signer) = signer {
             if let Err(e) = &result {
-                match e {
+                let body = match e {
                     near_chain::Error::InvalidChunkProofs(chunk_proofs) => {
-                        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkProofs(*chunk_proofs.clone()),
-                                &*signer,
-                            )),
-                        ));
+                        Some(ChallengeBody::ChunkProofs(*chunk_proofs.clone()))
                     }
                     near_chain::Error::InvalidChunkState(chunk_state) => {
-                        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkState(*chunk_state.clone()),
-                                &*signer,
-                            )),
-                        ));
+                        Some(ChallengeBody::ChunkState(*chunk_state.clone()))
+                    }
+                    _ => None,
+                };
+                if let Some(body) = body {
+                    match Challenge::produce(body, &*signer) {
+                        Ok(challenge) => {
+                            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                                NetworkRequests::Challenge(challenge),
+                            ));
+                        }
+                        Err(err) => warn!(target: "client", ?err, "Failed to sign challenge"),
                     }
-                    _ => {}
                 }
             }
         }
@@ -1559,7 +1692,103 @@ impl Client {
                 )
             });
 
-        Duration::nanoseconds(ns)
+        Duration::nanoseconds(ns) + self.sandbox_time_delta
+    }
+
+    /// Makes the next produced block have the given timestamp, in nanoseconds
+    /// since the Unix epoch.  Timestamps of the blocks after it advance from
+    /// there.  A block can't be older than its previous block though so
+    /// timestamps in the past are only honoured as far as possible.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_set_block_timestamp(&mut self, timestamp: u64) -> Result<(), Error> {
+        self.sandbox_time_delta = Duration::ZERO;
+        let now = self.clock.now_utc().unix_timestamp_nanos()
+            + self.sandbox_delta_time().whole_nanoseconds();
+        let delta = i64::try_from(i128::from(timestamp) - now).map_err(|_| {
+            Error::Other(format!("timestamp {timestamp} is too far from the current time"))
+        })?;
+        self.sandbox_time_delta = Duration::nanoseconds(delta);
+        Ok(())
+    }
+
+    /// Makes the node accept transactions of the account without checking
+    /// their signature, access key and nonce.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_impersonate_account(&mut self, account_id: AccountId) {
+        let mut accounts = self.runtime_adapter.impersonated_accounts();
+        accounts.insert(account_id);
+        self.runtime_adapter.set_impersonated_accounts(accounts);
+    }
+
+    /// Stops impersonating the account, see `sandbox_impersonate_account`.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_stop_impersonating_account(&mut self, account_id: &AccountId) {
+        let mut accounts = self.runtime_adapter.impersonated_accounts();
+        accounts.remove(account_id);
+        self.runtime_adapter.set_impersonated_accounts(accounts);
+    }
+
+    /// Takes a snapshot of the chain, the state and the impersonated accounts
+    /// which the node can be reverted to with `sandbox_revert`, and returns
+    /// its id.
+    ///
+    /// The database is snapshotted with a checkpoint, which shares its files
+    /// with the database until they're compacted away.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_snapshot(&mut self) -> Result<u64, Error> {
+        let snapshot_id = self.sandbox_snapshots.keys().next_back().map_or(0, |id| id + 1);
+        let path =
+            sandbox_snapshots_dir(self.runtime_adapter.as_ref()).join(snapshot_id.to_string());
+        let store =
+            self.chain.chain_store().store().snapshot(&path).map_err(near_chain::Error::from)?;
+        self.sandbox_snapshots.insert(
+            snapshot_id,
+            SandboxSnapshot {
+                store,
+                accrued_fastforward_delta: self.accrued_fastforward_delta,
+                time_delta: self.sandbox_time_delta,
+                impersonated_accounts: self.runtime_adapter.impersonated_accounts(),
+            },
+        );
+        tracing::info!(target: "client", snapshot_id, "Took sandbox snapshot");
+        Ok(snapshot_id)
+    }
+
+    /// Reverts the chain head, the state, the epoch manager, flat storage,
+    /// memtries and the impersonated accounts to the snapshot with the given
+    /// id.  The snapshot is kept so that the node can be reverted to it again,
+    /// while the snapshots taken after it are deleted.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_revert(&mut self, snapshot_id: u64) -> Result<(), Error> {
+        let snapshot = self
+            .sandbox_snapshots
+            .get(&snapshot_id)
+            .ok_or_else(|| Error::Other(format!("no sandbox snapshot with id {snapshot_id}")))?;
+        self.chain.revert_to_store_snapshot(&snapshot.store)?;
+        self.accrued_fastforward_delta = snapshot.accrued_fastforward_delta;
+        self.sandbox_time_delta = snapshot.time_delta;
+        self.runtime_adapter.set_impersonated_accounts(snapshot.impersonated_accounts.clone());
+        // They snapshot a chain which doesn't exist anymore.
+        self.sandbox_snapshots.retain(|id, _| *id <= snapshot_id);
+
+        // Doomslug keeps track of the approvals for heights which may now be
+        // ahead of the head so it's started anew.
+        self.doomslug = Doomslug::new(
+            self.clock.clone(),
+            self.chain.chain_store().largest_target_height()?,
+            self.config.min_block_production_delay,
+            self.config.max_block_production_delay,
+            self.config.max_block_production_delay / 10,
+            self.config.max_block_wait_delay,
+            self.chain.doomslug_threshold_mode,
+        );
+        self.check_and_update_doomslug_tip()?;
+        self.shards_manager_adapter.send(ShardsManagerRequestFromClient::UpdateChainHeads {
+            head: self.chain.head()?,
+            header_head: self.chain.header_head()?,
+        });
+        tracing::info!(target: "client", snapshot_id, head = ?self.chain.head()?, "Reverted to sandbox snapshot");
+        Ok(())
     }
 
     pub fn send_approval(
@@ -2393,6 +2622,13 @@ impl Client {
                                 trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Transaction pool is full, trying to forward the transaction.");
                             }
                         }
+                        InsertTransactionResult::SignerQuotaExceeded => {
+                            if is_forwarded {
+                                trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Signer has too many transactions in the pool, dropping the transaction.");
+                            } else {
+                                trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Signer has too many transactions in the pool, trying to forward the transaction.");
+                            }
+                        }
                     }
                 }
 
@@ -47,8 +47,10 @@ use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::logic::get_shards_cares_about_this_or_next_epoch;
 use near_client_primitives::types::{
-    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, NetworkInfoResponse,
-    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
+    Error, GetClientConfig, GetClientConfigError, GetMempoolError, GetMempoolStats,
+    GetMempoolTransaction, GetMempoolTransactionResponse, GetMempoolTransactions,
+    GetMempoolTransactionsResponse, GetNetworkInfo, NetworkInfoResponse, StateSyncStatus, Status,
+    StatusError, StatusSyncInfo, SyncStatus,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::{EpochManagerAdapter, RngSeed};
@@ -66,12 +68,16 @@ use near_primitives::block::Tip;
 use near_primitives::block_header::ApprovalType;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
-use near_primitives::types::{BlockHeight, EpochId};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{BlockHeight, EpochId, ShardId};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
-use near_primitives::views::{DetailedDebugStatus, ValidatorInfo};
+use near_primitives::views::{
+    DetailedDebugStatus, MempoolShardStatsView, MempoolTransactionStatusView,
+    MempoolTransactionView, ValidatorInfo,
+};
 #[cfg(feature = "test_features")]
 use near_store::DBCol;
 use near_store::ShardUId;
@@ -252,6 +258,9 @@ pub struct ClientActorInner {
     // Last time when log_summary method was called.
     log_summary_timer_next_attempt: near_async::time::Utc,
 
+    /// Next time the transaction pool should be persisted.
+    persist_transaction_pool_next_attempt: near_async::time::Utc,
+
     doomslug_timer_next_attempt: near_async::time::Utc,
     sync_timer_next_attempt: near_async::time::Utc,
     sync_started: bool,
@@ -362,6 +371,8 @@ impl ClientActorInner {
             info_helper,
             block_production_next_attempt: now,
             log_summary_timer_next_attempt: now,
+            persist_transaction_pool_next_attempt: now
+                + client.config.transaction_pool.persist_period,
             doomslug_timer_next_attempt: now,
             sync_timer_next_attempt: now,
             sync_started: false,
@@ -679,6 +690,56 @@ impl Handler<near_client_primitives::types::SandboxMessage> for ClientActorInner
                     self.fastforward_delta == 0,
                 )
             }
+            near_client_primitives::types::SandboxMessage::SandboxSnapshot => {
+                match self.client.sandbox_snapshot() {
+                    Ok(snapshot_id) => {
+                        near_client_primitives::types::SandboxResponse::SandboxSnapshotCreated(
+                            snapshot_id,
+                        )
+                    }
+                    Err(err) => {
+                        near_client_primitives::types::SandboxResponse::SandboxSnapshotFailed(
+                            err.to_string(),
+                        )
+                    }
+                }
+            }
+            near_client_primitives::types::SandboxMessage::SandboxRevert(snapshot_id) => {
+                match self.client.sandbox_revert(snapshot_id) {
+                    Ok(()) => {
+                        // A fast forward in progress belongs to the reverted chain.
+                        self.fastforward_delta = 0;
+                        near_client_primitives::types::SandboxResponse::SandboxRevertFinished
+                    }
+                    Err(err) => near_client_primitives::types::SandboxResponse::SandboxRevertFailed(
+                        err.to_string(),
+                    ),
+                }
+            }
+            near_client_primitives::types::SandboxMessage::SandboxSetBlockTimestamp(timestamp) => {
+                match self.client.sandbox_set_block_timestamp(timestamp) {
+                    Ok(()) => near_client_primitives::types::SandboxResponse::SandboxNoResponse,
+                    Err(err) => {
+                        near_client_primitives::types::SandboxResponse::SandboxSetBlockTimestampFailed(
+                            err.to_string(),
+                        )
+                    }
+                }
+            }
+            near_client_primitives::types::SandboxMessage::SandboxImpersonateAccount(
+                account_id,
+            ) => {
+                tracing::info!(target: "client", %account_id, "Impersonating account");
+                self.client.sandbox_impersonate_account(account_id);
+                near_client_primitives::types::SandboxResponse::SandboxNoResponse
+            }
+            near_client_primitives::types::SandboxMessage::SandboxStopImpersonatingAccount(
+                account_id,
+            ) => {
+                tracing::info!(target: "client", %account_id, "Stopped impersonating account");
+                self.client.sandbox_stop_impersonating_account(&account_id);
+                near_client_primitives::types::SandboxResponse::SandboxNoResponse
+            }
         }
     }
 }
@@ -853,6 +914,20 @@ impl Handler<ApplyChunksDoneMessage> for ClientActorInner {
     }
 }
 
+/// Saves the pending transactions to the database.  Sent on shutdown, so that
+/// the transactions received since the last periodic save are not lost.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub struct PersistTransactionPool;
+
+impl Handler<PersistTransactionPool> for ClientActorInner {
+    fn handle(&mut self, _msg: PersistTransactionPool) {
+        if let Err(err) = self.client.persist_transaction_pool() {
+            error!(target: "client", ?err, "Failed to persist the transaction pool");
+        }
+    }
+}
+
 #[derive(Debug)]
 enum SyncRequirement {
     SyncNeeded { peer_id: PeerId, highest_height: BlockHeight, head: Tip },
@@ -908,6 +983,10 @@ impl ClientActorInner {
     pub fn start(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
         self.start_flat_storage_creation(ctx);
 
+        if let Err(err) = self.client.restore_transaction_pool() {
+            error!(target: "client", ?err, "Failed to restore the transaction pool");
+        }
+
         // Start syncing job.
         self.start_sync(ctx);
 
@@ -962,10 +1041,19 @@ impl ClientActorInner {
         // Check client is part of the futures validators
         if self.client.is_validator(&next_epoch_id, &prev_block_hash, validator_signer) {
             debug!(target: "client", "Sending announce account for {}", signer.validator_id());
+            let signature = match signer.sign_account_announce(
+                signer.validator_id(),
+                &self.node_id,
+                &next_epoch_id,
+            ) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    warn!(target: "client", ?err, "Failed to sign account announcement");
+                    return;
+                }
+            };
             self.last_validator_announce_time = Some(now);
 
-            let signature =
-                signer.sign_account_announce(signer.validator_id(), &self.node_id, &next_epoch_id);
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::AnnounceAccount(AnnounceAccount {
                     account_id: signer.validator_id().clone(),
@@ -1259,6 +1347,21 @@ impl ClientActorInner {
             "log_summary",
         );
         delay = core::cmp::min(delay, self.log_summary_timer_next_attempt - now);
+
+        if self.client.config.transaction_pool.persist {
+            self.persist_transaction_pool_next_attempt = self.run_timer(
+                self.client.config.transaction_pool.persist_period,
+                self.persist_transaction_pool_next_attempt,
+                ctx,
+                |act, _ctx| {
+                    if let Err(err) = act.client.persist_transaction_pool() {
+                        error!(target: "client", ?err, "Failed to persist the transaction pool");
+                    }
+                },
+                "persist_transaction_pool",
+            );
+            delay = core::cmp::min(delay, self.persist_transaction_pool_next_attempt - now);
+        }
         timer.observe_duration();
         delay
     }
@@ -2168,6 +2271,105 @@ impl Handler<GetClientConfig> for ClientActorInner {
     }
 }
 
+impl Handler<GetMempoolTransactions> for ClientActorInner {
+    fn handle(
+        &mut self,
+        msg: GetMempoolTransactions,
+    ) -> Result<GetMempoolTransactionsResponse, GetMempoolError> {
+        tracing::debug!(target: "client", ?msg);
+
+        let mut transactions: Vec<_> = self
+            .client
+            .sharded_tx_pool
+            .pools()
+            .filter(|(shard_uid, _)| msg.shard_id.map_or(true, |id| shard_uid.shard_id() == id))
+            .flat_map(|(shard_uid, pool)| {
+                pool.transactions().map(move |tx| (shard_uid.shard_id(), tx))
+            })
+            .filter(|(_, tx)| {
+                msg.signer_id.as_ref().map_or(true, |id| tx.transaction.signer_id() == id)
+                    && msg
+                        .receiver_id
+                        .as_ref()
+                        .map_or(true, |id| tx.transaction.receiver_id() == id)
+            })
+            .collect();
+        fn cmp(
+            (a_shard_id, a): &(ShardId, &SignedTransaction),
+            (b_shard_id, b): &(ShardId, &SignedTransaction),
+        ) -> std::cmp::Ordering {
+            (a_shard_id, a.transaction.signer_id(), a.transaction.nonce()).cmp(&(
+                b_shard_id,
+                b.transaction.signer_id(),
+                b.transaction.nonce(),
+            ))
+        }
+        // Only the returned transactions are sorted, the pool may be much larger.
+        let truncated = transactions.len() > msg.limit;
+        if truncated {
+            transactions.select_nth_unstable_by(msg.limit, cmp);
+            transactions.truncate(msg.limit);
+        }
+        transactions.sort_unstable_by(cmp);
+        let transactions = transactions
+            .into_iter()
+            .map(|(shard_id, tx)| MempoolTransactionView {
+                shard_id,
+                transaction: tx.clone().into(),
+            })
+            .collect();
+        Ok(GetMempoolTransactionsResponse { transactions, truncated })
+    }
+}
+
+impl Handler<GetMempoolTransaction> for ClientActorInner {
+    fn handle(
+        &mut self,
+        msg: GetMempoolTransaction,
+    ) -> Result<GetMempoolTransactionResponse, GetMempoolError> {
+        tracing::debug!(target: "client", ?msg);
+
+        let response = match self.client.sharded_tx_pool.get_transaction(&msg.tx_hash) {
+            Some((shard_uid, tx, num_pending_before)) => GetMempoolTransactionResponse {
+                status: MempoolTransactionStatusView::Pending {
+                    shard_id: shard_uid.shard_id(),
+                    num_pending_before: num_pending_before as u64,
+                },
+                transaction: Some(tx.clone().into()),
+            },
+            None => GetMempoolTransactionResponse {
+                status: MempoolTransactionStatusView::NotInPool,
+                transaction: None,
+            },
+        };
+        Ok(response)
+    }
+}
+
+impl Handler<GetMempoolStats> for ClientActorInner {
+    fn handle(
+        &mut self,
+        msg: GetMempoolStats,
+    ) -> Result<Vec<MempoolShardStatsView>, GetMempoolError> {
+        tracing::debug!(target: "client", ?msg);
+
+        let size_limit_bytes = self.client.sharded_tx_pool.pool_size_limit();
+        let mut stats: Vec<_> = self
+            .client
+            .sharded_tx_pool
+            .pools()
+            .map(|(shard_uid, pool)| MempoolShardStatsView {
+                shard_id: shard_uid.shard_id(),
+                num_transactions: pool.len() as u64,
+                size_bytes: pool.transaction_size(),
+                size_limit_bytes,
+            })
+            .collect();
+        stats.sort_by_key(|stats| stats.shard_id);
+        Ok(stats)
+    }
+}
+
 impl Handler<SyncMessage> for ClientActorInner {
     fn handle(&mut self, msg: SyncMessage) {
         tracing::debug!(target: "client", ?msg);
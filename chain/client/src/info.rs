@@ -571,10 +571,12 @@ impl InfoHelper {
         };
         // Sign telemetry if there is a signer present.
         if let Some(signer) = signer {
-            signer.sign_telemetry(&info)
-        } else {
-            serde_json::to_value(&info).expect("Telemetry must serialize to json")
+            match signer.sign_telemetry(&info) {
+                Ok(value) => return value,
+                Err(err) => tracing::warn!(target: "telemetry", ?err, "Failed to sign telemetry"),
+            }
         }
+        serde_json::to_value(&info).expect("Telemetry must serialize to json")
     }
 
     fn log_chain_processing_info(&mut self, client: &crate::Client, epoch_id: &EpochId) {
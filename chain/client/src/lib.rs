@@ -11,11 +11,13 @@ pub use near_client_primitives::types::{
 pub use crate::client::{Client, ProduceChunkResult};
 #[cfg(feature = "test_features")]
 pub use crate::client_actor::NetworkAdversarialMessage;
-pub use crate::client_actor::{start_client, ClientActor, StartClientResult};
+pub use crate::client_actor::{
+    start_client, ClientActor, PersistTransactionPool, StartClientResult,
+};
 pub use crate::config_updater::ConfigUpdater;
 pub use crate::stateless_validation::chunk_validator::orphan_witness_handling::HandleOrphanWitnessOutcome;
 pub use crate::sync::adapter::{SyncAdapter, SyncMessage};
-pub use crate::view_client_actor::{ViewClientActor, ViewClientActorInner};
+pub use crate::view_client_actor::{ColdStoreFallback, ViewClientActor, ViewClientActorInner};
 pub use near_chain::stateless_validation::processing_tracker::{
     ProcessingDoneTracker, ProcessingDoneWaiter,
 };
@@ -222,7 +222,13 @@ pub(crate) fn send_chunk_endorsement_to_block_producers(
         "send_chunk_endorsement",
     );
 
-    let endorsement = ChunkEndorsement::new(chunk_header.chunk_hash(), signer);
+    let endorsement = match ChunkEndorsement::new(chunk_header, signer) {
+        Ok(endorsement) => endorsement,
+        Err(err) => {
+            tracing::warn!(target: "client", ?chunk_hash, ?err, "Failed to sign chunk endorsement");
+            return;
+        }
+    };
     for block_producer in block_producers {
         if signer.validator_id() == &block_producer {
             // Our own endorsements are not always valid (see issue #11750).
@@ -178,7 +178,7 @@ impl PartialWitnessActor {
         let encoder = self.encoders.entry(chunk_validators.len(), protocol_version);
         let (parts, encoded_length) = encoder.encode(&witness_bytes);
 
-        Ok(chunk_validators
+        chunk_validators
             .iter()
             .zip_eq(parts)
             .enumerate()
@@ -192,10 +192,11 @@ impl PartialWitnessActor {
                     part.unwrap().to_vec(),
                     encoded_length,
                     signer,
-                );
-                (chunk_validator.clone(), partial_witness)
+                )
+                .map_err(|err| Error::Other(format!("Failed to sign state witness part: {err}")))?;
+                Ok((chunk_validator.clone(), partial_witness))
             })
-            .collect_vec())
+            .collect()
     }
 
     // Break the state witness into parts and send each part to the corresponding chunk validator owner.
@@ -792,6 +792,7 @@ mod test {
                                 this_height,
                                 signer.as_ref(),
                             )
+                            .unwrap()
                             .signature,
                         ))
                     })
@@ -807,7 +808,8 @@ mod test {
                 block_merkle_tree.root(),
                 clock.clock(),
                 None,
-            );
+            )
+            .unwrap();
             block_merkle_tree.insert(*block.hash());
             chain2.process_block_header(block.header(), &mut Vec::new()).unwrap(); // just to validate
             process_block_sync(
@@ -147,6 +147,7 @@ pub fn setup(
         network_adapter.clone(),
         config.clone(),
         adv.clone(),
+        None,
     );
 
     let state_sync_adapter = Arc::new(RwLock::new(SyncAdapter::new(
@@ -263,6 +264,8 @@ pub fn setup_only_view(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
+            shard_scheduler: Default::default(),
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -298,6 +301,7 @@ pub fn setup_only_view(
         network_adapter,
         config,
         adv,
+        None,
     )
 }
 
@@ -1059,6 +1063,8 @@ pub fn setup_synchronous_shards_manager(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
+            shard_scheduler: Default::default(),
         }, // irrelevant
         None,
         Arc::new(RayonAsyncComputationSpawner),
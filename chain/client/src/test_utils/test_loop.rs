@@ -94,6 +94,9 @@ where
                 account_id: account_id.clone(),
                 method_name: method.to_string(),
                 args: args.to_vec().into(),
+                include_gas_usage: false,
+                include_function_profile: false,
+                state_overrides: vec![],
             },
         );
         if let QueryResponseKind::CallResult(call_result) = response.kind {
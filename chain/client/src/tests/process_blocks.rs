@@ -87,7 +87,8 @@ fn test_bad_shard_id() {
         chunk.prev_validator_proposals().collect(),
         congestion_info,
         &validator_signer,
-    );
+    )
+    .unwrap();
     modified_chunk.height_included = 2;
     chunks[0] = ShardChunkHeader::V3(modified_chunk);
     block.mut_header().get_mut().inner_rest.chunk_headers_root =
@@ -230,7 +231,8 @@ fn test_bad_congestion_info_impl(mode: BadCongestionInfoMode) {
         chunk.prev_validator_proposals().collect(),
         Some(congestion_info),
         &validator_signer,
-    );
+    )
+    .unwrap();
     modified_chunk_header.height_included = 2;
 
     let modified_chunk = ShardChunkHeader::V3(modified_chunk_header);
@@ -16,14 +16,16 @@ use near_chain::{
 use near_chain_configs::{ClientConfig, MutableValidatorSigner, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
-    Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
+    Error, GetAccountActivity, GetAccountActivityError, GetAccountActivityResponse, GetBlock,
+    GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
     GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
     GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
     GetMaintenanceWindowsError, GetNextLightClientBlockError, GetProtocolConfig,
     GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
     GetSplitStorageInfoError, GetStateChangesError, GetStateChangesWithCauseInBlock,
     GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
-    TxStatus, TxStatusError,
+    SimulateTransaction, SimulateTransactionError, SimulateTransactionResponse, TxStatus,
+    TxStatusError,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
@@ -46,19 +48,19 @@ use near_primitives::sharding::ShardChunk;
 use near_primitives::state_sync::{
     ShardStateSyncResponse, ShardStateSyncResponseHeader, ShardStateSyncResponseV3,
 };
-use near_primitives::transaction::SignedTransaction;
+use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, SignedTransaction};
 use near_primitives::types::{
     AccountId, BlockHeight, BlockId, BlockReference, EpochReference, Finality, MaybeBlockId,
-    ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
+    ShardId, StateChanges, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
 };
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
-    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView,
-    SignedTransactionView, SplitStorageInfoView, StateChangesKindsView, StateChangesView,
-    TxExecutionStatus, TxStatusView,
+    AccountActivityView, BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
+    ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum,
+    FinalExecutionStatus, GasPriceView, LightClientBlockView, MaintenanceWindowsView, QueryRequest,
+    QueryResponse, ReceiptView, SignedTransactionView, SplitStorageInfoView, StateChangesKindsView,
+    StateChangesView, TxExecutionStatus, TxStatusView,
 };
 use near_store::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::{DBCol, COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY};
@@ -86,6 +88,24 @@ pub struct ViewClientRequestManager {
 
 pub type ViewClientActor = SyncActixWrapper<ViewClientActorInner>;
 
+/// Epoch manager, shard tracker and runtime reading from both the hot and the
+/// cold storage of an archival node.  The view client falls back to them for
+/// queries about blocks whose state was garbage collected from the hot storage.
+#[derive(Clone)]
+pub struct ColdStoreFallback {
+    pub epoch_manager: Arc<dyn EpochManagerAdapter>,
+    pub shard_tracker: ShardTracker,
+    pub runtime: Arc<dyn RuntimeAdapter>,
+}
+
+/// Chain and runtime reading from both the hot and the cold storage, see
+/// `ColdStoreFallback`.
+struct ColdStoreView {
+    chain: Chain,
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    runtime: Arc<dyn RuntimeAdapter>,
+}
+
 /// View client provides currently committed (to the storage) view of the current chain and state.
 pub struct ViewClientActorInner {
     clock: Clock,
@@ -103,6 +123,9 @@ pub struct ViewClientActorInner {
     pub config: ClientConfig,
     request_manager: Arc<RwLock<ViewClientRequestManager>>,
     state_request_cache: Arc<Mutex<VecDeque<Instant>>>,
+    /// Set on archival nodes with a cold storage whose view client otherwise
+    /// reads from the hot storage only.
+    cold_store_view: Option<ColdStoreView>,
 }
 
 impl ViewClientRequestManager {
@@ -128,6 +151,7 @@ impl ViewClientActorInner {
         network_adapter: PeerManagerAdapter,
         config: ClientConfig,
         adv: crate::adversarial::Controls,
+        cold_store_fallback: Option<ColdStoreFallback>,
     ) -> Addr<ViewClientActor> {
         SyncArbiter::start(config.view_client_threads, move || {
             // TODO: should we create shared ChainStore that is passed to both Client and ViewClient?
@@ -141,6 +165,20 @@ impl ViewClientActorInner {
                 config.save_trie_changes,
             )
             .unwrap();
+            let cold_store_view = cold_store_fallback.as_ref().map(|fallback| ColdStoreView {
+                chain: Chain::new_for_view_client(
+                    clock.clone(),
+                    fallback.epoch_manager.clone(),
+                    fallback.shard_tracker.clone(),
+                    fallback.runtime.clone(),
+                    &chain_genesis,
+                    DoomslugThresholdMode::TwoThirds,
+                    config.save_trie_changes,
+                )
+                .unwrap(),
+                epoch_manager: fallback.epoch_manager.clone(),
+                runtime: fallback.runtime.clone(),
+            });
 
             let view_client_actor = ViewClientActorInner {
                 clock: clock.clone(),
@@ -154,6 +192,7 @@ impl ViewClientActorInner {
                 config: config.clone(),
                 request_manager: Arc::new(RwLock::new(ViewClientRequestManager::new())),
                 state_request_cache: Arc::new(Mutex::new(VecDeque::default())),
+                cold_store_view,
             };
             SyncActixWrapper::new(view_client_actor)
         })
@@ -316,7 +355,23 @@ impl ViewClientActorInner {
     }
 
     fn handle_query(&mut self, msg: Query) -> Result<QueryResponse, QueryError> {
-        let header = self.get_block_header_by_reference(&msg.block_reference);
+        let mut header = self.get_block_header_by_reference(&msg.block_reference);
+        // The block may be known only to the cold storage if it was garbage
+        // collected from the hot storage.
+        if let (
+            Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)),
+            Some(cold_store_view),
+            BlockReference::BlockId(block_id),
+        ) = (&header, &self.cold_store_view, &msg.block_reference)
+        {
+            header = match block_id {
+                BlockId::Height(height) => {
+                    cold_store_view.chain.get_block_header_by_height(*height)
+                }
+                BlockId::Hash(hash) => cold_store_view.chain.get_block_header(hash),
+            }
+            .map(Some);
+        }
         let header = match header {
             Ok(Some(header)) => Ok(header),
             Ok(None) => Err(QueryError::NoSyncedBlocks),
@@ -329,103 +384,106 @@ impl ViewClientActorInner {
             Err(err) => Err(QueryError::Unreachable { error_message: err.to_string() }),
         }?;
 
-        let account_id = match &msg.request {
-            QueryRequest::ViewAccount { account_id, .. } => account_id,
-            QueryRequest::ViewState { account_id, .. } => account_id,
-            QueryRequest::ViewAccessKey { account_id, .. } => account_id,
-            QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
-            QueryRequest::CallFunction { account_id, .. } => account_id,
-            QueryRequest::ViewCode { account_id, .. } => account_id,
+        let result = query_at_block(
+            &self.chain,
+            self.epoch_manager.as_ref(),
+            self.runtime.as_ref(),
+            &header,
+            &msg.request,
+        );
+        match (result, &self.cold_store_view) {
+            (Err(QueryError::GarbageCollectedBlock { .. }), Some(cold_store_view)) => {
+                query_at_block(
+                    &cold_store_view.chain,
+                    cold_store_view.epoch_manager.as_ref(),
+                    cold_store_view.runtime.as_ref(),
+                    &header,
+                    &msg.request,
+                )
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn simulate_transaction(
+        &self,
+        msg: SimulateTransaction,
+    ) -> Result<SimulateTransactionResponse, SimulateTransactionError> {
+        let header = match self.chain.final_head() {
+            Ok(final_head) => self.chain.get_block_header(&final_head.last_block_hash)?,
+            Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => {
+                return Err(SimulateTransactionError::NoSyncedBlocks)
+            }
+            Err(err) => return Err(err.into()),
         };
-        let shard_id = self
-            .epoch_manager
-            .account_id_to_shard_id(account_id, header.epoch_id())
-            .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
-        let shard_uid = self
-            .epoch_manager
-            .shard_id_to_uid(shard_id, header.epoch_id())
-            .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
-
-        let tip = self.chain.head();
-        let chunk_extra =
-            self.chain.get_chunk_extra(header.hash(), &shard_uid).map_err(|err| match err {
-                near_chain::near_chain_primitives::Error::DBNotFoundErr(_) => match tip {
-                    Ok(tip) => {
-                        let gc_stop_height = self.runtime.get_gc_stop_height(&tip.last_block_hash);
-                        if !self.config.archive && header.height() < gc_stop_height {
-                            QueryError::GarbageCollectedBlock {
-                                block_height: header.height(),
-                                block_hash: *header.hash(),
-                            }
-                        } else {
-                            QueryError::UnavailableShard { requested_shard_id: shard_id }
+        let epoch_id = header.epoch_id();
+        let shard_layout = self.epoch_manager.get_shard_layout(epoch_id).into_chain_error()?;
+        // Receipts of the transaction may end up on any shard, so the
+        // simulation needs the state of all of them.
+        let validator_signer = self.validator.get();
+        let untracked_shard_ids: Vec<_> = shard_layout
+            .shard_ids()
+            .filter(|shard_id| {
+                !self.shard_tracker.care_about_shard(
+                    validator_signer.as_ref().map(|v| v.validator_id()),
+                    header.prev_hash(),
+                    *shard_id,
+                    true,
+                )
+            })
+            .collect();
+        if !untracked_shard_ids.is_empty() {
+            return Err(SimulateTransactionError::UntrackedShards { untracked_shard_ids });
+        }
+        let mut state_roots = HashMap::new();
+        for shard_uid in shard_layout.shard_uids() {
+            let chunk_extra =
+                self.chain.get_chunk_extra(header.hash(), &shard_uid).map_err(|err| match err {
+                    near_chain::near_chain_primitives::Error::DBNotFoundErr(_) => {
+                        SimulateTransactionError::UnavailableShard {
+                            requested_shard_id: shard_uid.shard_id(),
                         }
                     }
-                    Err(err) => QueryError::InternalError { error_message: err.to_string() },
-                },
-                near_chain::near_chain_primitives::Error::IOErr(error) => {
-                    QueryError::InternalError { error_message: error.to_string() }
-                }
-                _ => QueryError::Unreachable { error_message: err.to_string() },
-            })?;
-
-        let state_root = chunk_extra.state_root();
-        match self.runtime.query(
-            shard_uid,
-            state_root,
-            header.height(),
-            header.raw_timestamp(),
-            header.prev_hash(),
-            header.hash(),
-            header.epoch_id(),
-            &msg.request,
-        ) {
-            Ok(query_response) => Ok(query_response),
-            Err(query_error) => Err(match query_error {
-                near_chain::near_chain_primitives::error::QueryError::InternalError {
-                    error_message,
-                    ..
-                } => QueryError::InternalError { error_message },
-                near_chain::near_chain_primitives::error::QueryError::InvalidAccount {
-                    requested_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::InvalidAccount { requested_account_id, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::UnknownAccount {
-                    requested_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::UnknownAccount { requested_account_id, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::NoContractCode {
-                    contract_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::NoContractCode { contract_account_id, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::UnknownAccessKey {
-                    public_key,
-                    block_height,
-                    block_hash,
-                } => QueryError::UnknownAccessKey { public_key, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::ContractExecutionError {
-                    error_message,
-                    block_hash,
-                    block_height,
-                } => QueryError::ContractExecutionError {
-                    vm_error: error_message,
-                    block_height,
-                    block_hash,
-                },
-                near_chain::near_chain_primitives::error::QueryError::TooLargeContractState {
-                    requested_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::TooLargeContractState {
-                    contract_account_id: requested_account_id,
-                    block_height,
-                    block_hash,
-                },
-            }),
+                    err => err.into(),
+                })?;
+            state_roots.insert(shard_uid, *chunk_extra.state_root());
         }
+
+        let signed_transaction = msg.signed_transaction;
+        let result = self
+            .runtime
+            .simulate_transaction(&header, &state_roots, &signed_transaction, msg.verify_signature)?
+            .map_err(SimulateTransactionError::InvalidTransaction)?;
+
+        let into_view = |outcome_with_id| {
+            ExecutionOutcomeWithIdView::from(ExecutionOutcomeWithIdAndProof {
+                proof: vec![],
+                block_hash: *header.hash(),
+                outcome_with_id,
+            })
+        };
+        let transaction_outcome = into_view(result.transaction_outcome);
+        let receipts_outcome: Vec<_> = result.receipts_outcome.into_iter().map(into_view).collect();
+        let mut outcomes = vec![transaction_outcome.clone()];
+        outcomes.extend(receipts_outcome.iter().cloned());
+        let status = self.chain.get_execution_status(&outcomes, &signed_transaction.get_hash());
+
+        let state_changes = StateChanges::from_changes(
+            result.state_changes.into_iter().flat_map(|(_, changes)| changes).map(Ok),
+        )
+        .map_err(|err| SimulateTransactionError::IOError(err.to_string()))?;
+        Ok(SimulateTransactionResponse {
+            block_height: header.height(),
+            block_hash: *header.hash(),
+            final_execution_outcome: FinalExecutionOutcomeView {
+                status,
+                transaction: signed_transaction.into(),
+                transaction_outcome,
+                receipts_outcome,
+            },
+            pending_receipts: result.pending_receipts.into_iter().map(Into::into).collect(),
+            state_changes: state_changes.into_iter().map(Into::into).collect(),
+        })
     }
 
     // Return the lowest status the node can proof
@@ -677,6 +735,120 @@ impl ViewClientActorInner {
     }
 }
 
+/// Answers the query about the state at the given block, reading the chunk
+/// extra and the state through `chain` and `runtime`.
+fn query_at_block(
+    chain: &Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+    header: &BlockHeader,
+    request: &QueryRequest,
+) -> Result<QueryResponse, QueryError> {
+    let account_id = match request {
+        QueryRequest::ViewAccount { account_id, .. } => account_id,
+        QueryRequest::ViewState { account_id, .. } => account_id,
+        QueryRequest::ViewAccessKey { account_id, .. } => account_id,
+        QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
+        QueryRequest::CallFunction { account_id, .. } => account_id,
+        QueryRequest::ViewCode { account_id, .. } => account_id,
+    };
+    let shard_id = epoch_manager
+        .account_id_to_shard_id(account_id, header.epoch_id())
+        .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+    let shard_uid = epoch_manager
+        .shard_id_to_uid(shard_id, header.epoch_id())
+        .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+
+    let chunk_extra = match chain.get_chunk_extra(header.hash(), &shard_uid) {
+        Ok(chunk_extra) => chunk_extra,
+        Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => {
+            let tip = chain
+                .head()
+                .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+            // The chunk extra of a block is garbage collected together with the
+            // block itself, so a missing block below the GC stop height means
+            // its state is gone from the storage `chain` reads from.  Archival
+            // nodes find both in the cold storage then.
+            let gc_stop_height = runtime.get_gc_stop_height(&tip.last_block_hash);
+            let block_exists = chain
+                .block_exists(header.hash())
+                .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+            return Err(if header.height() < gc_stop_height && !block_exists {
+                QueryError::GarbageCollectedBlock {
+                    block_height: header.height(),
+                    block_hash: *header.hash(),
+                }
+            } else {
+                QueryError::UnavailableShard { requested_shard_id: shard_id }
+            });
+        }
+        Err(near_chain::near_chain_primitives::Error::IOErr(error)) => {
+            return Err(QueryError::InternalError { error_message: error.to_string() })
+        }
+        Err(err) => return Err(QueryError::Unreachable { error_message: err.to_string() }),
+    };
+
+    let state_root = chunk_extra.state_root();
+    match runtime.query(
+        shard_uid,
+        state_root,
+        header.height(),
+        header.raw_timestamp(),
+        header.prev_hash(),
+        header.hash(),
+        header.epoch_id(),
+        request,
+    ) {
+        Ok(query_response) => Ok(query_response),
+        Err(query_error) => Err(match query_error {
+            near_chain::near_chain_primitives::error::QueryError::InternalError {
+                error_message,
+                ..
+            } => QueryError::InternalError { error_message },
+            near_chain::near_chain_primitives::error::QueryError::InvalidAccount {
+                requested_account_id,
+                block_height,
+                block_hash,
+            } => QueryError::InvalidAccount { requested_account_id, block_height, block_hash },
+            near_chain::near_chain_primitives::error::QueryError::UnknownAccount {
+                requested_account_id,
+                block_height,
+                block_hash,
+            } => QueryError::UnknownAccount { requested_account_id, block_height, block_hash },
+            near_chain::near_chain_primitives::error::QueryError::NoContractCode {
+                contract_account_id,
+                block_height,
+                block_hash,
+            } => QueryError::NoContractCode { contract_account_id, block_height, block_hash },
+            near_chain::near_chain_primitives::error::QueryError::UnknownAccessKey {
+                public_key,
+                block_height,
+                block_hash,
+            } => QueryError::UnknownAccessKey { public_key, block_height, block_hash },
+            near_chain::near_chain_primitives::error::QueryError::ContractExecutionError {
+                error_message,
+                gas_usage,
+                block_hash,
+                block_height,
+            } => QueryError::ContractExecutionError {
+                vm_error: error_message,
+                gas_usage,
+                block_height,
+                block_hash,
+            },
+            near_chain::near_chain_primitives::error::QueryError::TooLargeContractState {
+                requested_account_id,
+                block_height,
+                block_hash,
+            } => QueryError::TooLargeContractState {
+                contract_account_id: requested_account_id,
+                block_height,
+                block_hash,
+            },
+        }),
+    }
+}
+
 impl Handler<Query> for ViewClientActorInner {
     #[perf]
     fn handle(&mut self, msg: Query) -> Result<QueryResponse, QueryError> {
@@ -776,6 +948,20 @@ impl Handler<GetChunk> for ViewClientActorInner {
     }
 }
 
+impl Handler<SimulateTransaction> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: SimulateTransaction,
+    ) -> Result<SimulateTransactionResponse, SimulateTransactionError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["SimulateTransaction"])
+            .start_timer();
+        self.simulate_transaction(msg)
+    }
+}
+
 impl Handler<TxStatus> for ViewClientActorInner {
     #[perf]
     fn handle(&mut self, msg: TxStatus) -> Result<TxStatusView, TxStatusError> {
@@ -1123,6 +1309,40 @@ impl Handler<GetReceipt> for ViewClientActorInner {
     }
 }
 
+impl Handler<GetAccountActivity> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetAccountActivity,
+    ) -> Result<GetAccountActivityResponse, GetAccountActivityError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetAccountActivity"])
+            .start_timer();
+        if !self.config.save_account_activity {
+            return Err(GetAccountActivityError::NotEnabled);
+        }
+        // Fetch one more record to find out where the next page starts.
+        let mut records = self.chain.chain_store().get_account_activity(
+            &msg.account_id,
+            msg.from,
+            msg.limit.saturating_add(1),
+        )?;
+        let next = if records.len() > msg.limit {
+            records.pop().map(|(position, _)| position)
+        } else {
+            None
+        };
+        let activity = records
+            .into_iter()
+            .map(|((block_height, _), activity)| {
+                AccountActivityView::from_activity(activity, block_height)
+            })
+            .collect();
+        Ok(GetAccountActivityResponse { activity, next })
+    }
+}
+
 impl Handler<GetBlockProof> for ViewClientActorInner {
     #[perf]
     fn handle(&mut self, msg: GetBlockProof) -> Result<GetBlockProofResponse, GetBlockProofError> {
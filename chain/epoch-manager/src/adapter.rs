@@ -475,6 +475,10 @@ pub trait EpochManagerAdapter: Send + Sync {
 
     #[cfg(feature = "new_epoch_sync")]
     fn force_update_aggregator(&self, epoch_id: &EpochId, hash: &CryptoHash);
+
+    /// Reloads the state the epoch manager keeps in memory from the store,
+    /// see `EpochManager::reload_from_store`.
+    fn reload_from_store(&self) -> Result<(), EpochError>;
 }
 
 impl EpochManagerAdapter for EpochManagerHandle {
@@ -1145,6 +1149,11 @@ impl EpochManagerAdapter for EpochManagerHandle {
         epoch_manager.epoch_info_aggregator = EpochInfoAggregator::new(*epoch_id, *hash);
     }
 
+    fn reload_from_store(&self) -> Result<(), EpochError> {
+        let mut epoch_manager = self.write();
+        epoch_manager.reload_from_store()
+    }
+
     /// Returns the set of chunk validators for a given epoch
     fn get_epoch_all_validators(
         &self,
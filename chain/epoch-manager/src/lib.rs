@@ -1890,6 +1890,19 @@ impl EpochManager {
         Ok(())
     }
 
+    /// Reloads the epoch info aggregator from the store and resets the
+    /// largest final height, as if the epoch manager had just been created.
+    ///
+    /// This must be called when the store is reverted to an earlier state,
+    /// which leaves the aggregator ahead of the chain.  The cached epoch and
+    /// block infos are kept since they're keyed by hash.
+    pub fn reload_from_store(&mut self) -> Result<(), EpochError> {
+        self.epoch_info_aggregator =
+            self.store.get_ser(DBCol::EpochInfo, AGGREGATOR_KEY)?.unwrap_or_default();
+        self.largest_final_height = 0;
+        Ok(())
+    }
+
     /// Returns epoch info aggregate with state up to `last_block_hash`.
     ///
     /// The block hash passed as argument should be the latest block belonging
@@ -1331,6 +1331,39 @@ fn test_epoch_info_aggregator_data_loss() {
     );
 }
 
+/// After the store is reverted, the aggregator is reloaded from it and picks up
+/// the final blocks of the reverted chain.
+#[test]
+fn test_epoch_info_aggregator_reload_from_store() {
+    let stake_amount = 1_000_000;
+    let validators =
+        vec![("test1".parse().unwrap(), stake_amount), ("test2".parse().unwrap(), stake_amount)];
+    let epoch_length = 10;
+    let mut em =
+        setup_epoch_manager(validators, epoch_length, 1, 2, 10, 10, 0, default_reward_calculator());
+    let h = hash_range(6);
+    record_block(&mut em, Default::default(), h[0], 0, vec![]);
+    record_block_with_final_block_hash(&mut em, h[0], h[1], h[0], 1, vec![]);
+    record_block_with_final_block_hash(&mut em, h[1], h[3], h[0], 3, vec![]);
+    assert_eq!(h[0], em.epoch_info_aggregator.last_block_hash);
+    // The aggregator is the only thing the epoch manager reloads.
+    let saved_aggregator = em.store.get(DBCol::EpochInfo, AGGREGATOR_KEY).unwrap().unwrap();
+
+    record_block_with_final_block_hash(&mut em, h[3], h[5], h[1], 5, vec![]);
+    assert_eq!(h[1], em.epoch_info_aggregator.last_block_hash);
+    assert_eq!(em.largest_final_height, 3);
+
+    let mut store_update = em.store.store_update();
+    store_update.set(DBCol::EpochInfo, AGGREGATOR_KEY, &saved_aggregator);
+    store_update.commit().unwrap();
+    em.reload_from_store().unwrap();
+    assert_eq!(h[0], em.epoch_info_aggregator.last_block_hash);
+    assert_eq!(em.largest_final_height, 0);
+    // The final height of the new block is below the one before the revert.
+    record_block_with_final_block_hash(&mut em, h[3], h[4], h[1], 4, vec![]);
+    assert_eq!(h[1], em.epoch_info_aggregator.last_block_hash);
+}
+
 /// Aggregator should still work even if there is a reorg past the last final block.
 #[test]
 fn test_epoch_info_aggregator_reorg_past_final_block() {
@@ -2764,24 +2797,27 @@ fn test_chunk_header(h: &[CryptoHash], signer: &ValidatorSigner) -> ShardChunkHe
     let congestion_info = ProtocolFeature::CongestionControl
         .enabled(PROTOCOL_VERSION)
         .then_some(CongestionInfo::default());
-    ShardChunkHeader::V3(ShardChunkHeaderV3::new(
-        PROTOCOL_VERSION,
-        h[0],
-        h[2],
-        h[2],
-        h[2],
-        0,
-        1,
-        0,
-        0,
-        0,
-        0,
-        h[2],
-        h[2],
-        vec![],
-        congestion_info,
-        signer,
-    ))
+    ShardChunkHeader::V3(
+        ShardChunkHeaderV3::new(
+            PROTOCOL_VERSION,
+            h[0],
+            h[2],
+            h[2],
+            h[2],
+            0,
+            1,
+            0,
+            0,
+            0,
+            0,
+            h[2],
+            h[2],
+            vec![],
+            congestion_info,
+            signer,
+        )
+        .unwrap(),
+    )
 }
 
 #[test]
@@ -2826,7 +2862,7 @@ fn test_verify_chunk_endorsements() {
     let chunk_header = test_chunk_header(&h, signer.as_ref());
 
     // check chunk endorsement validity
-    let mut chunk_endorsement = ChunkEndorsement::new(chunk_header.chunk_hash(), signer.as_ref());
+    let mut chunk_endorsement = ChunkEndorsement::new(&chunk_header, signer.as_ref()).unwrap();
     assert!(epoch_manager.verify_chunk_endorsement(&chunk_header, &chunk_endorsement).unwrap());
 
     // check invalid chunk endorsement signature
@@ -2834,7 +2870,8 @@ fn test_verify_chunk_endorsements() {
     assert!(!epoch_manager.verify_chunk_endorsement(&chunk_header, &chunk_endorsement).unwrap());
 
     // check chunk endorsement invalidity when chunk header and chunk endorsement don't match
-    let chunk_endorsement = ChunkEndorsement::new(h[3].into(), signer.as_ref());
+    let other_chunk_header = test_chunk_header(&[h[0], h[1], h[3]], signer.as_ref());
+    let chunk_endorsement = ChunkEndorsement::new(&other_chunk_header, signer.as_ref()).unwrap();
     let err =
         epoch_manager.verify_chunk_endorsement(&chunk_header, &chunk_endorsement).unwrap_err();
     match err {
@@ -2844,7 +2881,7 @@ fn test_verify_chunk_endorsements() {
 
     // check chunk endorsement invalidity when signer is not chunk validator
     let bad_signer = Arc::new(create_test_signer("test2"));
-    let chunk_endorsement = ChunkEndorsement::new(chunk_header.chunk_hash(), bad_signer.as_ref());
+    let chunk_endorsement = ChunkEndorsement::new(&chunk_header, bad_signer.as_ref()).unwrap();
     let err =
         epoch_manager.verify_chunk_endorsement(&chunk_header, &chunk_endorsement).unwrap_err();
     match err {
@@ -2892,7 +2929,8 @@ fn test_verify_partial_witness_signature() {
         "witness".bytes().collect(),
         7,
         signer.as_ref(),
-    );
+    )
+    .unwrap();
     assert!(epoch_manager.verify_partial_witness_signature(&partial_witness).unwrap());
 
     // Check invalid chunk state witness signature.
@@ -2908,7 +2946,8 @@ fn test_verify_partial_witness_signature() {
         "witness".bytes().collect(),
         7,
         bad_signer.as_ref(),
-    );
+    )
+    .unwrap();
     assert!(!epoch_manager.verify_partial_witness_signature(&bad_partial_witness).unwrap());
 }
 
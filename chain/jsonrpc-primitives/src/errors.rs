@@ -35,6 +35,7 @@ pub enum RpcErrorKind {
 pub enum RpcRequestValidationErrorKind {
     MethodNotFound { method_name: String },
     ParseError { error_message: String },
+    InvalidRequest { error_message: String },
 }
 
 /// A general Server Error
@@ -137,6 +138,29 @@ impl RpcError {
         }
     }
 
+    /// Create an invalid request error.
+    pub fn invalid_request(e: String) -> Self {
+        RpcError {
+            code: -32_600,
+            message: "Invalid Request".to_owned(),
+            data: Some(Value::String(e.clone())),
+            error_struct: Some(RpcErrorKind::RequestValidationError(
+                RpcRequestValidationErrorKind::InvalidRequest { error_message: e },
+            )),
+        }
+    }
+
+    /// Create an error for a request rejected by the server's rate limits.
+    pub fn rate_limit_exceeded(method: String) -> Self {
+        Self::new_handler_error(
+            Some(Value::String(format!("Rate limit exceeded for method {}", method))),
+            serde_json::json!({
+                "name": "RATE_LIMIT_EXCEEDED",
+                "info": serde_json::json!({"method_name": method})
+            }),
+        )
+    }
+
     /// Create a method not found error.
     pub fn method_not_found(method: String) -> Self {
         RpcError {
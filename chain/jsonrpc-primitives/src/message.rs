@@ -142,6 +142,14 @@ pub struct Notification {
     pub params: Value,
 }
 
+impl Notification {
+    /// Converts the notification into a request without an ID, for servers
+    /// which process both the same way.
+    pub fn into_request(self) -> Request {
+        Request { jsonrpc: self.jsonrpc, method: self.method, params: self.params, id: Value::Null }
+    }
+}
+
 /// One message of the JSON RPC protocol.
 ///
 /// One message, directly mapped from the structures of the protocol. See the
@@ -0,0 +1,84 @@
+use near_primitives::types::{AccountId, BlockHeight};
+
+/// Number of records returned when the request doesn't specify a limit.
+pub const DEFAULT_ACCOUNT_ACTIVITY_LIMIT: u32 = 100;
+/// Maximum number of records returned in a single response.
+pub const MAX_ACCOUNT_ACTIVITY_LIMIT: u32 = 1000;
+
+/// Position of a record in the activity of an account.  Serialized as an
+/// opaque string which clients pass back to fetch the next page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AccountActivityCursor {
+    pub block_height: BlockHeight,
+    pub index: u32,
+}
+
+impl From<AccountActivityCursor> for String {
+    fn from(cursor: AccountActivityCursor) -> Self {
+        format!("{}-{}", cursor.block_height, cursor.index)
+    }
+}
+
+impl TryFrom<String> for AccountActivityCursor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("Invalid account activity cursor: {}", value);
+        let (block_height, index) = value.split_once('-').ok_or_else(invalid)?;
+        Ok(Self {
+            block_height: block_height.parse().map_err(|_| invalid())?,
+            index: index.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcAccountActivityRequest {
+    pub account_id: AccountId,
+    /// Cursor returned by the previous call.  Takes precedence over
+    /// `from_block_height`.
+    #[serde(default)]
+    pub cursor: Option<AccountActivityCursor>,
+    /// Lists the activity starting at this height.  By default the activity
+    /// is listed from the genesis.
+    #[serde(default)]
+    pub from_block_height: Option<BlockHeight>,
+    /// Defaults to [`DEFAULT_ACCOUNT_ACTIVITY_LIMIT`] and is capped at
+    /// [`MAX_ACCOUNT_ACTIVITY_LIMIT`].
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcAccountActivityResponse {
+    /// Transactions and receipts in the order they have been included in the
+    /// chain.
+    pub activity: Vec<near_primitives::views::AccountActivityView>,
+    /// Cursor of the next page, `None` if there are no more records.
+    pub next_cursor: Option<AccountActivityCursor>,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcAccountActivityError {
+    #[error("Account activity is not indexed by this node")]
+    NotEnabled,
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcAccountActivityError> for crate::errors::RpcError {
+    fn from(error: RpcAccountActivityError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcAccountActivityError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
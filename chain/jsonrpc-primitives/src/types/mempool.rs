@@ -0,0 +1,75 @@
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, ShardId};
+use near_primitives::views::{
+    MempoolShardStatsView, MempoolTransactionStatusView, MempoolTransactionView,
+    SignedTransactionView,
+};
+
+/// Number of transactions returned when the request doesn't specify a limit.
+pub const DEFAULT_MEMPOOL_TRANSACTIONS_LIMIT: u32 = 100;
+/// Maximum number of transactions returned in a single response.
+pub const MAX_MEMPOOL_TRANSACTIONS_LIMIT: u32 = 1000;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct RpcMempoolTransactionsRequest {
+    /// Only lists the transactions pending in the pool of this shard.
+    #[serde(default)]
+    pub shard_id: Option<ShardId>,
+    #[serde(default)]
+    pub signer_id: Option<AccountId>,
+    #[serde(default)]
+    pub receiver_id: Option<AccountId>,
+    /// Defaults to [`DEFAULT_MEMPOOL_TRANSACTIONS_LIMIT`] and is capped at
+    /// [`MAX_MEMPOOL_TRANSACTIONS_LIMIT`].
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcMempoolTransactionsResponse {
+    /// Sorted by shard, signer and nonce.
+    pub transactions: Vec<MempoolTransactionView>,
+    /// Whether more transactions matched the filters than the limit.
+    pub truncated: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcMempoolTransactionRequest {
+    pub tx_hash: CryptoHash,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcMempoolTransactionResponse {
+    pub status: MempoolTransactionStatusView,
+    /// `None` if the transaction is not in the pool.
+    pub transaction: Option<SignedTransactionView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcMempoolStatsResponse {
+    /// Shards with no transactions received since the start of the node are
+    /// omitted.
+    pub shards: Vec<MempoolShardStatsView>,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcMempoolError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcMempoolError> for crate::errors::RpcError {
+    fn from(error: RpcMempoolError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcMempoolError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod account_activity;
 pub mod blocks;
 pub mod changes;
 pub mod chunks;
@@ -8,11 +9,14 @@ pub mod entity_debug;
 pub mod gas_price;
 pub mod light_client;
 pub mod maintenance;
+pub mod mempool;
 pub mod network_info;
 pub mod query;
 pub mod receipts;
 pub mod sandbox;
+pub mod simulation;
 pub mod split_storage;
 pub mod status;
+pub mod subscriptions;
 pub mod transactions;
 pub mod validator;
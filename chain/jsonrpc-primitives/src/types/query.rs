@@ -57,6 +57,8 @@ pub enum RpcQueryError {
     #[error("Function call returned an error: {vm_error}")]
     ContractExecutionError {
         vm_error: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gas_usage: Option<near_primitives::views::CallGasUsageView>,
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
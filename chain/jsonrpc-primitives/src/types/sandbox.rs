@@ -1,5 +1,5 @@
 use near_primitives::state_record::StateRecord;
-use near_primitives::types::BlockHeightDelta;
+use near_primitives::types::{AccountId, BlockHeightDelta};
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct RpcSandboxPatchStateRequest {
@@ -60,3 +60,158 @@ impl From<RpcSandboxFastForwardError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
     }
 }
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxSnapshotRequest {}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxSnapshotResponse {
+    pub snapshot_id: u64,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxSnapshotError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSandboxSnapshotError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxSnapshotError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSandboxSnapshotError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxRevertRequest {
+    pub snapshot_id: u64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxRevertResponse {}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxRevertError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSandboxRevertError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxRevertError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSandboxRevertError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxSetBlockTimestampRequest {
+    /// Timestamp of the next block, in nanoseconds since the Unix epoch.
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub timestamp: u64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxSetBlockTimestampResponse {}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxSetBlockTimestampError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSandboxSetBlockTimestampError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxSetBlockTimestampError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSandboxSetBlockTimestampError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxImpersonateAccountRequest {
+    pub account_id: AccountId,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxImpersonateAccountResponse {}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxImpersonateAccountError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSandboxImpersonateAccountError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxImpersonateAccountError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSandboxImpersonateAccountError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxStopImpersonatingAccountRequest {
+    pub account_id: AccountId,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RpcSandboxStopImpersonatingAccountResponse {}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxStopImpersonatingAccountError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSandboxStopImpersonatingAccountError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxStopImpersonatingAccountError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!(
+                        "Failed to serialize RpcSandboxStopImpersonatingAccountError: {:?}",
+                        err
+                    ),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
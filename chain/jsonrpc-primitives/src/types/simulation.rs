@@ -0,0 +1,74 @@
+use serde_json::Value;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcSimulateTransactionRequest {
+    #[serde(rename = "signed_tx_base64")]
+    pub signed_transaction: near_primitives::transaction::SignedTransaction,
+    /// Skips verification of the transaction signature. Required to simulate
+    /// transactions which haven't been signed yet.
+    #[serde(default)]
+    pub skip_signature_verification: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcSimulateTransactionResponse {
+    /// Block whose post state the transaction has been executed on.
+    pub block_height: near_primitives::types::BlockHeight,
+    pub block_hash: near_primitives::hash::CryptoHash,
+    #[serde(flatten)]
+    pub final_execution_outcome: near_primitives::views::FinalExecutionOutcomeView,
+    /// Gas burnt by the transaction and all of its receipts. Gas burnt by
+    /// every single receipt is available in `receipts_outcome`.
+    pub total_gas_burnt: near_primitives::types::Gas,
+    /// Receipts which have not been executed because the simulation reached
+    /// its limit on the number of receipts, the receipt depth or the burnt
+    /// gas.
+    pub pending_receipts: Vec<near_primitives::views::ReceiptView>,
+    pub state_changes: near_primitives::views::StateChangesView,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSimulateTransactionError {
+    #[error("There are no fully synchronized blocks on the node yet")]
+    NoSyncedBlocks,
+    #[error("The node does not track the shard ID {requested_shard_id}")]
+    UnavailableShard { requested_shard_id: near_primitives::types::ShardId },
+    #[error("Simulating transactions requires tracking all shards, the node does not track shards {untracked_shard_ids:?}")]
+    UntrackedShards { untracked_shard_ids: Vec<near_primitives::types::ShardId> },
+    #[error("An error happened during transaction execution: {context:?}")]
+    InvalidTransaction { context: near_primitives::errors::InvalidTxError },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSimulateTransactionError> for crate::errors::RpcError {
+    fn from(error: RpcSimulateTransactionError) -> Self {
+        let error_data = match &error {
+            RpcSimulateTransactionError::InvalidTransaction { context } => {
+                if let Ok(value) =
+                    serde_json::to_value(crate::errors::ServerError::TxExecutionError(
+                        near_primitives::errors::TxExecutionError::InvalidTxError(context.clone()),
+                    ))
+                {
+                    value
+                } else {
+                    Value::String(error.to_string())
+                }
+            }
+            _ => Value::String(error.to_string()),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSimulateTransactionError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(Some(error_data), error_data_value)
+    }
+}
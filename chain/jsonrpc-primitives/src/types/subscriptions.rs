@@ -0,0 +1,138 @@
+/// Describes what a WebSocket client wants to be notified about.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "subscription_type", rename_all = "snake_case")]
+pub enum RpcSubscriptionRequest {
+    /// Every new block reaching the given finality.
+    Blocks {
+        #[serde(default)]
+        finality: near_primitives::types::Finality,
+    },
+    /// Every new chunk included into a block reaching the given finality.
+    Chunks {
+        #[serde(default)]
+        finality: near_primitives::types::Finality,
+    },
+    /// Outcome of a single transaction. The subscription is closed once the
+    /// transaction reaches `wait_until` execution status.
+    Transaction {
+        tx_hash: near_primitives::hash::CryptoHash,
+        sender_account_id: near_primitives::types::AccountId,
+        #[serde(default)]
+        wait_until: near_primitives::views::TxExecutionStatus,
+    },
+    /// State changes matching the same filters as `EXPERIMENTAL_changes`.
+    StateChanges {
+        #[serde(default)]
+        finality: near_primitives::types::Finality,
+        state_changes_request: near_primitives::views::StateChangesRequestView,
+    },
+    /// Receipts addressed to the given account.
+    Receipts {
+        #[serde(default)]
+        finality: near_primitives::types::Finality,
+        receiver_id: near_primitives::types::AccountId,
+    },
+}
+
+impl RpcSubscriptionRequest {
+    /// Short name of the subscription type, used for metrics labels.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Blocks { .. } => "blocks",
+            Self::Chunks { .. } => "chunks",
+            Self::Transaction { .. } => "transaction",
+            Self::StateChanges { .. } => "state_changes",
+            Self::Receipts { .. } => "receipts",
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcSubscribeResponse {
+    pub subscription_id: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcUnsubscribeRequest {
+    pub subscription_id: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcUnsubscribeResponse {
+    pub subscription_id: u64,
+}
+
+/// An event pushed to the client for one of its subscriptions.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(tag = "event_type", rename_all = "snake_case")]
+pub enum RpcSubscriptionEvent {
+    Block {
+        block: near_primitives::views::BlockView,
+    },
+    Chunk {
+        block_hash: near_primitives::hash::CryptoHash,
+        chunk: near_primitives::views::ChunkView,
+    },
+    Transaction {
+        outcome: crate::types::transactions::RpcTransactionResponse,
+    },
+    StateChanges {
+        block_hash: near_primitives::hash::CryptoHash,
+        changes: near_primitives::views::StateChangesView,
+    },
+    Receipt {
+        block_hash: near_primitives::hash::CryptoHash,
+        receipt: near_primitives::views::ReceiptView,
+    },
+    /// Blocks from `start_height` to `end_height` (inclusive) were skipped
+    /// because the node advanced too far between two polls.  Events of these
+    /// blocks are not reported.
+    Gap {
+        start_height: near_primitives::types::BlockHeight,
+        end_height: near_primitives::types::BlockHeight,
+    },
+    /// The subscription has been closed by the server, either because it
+    /// completed (e.g. the transaction reached the requested status) or
+    /// because of an error.
+    Closed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<crate::errors::RpcError>,
+    },
+}
+
+/// Parameters of the `subscription` notification sent over the WebSocket.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcSubscriptionNotification {
+    pub subscription_id: u64,
+    pub event: RpcSubscriptionEvent,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSubscriptionError {
+    #[error("Subscriptions are only available over the WebSocket endpoint")]
+    WebSocketRequired,
+    #[error("Connection already has the maximum of {limit} active subscriptions")]
+    TooManySubscriptions { limit: usize },
+    #[error("Subscription {subscription_id} does not exist on this connection")]
+    UnknownSubscription { subscription_id: u64 },
+    #[error("The connection has been closed")]
+    ConnectionClosed,
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcSubscriptionError> for crate::errors::RpcError {
+    fn from(error: RpcSubscriptionError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSubscriptionError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -267,6 +267,15 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_split_storage_info", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_simulate_transaction(
+        &self,
+        request: near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionResponse>
+    {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_simulate_transaction", request)
+    }
+
     pub fn validators(
         &self,
         epoch_id_or_block_id: Option<EpochReference>,
@@ -9,7 +9,7 @@ use near_async::{
 use near_chain_configs::GenesisConfig;
 use near_client::test_utils::setup_no_network_with_validity_period_and_no_epoch_sync;
 use near_client::ViewClientActor;
-use near_jsonrpc::{start_http, RpcConfig};
+use near_jsonrpc::{start_http, RpcConfig, RpcLimitsConfig};
 use near_jsonrpc_primitives::{
     message::{from_slice, Message},
     types::entity_debug::DummyEntityDebugHandler,
@@ -32,11 +32,35 @@ pub fn start_all(clock: Clock, node_type: NodeType) -> (Addr<ViewClientActor>, t
     start_all_with_validity_period_and_no_epoch_sync(clock, node_type, 100, false)
 }
 
+pub fn start_all_with_limits(
+    clock: Clock,
+    node_type: NodeType,
+    limits_config: RpcLimitsConfig,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(clock, node_type, 100, false, limits_config)
+}
+
 pub fn start_all_with_validity_period_and_no_epoch_sync(
     clock: Clock,
     node_type: NodeType,
     transaction_validity_period: NumBlocks,
     enable_doomslug: bool,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(
+        clock,
+        node_type,
+        transaction_validity_period,
+        enable_doomslug,
+        RpcLimitsConfig::default(),
+    )
+}
+
+fn start_all_with_config(
+    clock: Clock,
+    node_type: NodeType,
+    transaction_validity_period: NumBlocks,
+    enable_doomslug: bool,
+    limits_config: RpcLimitsConfig,
 ) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
     let actor_handles = setup_no_network_with_validity_period_and_no_epoch_sync(
         clock,
@@ -53,7 +77,7 @@ pub fn start_all_with_validity_period_and_no_epoch_sync(
 
     let addr = tcp::ListenerAddr::reserve_for_test();
     start_http(
-        RpcConfig::new(addr),
+        RpcConfig { limits_config, ..RpcConfig::new(addr) },
         TEST_GENESIS_CONFIG.clone(),
         actor_handles.client_actor.clone().with_auto_span_context().into_multi_sender(),
         actor_handles.view_client_actor.clone().with_auto_span_context().into_multi_sender(),
@@ -0,0 +1,121 @@
+use actix::System;
+use serde_json::{json, Value};
+
+use near_actix_test_utils::run_actix;
+use near_jsonrpc::{RateLimitConfig, RpcLimitsConfig};
+use near_o11y::testonly::init_test_logger;
+use near_time::Clock;
+
+use near_jsonrpc_tests as test_utils;
+
+/// Starts a node with the given limits, posts `body` to its JSON-RPC endpoint
+/// and checks the response with `check`, which gets the HTTP status code and
+/// the response body (`Value::Null` if it's empty).
+fn test_batch(
+    limits_config: RpcLimitsConfig,
+    body: Value,
+    check: impl FnOnce(u16, Value) + 'static,
+) {
+    init_test_logger();
+
+    run_actix(async move {
+        let (_, addr) = test_utils::start_all_with_limits(
+            Clock::real(),
+            test_utils::NodeType::NonValidator,
+            limits_config,
+        );
+
+        actix::spawn(async move {
+            let mut response = awc::Client::new()
+                .post(format!("http://{}", addr))
+                .insert_header(("Content-Type", "application/json"))
+                .send_json(&body)
+                .await
+                .unwrap();
+            let bytes = response.body().await.unwrap();
+            let value = if bytes.is_empty() {
+                Value::Null
+            } else {
+                serde_json::from_slice(&bytes).unwrap()
+            };
+            check(response.status().as_u16(), value);
+            System::current().stop();
+        });
+    });
+}
+
+fn request(method: &str, id: u64) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "id": id, "params": []})
+}
+
+fn notification(method: &str) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": []})
+}
+
+/// Responses to the requests of a batch are returned in order, errors are
+/// reported per request and notifications get no response.
+#[test]
+fn test_mixed_batch() {
+    let body =
+        json!([request("status", 1), notification("status"), request("no_such_method", 2), 5]);
+    test_batch(RpcLimitsConfig::default(), body, |status, response| {
+        assert_eq!(status, 200);
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 3, "{response}");
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["chain_id"], "unittest");
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["cause"]["name"], "METHOD_NOT_FOUND");
+        assert_eq!(responses[2]["id"], Value::Null);
+        assert_eq!(responses[2]["error"]["cause"]["name"], "INVALID_REQUEST");
+    });
+}
+
+/// A batch of notifications only gets no response at all.
+#[test]
+fn test_notification_only_batch() {
+    let body = json!([notification("status"), notification("status")]);
+    test_batch(RpcLimitsConfig::default(), body, |status, response| {
+        assert_eq!(status, 204);
+        assert_eq!(response, Value::Null);
+    });
+}
+
+#[test]
+fn test_empty_batch() {
+    test_batch(RpcLimitsConfig::default(), json!([]), |status, response| {
+        assert_eq!(status, 200);
+        assert_eq!(response["error"]["cause"]["name"], "INVALID_REQUEST", "{response}");
+    });
+}
+
+#[test]
+fn test_batch_over_size_limit() {
+    let limits_config = RpcLimitsConfig { max_batch_size: 2, ..Default::default() };
+    let body = json!([request("status", 1), request("status", 2), request("status", 3)]);
+    test_batch(limits_config, body, |status, response| {
+        assert_eq!(status, 200);
+        assert!(response.is_object(), "{response}");
+        assert_eq!(response["error"]["cause"]["name"], "INVALID_REQUEST");
+    });
+}
+
+/// Requests in a batch are rate limited individually, so only the ones over
+/// the limit fail.
+#[test]
+fn test_batch_rate_limited_per_request() {
+    let mut limits_config = RpcLimitsConfig::default();
+    limits_config
+        .rate_limits
+        .methods
+        .insert("status".to_string(), RateLimitConfig { burst: 1, requests_per_second: 0.001 });
+    let body = json!([request("status", 1), request("status", 2)]);
+    test_batch(limits_config, body, |status, response| {
+        assert_eq!(status, 200);
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 2, "{response}");
+        assert_eq!(responses[0]["result"]["chain_id"], "unittest");
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["cause"]["name"], "RATE_LIMIT_EXCEEDED");
+    });
+}
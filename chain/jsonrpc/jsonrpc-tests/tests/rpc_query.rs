@@ -15,7 +15,7 @@ use near_o11y::testonly::init_test_logger;
 use near_primitives::account::{AccessKey, AccessKeyPermission};
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{BlockId, BlockReference, EpochId, SyncCheckpoint};
-use near_primitives::views::QueryRequest;
+use near_primitives::views::{QueryRequest, StateOverride};
 use near_time::Clock;
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
@@ -325,6 +325,9 @@ fn test_query_call_function() {
                     account_id: "test".parse().unwrap(),
                     method_name: "method".to_string(),
                     args: vec![].into(),
+                    include_gas_usage: false,
+                    include_function_profile: false,
+                    state_overrides: vec![],
                 },
             })
             .await
@@ -343,6 +346,55 @@ fn test_query_call_function() {
     });
 }
 
+/// Function profiles are rejected unless debug RPC is enabled.
+#[test]
+fn test_query_call_function_profile_disabled() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let err = client
+            .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::CallFunction {
+                    account_id: "test".parse().unwrap(),
+                    method_name: "method".to_string(),
+                    args: vec![].into(),
+                    include_gas_usage: false,
+                    include_function_profile: true,
+                    state_overrides: vec![],
+                },
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, -32_600, "{err:?}");
+    });
+}
+
+/// State overrides are rejected unless enabled in the RPC config.
+#[test]
+fn test_query_call_function_state_overrides_disabled() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let err = client
+            .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::CallFunction {
+                    account_id: "test".parse().unwrap(),
+                    method_name: "method".to_string(),
+                    args: vec![].into(),
+                    include_gas_usage: false,
+                    include_function_profile: false,
+                    state_overrides: vec![StateOverride {
+                        account_id: "test".parse().unwrap(),
+                        code: Some(vec![0; 8]),
+                        balance: None,
+                        state: vec![],
+                    }],
+                },
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, -32_600, "{err:?}");
+    });
+}
+
 /// query contract code
 #[test]
 fn test_query_contract_code() {
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use actix::System;
+use awc::ws;
+use futures::{SinkExt, Stream, StreamExt};
+use serde_json::{json, Value};
+
+use near_actix_test_utils::run_actix;
+use near_o11y::testonly::init_test_logger;
+use near_time::Clock;
+
+use near_jsonrpc_tests as test_utils;
+
+/// Returns the next JSON message received over the WebSocket.
+async fn next_message(
+    connection: &mut (impl Stream<Item = Result<ws::Frame, ws::ProtocolError>> + Unpin),
+) -> Value {
+    loop {
+        match connection.next().await.unwrap().unwrap() {
+            ws::Frame::Text(text) => return serde_json::from_slice(&text).unwrap(),
+            ws::Frame::Ping(_) | ws::Frame::Pong(_) => {}
+            frame => panic!("unexpected WebSocket frame: {:?}", frame),
+        }
+    }
+}
+
+/// Subscribes to new blocks over the WebSocket endpoint and checks that the
+/// notifications report new blocks in order, without gaps.
+#[test]
+fn test_subscribe_blocks() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all(Clock::real(), test_utils::NodeType::Validator);
+
+        actix::spawn(async move {
+            let test = async move {
+                let (_, mut connection) =
+                    awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+                let request = json!({
+                    "jsonrpc": "2.0",
+                    "method": "subscribe",
+                    "id": "dontcare",
+                    "params": {"subscription_type": "blocks", "finality": "optimistic"},
+                });
+                connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+                let response = next_message(&mut connection).await;
+                let subscription_id = response["result"]["subscription_id"].as_u64().unwrap();
+
+                let mut last_height = None;
+                for _ in 0..5 {
+                    let notification = next_message(&mut connection).await;
+                    assert_eq!(notification["method"], "subscription");
+                    let params = &notification["params"];
+                    assert_eq!(params["subscription_id"], subscription_id);
+                    assert_eq!(params["event"]["event_type"], "block");
+                    let height = params["event"]["block"]["header"]["height"].as_u64().unwrap();
+                    if let Some(last_height) = last_height {
+                        assert!(height > last_height);
+                    }
+                    last_height = Some(height);
+                }
+            };
+            actix::clock::timeout(Duration::from_secs(30), test)
+                .await
+                .expect("timed out waiting for block notifications");
+            System::current().stop();
+        });
+    });
+}
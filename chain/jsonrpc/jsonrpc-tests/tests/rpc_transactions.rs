@@ -7,6 +7,7 @@ use futures::{future, FutureExt, TryFutureExt};
 use near_actix_test_utils::run_actix;
 use near_crypto::{InMemorySigner, KeyType};
 use near_jsonrpc::client::new_client;
+use near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionRequest;
 use near_jsonrpc_primitives::types::transactions::{RpcTransactionStatusRequest, TransactionInfo};
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
@@ -115,6 +116,44 @@ fn test_send_tx_commit() {
     });
 }
 
+/// Test simulating a transaction and checking that it doesn't get executed.
+#[test]
+fn test_simulate_transaction() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer.into(),
+            100,
+            block_hash,
+        );
+        let request = RpcSimulateTransactionRequest {
+            signed_transaction: tx.clone(),
+            skip_signature_verification: false,
+        };
+        let response = client.EXPERIMENTAL_simulate_transaction(request).await.unwrap();
+        let outcome = response.final_execution_outcome;
+        assert_eq!(outcome.transaction_outcome.id, tx.get_hash());
+        assert_eq!(outcome.status, FinalExecutionStatus::SuccessValue(Vec::new()));
+        assert!(response.total_gas_burnt > 0);
+        assert!(response.pending_receipts.is_empty());
+        assert!(!response.state_changes.is_empty());
+
+        // The simulated transaction has not been submitted to the chain.
+        let request = RpcTransactionStatusRequest {
+            transaction_info: TransactionInfo::TransactionId {
+                tx_hash: tx.get_hash(),
+                sender_account_id: "test1".parse().unwrap(),
+            },
+            wait_until: TxExecutionStatus::None,
+        };
+        assert!(client.tx(request).await.is_err());
+    });
+}
+
 /// Test that expired transaction should be rejected
 #[test]
 fn test_expired_tx() {
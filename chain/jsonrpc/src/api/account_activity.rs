@@ -0,0 +1,118 @@
+use near_async::messaging::AsyncSendError;
+use serde_json::Value;
+
+use near_client_primitives::types::{
+    GetAccountActivity, GetAccountActivityError, GetAccountActivityResponse,
+};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::account_activity::{
+    AccountActivityCursor, RpcAccountActivityError, RpcAccountActivityRequest,
+    RpcAccountActivityResponse, DEFAULT_ACCOUNT_ACTIVITY_LIMIT, MAX_ACCOUNT_ACTIVITY_LIMIT,
+};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcAccountActivityRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        let request: Self = Params::parse(value)?;
+        if request.limit == Some(0) {
+            return Err(RpcParseError("`limit` must be greater than zero".to_string()));
+        }
+        Ok(request)
+    }
+}
+
+impl RpcFrom<RpcAccountActivityRequest> for GetAccountActivity {
+    fn rpc_from(request: RpcAccountActivityRequest) -> Self {
+        let from = match request.cursor {
+            Some(cursor) => (cursor.block_height, cursor.index),
+            None => (request.from_block_height.unwrap_or(0), 0),
+        };
+        let limit =
+            request.limit.unwrap_or(DEFAULT_ACCOUNT_ACTIVITY_LIMIT).min(MAX_ACCOUNT_ACTIVITY_LIMIT);
+        Self { account_id: request.account_id, from, limit: limit as usize }
+    }
+}
+
+impl RpcFrom<GetAccountActivityResponse> for RpcAccountActivityResponse {
+    fn rpc_from(response: GetAccountActivityResponse) -> Self {
+        Self {
+            activity: response.activity,
+            next_cursor: response
+                .next
+                .map(|(block_height, index)| AccountActivityCursor { block_height, index }),
+        }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcAccountActivityError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetAccountActivityError> for RpcAccountActivityError {
+    fn rpc_from(error: GetAccountActivityError) -> Self {
+        match error {
+            GetAccountActivityError::NotEnabled => Self::NotEnabled,
+            GetAccountActivityError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            GetAccountActivityError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcAccountActivityError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{RpcFrom, RpcRequest};
+    use near_client_primitives::types::GetAccountActivity;
+    use near_jsonrpc_primitives::types::account_activity::{
+        AccountActivityCursor, RpcAccountActivityRequest, MAX_ACCOUNT_ACTIVITY_LIMIT,
+    };
+
+    #[test]
+    fn test_parse_account_activity_request() {
+        let params = serde_json::json!({"account_id": "alice.near"});
+        let request = RpcAccountActivityRequest::parse(params).unwrap();
+        let msg = GetAccountActivity::rpc_from(request);
+        assert_eq!(msg.from, (0, 0));
+        assert_eq!(msg.limit, 100);
+
+        let params = serde_json::json!({
+            "account_id": "alice.near",
+            "cursor": "42-7",
+            "from_block_height": 10,
+            "limit": 100_000,
+        });
+        let request = RpcAccountActivityRequest::parse(params).unwrap();
+        assert_eq!(request.cursor, Some(AccountActivityCursor { block_height: 42, index: 7 }));
+        let msg = GetAccountActivity::rpc_from(request);
+        assert_eq!(msg.from, (42, 7));
+        assert_eq!(msg.limit, MAX_ACCOUNT_ACTIVITY_LIMIT as usize);
+    }
+
+    #[test]
+    fn test_parse_invalid_account_activity_request() {
+        for cursor in ["", "42", "42-", "-7", "a-b", "42-7-1"] {
+            let params = serde_json::json!({"account_id": "alice.near", "cursor": cursor});
+            assert!(RpcAccountActivityRequest::parse(params).is_err(), "{cursor}");
+        }
+        let params = serde_json::json!({"account_id": "alice.near", "limit": 0});
+        assert!(RpcAccountActivityRequest::parse(params).is_err());
+    }
+
+    #[test]
+    fn test_account_activity_cursor_roundtrip() {
+        let cursor = AccountActivityCursor { block_height: 42, index: 7 };
+        let value = serde_json::to_value(cursor).unwrap();
+        assert_eq!(value, serde_json::json!("42-7"));
+        assert_eq!(serde_json::from_value::<AccountActivityCursor>(value).unwrap(), cursor);
+    }
+}
@@ -0,0 +1,132 @@
+use near_async::messaging::AsyncSendError;
+use serde_json::Value;
+
+use near_client_primitives::types::{
+    GetMempoolError, GetMempoolTransaction, GetMempoolTransactionResponse, GetMempoolTransactions,
+    GetMempoolTransactionsResponse,
+};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::mempool::{
+    RpcMempoolError, RpcMempoolTransactionRequest, RpcMempoolTransactionResponse,
+    RpcMempoolTransactionsRequest, RpcMempoolTransactionsResponse,
+    DEFAULT_MEMPOOL_TRANSACTIONS_LIMIT, MAX_MEMPOOL_TRANSACTIONS_LIMIT,
+};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcMempoolTransactionsRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        if value.is_null() {
+            return Ok(Self::default());
+        }
+        let request: Self = Params::parse(value)?;
+        if request.limit == Some(0) {
+            return Err(RpcParseError("`limit` must be greater than zero".to_string()));
+        }
+        Ok(request)
+    }
+}
+
+impl RpcRequest for RpcMempoolTransactionRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::new(value).try_singleton(|tx_hash| Ok(Self { tx_hash })).unwrap_or_parse()
+    }
+}
+
+impl RpcFrom<RpcMempoolTransactionsRequest> for GetMempoolTransactions {
+    fn rpc_from(request: RpcMempoolTransactionsRequest) -> Self {
+        let limit = request
+            .limit
+            .unwrap_or(DEFAULT_MEMPOOL_TRANSACTIONS_LIMIT)
+            .min(MAX_MEMPOOL_TRANSACTIONS_LIMIT);
+        Self {
+            shard_id: request.shard_id,
+            signer_id: request.signer_id,
+            receiver_id: request.receiver_id,
+            limit: limit as usize,
+        }
+    }
+}
+
+impl RpcFrom<GetMempoolTransactionsResponse> for RpcMempoolTransactionsResponse {
+    fn rpc_from(response: GetMempoolTransactionsResponse) -> Self {
+        Self { transactions: response.transactions, truncated: response.truncated }
+    }
+}
+
+impl RpcFrom<RpcMempoolTransactionRequest> for GetMempoolTransaction {
+    fn rpc_from(request: RpcMempoolTransactionRequest) -> Self {
+        Self { tx_hash: request.tx_hash }
+    }
+}
+
+impl RpcFrom<GetMempoolTransactionResponse> for RpcMempoolTransactionResponse {
+    fn rpc_from(response: GetMempoolTransactionResponse) -> Self {
+        Self { status: response.status, transaction: response.transaction }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcMempoolError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetMempoolError> for RpcMempoolError {
+    fn rpc_from(error: GetMempoolError) -> Self {
+        match error {
+            GetMempoolError::IOError(error_message) => Self::InternalError { error_message },
+            GetMempoolError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcMempoolError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{RpcFrom, RpcRequest};
+    use near_client_primitives::types::GetMempoolTransactions;
+    use near_jsonrpc_primitives::types::mempool::{
+        RpcMempoolTransactionRequest, RpcMempoolTransactionsRequest,
+        DEFAULT_MEMPOOL_TRANSACTIONS_LIMIT, MAX_MEMPOOL_TRANSACTIONS_LIMIT,
+    };
+    use near_primitives::hash::CryptoHash;
+
+    #[test]
+    fn test_parse_mempool_transactions_request() {
+        let request = RpcMempoolTransactionsRequest::parse(serde_json::Value::Null).unwrap();
+        let msg = GetMempoolTransactions::rpc_from(request);
+        assert_eq!(msg.shard_id, None);
+        assert_eq!(msg.limit, DEFAULT_MEMPOOL_TRANSACTIONS_LIMIT as usize);
+
+        let params = serde_json::json!({
+            "shard_id": 1,
+            "signer_id": "alice.near",
+            "receiver_id": "bob.near",
+            "limit": 100_000,
+        });
+        let request = RpcMempoolTransactionsRequest::parse(params).unwrap();
+        let msg = GetMempoolTransactions::rpc_from(request);
+        assert_eq!(msg.shard_id, Some(1));
+        assert_eq!(msg.signer_id, Some("alice.near".parse().unwrap()));
+        assert_eq!(msg.receiver_id, Some("bob.near".parse().unwrap()));
+        assert_eq!(msg.limit, MAX_MEMPOOL_TRANSACTIONS_LIMIT as usize);
+
+        let params = serde_json::json!({"limit": 0});
+        assert!(RpcMempoolTransactionsRequest::parse(params).is_err());
+    }
+
+    #[test]
+    fn test_parse_mempool_transaction_request() {
+        let tx_hash = CryptoHash::hash_bytes(b"tx");
+        let params = serde_json::json!([tx_hash]);
+        assert_eq!(RpcMempoolTransactionRequest::parse(params).unwrap().tx_hash, tx_hash);
+        let params = serde_json::json!({"tx_hash": tx_hash});
+        assert_eq!(RpcMempoolTransactionRequest::parse(params).unwrap().tx_hash, tx_hash);
+    }
+}
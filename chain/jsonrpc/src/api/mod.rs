@@ -4,6 +4,7 @@ use serde_json::Value;
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::errors::{RpcError, ServerError};
 
+mod account_activity;
 mod blocks;
 mod changes;
 mod chunks;
@@ -13,12 +14,15 @@ mod congestion;
 mod gas_price;
 mod light_client;
 mod maintenance;
+mod mempool;
 mod network_info;
 mod query;
 mod receipts;
 mod sandbox;
+mod simulation;
 mod split_storage;
 mod status;
+mod subscriptions;
 mod transactions;
 mod validator;
 
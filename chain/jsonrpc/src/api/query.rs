@@ -78,6 +78,9 @@ fn parse_path_data(path: String, data: String) -> Result<RpcQueryRequest, RpcPar
                 account_id,
                 method_name: method_name.to_string(),
                 args: parse_data()?.into(),
+                include_gas_usage: false,
+                include_function_profile: false,
+                state_overrides: vec![],
             },
             None => return Err(RpcParseError("Method name is missing".to_string())),
         },
@@ -117,9 +120,12 @@ impl RpcFrom<QueryError> for RpcQueryError {
             QueryError::UnknownAccessKey { public_key, block_height, block_hash } => {
                 Self::UnknownAccessKey { public_key, block_height, block_hash }
             }
-            QueryError::ContractExecutionError { vm_error, block_height, block_hash } => {
-                Self::ContractExecutionError { vm_error, block_height, block_hash }
-            }
+            QueryError::ContractExecutionError {
+                vm_error,
+                gas_usage,
+                block_height,
+                block_hash,
+            } => Self::ContractExecutionError { vm_error, gas_usage, block_height, block_hash },
             QueryError::Unreachable { ref error_message } => {
                 tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
                 crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
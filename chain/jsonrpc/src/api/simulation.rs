@@ -0,0 +1,151 @@
+use near_async::messaging::AsyncSendError;
+use serde_json::Value;
+
+use near_client_primitives::types::{
+    SimulateTransaction, SimulateTransactionError, SimulateTransactionResponse,
+};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::simulation::{
+    RpcSimulateTransactionError, RpcSimulateTransactionRequest, RpcSimulateTransactionResponse,
+};
+use near_primitives::borsh::BorshDeserialize;
+use near_primitives::transaction::{SignedTransaction, Transaction};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+/// Parameters of a simulation of a transaction which hasn't been signed yet.
+#[derive(serde::Deserialize)]
+struct UnsignedTransactionParams {
+    tx_base64: String,
+    #[serde(default)]
+    skip_signature_verification: bool,
+}
+
+impl RpcRequest for RpcSimulateTransactionRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        if value.get("tx_base64").is_none() {
+            return Params::new(value)
+                .try_singleton(|signed_tx| {
+                    Ok(RpcSimulateTransactionRequest {
+                        signed_transaction: decode_signed_transaction(signed_tx)?,
+                        skip_signature_verification: false,
+                    })
+                })
+                .unwrap_or_parse();
+        }
+        let params: UnsignedTransactionParams = Params::parse(value)?;
+        if !params.skip_signature_verification {
+            return Err(RpcParseError(
+                "Unsigned transactions can only be simulated with `skip_signature_verification`"
+                    .to_string(),
+            ));
+        }
+        let bytes = near_primitives::serialize::from_base64(&params.tx_base64)
+            .map_err(|err| RpcParseError(format!("Failed to decode transaction: {}", err)))?;
+        let transaction = Transaction::try_from_slice(&bytes)
+            .map_err(|err| RpcParseError(format!("Failed to decode transaction: {}", err)))?;
+        let signature = near_crypto::Signature::empty(transaction.public_key().key_type());
+        Ok(RpcSimulateTransactionRequest {
+            signed_transaction: SignedTransaction::new(signature, transaction),
+            skip_signature_verification: true,
+        })
+    }
+}
+
+impl RpcFrom<RpcSimulateTransactionRequest> for SimulateTransaction {
+    fn rpc_from(request: RpcSimulateTransactionRequest) -> Self {
+        Self {
+            signed_transaction: request.signed_transaction,
+            verify_signature: !request.skip_signature_verification,
+        }
+    }
+}
+
+impl RpcFrom<SimulateTransactionResponse> for RpcSimulateTransactionResponse {
+    fn rpc_from(response: SimulateTransactionResponse) -> Self {
+        let outcome = &response.final_execution_outcome;
+        let total_gas_burnt = outcome.transaction_outcome.outcome.gas_burnt
+            + outcome.receipts_outcome.iter().map(|o| o.outcome.gas_burnt).sum::<u64>();
+        Self {
+            block_height: response.block_height,
+            block_hash: response.block_hash,
+            final_execution_outcome: response.final_execution_outcome,
+            total_gas_burnt,
+            pending_receipts: response.pending_receipts,
+            state_changes: response.state_changes,
+        }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcSimulateTransactionError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<SimulateTransactionError> for RpcSimulateTransactionError {
+    fn rpc_from(error: SimulateTransactionError) -> Self {
+        match error {
+            SimulateTransactionError::NoSyncedBlocks => Self::NoSyncedBlocks,
+            SimulateTransactionError::UnavailableShard { requested_shard_id } => {
+                Self::UnavailableShard { requested_shard_id }
+            }
+            SimulateTransactionError::UntrackedShards { untracked_shard_ids } => {
+                Self::UntrackedShards { untracked_shard_ids }
+            }
+            SimulateTransactionError::InvalidTransaction(context) => {
+                Self::InvalidTransaction { context }
+            }
+            SimulateTransactionError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            SimulateTransactionError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcSimulateTransactionError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+fn decode_signed_transaction(value: String) -> Result<SignedTransaction, RpcParseError> {
+    let bytes = near_primitives::serialize::from_base64(&value)
+        .map_err(|err| RpcParseError(format!("Failed to decode transaction: {}", err)))?;
+    SignedTransaction::try_from_slice(&bytes)
+        .map_err(|err| RpcParseError(format!("Failed to decode transaction: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::RpcRequest;
+    use near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionRequest;
+    use near_primitives::borsh;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::serialize::to_base64;
+    use near_primitives::transaction::SignedTransaction;
+
+    #[test]
+    fn test_parse_signed_transaction() {
+        let tx = SignedTransaction::empty(CryptoHash::new());
+        let str_tx = to_base64(&borsh::to_vec(&tx).unwrap());
+        let request = RpcSimulateTransactionRequest::parse(serde_json::json!([str_tx])).unwrap();
+        assert!(!request.skip_signature_verification);
+        let params =
+            serde_json::json!({"signed_tx_base64": str_tx, "skip_signature_verification": true});
+        let request = RpcSimulateTransactionRequest::parse(params).unwrap();
+        assert!(request.skip_signature_verification);
+    }
+
+    #[test]
+    fn test_parse_unsigned_transaction() {
+        let tx = SignedTransaction::empty(CryptoHash::new()).transaction;
+        let str_tx = to_base64(&borsh::to_vec(&tx).unwrap());
+        let params = serde_json::json!({"tx_base64": str_tx, "skip_signature_verification": true});
+        let request = RpcSimulateTransactionRequest::parse(params).unwrap();
+        assert_eq!(request.signed_transaction.transaction, tx);
+        let params = serde_json::json!({"tx_base64": str_tx});
+        assert!(RpcSimulateTransactionRequest::parse(params).is_err());
+    }
+}
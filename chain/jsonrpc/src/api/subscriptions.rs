@@ -0,0 +1,71 @@
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::subscriptions::{
+    RpcSubscriptionRequest, RpcUnsubscribeRequest,
+};
+
+use super::{Params, RpcRequest};
+
+impl RpcRequest for RpcSubscriptionRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcUnsubscribeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::new(value)
+            .try_singleton(|subscription_id| Ok(Self { subscription_id }))
+            .unwrap_or_parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::RpcRequest;
+    use near_jsonrpc_primitives::types::subscriptions::{
+        RpcSubscriptionRequest, RpcUnsubscribeRequest,
+    };
+
+    #[test]
+    fn test_parse_blocks_subscription() {
+        let params = serde_json::json!({"subscription_type": "blocks", "finality": "optimistic"});
+        assert!(matches!(
+            RpcSubscriptionRequest::parse(params),
+            Ok(RpcSubscriptionRequest::Blocks { finality: near_primitives::types::Finality::None })
+        ));
+    }
+
+    #[test]
+    fn test_parse_state_changes_subscription() {
+        let params = serde_json::json!({
+            "subscription_type": "state_changes",
+            "state_changes_request": {
+                "changes_type": "account_changes",
+                "account_ids": ["alice.near"],
+            },
+        });
+        assert!(matches!(
+            RpcSubscriptionRequest::parse(params),
+            Ok(RpcSubscriptionRequest::StateChanges {
+                finality: near_primitives::types::Finality::Final,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_unsubscribe() {
+        assert_eq!(
+            RpcUnsubscribeRequest::parse(serde_json::json!([7])).unwrap().subscription_id,
+            7
+        );
+        assert_eq!(
+            RpcUnsubscribeRequest::parse(serde_json::json!({"subscription_id": 7}))
+                .unwrap()
+                .subscription_id,
+            7
+        );
+    }
+}
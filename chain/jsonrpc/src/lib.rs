@@ -19,7 +19,10 @@ use near_client::{
     GetReceipt, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
     ProcessTxRequest, ProcessTxResponse, Query, Status, TxStatus,
 };
-use near_client_primitives::types::GetSplitStorageInfo;
+use near_client_primitives::types::{
+    GetAccountActivity, GetMempoolStats, GetMempoolTransaction, GetMempoolTransactions,
+    GetSplitStorageInfo, SimulateTransaction,
+};
 pub use near_jsonrpc_client as client;
 use near_jsonrpc_primitives::errors::RpcError;
 use near_jsonrpc_primitives::message::{Message, Request};
@@ -40,7 +43,9 @@ use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockHeight, BlockId, BlockReference};
 use near_primitives::views::{QueryRequest, TxExecutionStatus};
 use serde_json::{json, Value};
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::{sleep, timeout};
@@ -48,6 +53,10 @@ use tracing::{error, info};
 
 mod api;
 mod metrics;
+mod rate_limit;
+mod subscriptions;
+
+pub use rate_limit::{RateLimitConfig, RpcRateLimitsConfig};
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
@@ -64,15 +73,56 @@ impl Default for RpcPollingConfig {
     }
 }
 
+fn default_max_ws_connections() -> usize {
+    1024
+}
+
+fn default_max_subscriptions_per_connection() -> usize {
+    64
+}
+
+fn default_ws_send_queue_size() -> usize {
+    1024
+}
+
+fn default_max_batch_size() -> usize {
+    100
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct RpcLimitsConfig {
     /// Maximum byte size of the json payload.
     pub json_payload_max_size: usize,
+    /// Maximum number of simultaneously open WebSocket connections.
+    #[serde(default = "default_max_ws_connections")]
+    pub max_ws_connections: usize,
+    /// Maximum number of active subscriptions on a single WebSocket connection.
+    #[serde(default = "default_max_subscriptions_per_connection")]
+    pub max_subscriptions_per_connection: usize,
+    /// Maximum number of messages queued for sending on a single WebSocket
+    /// connection.  Clients which don't keep up with their notifications are
+    /// disconnected once the queue is full.
+    #[serde(default = "default_ws_send_queue_size")]
+    pub ws_send_queue_size: usize,
+    /// Maximum number of requests in a single JSON-RPC batch.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Token-bucket rate limits of requests.  Requests in a batch are counted
+    /// individually.
+    #[serde(default)]
+    pub rate_limits: RpcRateLimitsConfig,
 }
 
 impl Default for RpcLimitsConfig {
     fn default() -> Self {
-        Self { json_payload_max_size: 10 * 1024 * 1024 }
+        Self {
+            json_payload_max_size: 10 * 1024 * 1024,
+            max_ws_connections: default_max_ws_connections(),
+            max_subscriptions_per_connection: default_max_subscriptions_per_connection(),
+            ws_send_queue_size: default_ws_send_queue_size(),
+            max_batch_size: default_max_batch_size(),
+            rate_limits: Default::default(),
+        }
     }
 }
 
@@ -93,6 +143,10 @@ pub struct RpcConfig {
     // We disable it by default, as some of those endpoints might be quite CPU heavy.
     #[serde(default = "default_enable_debug_rpc")]
     pub enable_debug_rpc: bool,
+    // If true, `call_function` queries may override the state of accounts, including the code of
+    // contracts.  Disabled by default as overridden code is compiled on every call.
+    #[serde(default)]
+    pub enable_state_overrides: bool,
     // For node developers only: if specified, the HTML files used to serve the debug pages will
     // be read from this directory, instead of the contents compiled into the binary. This allows
     // for quick iterative development.
@@ -108,6 +162,7 @@ impl Default for RpcConfig {
             polling_config: Default::default(),
             limits_config: Default::default(),
             enable_debug_rpc: false,
+            enable_state_overrides: false,
             experimental_debug_pages_src_path: None,
         }
     }
@@ -174,14 +229,21 @@ fn process_query_response(
         Err(err) => match err {
             near_jsonrpc_primitives::types::query::RpcQueryError::ContractExecutionError {
                 vm_error,
+                gas_usage,
                 block_height,
                 block_hash,
-            } => Ok(json!({
-                "error": vm_error,
-                "logs": json!([]),
-                "block_height": block_height,
-                "block_hash": block_hash,
-            })),
+            } => {
+                let mut response = json!({
+                    "error": vm_error,
+                    "logs": json!([]),
+                    "block_height": block_height,
+                    "block_hash": block_hash,
+                });
+                if let Some(gas_usage) = gas_usage {
+                    response["gas_usage"] = json!(gas_usage);
+                }
+                Ok(response)
+            }
             near_jsonrpc_primitives::types::query::RpcQueryError::UnknownAccessKey {
                 public_key,
                 block_height,
@@ -224,6 +286,9 @@ fn process_query_response(
 pub struct ClientSenderForRpc(
     AsyncSender<DebugStatus, ActixResult<DebugStatus>>,
     AsyncSender<GetClientConfig, ActixResult<GetClientConfig>>,
+    AsyncSender<GetMempoolStats, ActixResult<GetMempoolStats>>,
+    AsyncSender<GetMempoolTransaction, ActixResult<GetMempoolTransaction>>,
+    AsyncSender<GetMempoolTransactions, ActixResult<GetMempoolTransactions>>,
     AsyncSender<GetNetworkInfo, ActixResult<GetNetworkInfo>>,
     AsyncSender<ProcessTxRequest, ActixResult<ProcessTxRequest>>,
     AsyncSender<Status, ActixResult<Status>>,
@@ -243,6 +308,7 @@ pub struct ClientSenderForRpc(
 
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
 pub struct ViewClientSenderForRpc(
+    AsyncSender<GetAccountActivity, ActixResult<GetAccountActivity>>,
     AsyncSender<GetBlock, ActixResult<GetBlock>>,
     AsyncSender<GetBlockProof, ActixResult<GetBlockProof>>,
     AsyncSender<GetChunk, ActixResult<GetChunk>>,
@@ -258,6 +324,7 @@ pub struct ViewClientSenderForRpc(
     AsyncSender<GetValidatorInfo, ActixResult<GetValidatorInfo>>,
     AsyncSender<GetValidatorOrdered, ActixResult<GetValidatorOrdered>>,
     AsyncSender<Query, ActixResult<Query>>,
+    AsyncSender<SimulateTransaction, ActixResult<SimulateTransaction>>,
     AsyncSender<TxStatus, ActixResult<TxStatus>>,
     #[cfg(feature = "test_features")] Sender<near_client::NetworkAdversarialMessage>,
 );
@@ -281,30 +348,94 @@ struct JsonRpcHandler {
     #[cfg(feature = "test_features")]
     gc_sender: GCSenderForRpc,
     polling_config: RpcPollingConfig,
+    limits_config: RpcLimitsConfig,
     genesis_config: GenesisConfig,
     enable_debug_rpc: bool,
+    enable_state_overrides: bool,
     debug_pages_src_path: Option<PathBuf>,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    /// Number of open WebSocket connections, shared by all server workers.
+    ws_connections: Arc<AtomicUsize>,
+    rate_limiter: Arc<rate_limit::RateLimiter>,
 }
 
 impl JsonRpcHandler {
-    pub async fn process(&self, message: Message) -> Result<Message, HttpError> {
+    /// Processes a JSON-RPC message.
+    ///
+    /// Returns `None` if there's nothing to respond with, which happens for a
+    /// batch consisting of notifications only.
+    pub async fn process(
+        &self,
+        message: Message,
+        client_ip: Option<IpAddr>,
+    ) -> Result<Option<Message>, HttpError> {
         let id = message.id();
         match message {
             Message::Request(request) => {
-                Ok(Message::response(id, self.process_request(request).await))
+                Ok(Some(Message::response(id, self.process_request(request, client_ip).await)))
             }
-            _ => Ok(Message::error(RpcError::parse_error(
+            Message::Batch(messages) => Ok(self.process_batch(messages, client_ip).await),
+            _ => Ok(Some(Message::error(RpcError::parse_error(
                 "JSON RPC Request format was expected".to_owned(),
-            ))),
+            )))),
+        }
+    }
+
+    /// Processes all requests of a JSON-RPC batch concurrently.
+    ///
+    /// Notifications are processed as well, but as the protocol requires, they
+    /// get no response.
+    async fn process_batch(
+        &self,
+        messages: Vec<Message>,
+        client_ip: Option<IpAddr>,
+    ) -> Option<Message> {
+        if messages.is_empty() {
+            return Some(Message::error(RpcError::invalid_request("Batch is empty".to_owned())));
+        }
+        let max_batch_size = self.limits_config.max_batch_size;
+        if messages.len() > max_batch_size {
+            return Some(Message::error(RpcError::invalid_request(format!(
+                "Batch of {} requests exceeds the maximum batch size of {}",
+                messages.len(),
+                max_batch_size
+            ))));
+        }
+        metrics::RPC_BATCH_SIZE.observe(messages.len() as f64);
+
+        let responses = futures::future::join_all(messages.into_iter().map(|message| async move {
+            match message {
+                Message::Request(request) => {
+                    let id = request.id.clone();
+                    Some(Message::response(id, self.process_request(request, client_ip).await))
+                }
+                Message::Notification(notification) => {
+                    let _ = self.process_request(notification.into_request(), client_ip).await;
+                    None
+                }
+                _ => Some(Message::error(RpcError::invalid_request(
+                    "JSON RPC Request format was expected".to_owned(),
+                ))),
+            }
+        }))
+        .await;
+        let responses: Vec<Message> = responses.into_iter().flatten().collect();
+        if responses.is_empty() {
+            None
+        } else {
+            Some(Message::Batch(responses))
         }
     }
 
     // `process_request` increments affected metrics but the request processing is done by
     // `process_request_internal`.
-    async fn process_request(&self, request: Request) -> Result<Value, RpcError> {
+    async fn process_request(
+        &self,
+        request: Request,
+        client_ip: Option<IpAddr>,
+    ) -> Result<Value, RpcError> {
         let timer = Instant::now();
-        let (metrics_name, response) = self.process_request_internal(request).await;
+        let (metrics_name, response) = self.process_request_internal(request, client_ip).await;
 
         metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[&metrics_name]).inc();
         metrics::RPC_PROCESSING_TIME
@@ -326,8 +457,14 @@ impl JsonRpcHandler {
     async fn process_request_internal(
         &self,
         request: Request,
+        client_ip: Option<IpAddr>,
     ) -> (String, Result<Value, RpcError>) {
         let method_name = request.method.to_string();
+        if let Err(limit) = self.rate_limiter.check(&method_name, client_ip) {
+            metrics::RPC_RATE_LIMITED_COUNT.with_label_values(&[limit.as_str()]).inc();
+            return (method_name.clone(), Err(RpcError::rate_limit_exceeded(method_name)));
+        }
+
         let request = match self.process_adversarial_request_internal(request).await {
             Ok(response) => return (method_name, response),
             Err(request) => request,
@@ -358,6 +495,33 @@ impl JsonRpcHandler {
                     QueryRequest::ViewAccessKeyList { .. } => "query_view_access_key_list",
                     QueryRequest::CallFunction { .. } => "query_call_function",
                 };
+                if let QueryRequest::CallFunction {
+                    include_function_profile,
+                    state_overrides,
+                    ..
+                } = &params.request
+                {
+                    // Profiling recompiles the contract without the cache.
+                    if *include_function_profile && !self.enable_debug_rpc {
+                        let err = RpcError::invalid_request(
+                            "function profiles require enable_debug_rpc".to_string(),
+                        );
+                        return (metrics_name.to_string(), Err(err));
+                    }
+                    if !state_overrides.is_empty() && !self.enable_state_overrides {
+                        let err = RpcError::invalid_request(
+                            "state overrides are disabled on this node".to_string(),
+                        );
+                        return (metrics_name.to_string(), Err(err));
+                    }
+                }
+                if let Err(limit) = self.rate_limiter.check_method(metrics_name) {
+                    metrics::RPC_RATE_LIMITED_COUNT.with_label_values(&[limit.as_str()]).inc();
+                    return (
+                        metrics_name.to_string(),
+                        Err(RpcError::rate_limit_exceeded(metrics_name.to_string())),
+                    );
+                }
                 (metrics_name.to_string(), process_query_response(self.query(params).await))
             }
             _ => {
@@ -405,6 +569,9 @@ impl JsonRpcHandler {
             "client_config" => {
                 process_method_call(request, |_params: ()| self.client_config()).await
             }
+            "EXPERIMENTAL_account_activity" => {
+                process_method_call(request, |params| self.account_activity(params)).await
+            }
             "EXPERIMENTAL_changes" => {
                 process_method_call(request, |params| self.changes_in_block_by_type(params)).await
             }
@@ -435,6 +602,9 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_receipt" => {
                 process_method_call(request, |params| self.receipt(params)).await
             }
+            "EXPERIMENTAL_simulate_transaction" => {
+                process_method_call(request, |params| self.simulate_transaction(params)).await
+            }
             "EXPERIMENTAL_tx_status" => {
                 process_method_call(request, |params| self.tx_status_common(params, true)).await
             }
@@ -447,6 +617,26 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_split_storage_info" => {
                 process_method_call(request, |params| self.split_storage_info(params)).await
             }
+            "EXPERIMENTAL_mempool_transactions" => {
+                // Listing the pool walks all of its transactions on the client actor.
+                if self.enable_debug_rpc {
+                    process_method_call(request, |params| self.mempool_transactions(params)).await
+                } else {
+                    Err(RpcError::invalid_request(
+                        "listing mempool transactions requires enable_debug_rpc".to_string(),
+                    ))
+                }
+            }
+            "EXPERIMENTAL_mempool_transaction" => {
+                process_method_call(request, |params| self.mempool_transaction(params)).await
+            }
+            "EXPERIMENTAL_mempool_stats" => {
+                process_method_call(request, |_params: ()| self.mempool_stats()).await
+            }
+            "subscribe" | "unsubscribe" => Err(
+                near_jsonrpc_primitives::types::subscriptions::RpcSubscriptionError::WebSocketRequired
+                    .into(),
+            ),
             #[cfg(feature = "sandbox")]
             "sandbox_patch_state" => {
                 process_method_call(request, |params| self.sandbox_patch_state(params)).await
@@ -455,6 +645,31 @@ impl JsonRpcHandler {
             "sandbox_fast_forward" => {
                 process_method_call(request, |params| self.sandbox_fast_forward(params)).await
             }
+            #[cfg(feature = "sandbox")]
+            "sandbox_snapshot" => {
+                process_method_call(request, |params| self.sandbox_snapshot(params)).await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_revert" => {
+                process_method_call(request, |params| self.sandbox_revert(params)).await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_impersonate_account" => {
+                process_method_call(request, |params| self.sandbox_impersonate_account(params))
+                    .await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_stop_impersonating_account" => {
+                process_method_call(request, |params| {
+                    self.sandbox_stop_impersonating_account(params)
+                })
+                .await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_set_block_timestamp" => {
+                process_method_call(request, |params| self.sandbox_set_block_timestamp(params))
+                    .await
+            }
             _ => return Err(request),
         })
     }
@@ -1148,6 +1363,66 @@ impl JsonRpcHandler {
         None
     }
 
+    /// Executes the transaction and all of its receipts on top of the latest
+    /// final state without broadcasting it or committing any changes.
+    async fn simulate_transaction(
+        &self,
+        request_data: near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionResponse,
+        near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionError,
+    > {
+        let response = self.view_client_send(SimulateTransaction::rpc_from(request_data)).await?;
+        Ok(response.rpc_into())
+    }
+
+    /// Lists transactions and receipts the account took part in.
+    async fn account_activity(
+        &self,
+        request_data: near_jsonrpc_primitives::types::account_activity::RpcAccountActivityRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::account_activity::RpcAccountActivityResponse,
+        near_jsonrpc_primitives::types::account_activity::RpcAccountActivityError,
+    > {
+        let response = self.view_client_send(GetAccountActivity::rpc_from(request_data)).await?;
+        Ok(response.rpc_into())
+    }
+
+    /// Lists the transactions pending in the pool of this node.
+    async fn mempool_transactions(
+        &self,
+        request_data: near_jsonrpc_primitives::types::mempool::RpcMempoolTransactionsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::mempool::RpcMempoolTransactionsResponse,
+        near_jsonrpc_primitives::types::mempool::RpcMempoolError,
+    > {
+        let response = self.client_send(GetMempoolTransactions::rpc_from(request_data)).await?;
+        Ok(response.rpc_into())
+    }
+
+    /// Returns whether the transaction is pending in the pool of this node.
+    async fn mempool_transaction(
+        &self,
+        request_data: near_jsonrpc_primitives::types::mempool::RpcMempoolTransactionRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::mempool::RpcMempoolTransactionResponse,
+        near_jsonrpc_primitives::types::mempool::RpcMempoolError,
+    > {
+        let response = self.client_send(GetMempoolTransaction::rpc_from(request_data)).await?;
+        Ok(response.rpc_into())
+    }
+
+    /// Returns the number and size of the pending transactions of every shard.
+    async fn mempool_stats(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::mempool::RpcMempoolStatsResponse,
+        near_jsonrpc_primitives::types::mempool::RpcMempoolError,
+    > {
+        let shards = self.client_send(GetMempoolStats {}).await?;
+        Ok(near_jsonrpc_primitives::types::mempool::RpcMempoolStatsResponse { shards })
+    }
+
     /// Returns the future windows for maintenance in current epoch for the specified account
     /// In the maintenance windows, the node will not be block producer or chunk producer
     async fn maintenance_windows(
@@ -1277,6 +1552,124 @@ impl JsonRpcHandler {
 
         Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxFastForwardResponse {})
     }
+
+    async fn sandbox_snapshot(
+        &self,
+        _request: near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotError,
+    > {
+        use near_client_primitives::types::SandboxResponse;
+
+        let response = self
+            .client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxSnapshot)
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        match response {
+            SandboxResponse::SandboxSnapshotCreated(snapshot_id) => {
+                Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotResponse {
+                    snapshot_id,
+                })
+            }
+            response => Err(
+                near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotError::InternalError {
+                    error_message: format!("sandbox failed to take a snapshot: {:?}", response),
+                },
+            ),
+        }
+    }
+
+    async fn sandbox_revert(
+        &self,
+        revert_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertError,
+    > {
+        use near_client_primitives::types::SandboxResponse;
+
+        let response = self
+            .client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxRevert(
+                revert_request.snapshot_id,
+            ))
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        match response {
+            SandboxResponse::SandboxRevertFinished => {
+                Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertResponse {})
+            }
+            response => {
+                Err(near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertError::InternalError {
+                    error_message: format!("sandbox failed to revert: {:?}", response),
+                })
+            }
+        }
+    }
+
+    async fn sandbox_impersonate_account(
+        &self,
+        impersonate_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxImpersonateAccountRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxImpersonateAccountResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxImpersonateAccountError,
+    > {
+        self.client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxImpersonateAccount(
+                impersonate_request.account_id,
+            ))
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxImpersonateAccountResponse {})
+    }
+
+    async fn sandbox_stop_impersonating_account(
+        &self,
+        stop_impersonating_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxStopImpersonatingAccountRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxStopImpersonatingAccountResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxStopImpersonatingAccountError,
+    > {
+        self.client_sender
+            .send_async(
+                near_client_primitives::types::SandboxMessage::SandboxStopImpersonatingAccount(
+                    stop_impersonating_request.account_id,
+                ),
+            )
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxStopImpersonatingAccountResponse {})
+    }
+
+    async fn sandbox_set_block_timestamp(
+        &self,
+        set_block_timestamp_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxSetBlockTimestampRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSetBlockTimestampResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSetBlockTimestampError,
+    > {
+        use near_client_primitives::types::SandboxResponse;
+
+        let response = self
+            .client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxSetBlockTimestamp(
+                set_block_timestamp_request.timestamp,
+            ))
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        match response {
+            SandboxResponse::SandboxNoResponse => {
+                Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxSetBlockTimestampResponse {})
+            }
+            response => Err(
+                near_jsonrpc_primitives::types::sandbox::RpcSandboxSetBlockTimestampError::InternalError {
+                    error_message: format!("sandbox failed to set the block timestamp: {:?}", response),
+                },
+            ),
+        }
+    }
 }
 
 #[cfg(feature = "test_features")]
@@ -1356,12 +1749,16 @@ impl JsonRpcHandler {
 }
 
 fn rpc_handler(
+    req: HttpRequest,
     message: web::Json<Message>,
     handler: web::Data<JsonRpcHandler>,
 ) -> impl Future<Output = Result<HttpResponse, HttpError>> {
+    let client_ip = handler.rate_limiter.client_ip(&req);
     let response = async move {
-        let message = handler.process(message.0).await?;
-        Ok(HttpResponse::Ok().json(&message))
+        match handler.process(message.0, client_ip).await? {
+            Some(message) => Ok(HttpResponse::Ok().json(&message)),
+            None => Ok(HttpResponse::NoContent().finish()),
+        }
     };
     response.boxed()
 }
@@ -1562,10 +1959,13 @@ pub fn start_http(
         polling_config,
         limits_config,
         enable_debug_rpc,
+        enable_state_overrides,
         experimental_debug_pages_src_path: debug_pages_src_path,
     } = config;
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr.to_string());
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
+    let ws_connections = Arc::new(AtomicUsize::new(0));
+    let rate_limiter = Arc::new(rate_limit::RateLimiter::new(&limits_config.rate_limits));
     info!(target:"network", "Starting http server at {}", addr);
     let mut servers = Vec::new();
     let listener = HttpServer::new(move || {
@@ -1576,16 +1976,21 @@ pub fn start_http(
                 view_client_sender: view_client_sender.clone(),
                 peer_manager_sender: peer_manager_sender.clone(),
                 polling_config,
+                limits_config: limits_config.clone(),
                 genesis_config: genesis_config.clone(),
                 enable_debug_rpc,
+                enable_state_overrides,
                 debug_pages_src_path: debug_pages_src_path.clone().map(Into::into),
                 entity_debug_handler: entity_debug_handler.clone(),
+                ws_connections: ws_connections.clone(),
+                rate_limiter: rate_limiter.clone(),
                 #[cfg(feature = "test_features")]
                 gc_sender: gc_sender.clone(),
             }))
             .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
             .wrap(middleware::Logger::default())
             .service(web::resource("/").route(web::post().to(rpc_handler)))
+            .service(web::resource("/ws").route(web::get().to(subscriptions::ws_handler)))
             .service(
                 web::resource("/status")
                     .route(web::get().to(status_handler))
@@ -1,4 +1,6 @@
-use near_o11y::metrics::{exponential_buckets, HistogramVec, IntCounter, IntCounterVec};
+use near_o11y::metrics::{
+    exponential_buckets, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
 use once_cell::sync::Lazy;
 
 pub static RPC_PROCESSING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
@@ -55,3 +57,64 @@ pub static RPC_UNREACHABLE_ERROR_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static RPC_WS_ACTIVE_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_gauge(
+        "near_rpc_ws_active_connections",
+        "Number of currently open RPC WebSocket connections",
+    )
+    .unwrap()
+});
+pub static RPC_WS_REJECTED_CONNECTIONS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter(
+        "near_rpc_ws_rejected_connections_total",
+        "Total count of RPC WebSocket connections rejected because of the connection limit",
+    )
+    .unwrap()
+});
+pub static RPC_WS_SLOW_CONNECTIONS_CLOSED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter(
+        "near_rpc_ws_slow_connections_closed_total",
+        "Total count of RPC WebSocket connections closed because their send queue was full",
+    )
+    .unwrap()
+});
+pub static RPC_ACTIVE_SUBSCRIPTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_gauge_vec(
+        "near_rpc_active_subscriptions",
+        "Number of currently active RPC subscriptions, by subscription type",
+        &["subscription_type"],
+    )
+    .unwrap()
+});
+pub static RPC_SUBSCRIPTION_NOTIFICATIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter_vec(
+        "near_rpc_subscription_notifications_total",
+        "Total count of notifications sent to RPC subscribers, by subscription type",
+        &["subscription_type"],
+    )
+    .unwrap()
+});
+pub static RPC_SUBSCRIPTION_ERRORS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter_vec(
+        "near_rpc_subscription_errors_total",
+        "Total count of RPC subscriptions closed because of an error, by subscription type",
+        &["subscription_type"],
+    )
+    .unwrap()
+});
+pub static RPC_BATCH_SIZE: Lazy<Histogram> = Lazy::new(|| {
+    near_o11y::metrics::try_create_histogram_with_buckets(
+        "near_rpc_batch_size",
+        "Number of requests in JSON-RPC batches",
+        exponential_buckets(1.0, 2.0, 12).unwrap(),
+    )
+    .unwrap()
+});
+pub static RPC_RATE_LIMITED_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter_vec(
+        "near_rpc_rate_limited_total",
+        "Total count of RPC requests rejected because of rate limits, by the exceeded limit",
+        &["limit"],
+    )
+    .unwrap()
+});
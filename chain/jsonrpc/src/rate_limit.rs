@@ -0,0 +1,267 @@
+//! Token-bucket rate limiting of JSON-RPC requests.
+//!
+//! Operators can limit the rate of specific (usually heavy) methods, which is
+//! shared by all clients, as well as the rate of requests coming from a single
+//! client IP address.
+//!
+//! Clients are identified by the address of the peer connecting to the node.
+//! Behind a reverse proxy that is the address of the proxy, so all clients
+//! would share a single bucket unless `client_ip_header` is configured.
+
+use lru::LruCache;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Maximum number of tracked client IPs.  Once it is reached, the bucket of
+/// the least recently seen client is dropped, which only resets the limit of
+/// a client that hasn't sent requests for a while.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Configuration of a single token bucket.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RateLimitConfig {
+    /// Maximum number of requests which can be served in a burst.
+    pub burst: u64,
+    /// Number of requests per second the bucket is refilled with.
+    pub requests_per_second: f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RpcRateLimitsConfig {
+    /// Limits keyed by method name, shared by all clients.  Calls of `query`
+    /// may also be limited by the request kind, using the same names as the
+    /// `near_rpc_total_count` metric (e.g. `query_call_function`).
+    #[serde(default)]
+    pub methods: HashMap<String, RateLimitConfig>,
+    /// Limit applied to every client IP address across all methods.
+    #[serde(default)]
+    pub per_client_ip: Option<RateLimitConfig>,
+    /// Header (e.g. `X-Forwarded-For`) from which the client IP address is
+    /// taken instead of the peer address.  The last address in the header is
+    /// used, i.e. the one appended by the proxy in front of the node.  Only set
+    /// this if the node can be reached through a trusted proxy alone, since
+    /// clients can put any value in the header otherwise.
+    #[serde(default)]
+    pub client_ip_header: Option<String>,
+}
+
+struct TokenBucket {
+    config: RateLimitConfig,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: RateLimitConfig, now: Instant) -> Self {
+        Self { config, tokens: config.burst as f64, last_refill: now }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.config.requests_per_second).min(self.config.burst as f64);
+        self.last_refill = now;
+    }
+
+    /// Takes a token from the bucket if there is one.
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The limit a rejected request has hit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExceededLimit {
+    Method,
+    ClientIp,
+}
+
+impl ExceededLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Method => "method",
+            Self::ClientIp => "client_ip",
+        }
+    }
+}
+
+/// Returns the address of the client, preferring the last address in the
+/// value of the configured client IP header over the peer address.
+fn client_ip_from(peer_ip: Option<IpAddr>, header_value: Option<&str>) -> Option<IpAddr> {
+    header_value
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|last| last.trim().parse().ok())
+        .or(peer_ip)
+}
+
+/// Rate limiter shared by all workers of the JSON-RPC server.
+pub(crate) struct RateLimiter {
+    method_buckets: HashMap<String, Mutex<TokenBucket>>,
+    per_client_ip: Option<RateLimitConfig>,
+    client_ip_header: Option<String>,
+    client_buckets: Mutex<LruCache<IpAddr, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RpcRateLimitsConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            method_buckets: config
+                .methods
+                .iter()
+                .map(|(method, config)| {
+                    (method.clone(), Mutex::new(TokenBucket::new(*config, now)))
+                })
+                .collect(),
+            per_client_ip: config.per_client_ip,
+            client_ip_header: config.client_ip_header.clone(),
+            client_buckets: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_TRACKED_CLIENTS).unwrap(),
+            )),
+        }
+    }
+
+    /// Returns the IP address the client limit of a request is tracked by.
+    pub fn client_ip(&self, req: &actix_web::HttpRequest) -> Option<IpAddr> {
+        let header_value = self
+            .client_ip_header
+            .as_ref()
+            .and_then(|header| req.headers().get(header.as_str()))
+            .and_then(|value| value.to_str().ok());
+        client_ip_from(req.peer_addr().map(|addr| addr.ip()), header_value)
+    }
+
+    /// Checks whether a call of `method` by the given client is allowed,
+    /// consuming a token from each relevant bucket.
+    pub fn check(&self, method: &str, client_ip: Option<IpAddr>) -> Result<(), ExceededLimit> {
+        self.check_at(method, client_ip, Instant::now())
+    }
+
+    /// Checks whether a call of `method` is allowed, ignoring client limits.
+    ///
+    /// Used for limits which can only be checked after the request has been
+    /// parsed, like the kind of a `query` request.
+    pub fn check_method(&self, method: &str) -> Result<(), ExceededLimit> {
+        self.check_method_at(method, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        method: &str,
+        client_ip: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), ExceededLimit> {
+        // Check the client limit first so that a single misbehaving client
+        // doesn't use up the budget of methods shared with everyone else.
+        if let (Some(config), Some(client_ip)) = (self.per_client_ip, client_ip) {
+            let mut client_buckets = self.client_buckets.lock().unwrap();
+            let bucket =
+                client_buckets.get_or_insert_mut(client_ip, || TokenBucket::new(config, now));
+            if !bucket.try_acquire(now) {
+                return Err(ExceededLimit::ClientIp);
+            }
+        }
+        self.check_method_at(method, now)
+    }
+
+    fn check_method_at(&self, method: &str, now: Instant) -> Result<(), ExceededLimit> {
+        match self.method_buckets.get(method) {
+            Some(bucket) if !bucket.lock().unwrap().try_acquire(now) => Err(ExceededLimit::Method),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        client_ip_from, ExceededLimit, RateLimitConfig, RateLimiter, RpcRateLimitsConfig,
+        MAX_TRACKED_CLIENTS,
+    };
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    const LIMIT: RateLimitConfig = RateLimitConfig { burst: 2, requests_per_second: 1.0 };
+
+    #[test]
+    fn test_method_limit() {
+        let config = RpcRateLimitsConfig {
+            methods: [("query".to_string(), LIMIT)].into_iter().collect(),
+            per_client_ip: None,
+            client_ip_header: None,
+        };
+        let limiter = RateLimiter::new(&config);
+        let now = Instant::now();
+        assert!(limiter.check_at("query", None, now).is_ok());
+        assert!(limiter.check_at("query", None, now).is_ok());
+        assert_eq!(limiter.check_at("query", None, now), Err(ExceededLimit::Method));
+        // Methods without a configured limit are never limited.
+        assert!(limiter.check_at("block", None, now).is_ok());
+        // The bucket refills over time, but never above the burst size.
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.check_at("query", None, later).is_ok());
+        assert!(limiter.check_at("query", None, later).is_ok());
+        assert_eq!(limiter.check_at("query", None, later), Err(ExceededLimit::Method));
+    }
+
+    #[test]
+    fn test_client_limit() {
+        let config = RpcRateLimitsConfig {
+            methods: Default::default(),
+            per_client_ip: Some(LIMIT),
+            client_ip_header: None,
+        };
+        let limiter = RateLimiter::new(&config);
+        let now = Instant::now();
+        let alice: IpAddr = "10.0.0.1".parse().unwrap();
+        let bob: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(limiter.check_at("block", Some(alice), now).is_ok());
+        assert!(limiter.check_at("status", Some(alice), now).is_ok());
+        assert_eq!(limiter.check_at("block", Some(alice), now), Err(ExceededLimit::ClientIp));
+        assert!(limiter.check_at("block", Some(bob), now).is_ok());
+        assert!(limiter.check_at("block", Some(alice), now + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_tracked_clients_are_capped() {
+        let config = RpcRateLimitsConfig {
+            methods: Default::default(),
+            per_client_ip: Some(LIMIT),
+            client_ip_header: None,
+        };
+        let limiter = RateLimiter::new(&config);
+        let now = Instant::now();
+        let first: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.check_at("block", Some(first), now).is_ok());
+        assert!(limiter.check_at("block", Some(first), now).is_ok());
+        assert_eq!(limiter.check_at("block", Some(first), now), Err(ExceededLimit::ClientIp));
+        for i in 0..MAX_TRACKED_CLIENTS as u32 {
+            let client = IpAddr::from(std::net::Ipv4Addr::from(0x0b00_0000 + i));
+            assert!(limiter.check_at("block", Some(client), now).is_ok());
+        }
+        assert_eq!(limiter.client_buckets.lock().unwrap().len(), MAX_TRACKED_CLIENTS);
+        // The least recently seen client was dropped and starts over.
+        assert!(limiter.check_at("block", Some(first), now).is_ok());
+    }
+
+    #[test]
+    fn test_client_ip_from_header() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "192.0.2.7".parse().unwrap();
+        assert_eq!(client_ip_from(Some(proxy), None), Some(proxy));
+        assert_eq!(client_ip_from(Some(proxy), Some("192.0.2.7")), Some(client));
+        // Only the address appended by the trusted proxy is used, not the
+        // ones sent by the client itself.
+        assert_eq!(client_ip_from(Some(proxy), Some("203.0.113.1, 192.0.2.7")), Some(client));
+        assert_eq!(client_ip_from(Some(proxy), Some("garbage")), Some(proxy));
+    }
+}
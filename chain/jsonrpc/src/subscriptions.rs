@@ -0,0 +1,563 @@
+//! WebSocket endpoint with `subscribe` and `unsubscribe` methods.
+//!
+//! Besides the subscription methods, the WebSocket accepts any regular JSON-RPC
+//! request and answers it exactly like the HTTP endpoint does.  Subscriptions
+//! are served by polling the view client at the configured polling interval and
+//! pushing `subscription` notifications to the client whenever something new
+//! shows up, so that clients don't have to poll the node themselves.
+//!
+//! Messages to the client go through a bounded queue.  Clients which don't
+//! read their notifications fast enough to keep up are disconnected once the
+//! queue is full rather than having the node buffer messages for them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use actix_http::ws;
+use actix_web::http::header;
+use actix_web::{web, Error as HttpError, HttpRequest, HttpResponse};
+use bytes::BytesMut;
+use futures::channel::mpsc;
+use futures::future::{AbortHandle, Abortable};
+use futures::StreamExt;
+use near_client::{GetBlock, GetChunk, GetStateChanges, TxStatus};
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::message::Message;
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_jsonrpc_primitives::types::changes::RpcStateChangesError;
+use near_jsonrpc_primitives::types::chunks::RpcChunkError;
+use near_jsonrpc_primitives::types::subscriptions::{
+    RpcSubscribeResponse, RpcSubscriptionError, RpcSubscriptionEvent, RpcSubscriptionNotification,
+    RpcSubscriptionRequest, RpcUnsubscribeRequest, RpcUnsubscribeResponse,
+};
+use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockId, BlockReference, Finality};
+use near_primitives::views::{BlockView, ChunkView, TxExecutionStatus, TxStatusView};
+use serde_json::Value;
+use tokio::time::sleep;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::api::RpcRequest;
+use crate::{metrics, serialize_response, tx_execution_status_meets_expectations, JsonRpcHandler};
+
+/// Name of the JSON-RPC notification method used to push subscription events.
+const SUBSCRIPTION_NOTIFICATION_METHOD: &str = "subscription";
+
+/// Maximum number of blocks a subscription catches up on after a single poll.
+///
+/// If the node advances by more than this many blocks between two polls only
+/// the most recent ones are reported, preceded by a `gap` event with the range
+/// of skipped heights.
+const MAX_BLOCKS_PER_POLL: u64 = 16;
+
+/// Handles the `/ws` endpoint: performs the WebSocket handshake and spawns a
+/// task serving the connection.
+pub(crate) async fn ws_handler(
+    req: HttpRequest,
+    mut payload: web::Payload,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    ws::verify_handshake(req.head())?;
+    let accept_key = match req.headers().get(header::SEC_WEBSOCKET_KEY) {
+        Some(key) => ws::hash_key(key.as_bytes()),
+        None => return Ok(HttpResponse::BadRequest().finish()),
+    };
+
+    let connection_guard = match ConnectionGuard::new(
+        handler.ws_connections.clone(),
+        handler.limits_config.max_ws_connections,
+    ) {
+        Some(guard) => guard,
+        None => {
+            metrics::RPC_WS_REJECTED_CONNECTIONS_TOTAL.inc();
+            return Ok(HttpResponse::ServiceUnavailable().finish());
+        }
+    };
+
+    let client_ip = handler.rate_limiter.client_ip(&req);
+    let max_frame_size = handler.limits_config.json_payload_max_size;
+    let (sender, receiver) = mpsc::channel(handler.limits_config.ws_send_queue_size);
+    let sender = WsSender::new(sender);
+    actix_web::rt::spawn(async move {
+        let _connection_guard = connection_guard;
+        let mut connection = WsConnection::new(handler, client_ip, sender);
+        let mut codec = ws::Codec::new().max_size(max_frame_size);
+        let mut buf = BytesMut::new();
+        'connection: while let Some(chunk) = payload.next().await {
+            match chunk {
+                Ok(chunk) => buf.extend_from_slice(&chunk),
+                Err(err) => {
+                    tracing::debug!(target: "jsonrpc", ?err, "Failed to read WebSocket payload");
+                    break;
+                }
+            }
+            loop {
+                match codec.decode(&mut buf) {
+                    Ok(Some(frame)) => {
+                        if !connection.handle_frame(frame).await {
+                            break 'connection;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        tracing::debug!(target: "jsonrpc", ?err, "WebSocket protocol error");
+                        break 'connection;
+                    }
+                }
+            }
+        }
+        connection.close();
+    });
+
+    let mut codec = ws::Codec::new().max_size(max_frame_size);
+    let stream = receiver.map(move |message| {
+        let mut buf = BytesMut::new();
+        codec.encode(message, &mut buf).map(|()| buf.freeze())
+    });
+    Ok(HttpResponse::SwitchingProtocols()
+        .upgrade("websocket")
+        .insert_header((
+            header::SEC_WEBSOCKET_ACCEPT,
+            // The key is base64 encoded and thus a valid header value.
+            header::HeaderValue::from_bytes(&accept_key).unwrap(),
+        ))
+        .streaming(stream))
+}
+
+/// Keeps track of the number of open WebSocket connections.
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl ConnectionGuard {
+    /// Registers a new connection unless there are already `limit` open ones.
+    fn new(counter: Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+        counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < limit).then_some(n + 1))
+            .ok()?;
+        metrics::RPC_WS_ACTIVE_CONNECTIONS.inc();
+        Some(Self(counter))
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+        metrics::RPC_WS_ACTIVE_CONNECTIONS.dec();
+    }
+}
+
+/// A running subscription.  Dropping it stops the subscription.
+struct ActiveSubscription {
+    name: &'static str,
+    abort_handle: AbortHandle,
+}
+
+impl ActiveSubscription {
+    fn new(name: &'static str, abort_handle: AbortHandle) -> Self {
+        metrics::RPC_ACTIVE_SUBSCRIPTIONS.with_label_values(&[name]).inc();
+        Self { name, abort_handle }
+    }
+}
+
+impl Drop for ActiveSubscription {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+        metrics::RPC_ACTIVE_SUBSCRIPTIONS.with_label_values(&[self.name]).dec();
+    }
+}
+
+type Subscriptions = Rc<RefCell<HashMap<u64, ActiveSubscription>>>;
+
+/// Queue of messages to be sent to the client, shared by the connection and
+/// all of its subscriptions.
+#[derive(Clone)]
+struct WsSender(Rc<RefCell<mpsc::Sender<ws::Message>>>);
+
+impl WsSender {
+    fn new(sender: mpsc::Sender<ws::Message>) -> Self {
+        Self(Rc::new(RefCell::new(sender)))
+    }
+
+    /// Queues a message for the client.
+    ///
+    /// If the queue is full the client doesn't keep up with its messages and
+    /// the connection is closed.  Returns `false` if the message was dropped
+    /// because the connection is closed.
+    fn send(&self, message: ws::Message) -> bool {
+        let mut sender = self.0.borrow_mut();
+        match sender.try_send(message) {
+            Ok(()) => true,
+            Err(err) if err.is_full() => {
+                tracing::debug!(target: "jsonrpc", "Closing WebSocket connection of a slow client");
+                metrics::RPC_WS_SLOW_CONNECTIONS_CLOSED_TOTAL.inc();
+                sender.close_channel();
+                false
+            }
+            // The connection is being closed.
+            Err(_) => false,
+        }
+    }
+
+    /// Serializes and queues a JSON-RPC message, see [`Self::send`].
+    fn send_json(&self, message: &Message) -> bool {
+        match serde_json::to_string(message) {
+            Ok(text) => self.send(ws::Message::Text(text.into())),
+            Err(err) => {
+                tracing::error!(target: "jsonrpc", ?err, "Failed to serialize WebSocket message");
+                true
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.0.borrow().is_closed()
+    }
+}
+
+/// State of a single WebSocket connection.
+struct WsConnection {
+    handler: web::Data<JsonRpcHandler>,
+    client_ip: Option<IpAddr>,
+    sender: WsSender,
+    subscriptions: Subscriptions,
+    next_subscription_id: u64,
+}
+
+impl WsConnection {
+    fn new(
+        handler: web::Data<JsonRpcHandler>,
+        client_ip: Option<IpAddr>,
+        sender: WsSender,
+    ) -> Self {
+        Self {
+            handler,
+            client_ip,
+            sender,
+            subscriptions: Default::default(),
+            next_subscription_id: 1,
+        }
+    }
+
+    /// Handles a single frame received from the client.
+    ///
+    /// Returns `false` if the connection should be closed.
+    async fn handle_frame(&mut self, frame: ws::Frame) -> bool {
+        match frame {
+            ws::Frame::Text(text) => {
+                if let Some(response) = self.handle_text(&text).await {
+                    self.sender.send_json(&response);
+                }
+            }
+            ws::Frame::Binary(_) | ws::Frame::Continuation(_) => {
+                self.sender.send_json(&Message::error(RpcError::parse_error(
+                    "Only unfragmented text frames are supported".to_owned(),
+                )));
+            }
+            ws::Frame::Ping(bytes) => {
+                self.sender.send(ws::Message::Pong(bytes));
+            }
+            ws::Frame::Pong(_) => {}
+            ws::Frame::Close(reason) => {
+                self.sender.send(ws::Message::Close(reason));
+                return false;
+            }
+        }
+        !self.sender.is_closed()
+    }
+
+    async fn handle_text(&mut self, text: &[u8]) -> Option<Message> {
+        let message: Message = match serde_json::from_slice(text) {
+            Ok(message) => message,
+            Err(err) => {
+                return Some(Message::error(RpcError::parse_error(format!(
+                    "Failed to parse JSON RPC message: {}",
+                    err
+                ))))
+            }
+        };
+        let id = message.id();
+        match message {
+            Message::Request(request) if request.method == "subscribe" => {
+                Some(Message::response(id, self.subscribe(request.params)))
+            }
+            Message::Request(request) if request.method == "unsubscribe" => {
+                Some(Message::response(id, self.unsubscribe(request.params)))
+            }
+            // Processing a message never fails with an HTTP error.
+            message => match self.handler.process(message, self.client_ip).await {
+                Ok(response) => response,
+                Err(err) => {
+                    Some(Message::error(RpcError::new_internal_error(None, err.to_string())))
+                }
+            },
+        }
+    }
+
+    fn subscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        let request = RpcSubscriptionRequest::parse(params)?;
+        let limit = self.handler.limits_config.max_subscriptions_per_connection;
+        if self.subscriptions.borrow().len() >= limit {
+            return Err(RpcSubscriptionError::TooManySubscriptions { limit }.into());
+        }
+
+        let subscription_id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        let name = request.name();
+        let notifier = Notifier { subscription_id, name, sender: self.sender.clone() };
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let task = Abortable::new(
+            run_subscription(self.handler.clone(), request, notifier),
+            abort_registration,
+        );
+        let subscriptions = self.subscriptions.clone();
+        actix_web::rt::spawn(async move {
+            // Subscriptions which finish on their own (rather than being
+            // aborted) have to be removed from the connection.
+            if task.await.is_ok() {
+                subscriptions.borrow_mut().remove(&subscription_id);
+            }
+        });
+        self.subscriptions
+            .borrow_mut()
+            .insert(subscription_id, ActiveSubscription::new(name, abort_handle));
+        serialize_response(RpcSubscribeResponse { subscription_id })
+    }
+
+    fn unsubscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        let RpcUnsubscribeRequest { subscription_id } = RpcUnsubscribeRequest::parse(params)?;
+        match self.subscriptions.borrow_mut().remove(&subscription_id) {
+            Some(_) => serialize_response(RpcUnsubscribeResponse { subscription_id }),
+            None => Err(RpcSubscriptionError::UnknownSubscription { subscription_id }.into()),
+        }
+    }
+
+    /// Stops all subscriptions of the connection.
+    fn close(self) {
+        self.subscriptions.borrow_mut().clear();
+    }
+}
+
+/// Pushes events of a single subscription to the client.
+struct Notifier {
+    subscription_id: u64,
+    name: &'static str,
+    sender: WsSender,
+}
+
+impl Notifier {
+    fn notify(&self, event: RpcSubscriptionEvent) -> Result<(), RpcError> {
+        let params = serialize_response(RpcSubscriptionNotification {
+            subscription_id: self.subscription_id,
+            event,
+        })?;
+        let message = Message::notification(SUBSCRIPTION_NOTIFICATION_METHOD.to_string(), params);
+        if !self.sender.send_json(&message) {
+            return Err(RpcSubscriptionError::ConnectionClosed.into());
+        }
+        metrics::RPC_SUBSCRIPTION_NOTIFICATIONS_TOTAL.with_label_values(&[self.name]).inc();
+        Ok(())
+    }
+}
+
+async fn run_subscription(
+    handler: web::Data<JsonRpcHandler>,
+    request: RpcSubscriptionRequest,
+    notifier: Notifier,
+) {
+    let result = match &request {
+        RpcSubscriptionRequest::Transaction { tx_hash, sender_account_id, wait_until } => {
+            watch_transaction(&handler, *tx_hash, sender_account_id, wait_until, &notifier).await
+        }
+        RpcSubscriptionRequest::Blocks { finality }
+        | RpcSubscriptionRequest::Chunks { finality }
+        | RpcSubscriptionRequest::StateChanges { finality, .. }
+        | RpcSubscriptionRequest::Receipts { finality, .. } => {
+            follow_blocks(&handler, &request, finality, &notifier).await
+        }
+    };
+    if result.is_err() {
+        metrics::RPC_SUBSCRIPTION_ERRORS_TOTAL.with_label_values(&[notifier.name]).inc();
+    }
+    let _ = notifier.notify(RpcSubscriptionEvent::Closed { error: result.err() });
+}
+
+async fn watch_transaction(
+    handler: &JsonRpcHandler,
+    tx_hash: CryptoHash,
+    signer_account_id: &AccountId,
+    wait_until: &TxExecutionStatus,
+    notifier: &Notifier,
+) -> Result<(), RpcError> {
+    loop {
+        let status: Result<TxStatusView, RpcTransactionError> = handler
+            .view_client_send(TxStatus {
+                tx_hash,
+                signer_account_id: signer_account_id.clone(),
+                fetch_receipt: false,
+            })
+            .await;
+        match status {
+            Ok(status) => {
+                if tx_execution_status_meets_expectations(wait_until, &status.status) {
+                    return notifier
+                        .notify(RpcSubscriptionEvent::Transaction { outcome: status.into() });
+                }
+            }
+            Err(RpcTransactionError::UnknownTransaction { .. }) => {}
+            Err(err) => return Err(err.into()),
+        }
+        sleep(handler.polling_config.polling_interval).await;
+    }
+}
+
+/// Polls for new blocks with the given finality and reports each of them to
+/// [`process_block`].  Only returns on error.
+async fn follow_blocks(
+    handler: &JsonRpcHandler,
+    request: &RpcSubscriptionRequest,
+    finality: &Finality,
+    notifier: &Notifier,
+) -> Result<(), RpcError> {
+    let mut last_height = None;
+    loop {
+        let head: Result<BlockView, RpcBlockError> =
+            handler.view_client_send(GetBlock(BlockReference::Finality(finality.clone()))).await;
+        let head = head?;
+        let head_height = head.header.height;
+        if last_height.map_or(true, |last_height| head_height > last_height) {
+            let first_height = match last_height {
+                Some(last_height) => {
+                    let first_height = std::cmp::max(
+                        last_height + 1,
+                        head_height.saturating_sub(MAX_BLOCKS_PER_POLL),
+                    );
+                    if first_height > last_height + 1 {
+                        notifier.notify(RpcSubscriptionEvent::Gap {
+                            start_height: last_height + 1,
+                            end_height: first_height - 1,
+                        })?;
+                    }
+                    first_height
+                }
+                None => head_height,
+            };
+            for height in first_height..head_height {
+                let block: Result<BlockView, RpcBlockError> = handler
+                    .view_client_send(GetBlock(BlockReference::BlockId(BlockId::Height(height))))
+                    .await;
+                match block {
+                    Ok(block) => process_block(handler, request, block, notifier).await?,
+                    // Skipped heights don't have blocks.
+                    Err(RpcBlockError::UnknownBlock { .. }) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            process_block(handler, request, head, notifier).await?;
+            last_height = Some(head_height);
+        }
+        sleep(handler.polling_config.polling_interval).await;
+    }
+}
+
+async fn process_block(
+    handler: &JsonRpcHandler,
+    request: &RpcSubscriptionRequest,
+    block: BlockView,
+    notifier: &Notifier,
+) -> Result<(), RpcError> {
+    let block_hash = block.header.hash;
+    match request {
+        RpcSubscriptionRequest::Blocks { .. } => {
+            notifier.notify(RpcSubscriptionEvent::Block { block })?;
+        }
+        RpcSubscriptionRequest::Chunks { .. } => {
+            for chunk in fetch_new_chunks(handler, &block).await? {
+                notifier.notify(RpcSubscriptionEvent::Chunk { block_hash, chunk })?;
+            }
+        }
+        RpcSubscriptionRequest::StateChanges { state_changes_request, .. } => {
+            let changes: Result<_, RpcStateChangesError> = handler
+                .view_client_send(GetStateChanges {
+                    block_hash,
+                    state_changes_request: state_changes_request.clone(),
+                })
+                .await;
+            let changes = changes?;
+            if !changes.is_empty() {
+                notifier.notify(RpcSubscriptionEvent::StateChanges { block_hash, changes })?;
+            }
+        }
+        RpcSubscriptionRequest::Receipts { receiver_id, .. } => {
+            for chunk in fetch_new_chunks(handler, &block).await? {
+                for receipt in chunk.receipts {
+                    if &receipt.receiver_id == receiver_id {
+                        notifier.notify(RpcSubscriptionEvent::Receipt { block_hash, receipt })?;
+                    }
+                }
+            }
+        }
+        RpcSubscriptionRequest::Transaction { .. } => {
+            unreachable!("transaction subscriptions don't follow blocks")
+        }
+    }
+    Ok(())
+}
+
+/// Returns the chunks included for the first time in the given block.
+///
+/// Chunks of shards which aren't tracked by this node are skipped.
+async fn fetch_new_chunks(
+    handler: &JsonRpcHandler,
+    block: &BlockView,
+) -> Result<Vec<ChunkView>, RpcError> {
+    let mut chunks = vec![];
+    for chunk_header in &block.chunks {
+        if chunk_header.height_included != block.header.height {
+            continue;
+        }
+        let chunk: Result<ChunkView, RpcChunkError> = handler
+            .view_client_send(GetChunk::BlockHash(block.header.hash, chunk_header.shard_id))
+            .await;
+        match chunk {
+            Ok(chunk) => chunks.push(chunk),
+            Err(RpcChunkError::UnknownChunk { .. }) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WsSender;
+    use actix_http::ws;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_slow_client_is_disconnected() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let sender = WsSender::new(sender);
+        let mut queued = 0;
+        while sender.send(ws::Message::Ping(Default::default())) {
+            queued += 1;
+            assert!(queued <= 5, "the send queue is unbounded");
+        }
+        assert!(sender.is_closed());
+        assert!(!sender.send(ws::Message::Ping(Default::default())));
+
+        // Messages queued before the connection got closed are still sent.
+        let sent = futures::executor::block_on(async {
+            let mut sent = 0;
+            while receiver.next().await.is_some() {
+                sent += 1;
+            }
+            sent
+        });
+        assert_eq!(sent, queued);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::blacklist;
 use crate::concurrency::rate;
+use crate::config_json::EncryptionMode;
+use crate::network_protocol::Compression;
 use crate::network_protocol::PeerAddr;
 use crate::network_protocol::PeerInfo;
 use crate::peer_manager::peer_store;
@@ -195,6 +197,10 @@ pub struct NetworkConfig {
 
     /// Configuration of rate limits for incoming messages.
     pub received_messages_rate_limits: messages_limits::Config,
+    /// Compression algorithms supported by this node, in the order of
+    /// preference. See `config_json::Config::message_compression`.
+    pub message_compression: Vec<Compression>,
+    pub encryption: EncryptionMode,
 
     #[cfg(test)]
     pub(crate) event_sink:
@@ -380,6 +386,8 @@ impl NetworkConfig {
             },
             // Use a preset to configure rate limits and override entries with user defined values later.
             received_messages_rate_limits: messages_limits::Config::standard_preset(),
+            message_compression: cfg.message_compression,
+            encryption: cfg.encryption,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -458,6 +466,8 @@ impl NetworkConfig {
             }),
             skip_tombstones: None,
             received_messages_rate_limits: messages_limits::Config::default(),
+            message_compression: vec![],
+            encryption: EncryptionMode::Preferred,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
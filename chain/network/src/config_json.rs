@@ -1,4 +1,4 @@
-use crate::network_protocol::PeerAddr;
+use crate::network_protocol::{Compression, PeerAddr};
 use crate::rate_limits::messages_limits;
 use crate::stun;
 use near_async::time::Duration;
@@ -209,12 +209,36 @@ pub struct Config {
     /// such a case.
     #[serde(default = "default_trusted_stun_servers")]
     pub trusted_stun_servers: Vec<stun::ServerAddr>,
+    /// Algorithms that may be used to compress the messages exchanged with
+    /// peers, in the order of preference, e.g. `["zstd", "lz4"]`. An
+    /// algorithm is used on a connection only if the peer supports it too.
+    /// Compression is disabled if empty.
+    #[serde(default)]
+    pub message_compression: Vec<Compression>,
+    /// Whether the connections with peers are encrypted. See `EncryptionMode`.
+    #[serde(default)]
+    pub encryption: EncryptionMode,
     // Experimental part of the JSON config. Regular users/validators should not have to set any values there.
     // Field names in here can change/disappear at any moment without warning.
     #[serde(default)]
     pub experimental: ExperimentalConfig,
 }
 
+/// Encryption of the connections with peers, using the Noise protocol keyed
+/// by the node key.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionMode {
+    /// Connections are never encrypted.
+    Disabled,
+    /// Connections are encrypted if the peer supports it, and sent in
+    /// plaintext otherwise.
+    #[default]
+    Preferred,
+    /// Connections with peers which don't support encryption are refused.
+    Required,
+}
+
 fn default_tier1_enable_inbound() -> bool {
     true
 }
@@ -332,6 +356,8 @@ impl Default for Config {
             public_addrs: vec![],
             allow_private_ip_in_public_addrs: false,
             trusted_stun_servers: default_trusted_stun_servers(),
+            message_compression: vec![],
+            encryption: EncryptionMode::default(),
             experimental: Default::default(),
         }
     }
@@ -0,0 +1,265 @@
+//! Compression of the frames sent over peer connections.
+//!
+//! Peers advertise the algorithms they support in their `Handshake`. Once both
+//! handshakes have been exchanged, every frame of the connection starts with a
+//! byte telling how the rest of the frame is compressed, so that messages for
+//! which compression doesn't pay off can still be sent as they are. Peers which
+//! don't advertise any algorithm (in particular older nodes) keep exchanging
+//! plain frames.
+use crate::stats::metrics;
+use std::borrow::Cow;
+use std::io::{Read as _, Write as _};
+
+/// Algorithm used to compress the frames of a connection.
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    strum::IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Compression {
+    Zstd,
+    Lz4,
+}
+
+const TAG_NONE: u8 = 0;
+const TAG_ZSTD: u8 = 1;
+const TAG_LZ4: u8 = 2;
+
+/// Messages smaller than that are sent uncompressed.
+const MIN_COMPRESSED_SIZE: usize = 1024;
+
+/// Maximum ratio between the size of a message and the size of its compressed
+/// frame. Messages which compress better are sent uncompressed, and frames
+/// which decompress to more are rejected, so that a peer can't make us
+/// allocate much more memory than it sent.
+pub(crate) const MAX_COMPRESSION_RATIO: usize = 32;
+
+/// Bounds of the zstd window, as powers of two. The decompressor allocates the
+/// whole window declared by a frame, so frames are compressed with a window no
+/// larger than the message and frames declaring a window larger than the
+/// message may be are rejected.
+const ZSTD_MIN_WINDOW_LOG: u32 = 10;
+const ZSTD_MAX_WINDOW_LOG: u32 = 21;
+
+/// Smallest zstd window (within bounds) covering a message of `size` bytes.
+fn zstd_window_log(size: usize) -> u32 {
+    let log = usize::BITS - size.saturating_sub(1).leading_zeros();
+    log.clamp(ZSTD_MIN_WINDOW_LOG, ZSTD_MAX_WINDOW_LOG)
+}
+
+impl Compression {
+    fn tag(self) -> u8 {
+        match self {
+            Compression::Zstd => TAG_ZSTD,
+            Compression::Lz4 => TAG_LZ4,
+        }
+    }
+}
+
+/// Picks the algorithm used by both sides of a connection: the first algorithm
+/// supported by the initiator of the connection which the other side supports
+/// as well.
+pub(crate) fn negotiate(outbound: &[Compression], inbound: &[Compression]) -> Option<Compression> {
+    outbound.iter().find(|c| inbound.contains(c)).copied()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum DecompressFrameError {
+    #[error("empty frame")]
+    Empty,
+    #[error("unknown compression tag {0}")]
+    UnknownTag(u8),
+    #[error("decompressed frame is larger than {0} bytes")]
+    TooLarge(usize),
+    #[error("zstd: {0}")]
+    Zstd(std::io::Error),
+    #[error("lz4: {0}")]
+    Lz4(lz4_flex::block::DecompressError),
+    #[error("lz4: decompressed {got} bytes, expected {want}")]
+    Lz4SizeMismatch { got: usize, want: usize },
+}
+
+/// Builds the frame carrying `data` on a connection which negotiated
+/// `compression`. `msg_type` is only used for metrics.
+pub(crate) fn compress_frame(compression: Compression, data: &[u8], msg_type: &str) -> Vec<u8> {
+    let mut frame = vec![];
+    if data.len() >= MIN_COMPRESSED_SIZE {
+        frame.push(compression.tag());
+        match compression {
+            Compression::Zstd => {
+                let mut encoder =
+                    zstd::stream::Encoder::new(&mut frame, zstd::DEFAULT_COMPRESSION_LEVEL)
+                        .expect("creating an encoder never fails");
+                encoder
+                    .window_log(zstd_window_log(data.len()))
+                    .and_then(|()| encoder.write_all(data))
+                    .and_then(|()| encoder.finish())
+                    .expect("writing to a Vec never fails");
+            }
+            Compression::Lz4 => frame.extend(lz4_flex::compress_prepend_size(data)),
+        }
+        metrics::PEER_MESSAGE_COMPRESSION_RATIO
+            .with_label_values(&[msg_type])
+            .observe(frame.len() as f64 / data.len() as f64);
+    }
+    let compression = if frame.is_empty()
+        || frame.len() > data.len()
+        || frame.len().saturating_mul(MAX_COMPRESSION_RATIO) < data.len()
+    {
+        frame.clear();
+        frame.push(TAG_NONE);
+        frame.extend_from_slice(data);
+        "none"
+    } else {
+        compression.into()
+    };
+    metrics::PEER_MESSAGE_COMPRESSION_INPUT_BYTES
+        .with_label_values(&[msg_type, compression])
+        .inc_by(data.len() as u64);
+    metrics::PEER_MESSAGE_COMPRESSION_OUTPUT_BYTES
+        .with_label_values(&[msg_type, compression])
+        .inc_by(frame.len() as u64);
+    frame
+}
+
+/// Extracts the message from a frame built by `compress_frame`, refusing to
+/// decompress it to more than `max_size` bytes or more than
+/// `MAX_COMPRESSION_RATIO` times the size of the frame. Zstd frames declaring
+/// a window larger than needed for such a message are rejected as well.
+pub(crate) fn decompress_frame(
+    frame: &[u8],
+    max_size: usize,
+) -> Result<Cow<[u8]>, DecompressFrameError> {
+    let max_size = max_size.min(frame.len().saturating_mul(MAX_COMPRESSION_RATIO));
+    let (tag, data) = frame.split_first().ok_or(DecompressFrameError::Empty)?;
+    match *tag {
+        TAG_NONE => Ok(Cow::Borrowed(data)),
+        TAG_ZSTD => {
+            let mut msg = vec![];
+            zstd::stream::Decoder::with_buffer(data)
+                .and_then(|mut decoder| {
+                    decoder.window_log_max(zstd_window_log(max_size))?;
+                    decoder.take(max_size as u64 + 1).read_to_end(&mut msg)
+                })
+                .map_err(DecompressFrameError::Zstd)?;
+            if msg.len() > max_size {
+                return Err(DecompressFrameError::TooLarge(max_size));
+            }
+            Ok(Cow::Owned(msg))
+        }
+        TAG_LZ4 => {
+            // `compress_prepend_size` stores the uncompressed size as a little
+            // endian u32 in front of the compressed data.
+            let size = data
+                .get(..4)
+                .map(|size| u32::from_le_bytes(size.try_into().unwrap()) as usize)
+                .ok_or(DecompressFrameError::Lz4(
+                    lz4_flex::block::DecompressError::ExpectedAnotherByte,
+                ))?;
+            if size > max_size {
+                return Err(DecompressFrameError::TooLarge(max_size));
+            }
+            let msg = lz4_flex::decompress(&data[4..], size).map_err(DecompressFrameError::Lz4)?;
+            if msg.len() != size {
+                return Err(DecompressFrameError::Lz4SizeMismatch { got: msg.len(), want: size });
+            }
+            Ok(Cow::Owned(msg))
+        }
+        tag => Err(DecompressFrameError::UnknownTag(tag)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zstd_encode(data: &[u8], window_log: u32) -> Vec<u8> {
+        let mut encoder = zstd::stream::Encoder::new(vec![], 0).unwrap();
+        encoder.window_log(window_log).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        use Compression::*;
+        assert_eq!(negotiate(&[Zstd, Lz4], &[Lz4, Zstd]), Some(Zstd));
+        assert_eq!(negotiate(&[Lz4, Zstd], &[Zstd, Lz4]), Some(Lz4));
+        assert_eq!(negotiate(&[Zstd, Lz4], &[Lz4]), Some(Lz4));
+        assert_eq!(negotiate(&[Zstd], &[Lz4]), None);
+        assert_eq!(negotiate(&[], &[Zstd, Lz4]), None);
+        assert_eq!(negotiate(&[Zstd, Lz4], &[]), None);
+    }
+
+    #[test]
+    fn test_compress_frame() {
+        let small = b"hello".to_vec();
+        // Compresses to about a quarter of its size.
+        let large: Vec<u8> = (0..12000).map(|_| b"abcd"[rand::random::<usize>() % 4]).collect();
+        let random: Vec<u8> = (0..5000).map(|_| rand::random()).collect();
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            for data in [&small, &large, &random] {
+                let frame = compress_frame(compression, data, "test");
+                assert!(frame.len() <= data.len() + 1);
+                let msg = decompress_frame(&frame, data.len()).unwrap();
+                assert_eq!(&msg[..], &data[..]);
+            }
+            // Small and incompressible messages are sent uncompressed.
+            assert_eq!(compress_frame(compression, &small, "test")[0], TAG_NONE);
+            assert_eq!(compress_frame(compression, &random, "test")[0], TAG_NONE);
+            let frame = compress_frame(compression, &large, "test");
+            assert_eq!(frame[0], compression.tag());
+            assert!(frame.len() < large.len() / 2);
+            // Messages larger than the limit are rejected.
+            assert!(matches!(
+                decompress_frame(&frame, large.len() - 1),
+                Err(DecompressFrameError::TooLarge(_))
+            ));
+            assert!(decompress_frame(&frame[..frame.len() / 2], large.len()).is_err());
+
+            // Messages compressing too well are sent uncompressed, and frames
+            // decompressing to too much data are rejected.
+            let zeros = vec![0; 1 << 20];
+            assert_eq!(compress_frame(compression, &zeros, "test")[0], TAG_NONE);
+            let mut bomb = vec![compression.tag()];
+            match compression {
+                Compression::Zstd => bomb.extend(zstd_encode(&zeros, ZSTD_MIN_WINDOW_LOG)),
+                Compression::Lz4 => bomb.extend(lz4_flex::compress_prepend_size(&zeros)),
+            }
+            assert!(bomb.len() * MAX_COMPRESSION_RATIO < zeros.len());
+            assert!(matches!(
+                decompress_frame(&bomb, zeros.len()),
+                Err(DecompressFrameError::TooLarge(_))
+            ));
+        }
+        assert!(matches!(decompress_frame(&[], 100), Err(DecompressFrameError::Empty)));
+        assert!(matches!(
+            decompress_frame(&[7, 1, 2], 100),
+            Err(DecompressFrameError::UnknownTag(7))
+        ));
+    }
+
+    #[test]
+    fn test_zstd_window() {
+        let data: Vec<u8> = (0..12000).map(|_| b"abcd"[rand::random::<usize>() % 4]).collect();
+        let frame = compress_frame(Compression::Zstd, &data, "test");
+        assert_eq!(frame[0], TAG_ZSTD);
+        assert_eq!(&decompress_frame(&frame, data.len()).unwrap()[..], &data[..]);
+
+        // A frame declaring a window much larger than the message would make
+        // the decompressor allocate the whole window up front. The window is
+        // bounded by the size of the frame even if the message size isn't.
+        let mut frame = vec![TAG_ZSTD];
+        frame.extend(zstd_encode(&data, 27));
+        assert!(frame.len() < data.len() / 2);
+        assert!(matches!(decompress_frame(&frame, usize::MAX), Err(DecompressFrameError::Zstd(_))));
+    }
+}
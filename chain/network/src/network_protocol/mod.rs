@@ -2,10 +2,13 @@
 #[path = "borsh.rs"]
 mod borsh_;
 mod borsh_conv;
+mod compression;
 mod edge;
 mod peer;
 mod proto_conv;
 mod state_sync;
+pub use compression::Compression;
+pub(crate) use compression::{compress_frame, decompress_frame, negotiate};
 pub use edge::*;
 use near_primitives::stateless_validation::ChunkEndorsement;
 use near_primitives::stateless_validation::ChunkStateWitnessAck;
@@ -44,7 +47,7 @@ use near_primitives::state_sync::{ShardStateSyncResponse, ShardStateSyncResponse
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::AccountId;
 use near_primitives::types::{BlockHeight, ShardId};
-use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::validator_signer::{ValidatorSigner, ValidatorSignerError};
 use near_primitives::views::FinalExecutionOutcomeView;
 use protobuf::Message as _;
 use std::collections::HashSet;
@@ -184,7 +187,7 @@ impl VersionedAccountData {
                 MAX_ACCOUNT_DATA_SIZE_BYTES
             );
         }
-        let signature = signer.sign_account_key_payload(&payload);
+        let signature = signer.sign_account_key_payload(&payload)?;
         Ok(SignedAccountData {
             account_data: self,
             payload: AccountKeySignedPayload { payload, signature },
@@ -220,6 +223,25 @@ impl AccountKeySignedPayload {
     }
 }
 
+/// Checks that `payload` is a serialized AccountData or OwnedAccount, which are
+/// the only payloads ever signed with the account key. Signers which get the
+/// payload from another process (like a remote signer daemon) use it to refuse
+/// signing anything else, e.g. a block hash.
+pub fn validate_account_key_payload(payload: &[u8]) -> anyhow::Result<()> {
+    use proto::account_key_payload::Payload_type as ProtoPT;
+    let payload = proto::AccountKeyPayload::parse_from_bytes(payload)?;
+    match &payload.payload_type {
+        Some(ProtoPT::AccountData(_)) => {
+            VersionedAccountData::try_from(&payload)?;
+        }
+        Some(ProtoPT::OwnedAccount(_)) => {
+            OwnedAccount::try_from(&payload)?;
+        }
+        None => anyhow::bail!("missing payload type"),
+    }
+    Ok(())
+}
+
 // TODO(gprusak): this is effectively immutable, and we always pass it around
 // in an Arc, so the Arc can be moved inside (except that constructing malformed
 // SignedAccountData for tests may get a little tricky).
@@ -256,18 +278,22 @@ impl OwnedAccount {
     /// Serializes OwnedAccount to proto and signs it using `signer`.
     /// Panics if OwnedAccount.account_key doesn't match signer.public_key(),
     /// as this would likely be a bug.
-    pub fn sign(self, signer: &ValidatorSigner) -> SignedOwnedAccount {
+    /// Returns an error if the signer fails to sign the payload.
+    pub fn sign(
+        self,
+        signer: &ValidatorSigner,
+    ) -> Result<SignedOwnedAccount, ValidatorSignerError> {
         assert_eq!(
             self.account_key,
             signer.public_key(),
             "OwnedAccount.account_key doesn't match the signer's account_key"
         );
         let payload = proto::AccountKeyPayload::from(&self).write_to_bytes().unwrap();
-        let signature = signer.sign_account_key_payload(&payload);
-        SignedOwnedAccount {
+        let signature = signer.sign_account_key_payload(&payload)?;
+        Ok(SignedOwnedAccount {
             owned_account: self,
             payload: AccountKeySignedPayload { payload, signature },
-        }
+        })
     }
 }
 
@@ -355,6 +381,14 @@ pub struct Handshake {
     pub(crate) partial_edge_info: PartialEdgeInfo,
     /// Account owned by the sender.
     pub(crate) owned_account: Option<SignedOwnedAccount>,
+    /// Compression algorithms supported by the sender, in the order of
+    /// preference.
+    pub(crate) supported_compression: Vec<Compression>,
+    /// Whether the sender supports encrypting the connection.
+    pub(crate) supports_encryption: bool,
+    /// Signature of `supports_encryption` made with the node key of the
+    /// sender. A Handshake without it is treated as not supporting encryption.
+    pub(crate) encryption_signature: Option<Signature>,
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, strum::IntoStaticStr)]
@@ -2,10 +2,10 @@
 use super::*;
 
 use crate::network_protocol::proto;
-use crate::network_protocol::{Handshake, HandshakeFailureReason};
+use crate::network_protocol::{Compression, Handshake, HandshakeFailureReason};
 use crate::network_protocol::{PeerChainInfoV2, PeerInfo};
 use near_primitives::block::GenesisId;
-use protobuf::MessageField as MF;
+use protobuf::{EnumOrUnknown, MessageField as MF};
 
 impl From<&GenesisId> for proto::GenesisId {
     fn from(x: &GenesisId) -> Self {
@@ -63,6 +63,25 @@ impl TryFrom<&proto::PeerChainInfo> for PeerChainInfoV2 {
 
 //////////////////////////////////////////
 
+impl From<Compression> for proto::Compression {
+    fn from(x: Compression) -> Self {
+        match x {
+            Compression::Zstd => Self::ZSTD,
+            Compression::Lz4 => Self::LZ4,
+        }
+    }
+}
+
+fn parse_compression(p: &EnumOrUnknown<proto::Compression>) -> Option<Compression> {
+    match p.enum_value() {
+        Ok(proto::Compression::ZSTD) => Some(Compression::Zstd),
+        Ok(proto::Compression::LZ4) => Some(Compression::Lz4),
+        Ok(proto::Compression::COMPRESSION_NONE) | Err(_) => None,
+    }
+}
+
+//////////////////////////////////////////
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseHandshakeError {
     #[error("sender_peer_id {0}")]
@@ -77,6 +96,8 @@ pub enum ParseHandshakeError {
     PartialEdgeInfo(ParseRequiredError<ParsePartialEdgeInfoError>),
     #[error("owned_account {0}")]
     OwnedAccount(ParseSignedOwnedAccountError),
+    #[error("supports_noise_signature {0}")]
+    SupportsNoiseSignature(ParseSignatureError),
 }
 
 impl From<&Handshake> for proto::Handshake {
@@ -90,6 +111,13 @@ impl From<&Handshake> for proto::Handshake {
             sender_chain_info: MF::some((&x.sender_chain_info).into()),
             partial_edge_info: MF::some((&x.partial_edge_info).into()),
             owned_account: x.owned_account.as_ref().map(Into::into).into(),
+            supported_compression: x
+                .supported_compression
+                .iter()
+                .map(|c| proto::Compression::from(*c).into())
+                .collect(),
+            supports_noise: x.supports_encryption,
+            supports_noise_signature: x.encryption_signature.as_ref().map(Into::into).into(),
             ..Self::default()
         }
     }
@@ -120,6 +148,15 @@ impl TryFrom<&proto::Handshake> for Handshake {
                 .map_err(Self::Error::PartialEdgeInfo)?,
             owned_account: try_from_optional(&p.owned_account)
                 .map_err(Self::Error::OwnedAccount)?,
+            // Algorithms unknown to this node are ignored.
+            supported_compression: p
+                .supported_compression
+                .iter()
+                .filter_map(parse_compression)
+                .collect(),
+            supports_encryption: p.supports_noise,
+            encryption_signature: try_from_optional(&p.supports_noise_signature)
+                .map_err(Self::Error::SupportsNoiseSignature)?,
         })
     }
 }
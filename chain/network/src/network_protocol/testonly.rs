@@ -70,6 +70,7 @@ pub fn make_block(
         clock,
         None,
     )
+    .unwrap()
 }
 
 pub fn make_account_id<R: Rng>(rng: &mut R) -> AccountId {
@@ -106,11 +107,9 @@ pub fn make_peer_info<R: Rng>(rng: &mut R) -> PeerInfo {
 pub fn make_announce_account<R: Rng>(rng: &mut R) -> AnnounceAccount {
     let peer_id = make_peer_id(rng);
     let validator_signer = make_validator_signer(rng);
-    let signature = validator_signer.sign_account_announce(
-        validator_signer.validator_id(),
-        &peer_id,
-        &EpochId::default(),
-    );
+    let signature = validator_signer
+        .sign_account_announce(validator_signer.validator_id(), &peer_id, &EpochId::default())
+        .unwrap();
     AnnounceAccount {
         account_id: validator_signer.validator_id().clone(),
         peer_id: peer_id,
@@ -175,6 +174,7 @@ pub fn make_challenge<R: Rng>(rng: &mut R) -> Challenge {
         }),
         &make_validator_signer(rng).into(),
     )
+    .unwrap()
 }
 
 // Based on ShardsManager::prepare_partial_encoded_chunk_response_from_chunk.
@@ -348,6 +348,9 @@ pub fn make_handshake<R: Rng>(rng: &mut R, chain: &Chain) -> Handshake {
         sender_chain_info: chain.get_peer_chain_info(),
         partial_edge_info: make_partial_edge(rng),
         owned_account: None,
+        supported_compression: vec![],
+        supports_encryption: false,
+        encryption_signature: None,
     }
 }
 
@@ -7,6 +7,7 @@ use crate::types::{PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg
 use anyhow::{bail, Context as _};
 use itertools::Itertools as _;
 use near_async::time;
+use near_primitives::block::ApprovalInner;
 use rand::Rng as _;
 
 #[test]
@@ -56,6 +57,30 @@ fn bad_account_data_size() {
     assert!(ad.sign(&signer.into()).is_err());
 }
 
+#[test]
+fn account_key_payload_validation() {
+    let mut rng = make_rng(61263549);
+    let clock = time::FakeClock::default();
+
+    let ad = data::make_signed_account_data(&mut rng, &clock.clock());
+    validate_account_key_payload(&ad.payload().payload).unwrap();
+    let signer = data::make_validator_signer(&mut rng);
+    let oa = OwnedAccount {
+        account_key: signer.public_key(),
+        peer_id: data::make_peer_id(&mut rng),
+        timestamp: clock.now_utc(),
+    };
+    let oa = oa.sign(&signer.into()).unwrap();
+    validate_account_key_payload(&oa.payload().payload).unwrap();
+
+    // Nothing else may be signed with the account key.
+    let block_hash = CryptoHash::hash_bytes(b"block");
+    assert!(validate_account_key_payload(block_hash.as_ref()).is_err());
+    let approval = Approval::get_data_for_sig(&ApprovalInner::Endorsement(block_hash), 11);
+    assert!(validate_account_key_payload(&approval).is_err());
+    assert!(validate_account_key_payload(&[]).is_err());
+}
+
 #[test]
 fn serialize_deserialize_protobuf_only() {
     let mut rng = make_rng(39521947542);
@@ -170,3 +195,18 @@ fn serialize_deserialize() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn handshake_supported_compression() {
+    let mut rng = make_rng(89028037453);
+    let mut clock = time::FakeClock::default();
+    let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let mut handshake = data::make_handshake(&mut rng, &chain);
+    handshake.supported_compression = vec![Compression::Lz4, Compression::Zstd];
+
+    let mut p = proto::Handshake::from(&handshake);
+    assert_eq!(Handshake::try_from(&p).unwrap(), handshake);
+    // Algorithms unknown to this node are ignored.
+    p.supported_compression.insert(0, protobuf::EnumOrUnknown::from_i32(1000));
+    assert_eq!(Handshake::try_from(&p).unwrap(), handshake);
+}
@@ -0,0 +1,343 @@
+//! Encryption of peer connections with the Noise protocol framework.
+//!
+//! Peers which both support it run a Noise XX handshake right after exchanging
+//! their `Handshake` messages, and encrypt all the following frames of the
+//! connection. The initiator of the TCP connection is the initiator of the
+//! Noise handshake.
+//!
+//! The Noise static keys are X25519 keys generated for every connection. Each
+//! peer proves that its static key belongs to it by sending, as the payload of
+//! the handshake message carrying the key, a signature of the key made with
+//! its node key, which is verified against the `PeerId` of the peer.
+//!
+//! The encoded `Handshake` messages exchanged before are used as the prologue
+//! of the Noise handshake, so that it fails if any of them has been modified
+//! in transit. The flag advertising support for encryption is in addition
+//! signed with the node key of the sender, so that it can't be cleared to
+//! downgrade the connection to plaintext.
+use near_crypto::{SecretKey, Signature};
+use near_primitives::network::PeerId;
+
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+/// Maximal size of a Noise message, imposed by the Noise specification.
+const MAX_NOISE_MESSAGE_SIZE: usize = 65535;
+/// Size of the authentication tag added by the cipher to every message.
+const TAG_SIZE: usize = 16;
+/// Frames larger than that are encrypted as several Noise messages.
+const MAX_CHUNK_SIZE: usize = MAX_NOISE_MESSAGE_SIZE - TAG_SIZE;
+/// Prefix of the data signed to authenticate a Noise static key, so that the
+/// signature can't be used for anything else.
+const STATIC_KEY_SIGNATURE_PREFIX: &[u8] = b"near-network-noise-static-key:";
+/// Prefix of the data signed to authenticate the encryption flag of a
+/// `Handshake`.
+const ENCRYPTION_FLAG_SIGNATURE_PREFIX: &[u8] = b"near-network-noise-flag:";
+/// Prefix of the Noise prologue.
+const PROLOGUE_PREFIX: &[u8] = b"near-network-noise-prologue:";
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum NoiseError {
+    #[error("noise: {0}")]
+    Snow(#[from] snow::Error),
+    #[error("invalid handshake payload: {0}")]
+    InvalidPayload(std::io::Error),
+    #[error("the static key of the peer is not signed with its node key")]
+    InvalidSignature,
+    #[error("invalid encrypted frame")]
+    InvalidFrame,
+    #[error("more than {0} bytes of frames queued during the handshake")]
+    QueueFull(usize),
+}
+
+fn static_key_signature_data(static_key: &[u8]) -> Vec<u8> {
+    [STATIC_KEY_SIGNATURE_PREFIX, static_key].concat()
+}
+
+fn encryption_flag_signature_data(
+    sender_peer_id: &PeerId,
+    target_peer_id: &PeerId,
+    nonce: u64,
+    supports_encryption: bool,
+) -> Vec<u8> {
+    let data = borsh::to_vec(&(sender_peer_id, target_peer_id, nonce, supports_encryption));
+    [ENCRYPTION_FLAG_SIGNATURE_PREFIX, &data.unwrap()].concat()
+}
+
+/// Signs whether we support encryption, for the `Handshake` with the given
+/// target and edge nonce.
+pub(crate) fn sign_encryption_flag(
+    node_key: &SecretKey,
+    target_peer_id: &PeerId,
+    nonce: u64,
+    supports_encryption: bool,
+) -> Signature {
+    let sender_peer_id = PeerId::new(node_key.public_key());
+    node_key.sign(&encryption_flag_signature_data(
+        &sender_peer_id,
+        target_peer_id,
+        nonce,
+        supports_encryption,
+    ))
+}
+
+/// Verifies the signature of the encryption flag of a `Handshake`.
+pub(crate) fn verify_encryption_flag(
+    signature: &Signature,
+    sender_peer_id: &PeerId,
+    target_peer_id: &PeerId,
+    nonce: u64,
+    supports_encryption: bool,
+) -> bool {
+    let data =
+        encryption_flag_signature_data(sender_peer_id, target_peer_id, nonce, supports_encryption);
+    signature.verify(&data, sender_peer_id.public_key())
+}
+
+/// Builds the Noise prologue from the encoded `Handshake` messages sent by the
+/// initiator and by the responder of the connection.
+pub(crate) fn prologue(initiator_handshake: &[u8], responder_handshake: &[u8]) -> Vec<u8> {
+    let mut prologue = PROLOGUE_PREFIX.to_vec();
+    for handshake in [initiator_handshake, responder_handshake] {
+        prologue.extend_from_slice(&(handshake.len() as u32).to_le_bytes());
+        prologue.extend_from_slice(handshake);
+    }
+    prologue
+}
+
+/// Noise handshake in progress.
+pub(crate) struct NoiseHandshake {
+    state: snow::HandshakeState,
+    /// Signature of our static key, sent along with it.
+    payload: Vec<u8>,
+    /// Peer on the other side of the connection.
+    peer_id: PeerId,
+}
+
+impl NoiseHandshake {
+    /// Starts a handshake with the given peer. Returns the message to send to
+    /// the peer if we are the initiator. Both peers must pass the same
+    /// `prologue`, built with `prologue()`.
+    pub(crate) fn start(
+        initiator: bool,
+        node_key: &SecretKey,
+        peer_id: PeerId,
+        prologue: &[u8],
+    ) -> Result<(Self, Option<Vec<u8>>), NoiseError> {
+        let params: snow::params::NoiseParams = NOISE_PARAMS.parse()?;
+        let keypair = snow::Builder::new(params.clone()).generate_keypair()?;
+        let builder =
+            snow::Builder::new(params).local_private_key(&keypair.private).prologue(prologue);
+        let state =
+            if initiator { builder.build_initiator()? } else { builder.build_responder()? };
+        let signature = node_key.sign(&static_key_signature_data(&keypair.public));
+        let payload = borsh::to_vec(&signature).unwrap();
+        let mut handshake = Self { state, payload, peer_id };
+        let msg = if initiator { Some(handshake.write_message(&[])?) } else { None };
+        Ok((handshake, msg))
+    }
+
+    fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let mut msg = vec![0; MAX_NOISE_MESSAGE_SIZE];
+        let n = self.state.write_message(payload, &mut msg)?;
+        msg.truncate(n);
+        Ok(msg)
+    }
+
+    /// Processes a handshake message received from the peer. Returns the
+    /// message to send back, if any.
+    pub(crate) fn read_message(&mut self, msg: &[u8]) -> Result<Option<Vec<u8>>, NoiseError> {
+        let had_remote_static = self.state.get_remote_static().is_some();
+        let mut payload = vec![0; MAX_NOISE_MESSAGE_SIZE];
+        let n = self.state.read_message(msg, &mut payload)?;
+        if !had_remote_static {
+            if let Some(remote_static) = self.state.get_remote_static() {
+                let signature = borsh::from_slice::<Signature>(&payload[..n])
+                    .map_err(NoiseError::InvalidPayload)?;
+                let data = static_key_signature_data(remote_static);
+                if !signature.verify(&data, self.peer_id.public_key()) {
+                    return Err(NoiseError::InvalidSignature);
+                }
+            }
+        }
+        if self.state.is_handshake_finished() || !self.state.is_my_turn() {
+            return Ok(None);
+        }
+        // In the XX pattern, both messages sent after the first one carry the
+        // static key of the sender.
+        let payload = std::mem::take(&mut self.payload);
+        Ok(Some(self.write_message(&payload)?))
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.state.is_handshake_finished()
+    }
+
+    pub(crate) fn into_transport(self) -> Result<NoiseTransport, NoiseError> {
+        Ok(NoiseTransport(self.state.into_transport_mode()?))
+    }
+}
+
+/// Connection encrypted after a completed handshake.
+pub(crate) struct NoiseTransport(snow::TransportState);
+
+impl NoiseTransport {
+    /// Encrypts a frame. Frames are split into chunks of `MAX_CHUNK_SIZE`
+    /// bytes, each encrypted as a separate Noise message, so that the chunks
+    /// can be found again from the size of the frame alone.
+    pub(crate) fn encrypt(&mut self, frame: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let num_chunks = std::cmp::max(1, frame.len().div_ceil(MAX_CHUNK_SIZE));
+        let mut out = vec![0; frame.len() + num_chunks * TAG_SIZE];
+        let mut offset = 0;
+        for i in 0..num_chunks {
+            let chunk =
+                &frame[i * MAX_CHUNK_SIZE..std::cmp::min(frame.len(), (i + 1) * MAX_CHUNK_SIZE)];
+            offset += self.0.write_message(chunk, &mut out[offset..])?;
+        }
+        debug_assert_eq!(offset, out.len());
+        Ok(out)
+    }
+
+    /// Decrypts a frame encrypted with `encrypt` by the peer.
+    pub(crate) fn decrypt(&mut self, frame: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        if frame.is_empty() {
+            return Err(NoiseError::InvalidFrame);
+        }
+        let mut out = vec![];
+        for chunk in frame.chunks(MAX_NOISE_MESSAGE_SIZE) {
+            if chunk.len() < TAG_SIZE {
+                return Err(NoiseError::InvalidFrame);
+            }
+            let offset = out.len();
+            out.resize(offset + chunk.len() - TAG_SIZE, 0);
+            self.0.read_message(chunk, &mut out[offset..])?;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_protocol::testonly as data;
+    use crate::testonly::make_rng;
+
+    fn handshake_with_prologues(
+        initiator_key: &SecretKey,
+        initiator_id: PeerId,
+        initiator_prologue: &[u8],
+        responder_key: &SecretKey,
+        responder_id: PeerId,
+        responder_prologue: &[u8],
+    ) -> Result<(NoiseTransport, NoiseTransport), NoiseError> {
+        let (mut initiator, msg1) =
+            NoiseHandshake::start(true, initiator_key, responder_id, initiator_prologue)?;
+        let (mut responder, none) =
+            NoiseHandshake::start(false, responder_key, initiator_id, responder_prologue)?;
+        assert!(none.is_none());
+        let msg2 = responder.read_message(&msg1.unwrap())?.unwrap();
+        let msg3 = initiator.read_message(&msg2)?.unwrap();
+        assert!(initiator.is_finished());
+        assert!(responder.read_message(&msg3)?.is_none());
+        assert!(responder.is_finished());
+        Ok((initiator.into_transport()?, responder.into_transport()?))
+    }
+
+    fn handshake(
+        initiator_key: &SecretKey,
+        initiator_id: PeerId,
+        responder_key: &SecretKey,
+        responder_id: PeerId,
+    ) -> Result<(NoiseTransport, NoiseTransport), NoiseError> {
+        let prologue = prologue(b"initiator", b"responder");
+        handshake_with_prologues(
+            initiator_key,
+            initiator_id,
+            &prologue,
+            responder_key,
+            responder_id,
+            &prologue,
+        )
+    }
+
+    #[test]
+    fn test_encrypted_connection() {
+        let mut rng = make_rng(921853233);
+        let a = data::make_secret_key(&mut rng);
+        let b = data::make_secret_key(&mut rng);
+        let a_id = PeerId::new(a.public_key());
+        let b_id = PeerId::new(b.public_key());
+        let (mut a_transport, mut b_transport) =
+            handshake(&a, a_id.clone(), &b, b_id.clone()).unwrap();
+        for len in [0, 1, 1000, MAX_CHUNK_SIZE, MAX_CHUNK_SIZE + 1, 3 * MAX_CHUNK_SIZE + 17] {
+            let frame: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let encrypted = a_transport.encrypt(&frame).unwrap();
+            if len > 0 {
+                assert_ne!(&encrypted[..frame.len()], &frame[..]);
+            }
+            assert_eq!(b_transport.decrypt(&encrypted).unwrap(), frame);
+            let encrypted = b_transport.encrypt(&frame).unwrap();
+            assert_eq!(a_transport.decrypt(&encrypted).unwrap(), frame);
+        }
+        // Tampered, replayed and truncated frames are rejected.
+        let mut encrypted = a_transport.encrypt(b"hello").unwrap();
+        encrypted[0] ^= 1;
+        assert!(b_transport.decrypt(&encrypted).is_err());
+        let (mut a_transport, mut b_transport) =
+            handshake(&a, a_id.clone(), &b, b_id.clone()).unwrap();
+        let encrypted = a_transport.encrypt(b"hello").unwrap();
+        assert_eq!(b_transport.decrypt(&encrypted).unwrap(), b"hello");
+        assert!(b_transport.decrypt(&encrypted).is_err());
+        assert!(b_transport.decrypt(&encrypted[..TAG_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn test_impersonation() {
+        let mut rng = make_rng(921853233);
+        let a = data::make_secret_key(&mut rng);
+        let b = data::make_secret_key(&mut rng);
+        let c = data::make_secret_key(&mut rng);
+        let a_id = PeerId::new(a.public_key());
+        let b_id = PeerId::new(b.public_key());
+        // `c` pretends to be `b` or `a`.
+        assert!(matches!(
+            handshake(&a, a_id.clone(), &c, b_id.clone()),
+            Err(NoiseError::InvalidSignature)
+        ));
+        assert!(matches!(handshake(&c, a_id, &b, b_id), Err(NoiseError::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_prologue_mismatch() {
+        let mut rng = make_rng(921853233);
+        let a = data::make_secret_key(&mut rng);
+        let b = data::make_secret_key(&mut rng);
+        let a_id = PeerId::new(a.public_key());
+        let b_id = PeerId::new(b.public_key());
+        // The peers saw different Handshakes.
+        assert!(matches!(
+            handshake_with_prologues(
+                &a,
+                a_id,
+                &prologue(b"initiator", b"responder"),
+                &b,
+                b_id,
+                &prologue(b"initiator", b"tampered"),
+            ),
+            Err(NoiseError::Snow(_))
+        ));
+    }
+
+    #[test]
+    fn test_encryption_flag_signature() {
+        let mut rng = make_rng(921853233);
+        let a = data::make_secret_key(&mut rng);
+        let b = data::make_secret_key(&mut rng);
+        let a_id = PeerId::new(a.public_key());
+        let b_id = PeerId::new(b.public_key());
+        let signature = sign_encryption_flag(&a, &b_id, 7, true);
+        assert!(verify_encryption_flag(&signature, &a_id, &b_id, 7, true));
+        // The signature doesn't cover a cleared flag, another nonce or sender.
+        assert!(!verify_encryption_flag(&signature, &a_id, &b_id, 7, false));
+        assert!(!verify_encryption_flag(&signature, &a_id, &b_id, 8, true));
+        assert!(!verify_encryption_flag(&signature, &b_id, &a_id, 7, true));
+    }
+}
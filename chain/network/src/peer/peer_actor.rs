@@ -7,13 +7,15 @@ use crate::client::{
 use crate::concurrency::atomic_cell::AtomicCell;
 use crate::concurrency::demux;
 use crate::config::PEERS_RESPONSE_MAX_PEERS;
+use crate::config_json::EncryptionMode;
 use crate::network_protocol::SnapshotHostInfoVerificationError;
 use crate::network_protocol::{
-    DistanceVector, Edge, EdgeState, Encoding, OwnedAccount, ParsePeerMessageError,
-    PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, PeersRequest, PeersResponse,
-    RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate, StateResponseInfo, SyncAccountsData,
-    SyncSnapshotHosts,
+    compress_frame, decompress_frame, negotiate, Compression, DistanceVector, Edge, EdgeState,
+    Encoding, OwnedAccount, ParsePeerMessageError, PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash,
+    PeerInfo, PeersRequest, PeersResponse, RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate,
+    StateResponseInfo, SyncAccountsData, SyncSnapshotHosts,
 };
+use crate::peer::noise::{self, NoiseError, NoiseHandshake, NoiseTransport};
 use crate::peer::stream;
 use crate::peer::tracker::Tracker;
 use crate::peer_manager::connection;
@@ -81,6 +83,9 @@ pub(crate) const DROP_DUPLICATED_MESSAGES_PERIOD: time::Duration = time::Duratio
 const SYNC_LATEST_BLOCK_INTERVAL: time::Duration = time::Duration::seconds(60);
 /// How often to perform a full sync of AccountsData with the peer.
 const ACCOUNTS_DATA_FULL_SYNC_INTERVAL: time::Duration = time::Duration::minutes(10);
+/// Maximum size of the frames queued while the Noise handshake is in progress.
+/// The connection is closed if more is sent before the handshake completes.
+const MAX_NOISE_QUEUED_BYTES: usize = 64 * bytesize::MIB as usize;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConnectionClosedEvent {
@@ -127,6 +132,10 @@ pub(crate) enum ClosingReason {
     TooLargeClockSkew,
     #[error("owned_account.peer_id doesn't match handshake.sender_peer_id")]
     OwnedAccountMismatch,
+    #[error("encryption is required but not supported by the peer")]
+    EncryptionRequired,
+    #[error("failed to encrypt the connection: {0}")]
+    EncryptionFailed(String),
     #[error("PeerActor stopped NOT via PeerActor::stop()")]
     Unknown,
 }
@@ -148,6 +157,8 @@ impl ClosingReason {
             ClosingReason::DisconnectMessage => false, // graceful disconnect
             ClosingReason::TooLargeClockSkew => true, // reconnect will fail for the same reason
             ClosingReason::OwnedAccountMismatch => true, // misbehaving peer
+            ClosingReason::EncryptionRequired => true, // reconnect will fail for the same reason
+            ClosingReason::EncryptionFailed(_) => false, // may be caused by an on-path attacker
             ClosingReason::Unknown => false,        // only happens in tests
         }
     }
@@ -182,6 +193,16 @@ pub(crate) struct PeerActor {
     /// Whether the PeerActor should skip protobuf support detection and use
     /// a given encoding right away.
     force_encoding: Option<Encoding>,
+    /// Compression algorithm negotiated during the handshake. Frames are
+    /// exchanged without the compression tag until it is set.
+    compression: Option<Compression>,
+    /// Encryption of the frames, set up after the handshake if both peers
+    /// support it.
+    noise: Mutex<Option<NoiseState>>,
+    /// Encoded Handshakes sent to and received from the peer, used as the
+    /// prologue of the Noise handshake.
+    sent_handshake: Option<Vec<u8>>,
+    received_handshake: Option<Vec<u8>>,
 
     /// Peer status.
     peer_status: PeerStatus,
@@ -202,6 +223,19 @@ impl Debug for PeerActor {
     }
 }
 
+enum NoiseState {
+    /// Frames sent during the Noise handshake are queued until it completes,
+    /// up to `MAX_NOISE_QUEUED_BYTES`. Frames are dropped once the queue is
+    /// full and `overflowed` is set, which fails the handshake.
+    Handshake {
+        handshake: NoiseHandshake,
+        queued: Vec<Vec<u8>>,
+        queued_bytes: usize,
+        overflowed: bool,
+    },
+    Transport(NoiseTransport),
+}
+
 #[derive(Clone, Debug)]
 struct HandshakeSpec {
     /// ID of the peer on the other side of the connection.
@@ -349,6 +383,10 @@ impl PeerActor {
                     ),
                     protocol_buffers_supported: false,
                     force_encoding,
+                    compression: None,
+                    noise: Mutex::new(None),
+                    sent_handshake: None,
+                    received_handshake: None,
                     peer_info: match &stream_type {
                         tcp::StreamType::Inbound => None,
                         tcp::StreamType::Outbound { peer_id, .. } => Some(PeerInfo {
@@ -441,26 +479,136 @@ impl PeerActor {
             _ => (),
         };
 
-        let bytes = msg.serialize(enc);
+        let msg_type = msg.msg_variant();
+        let bytes = match self.compression {
+            Some(compression) => compress_frame(compression, &msg.serialize(enc), msg_type),
+            None => msg.serialize(enc),
+        };
         self.tracker.lock().increment_sent(&self.clock, bytes.len() as u64);
         let bytes_len = bytes.len();
         tracing::trace!(target: "network", msg_len = bytes_len);
-        self.framed.send(stream::Frame(bytes));
+        self.send_frame(bytes);
         metrics::PEER_DATA_SENT_BYTES.inc_by(bytes_len as u64);
-        let msg_type = msg.msg_variant();
         metrics::PEER_MESSAGE_SENT_BY_TYPE_TOTAL.with_label_values(&[msg_type]).inc();
         metrics::PEER_MESSAGE_SENT_BY_TYPE_BYTES
             .with_label_values(&[msg_type])
             .inc_by(bytes_len as u64);
     }
 
-    fn send_handshake(&self, spec: HandshakeSpec) {
+    /// Sends a frame to the peer, encrypting it if the connection is encrypted.
+    fn send_frame(&self, frame: Vec<u8>) {
+        let frame = match &mut *self.noise.lock() {
+            None => frame,
+            Some(NoiseState::Handshake { queued, queued_bytes, overflowed, .. }) => {
+                if *overflowed || *queued_bytes + frame.len() > MAX_NOISE_QUEUED_BYTES {
+                    tracing::debug!(target: "network", peer = %self.peer_info, "Dropping frame queued during the Noise handshake");
+                    queued.clear();
+                    *overflowed = true;
+                } else {
+                    *queued_bytes += frame.len();
+                    queued.push(frame);
+                }
+                return;
+            }
+            Some(NoiseState::Transport(transport)) => match transport.encrypt(&frame) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    tracing::error!(target: "network", peer = %self.peer_info, ?err, "Failed to encrypt frame");
+                    return;
+                }
+            },
+        };
+        self.framed.send(stream::Frame(frame));
+    }
+
+    /// Starts the Noise handshake with the peer. The initiator of the
+    /// connection sends the first message. The connection is closed if the
+    /// handshake doesn't complete within the handshake timeout.
+    fn start_noise_handshake(
+        &mut self,
+        ctx: &mut actix::Context<PeerActor>,
+        peer_id: PeerId,
+    ) -> Result<(), NoiseError> {
+        let sent = self.sent_handshake.take().expect("Handshake sent before encrypting");
+        let received =
+            self.received_handshake.take().expect("Handshake received before encrypting");
+        let prologue = match self.peer_type {
+            PeerType::Outbound => noise::prologue(&sent, &received),
+            PeerType::Inbound => noise::prologue(&received, &sent),
+        };
+        let (handshake, msg) = NoiseHandshake::start(
+            self.peer_type == PeerType::Outbound,
+            &self.network_state.config.node_key,
+            peer_id,
+            &prologue,
+        )?;
+        *self.noise.get_mut() = Some(NoiseState::Handshake {
+            handshake,
+            queued: vec![],
+            queued_bytes: 0,
+            overflowed: false,
+        });
+        if let Some(msg) = msg {
+            self.framed.send(stream::Frame(msg));
+        }
+        near_performance_metrics::actix::run_later(
+            ctx,
+            self.network_state.config.handshake_timeout.try_into().unwrap(),
+            move |act, ctx| {
+                if let Some(NoiseState::Handshake { .. }) = act.noise.get_mut() {
+                    tracing::info!(target: "network", "Noise handshake timeout expired for {}", act.peer_info);
+                    act.stop(
+                        ctx,
+                        ClosingReason::EncryptionFailed("handshake timed out".to_string()),
+                    );
+                }
+            },
+        );
+        Ok(())
+    }
+
+    /// Processes a Noise handshake message received from the peer. Once the
+    /// handshake completes, the frames queued in the meantime are sent.
+    fn process_noise_message(&mut self, msg: &[u8]) -> Result<(), NoiseError> {
+        let noise = self.noise.get_mut();
+        let Some(NoiseState::Handshake { handshake, overflowed, .. }) = noise else {
+            unreachable!("process_noise_message called without a Noise handshake in progress");
+        };
+        if *overflowed {
+            return Err(NoiseError::QueueFull(MAX_NOISE_QUEUED_BYTES));
+        }
+        if let Some(reply) = handshake.read_message(msg)? {
+            self.framed.send(stream::Frame(reply));
+        }
+        if !handshake.is_finished() {
+            return Ok(());
+        }
+        let Some(NoiseState::Handshake { handshake, queued, .. }) = noise.take() else {
+            unreachable!();
+        };
+        let mut transport = handshake.into_transport()?;
+        for frame in queued {
+            self.framed.send(stream::Frame(transport.encrypt(&frame)?));
+        }
+        *noise = Some(NoiseState::Transport(transport));
+        tracing::debug!(target: "network", peer = %self.peer_info, "Connection encrypted");
+        Ok(())
+    }
+
+    fn send_handshake(&mut self, spec: HandshakeSpec) {
         let (height, tracked_shards) =
             if let Some(chain_info) = self.network_state.chain_info.load().as_ref() {
                 (chain_info.block.header().height(), chain_info.tracked_shards.clone())
             } else {
                 (0, vec![])
             };
+        let supports_encryption = self.network_state.config.encryption != EncryptionMode::Disabled;
+        let encryption_signature = noise::sign_encryption_flag(
+            &self.network_state.config.node_key,
+            &spec.peer_id,
+            spec.partial_edge_info.nonce,
+            supports_encryption,
+        );
         let handshake = Handshake {
             protocol_version: spec.protocol_version,
             oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
@@ -475,19 +623,30 @@ impl PeerActor {
                 archival: self.network_state.config.archive,
             },
             partial_edge_info: spec.partial_edge_info,
-            owned_account: self.network_state.config.validator.signer.get().map(|signer| {
+            // Without a signature the peer treats us as a non-validator node.
+            owned_account: self.network_state.config.validator.signer.get().and_then(|signer| {
                 OwnedAccount {
                     account_key: signer.public_key(),
                     peer_id: self.network_state.config.node_id(),
                     timestamp: self.clock.now_utc(),
                 }
                 .sign(&signer)
+                .map_err(
+                    |err| tracing::warn!(target: "network", ?err, "Failed to sign owned account"),
+                )
+                .ok()
             }),
+            supported_compression: self.network_state.config.message_compression.clone(),
+            supports_encryption,
+            encryption_signature: Some(encryption_signature),
         };
         let msg = match spec.tier {
             tcp::Tier::T1 => PeerMessage::Tier1Handshake(handshake),
             tcp::Tier::T2 => PeerMessage::Tier2Handshake(handshake),
         };
+        // Frames are neither compressed nor encrypted yet, so the peer
+        // receives exactly these bytes.
+        self.sent_handshake = Some(msg.serialize(self.encoding().unwrap_or(Encoding::Proto)));
         self.send_message_or_log(&msg);
     }
 
@@ -606,6 +765,33 @@ impl PeerActor {
             }
         }
 
+        // A flag without a valid signature may have been set or cleared by
+        // someone else than the peer. A missing signature can't be told apart
+        // from a peer not supporting encryption, which is why only the
+        // `Required` mode protects against a downgrade to plaintext.
+        let supports_encryption = match &handshake.encryption_signature {
+            None => false,
+            Some(signature) => {
+                if !noise::verify_encryption_flag(
+                    signature,
+                    &handshake.sender_peer_id,
+                    &handshake.target_peer_id,
+                    handshake.partial_edge_info.nonce,
+                    handshake.supports_encryption,
+                ) {
+                    self.stop(ctx, ClosingReason::Ban(ReasonForBan::InvalidSignature));
+                    return;
+                }
+                handshake.supports_encryption
+            }
+        };
+        if self.network_state.config.encryption == EncryptionMode::Required && !supports_encryption
+        {
+            tracing::debug!(target: "network", peer_id=?handshake.sender_peer_id, "Peer doesn't support encryption, disconnecting");
+            self.stop(ctx, ClosingReason::EncryptionRequired);
+            return;
+        }
+
         // Verify that handshake.owned_account is valid.
         if let Some(owned_account) = &handshake.owned_account {
             if let Err(_) = owned_account.payload().verify(&owned_account.account_key) {
@@ -731,6 +917,24 @@ impl PeerActor {
                                 partial_edge_info: partial_edge_info,
                             });
                         }
+                        // Both handshakes have been exchanged, so all the following frames
+                        // are compressed with the negotiated algorithm, if any.
+                        let ours = &act.network_state.config.message_compression;
+                        let theirs = &handshake.supported_compression;
+                        act.compression = match act.peer_type {
+                            PeerType::Outbound => negotiate(ours, theirs),
+                            PeerType::Inbound => negotiate(theirs, ours),
+                        };
+                        // Encryption must be set up before any other message is sent,
+                        // so that none of them goes out in plaintext.
+                        if act.network_state.config.encryption != EncryptionMode::Disabled
+                            && supports_encryption
+                        {
+                            if let Err(err) = act.start_noise_handshake(ctx, handshake.sender_peer_id.clone()) {
+                                act.stop(ctx, ClosingReason::EncryptionFailed(err.to_string()));
+                                return;
+                            }
+                        }
                         // TIER1 is strictly reserved for BFT consensensus messages,
                         // so all kinds of periodical syncs happen only on TIER2 connections.
                         if tier==tcp::Tier::T2 {
@@ -1731,13 +1935,60 @@ impl actix::Handler<stream::Frame> for PeerActor {
             self.tracker.lock().increment_received(&self.clock, msg.len() as u64);
         }
 
+        let frame_len = msg.len();
+        let msg = match self.noise.get_mut() {
+            None => msg,
+            Some(NoiseState::Handshake { .. }) => {
+                if let Err(err) = self.process_noise_message(&msg) {
+                    tracing::debug!(target: "network", peer = %self.peer_info, ?err, "Noise handshake failed");
+                    self.stop(ctx, ClosingReason::EncryptionFailed(err.to_string()));
+                }
+                return;
+            }
+            Some(NoiseState::Transport(transport)) => match transport.decrypt(&msg) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    tracing::debug!(target: "network", peer = %self.peer_info, ?err, "Failed to decrypt frame");
+                    self.stop(ctx, ClosingReason::EncryptionFailed(err.to_string()));
+                    return;
+                }
+            },
+        };
+        let msg = match self.compression {
+            Some(_) => match decompress_frame(&msg, stream::NETWORK_MESSAGE_MAX_SIZE_BYTES) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    tracing::debug!(target: "network", "Received invalid frame {} from {}: {}", near_fmt::AbbrBytes(&msg), self.peer_info, err);
+                    return;
+                }
+            },
+            None => std::borrow::Cow::Borrowed(&msg[..]),
+        };
+        if msg.len() > frame_len {
+            // Decompressed data counts towards the traffic of the peer, so that
+            // sending highly compressible messages doesn't get around the limits.
+            self.tracker.lock().increment_received(&self.clock, (msg.len() - frame_len) as u64);
+            if !self
+                .received_messages_rate_limits
+                .is_decompressed_size_allowed(msg.len(), self.clock.now())
+            {
+                metrics::PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL
+                    .with_label_values(&["DecompressedBytes"])
+                    .inc();
+                tracing::debug!(target: "network", "Peer {} is being rate limited for decompressing {} bytes", self.peer_info, msg.len());
+                return;
+            }
+        }
         let mut peer_msg = match self.parse_message(&msg) {
             Ok(msg) => msg,
             Err(err) => {
-                tracing::debug!(target: "network", "Received invalid data {} from {}: {}", near_fmt::AbbrBytes(&msg), self.peer_info, err);
+                tracing::debug!(target: "network", "Received invalid data {} from {}: {}", near_fmt::AbbrBytes(&msg[..]), self.peer_info, err);
                 return;
             }
         };
+        if let PeerMessage::Tier1Handshake(_) | PeerMessage::Tier2Handshake(_) = &peer_msg {
+            self.received_handshake = Some(msg.to_vec());
+        }
 
         tracing::trace!(target: "network", "Received message: {}", peer_msg);
 
@@ -1747,7 +1998,7 @@ impl actix::Handler<stream::Frame> for PeerActor {
             metrics::PEER_MESSAGE_RECEIVED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
             metrics::PEER_MESSAGE_RECEIVED_BY_TYPE_BYTES
                 .with_label_values(&labels)
-                .inc_by(msg.len() as u64);
+                .inc_by(frame_len as u64);
             if !self.received_messages_rate_limits.is_allowed(&peer_msg, now) {
                 metrics::PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
                 tracing::debug!(target: "network", "Peer {} is being rate limited for message {}", self.peer_info, peer_msg.msg_variant());
@@ -1,7 +1,7 @@
 use crate::network_protocol::testonly as data;
 use crate::network_protocol::{
-    Encoding, Handshake, HandshakeFailureReason, PartialEdgeInfo, PeerMessage, PeersRequest,
-    PeersResponse, RoutedMessageBody,
+    Compression, Encoding, Handshake, HandshakeFailureReason, PartialEdgeInfo, PeerMessage,
+    PeersRequest, PeersResponse, RoutedMessageBody,
 };
 use crate::peer::testonly::{Event, PeerConfig, PeerHandle};
 use crate::peer_manager::peer_manager_actor::Event as PME;
@@ -19,23 +19,27 @@ use std::sync::Arc;
 async fn test_peer_communication(
     outbound_encoding: Option<Encoding>,
     inbound_encoding: Option<Encoding>,
+    outbound_compression: &[Compression],
+    inbound_compression: &[Compression],
 ) -> anyhow::Result<()> {
-    tracing::info!("test_peer_communication({outbound_encoding:?},{inbound_encoding:?})");
+    tracing::info!("test_peer_communication({outbound_encoding:?},{inbound_encoding:?},{outbound_compression:?},{inbound_compression:?})");
 
     let mut rng = make_rng(89028037453);
     let mut clock = time::FakeClock::default();
 
     let chain = Arc::new(data::Chain::make(&mut clock, &mut rng, 12));
-    let inbound_cfg = PeerConfig {
+    let mut inbound_cfg = PeerConfig {
         chain: chain.clone(),
         network: chain.make_config(&mut rng),
         force_encoding: inbound_encoding,
     };
-    let outbound_cfg = PeerConfig {
+    inbound_cfg.network.message_compression = inbound_compression.to_vec();
+    let mut outbound_cfg = PeerConfig {
         chain: chain.clone(),
         network: chain.make_config(&mut rng),
         force_encoding: outbound_encoding,
     };
+    outbound_cfg.network.message_compression = outbound_compression.to_vec();
     let (outbound_stream, inbound_stream) =
         tcp::Stream::loopback(inbound_cfg.id(), tcp::Tier::T2).await;
     let mut inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
@@ -171,7 +175,24 @@ async fn peer_communication() -> anyhow::Result<()> {
                     continue;
                 }
             }
-            test_peer_communication(*outbound, *inbound)
+            test_peer_communication(*outbound, *inbound, &[], &[])
+                .await
+                .with_context(|| format!("(outbound={outbound:?},inbound={inbound:?})"))?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+// Verifies that peers exchange messages whichever compression algorithms they support,
+// including none.
+async fn peer_communication_with_compression() -> anyhow::Result<()> {
+    init_test_logger();
+    use Compression::*;
+    let compressions: [&[Compression]; 5] = [&[], &[Zstd], &[Lz4], &[Zstd, Lz4], &[Lz4, Zstd]];
+    for outbound in &compressions {
+        for inbound in &compressions {
+            test_peer_communication(Some(Encoding::Proto), None, outbound, inbound)
                 .await
                 .with_context(|| format!("(outbound={outbound:?},inbound={inbound:?})"))?;
         }
@@ -211,6 +232,9 @@ async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: O
         partial_edge_info: outbound_cfg
             .partial_edge_info(&inbound.cfg.id(), Edge::create_fresh_nonce(&clock.clock())),
         owned_account: None,
+        supported_compression: vec![],
+        supports_encryption: false,
+        encryption_signature: None,
     };
     // We will also introduce chain_id mismatch, but ProtocolVersionMismatch is expected to take priority.
     handshake.sender_chain_info.genesis_id.chain_id = "unknown_chain".to_string();
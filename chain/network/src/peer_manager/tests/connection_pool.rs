@@ -107,6 +107,9 @@ async fn loop_connection() {
                 &pm.cfg.node_key,
             ),
             owned_account: None,
+            supported_compression: vec![],
+            supports_encryption: false,
+            encryption_signature: None,
         }))
         .await;
     let reason = events
@@ -175,8 +178,12 @@ async fn owned_account_mismatch() {
                     peer_id: data::make_peer_id(rng),
                     timestamp: clock.now_utc(),
                 }
-                .sign(&signer),
+                .sign(&signer)
+                .unwrap(),
             ),
+            supported_compression: vec![],
+            supports_encryption: false,
+            encryption_signature: None,
         }))
         .await;
     let reason = events
@@ -297,8 +304,12 @@ async fn invalid_edge() {
                         peer_id: cfg.node_id(),
                         timestamp: clock.now_utc(),
                     }
-                    .sign(&signer),
+                    .sign(&signer)
+                    .unwrap(),
                 ),
+                supported_compression: vec![],
+                supports_encryption: false,
+                encryption_signature: None,
             };
             let handshake = match tier {
                 tcp::Tier::T1 => PeerMessage::Tier1Handshake(handshake),
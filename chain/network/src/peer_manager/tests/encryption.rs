@@ -0,0 +1,310 @@
+use crate::config::SocketOptions;
+use crate::config_json::EncryptionMode;
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::{Encoding, Handshake, PartialEdgeInfo, PeerMessage};
+use crate::peer::noise::{self, NoiseError, NoiseHandshake};
+use crate::peer::peer_actor::ClosingReason;
+use crate::peer_manager;
+use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::peer_manager::testonly::Event;
+use crate::tcp;
+use crate::testonly::make_rng;
+use crate::testonly::stream::Stream;
+use crate::types::{Edge, ReasonForBan};
+use near_async::time;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::network::PeerId;
+use near_primitives::version::{PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn encryption_modes() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    use EncryptionMode::*;
+    for (pm_mode, peer_mode, accepted) in [
+        (Preferred, Preferred, true),
+        (Preferred, Disabled, true),
+        (Disabled, Preferred, true),
+        (Required, Preferred, true),
+        (Preferred, Required, true),
+        (Required, Disabled, false),
+    ] {
+        tracing::info!(target: "test", ?pm_mode, ?peer_mode, "connecting");
+        let mut cfg = chain.make_config(rng);
+        cfg.encryption = pm_mode;
+        let pm = peer_manager::testonly::start(
+            clock.clock(),
+            near_store::db::TestDB::new(),
+            cfg,
+            chain.clone(),
+        )
+        .await;
+        let mut peer_cfg = chain.make_config(rng);
+        peer_cfg.encryption = peer_mode;
+        let conn = pm.start_inbound(chain.clone(), peer_cfg).await;
+        if !accepted {
+            assert_eq!(
+                ClosingReason::EncryptionRequired,
+                conn.manager_fail_handshake(&clock.clock()).await
+            );
+            continue;
+        }
+        let peer = conn.handshake(&clock.clock()).await;
+
+        // Messages get through, whether the connection is encrypted or not.
+        let mut events = pm.events.from_now();
+        let want = PeerMessage::Transaction(data::make_signed_transaction(rng));
+        peer.send(want.clone()).await;
+        events
+            .recv_until(|ev| match ev {
+                Event::PeerManager(PME::MessageProcessed(_, got)) if got == want => Some(()),
+                _ => None,
+            })
+            .await;
+    }
+}
+
+/// Connects to a PeerManager with a raw stream and sends a Handshake
+/// supporting encryption, with the flag signed with `flag_key`. Returns the
+/// encoded Handshake.
+async fn send_handshake(
+    pm: &peer_manager::testonly::ActorHandler,
+    chain: &data::Chain,
+    clock: &time::Clock,
+    node_key: &near_crypto::SecretKey,
+    flag_key: &near_crypto::SecretKey,
+) -> (Stream, Vec<u8>, tcp::StreamId) {
+    let stream = tcp::Stream::connect(&pm.peer_info(), tcp::Tier::T2, &SocketOptions::default())
+        .await
+        .unwrap();
+    let stream_id = stream.id();
+    let mut stream = Stream::new(Some(Encoding::Proto), stream);
+    let peer_id = PeerId::new(node_key.public_key());
+    let nonce = Edge::create_fresh_nonce(clock);
+    let handshake = PeerMessage::Tier2Handshake(Handshake {
+        protocol_version: PROTOCOL_VERSION,
+        oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+        sender_peer_id: peer_id.clone(),
+        target_peer_id: pm.cfg.node_id(),
+        sender_listen_port: Some(24567),
+        sender_chain_info: chain.get_peer_chain_info(),
+        partial_edge_info: PartialEdgeInfo::new(&peer_id, &pm.cfg.node_id(), nonce, node_key),
+        owned_account: None,
+        supported_compression: vec![],
+        supports_encryption: true,
+        encryption_signature: Some(noise::sign_encryption_flag(
+            flag_key,
+            &pm.cfg.node_id(),
+            nonce,
+            true,
+        )),
+    })
+    .serialize(Encoding::Proto);
+    stream.write_frame(&handshake).await;
+    (stream, handshake, stream_id)
+}
+
+/// Connects to a PeerManager with a raw stream, sends a Handshake supporting
+/// encryption and runs the Noise handshake signing with `noise_key`. The
+/// Handshake received from the PeerManager is replaced with
+/// `tampered_handshake` in the prologue, if set.
+async fn start_noise_handshake(
+    pm: &peer_manager::testonly::ActorHandler,
+    chain: &data::Chain,
+    clock: &time::Clock,
+    node_key: &near_crypto::SecretKey,
+    noise_key: &near_crypto::SecretKey,
+    tampered_handshake: Option<&[u8]>,
+) -> (Stream, NoiseHandshake, tcp::StreamId) {
+    let (mut stream, sent, stream_id) = send_handshake(pm, chain, clock, node_key, node_key).await;
+    let received = stream.read_frame().await.unwrap();
+    match PeerMessage::deserialize(Encoding::Proto, &received) {
+        Ok(PeerMessage::Tier2Handshake(handshake)) => assert!(handshake.supports_encryption),
+        got => panic!("got = {got:?}, want Handshake"),
+    }
+    let prologue = noise::prologue(&sent, tampered_handshake.unwrap_or(&received[..]));
+    let (handshake, msg) =
+        NoiseHandshake::start(true, noise_key, pm.cfg.node_id(), &prologue).unwrap();
+    stream.write_frame(&msg.unwrap()).await;
+    (stream, handshake, stream_id)
+}
+
+#[tokio::test]
+async fn encrypted_frames() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        chain.make_config(rng),
+        chain.clone(),
+    )
+    .await;
+
+    let node_key = data::make_secret_key(rng);
+    let (mut stream, mut handshake, stream_id) =
+        start_noise_handshake(&pm, &chain, &clock.clock(), &node_key, &node_key, None).await;
+    let reply = handshake.read_message(&stream.read_frame().await.unwrap()).unwrap();
+    stream.write_frame(&reply.unwrap()).await;
+    let mut transport = handshake.into_transport().unwrap();
+
+    tracing::info!(target: "test", "frames sent by the PeerManager are encrypted");
+    let frame = stream.read_frame().await.unwrap();
+    PeerMessage::deserialize(Encoding::Proto, &transport.decrypt(&frame).unwrap()).unwrap();
+
+    tracing::info!(target: "test", "encrypted frames are accepted");
+    let mut events = pm.events.from_now();
+    let want = PeerMessage::Transaction(data::make_signed_transaction(rng));
+    stream.write_frame(&transport.encrypt(&want.serialize(Encoding::Proto)).unwrap()).await;
+    events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::MessageProcessed(_, got)) if got == want => Some(()),
+            _ => None,
+        })
+        .await;
+
+    tracing::info!(target: "test", "plaintext frames close the connection");
+    let mut events = pm.events.from_now();
+    stream.write(&want).await;
+    let reason = events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev)) if ev.stream_id == stream_id => {
+                Some(ev.reason)
+            }
+            _ => None,
+        })
+        .await;
+    assert!(matches!(reason, ClosingReason::EncryptionFailed(_)), "reason = {reason:?}");
+}
+
+#[tokio::test]
+async fn noise_impersonation() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        chain.make_config(rng),
+        chain.clone(),
+    )
+    .await;
+
+    // The Noise static key is signed with a key other than the one of the
+    // PeerId sent in the Handshake.
+    let node_key = data::make_secret_key(rng);
+    let other_key = data::make_secret_key(rng);
+    let mut events = pm.events.from_now();
+    let (mut stream, mut handshake, stream_id) =
+        start_noise_handshake(&pm, &chain, &clock.clock(), &node_key, &other_key, None).await;
+    let reply = handshake.read_message(&stream.read_frame().await.unwrap()).unwrap();
+    stream.write_frame(&reply.unwrap()).await;
+    let reason = events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev)) if ev.stream_id == stream_id => {
+                Some(ev.reason)
+            }
+            _ => None,
+        })
+        .await;
+    assert!(matches!(reason, ClosingReason::EncryptionFailed(_)), "reason = {reason:?}");
+}
+
+#[tokio::test]
+async fn noise_handshake_timeout() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let mut cfg = chain.make_config(rng);
+    cfg.handshake_timeout = time::Duration::seconds(2);
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        cfg,
+        chain.clone(),
+    )
+    .await;
+
+    // The peer never completes the Noise handshake.
+    let node_key = data::make_secret_key(rng);
+    let mut events = pm.events.from_now();
+    let (_stream, _handshake, stream_id) =
+        start_noise_handshake(&pm, &chain, &clock.clock(), &node_key, &node_key, None).await;
+    let reason = events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev)) if ev.stream_id == stream_id => {
+                Some(ev.reason)
+            }
+            _ => None,
+        })
+        .await;
+    assert!(matches!(reason, ClosingReason::EncryptionFailed(_)), "reason = {reason:?}");
+}
+
+#[tokio::test]
+async fn noise_handshake_tampered() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        chain.make_config(rng),
+        chain.clone(),
+    )
+    .await;
+
+    // The peer saw another Handshake than the one sent by the PeerManager,
+    // so the Noise handshake fails.
+    let node_key = data::make_secret_key(rng);
+    let (mut stream, mut handshake, _) =
+        start_noise_handshake(&pm, &chain, &clock.clock(), &node_key, &node_key, Some(b"tampered"))
+            .await;
+    let reply = handshake.read_message(&stream.read_frame().await.unwrap());
+    assert!(matches!(reply, Err(NoiseError::Snow(_))), "reply = {reply:?}");
+}
+
+#[tokio::test]
+async fn encryption_flag_signed_by_another_key() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        chain.make_config(rng),
+        chain.clone(),
+    )
+    .await;
+
+    let node_key = data::make_secret_key(rng);
+    let other_key = data::make_secret_key(rng);
+    let mut events = pm.events.from_now();
+    let (_stream, _, stream_id) =
+        send_handshake(&pm, &chain, &clock.clock(), &node_key, &other_key).await;
+    let reason = events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev)) if ev.stream_id == stream_id => {
+                Some(ev.reason)
+            }
+            _ => None,
+        })
+        .await;
+    assert_eq!(ClosingReason::Ban(ReasonForBan::InvalidSignature), reason);
+}
@@ -1,5 +1,6 @@
 mod accounts_data;
 mod connection_pool;
+mod encryption;
 mod fuzzers;
 mod nonce;
 mod routing;
@@ -57,6 +57,17 @@ impl RateLimits {
         }
         true
     }
+
+    /// Checks if a message which was decompressed to `size` bytes is under the
+    /// rate limit of decompressed data, whose tokens are KiB.
+    pub fn is_decompressed_size_allowed(&mut self, size: usize, now: Instant) -> bool {
+        match &mut self.buckets[RateLimitedPeerMessageKey::DecompressedBytes] {
+            Some(bucket) => {
+                bucket.acquire(u32::try_from(size.div_ceil(1024)).unwrap_or(u32::MAX), now)
+            }
+            None => true,
+        }
+    }
 }
 
 /// Rate limit configuration for a single network message.
@@ -111,7 +122,14 @@ impl Config {
     /// Returns a good preset of rate limit configuration valid for any type of node.
     pub fn standard_preset() -> Self {
         // TODO(trisfald): make preset
-        Self::default()
+        let mut config = Self::default();
+        // Compressed messages may decompress to 2 GiB in a burst and 512 MiB
+        // per second afterwards.
+        config.rate_limits.insert(
+            RateLimitedPeerMessageKey::DecompressedBytes,
+            SingleMessageConfig::new(2 * 1024 * 1024, 512.0 * 1024.0, None),
+        );
+        config
     }
 
     /// Applies rate limits configuration overrides to `self`. In practice, merges the two configurations
@@ -128,6 +146,10 @@ impl Config {
 
 /// This enum represents the variants of [PeerMessage] that can be rate limited.
 /// It is meant to be used as an index for mapping peer messages to a value.
+///
+/// `DecompressedBytes` is not a message type: it limits the size of the data
+/// decompressed from compressed frames, in KiB, see
+/// [RateLimits::is_decompressed_size_allowed].
 #[derive(
     Clone,
     Copy,
@@ -170,6 +192,7 @@ pub enum RateLimitedPeerMessageKey {
     ChunkStateWitnessAck,
     PartialEncodedStateWitness,
     PartialEncodedStateWitnessForward,
+    DecompressedBytes,
 }
 
 /// Given a `PeerMessage` returns a tuple containing the `RateLimitedPeerMessageKey`
@@ -288,6 +311,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn is_decompressed_size_allowed() {
+        let now = Instant::now();
+        let mut limits = RateLimits::default();
+        assert!(limits.is_decompressed_size_allowed(1 << 30, now));
+
+        // Tokens are KiB, rounded up.
+        limits.buckets[RateLimitedPeerMessageKey::DecompressedBytes] =
+            Some(TokenBucket::new(3, 3, 0.0, now).unwrap());
+        assert!(limits.is_decompressed_size_allowed(1025, now));
+        assert!(!limits.is_decompressed_size_allowed(1025, now));
+        assert!(limits.is_decompressed_size_allowed(1, now));
+        assert!(!limits.is_decompressed_size_allowed(1, now));
+    }
+
     #[test]
     fn configuration() {
         use RateLimitedPeerMessageKey::*;
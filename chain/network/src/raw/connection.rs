@@ -218,6 +218,9 @@ fn new_handshake(
         },
         partial_edge_info: PartialEdgeInfo::new(my_peer_id, target_peer_id, nonce, secret_key),
         owned_account: None,
+        supported_compression: vec![],
+        supports_encryption: false,
+        encryption_signature: None,
     })
 }
 
@@ -5,7 +5,7 @@ use crate::types::PeerType;
 use near_async::time;
 use near_o11y::metrics::prometheus;
 use near_o11y::metrics::{
-    exponential_buckets, try_create_histogram, try_create_histogram_vec,
+    exponential_buckets, linear_buckets, try_create_histogram, try_create_histogram_vec,
     try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
     try_create_int_gauge, try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter,
     IntCounterVec, IntGauge, IntGaugeVec, MetricVec, MetricVecBuilder,
@@ -192,6 +192,31 @@ pub(crate) static PEER_MESSAGE_SENT_BY_TYPE_TOTAL: Lazy<IntCounterVec> = Lazy::n
     )
     .unwrap()
 });
+pub(crate) static PEER_MESSAGE_COMPRESSION_INPUT_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_message_compression_input_bytes",
+        "Total size of compressed messages sent to peers before compression, by message type and algorithm",
+        &["type", "compression"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_MESSAGE_COMPRESSION_OUTPUT_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_message_compression_output_bytes",
+        "Total size of compressed messages sent to peers after compression, by message type and algorithm",
+        &["type", "compression"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_MESSAGE_COMPRESSION_RATIO: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_message_compression_ratio",
+        "Ratio of compressed to uncompressed size of messages sent to peers, by message type",
+        &["type"],
+        Some(linear_buckets(0.05, 0.05, 20).unwrap()),
+    )
+    .unwrap()
+});
 pub(crate) static PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_peer_message_rate_limited_by_type_total",
@@ -27,12 +27,18 @@ impl Stream {
         return None;
     }
 
+    /// Reads a frame without decoding it.
+    pub async fn read_frame(&mut self) -> Result<BytesMut, std::io::Error> {
+        let n = self.stream.stream.read_u32_le().await? as usize;
+        let mut buf = BytesMut::new();
+        buf.resize(n, 0);
+        self.stream.stream.read_exact(&mut buf[..]).await?;
+        Ok(buf)
+    }
+
     pub async fn read(&mut self) -> Result<PeerMessage, std::io::Error> {
         'read: loop {
-            let n = self.stream.stream.read_u32_le().await? as usize;
-            let mut buf = BytesMut::new();
-            buf.resize(n, 0);
-            self.stream.stream.read_exact(&mut buf[..]).await?;
+            let buf = self.read_frame().await?;
             for enc in [Encoding::Proto, Encoding::Borsh] {
                 if let Ok(msg) = PeerMessage::deserialize(enc, &buf[..]) {
                     // If deserialize() succeeded but we expected different encoding, ignore the
@@ -53,14 +59,15 @@ impl Stream {
 
     pub async fn write(&mut self, msg: &PeerMessage) {
         if let Some(enc) = self.encoding() {
-            self.write_encoded(&msg.serialize(enc)).await;
+            self.write_frame(&msg.serialize(enc)).await;
         } else {
-            self.write_encoded(&msg.serialize(Encoding::Proto)).await;
-            self.write_encoded(&msg.serialize(Encoding::Borsh)).await;
+            self.write_frame(&msg.serialize(Encoding::Proto)).await;
+            self.write_frame(&msg.serialize(Encoding::Borsh)).await;
         }
     }
 
-    async fn write_encoded(&mut self, msg: &[u8]) {
+    /// Writes a frame as it is.
+    pub async fn write_frame(&mut self, msg: &[u8]) {
         self.stream.stream.write_u32_le(msg.len() as u32).await.unwrap();
         self.stream.stream.write_all(msg).await.unwrap();
         self.stream.stream.flush().await.unwrap();
@@ -1,17 +1,21 @@
-use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
+use crate::ordering::{transaction_priority, Scheduler};
 use crate::types::{PoolKey, TransactionGroup, TransactionGroupIterator};
 
+use near_chain_configs::{TransactionPoolConfig, TransactionPoolOrdering};
 use near_crypto::PublicKey;
 use near_o11y::metrics::prometheus::core::{AtomicI64, GenericGauge};
+use near_o11y::metrics::IntCounter;
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::shard_layout::ShardLayout;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, Balance};
 use std::ops::Bound;
 
 mod metrics;
+mod ordering;
 pub mod types;
 
 #[derive(Debug, PartialEq)]
@@ -22,6 +26,8 @@ pub enum InsertTransactionResult {
     Duplicate,
     /// Not enough space to fit the transaction.
     NoSpaceLeft,
+    /// The signer already has the maximum number of transactions in the pool.
+    SignerQuotaExceeded,
 }
 
 /// Transaction pool: keeps track of transactions that were not yet accepted into the block chain.
@@ -30,44 +36,65 @@ pub struct TransactionPool {
     /// NOTE: It's more efficient on average to keep transactions unsorted and with potentially
     /// conflicting nonce than to create a BTreeMap for every transaction.
     transactions: BTreeMap<PoolKey, Vec<SignedTransaction>>,
-    /// Set of all hashes to quickly check if the given transaction is in the pool.
-    unique_transactions: HashSet<CryptoHash>,
+    /// Hashes of all transactions mapped to the key of their group, to quickly check if the
+    /// given transaction is in the pool and to find it.
+    unique_transactions: HashMap<CryptoHash, PoolKey>,
     /// A uniquely generated key seed to randomize PoolKey order.
     key_seed: RngSeed,
     /// The key after which the pool iterator starts. Doesn't have to be present in the pool.
     last_used_key: PoolKey,
-    /// If set, new transactions that bring the size of the pool over this limit will be rejected.
+    /// If set, new transactions that bring the size of the pool over this limit will be rejected
+    /// or evict transactions with lower priority.
     total_transaction_size_limit: Option<u64>,
     /// Total size of transactions in the pool measured in bytes.
     total_transaction_size: u64,
+    /// Ordering, quotas and eviction policy.
+    config: TransactionPoolConfig,
+    /// Number of transactions in the pool by signer, including the ones held by a pool iterator.
+    signer_transaction_counts: HashMap<AccountId, usize>,
+    /// Priority of every group in `transactions`, which is the highest priority of its
+    /// transactions.
+    group_priorities: HashMap<PoolKey, Balance>,
+    /// Groups in `transactions` ordered by priority, to find the ones to evict.
+    groups_by_priority: BTreeSet<(Balance, PoolKey)>,
     /// Metrics tracked for transaction pool.
     transaction_pool_count_metric: GenericGauge<AtomicI64>,
     transaction_pool_size_metric: GenericGauge<AtomicI64>,
+    transaction_pool_evicted_metric: IntCounter,
 }
 
 impl TransactionPool {
     pub fn new(
         key_seed: RngSeed,
         total_transaction_size_limit: Option<u64>,
+        config: TransactionPoolConfig,
         metrics_label: &str,
     ) -> Self {
         let transaction_pool_count_metric =
             metrics::TRANSACTION_POOL_COUNT.with_label_values(&[metrics_label]);
         let transaction_pool_size_metric =
             metrics::TRANSACTION_POOL_SIZE.with_label_values(&[metrics_label]);
+        let transaction_pool_evicted_metric =
+            metrics::TRANSACTION_POOL_EVICTED.with_label_values(&[metrics_label]);
         // A `get()` call initializes a metric even if its value is zero.
         transaction_pool_count_metric.get();
         transaction_pool_size_metric.get();
+        transaction_pool_evicted_metric.get();
 
         Self {
             key_seed,
             transactions: BTreeMap::new(),
-            unique_transactions: HashSet::new(),
+            unique_transactions: HashMap::new(),
             last_used_key: CryptoHash::default(),
             total_transaction_size_limit,
             total_transaction_size: 0,
+            config,
+            signer_transaction_counts: HashMap::new(),
+            group_priorities: HashMap::new(),
+            groups_by_priority: BTreeSet::new(),
             transaction_pool_count_metric,
             transaction_pool_size_metric,
+            transaction_pool_evicted_metric,
         }
     }
 
@@ -84,10 +111,18 @@ impl TransactionPool {
         &mut self,
         signed_transaction: SignedTransaction,
     ) -> InsertTransactionResult {
-        if !self.unique_transactions.insert(signed_transaction.get_hash()) {
+        if self.unique_transactions.contains_key(&signed_transaction.get_hash()) {
             // The hash of this transaction was already seen, skip it.
             return InsertTransactionResult::Duplicate;
         }
+        let signer_id = signed_transaction.transaction.signer_id();
+        if let Some(max_transactions_per_signer) = self.config.max_transactions_per_signer {
+            let signer_transaction_count =
+                self.signer_transaction_counts.get(signer_id).copied().unwrap_or_default();
+            if signer_transaction_count >= max_transactions_per_signer {
+                return InsertTransactionResult::SignerQuotaExceeded;
+            }
+        }
         // We never expect the total size to go over `u64` during real operation as that would
         // be more than 10^9 GiB of RAM consumed for transaction pool, so panicing here is intended
         // to catch a logic error in estimation of transaction size.
@@ -95,33 +130,147 @@ impl TransactionPool {
             .total_transaction_size
             .checked_add(signed_transaction.get_size())
             .expect("Total transaction size is too large");
+        let key = self.key(signer_id, signed_transaction.transaction.public_key());
+        let priority = transaction_priority(&signed_transaction);
         if let Some(limit) = self.total_transaction_size_limit {
-            if new_total_transaction_size > limit {
+            if new_total_transaction_size > limit
+                && !self.evict_lower_priority(key, priority, new_total_transaction_size - limit)
+            {
                 return InsertTransactionResult::NoSpaceLeft;
             }
         }
 
         // At this point transaction is accepted to the pool.
-        self.total_transaction_size = new_total_transaction_size;
-        let signer_id = signed_transaction.transaction.signer_id();
-        let signer_public_key = signed_transaction.transaction.public_key();
-        self.transactions
-            .entry(self.key(signer_id, signer_public_key))
-            .or_insert_with(Vec::new)
-            .push(signed_transaction);
+        self.unique_transactions.insert(signed_transaction.get_hash(), key);
+        self.total_transaction_size = self
+            .total_transaction_size
+            .checked_add(signed_transaction.get_size())
+            .expect("Total transaction size is too large");
+        *self.signer_transaction_counts.entry(signer_id.clone()).or_default() += 1;
+        let old_priority = self.group_priorities.get(&key).copied();
+        let new_priority = old_priority.map_or(priority, |old_priority| old_priority.max(priority));
+        if old_priority != Some(new_priority) {
+            if let Some(old_priority) = old_priority {
+                self.groups_by_priority.remove(&(old_priority, key));
+            }
+            self.groups_by_priority.insert((new_priority, key));
+            self.group_priorities.insert(key, new_priority);
+        }
+        self.transactions.entry(key).or_insert_with(Vec::new).push(signed_transaction);
 
         self.transaction_pool_count_metric.inc();
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
         InsertTransactionResult::Success
     }
 
+    /// Evicts the groups with the lowest priority to free at least `size` bytes for a new
+    /// transaction with the given priority and key.  Only groups whose transactions all have a
+    /// lower priority than the new one may be evicted, and never the group of the new transaction.
+    /// Returns false without evicting anything if not enough space can be freed.
+    fn evict_lower_priority(&mut self, key: PoolKey, priority: Balance, size: u64) -> bool {
+        if !self.config.evict_lowest_priority {
+            return false;
+        }
+        let mut evicted_keys = vec![];
+        let mut evicted_size = 0;
+        for (group_priority, group_key) in &self.groups_by_priority {
+            if evicted_size >= size || *group_priority >= priority {
+                break;
+            }
+            if *group_key == key {
+                continue;
+            }
+            evicted_size +=
+                self.transactions[group_key].iter().map(|tx| tx.get_size()).sum::<u64>();
+            evicted_keys.push(*group_key);
+        }
+        if evicted_size < size {
+            return false;
+        }
+        for group_key in evicted_keys {
+            let transactions = self.take_group(&group_key).expect("group is in the pool");
+            for tx in &transactions {
+                self.unique_transactions.remove(&tx.get_hash());
+            }
+            self.release_transactions(
+                transactions[0].transaction.signer_id(),
+                transactions.len(),
+                transactions.iter().map(|tx| tx.get_size()).sum(),
+            );
+            self.transaction_pool_evicted_metric.inc_by(transactions.len() as u64);
+        }
+        true
+    }
+
+    /// Takes the group out of the pool, e.g. to hand it over to a pool iterator.
+    fn take_group(&mut self, key: &PoolKey) -> Option<Vec<SignedTransaction>> {
+        let transactions = self.transactions.remove(key)?;
+        if let Some(priority) = self.group_priorities.remove(key) {
+            self.groups_by_priority.remove(&(priority, *key));
+        }
+        Some(transactions)
+    }
+
+    /// Puts a non-empty group taken with `take_group` back into the pool.
+    fn put_group(&mut self, key: PoolKey, transactions: Vec<SignedTransaction>) {
+        let priority = transactions.iter().map(transaction_priority).max().unwrap_or_default();
+        self.group_priorities.insert(key, priority);
+        self.groups_by_priority.insert((priority, key));
+        self.transactions.insert(key, transactions);
+    }
+
+    /// Updates the total size and the signer's transaction count once transactions have left
+    /// the pool.
+    fn release_transactions(&mut self, signer_id: &AccountId, num_transactions: usize, size: u64) {
+        // See the comment in `insert_transaction` where we increase the size for reasoning why
+        // panicing here catches a logic error.
+        self.total_transaction_size = self
+            .total_transaction_size
+            .checked_sub(size)
+            .expect("Total transaction size dropped below zero");
+        if num_transactions == 0 {
+            return;
+        }
+        let count = self
+            .signer_transaction_counts
+            .get_mut(signer_id)
+            .expect("signer of a transaction in the pool must be tracked");
+        *count = count
+            .checked_sub(num_transactions)
+            .expect("Signer transaction count dropped below zero");
+        if *count == 0 {
+            self.signer_transaction_counts.remove(signer_id);
+        }
+    }
+
+    /// Forgets the transactions that were pulled from the group by a pool iterator.
+    fn release_removed_transactions(&mut self, group: &TransactionGroup) {
+        for hash in &group.removed_transaction_hashes {
+            self.unique_transactions.remove(hash);
+        }
+        self.release_transactions(
+            &group.signer_id,
+            group.removed_transaction_hashes.len(),
+            group.removed_transaction_size,
+        );
+    }
+
     /// Returns a pool iterator wrapper that implements an iterator-like trait to iterate over
-    /// transaction groups in the proper order defined by the protocol.
+    /// transaction groups in the order defined by the pool ordering policy.
     /// When the iterator is dropped, all remaining groups are inserted back into the pool.
     pub fn pool_iterator(&mut self) -> PoolIteratorWrapper<'_> {
         PoolIteratorWrapper::new(self)
     }
 
+    /// Same as `pool_iterator`, but uses the shard layout to find receiver shards for the
+    /// `ReceiverShardFairness` ordering.
+    pub fn pool_iterator_with_shard_layout(
+        &mut self,
+        shard_layout: &ShardLayout,
+    ) -> PoolIteratorWrapper<'_> {
+        PoolIteratorWrapper::with_shard_layout(self, Some(shard_layout))
+    }
+
     /// Removes given transactions from the pool.
     ///
     /// In practice, used to evict transactions that have already been included into the block or
@@ -130,7 +279,7 @@ impl TransactionPool {
         let mut grouped_transactions = HashMap::new();
         for tx in transactions {
             // If transaction is not present in the pool, skip it.
-            if !self.unique_transactions.remove(&tx.get_hash()) {
+            if self.unique_transactions.remove(&tx.get_hash()).is_none() {
                 continue;
             }
 
@@ -142,22 +291,20 @@ impl TransactionPool {
                 .insert(tx.get_hash());
         }
         for (key, hashes) in grouped_transactions {
-            if let Entry::Occupied(mut entry) = self.transactions.entry(key) {
-                entry.get_mut().retain(|tx| {
-                    if !hashes.contains(&tx.get_hash()) {
-                        return true;
-                    }
-                    // See the comment above where we increase the size for reasoning why panicing
-                    // here catches a logic error.
-                    self.total_transaction_size = self
-                        .total_transaction_size
-                        .checked_sub(tx.get_size())
-                        .expect("Total transaction size dropped below zero");
-                    false
-                });
-                if entry.get().is_empty() {
-                    entry.remove_entry();
-                }
+            let Some(transactions) = self.take_group(&key) else {
+                continue;
+            };
+            let (removed, remaining): (Vec<_>, Vec<_>) =
+                transactions.into_iter().partition(|tx| hashes.contains(&tx.get_hash()));
+            if let Some(tx) = removed.first() {
+                self.release_transactions(
+                    tx.transaction.signer_id(),
+                    removed.len(),
+                    removed.iter().map(|tx| tx.get_size()).sum(),
+                );
+            }
+            if !remaining.is_empty() {
+                self.put_group(key, remaining);
             }
         }
 
@@ -166,6 +313,23 @@ impl TransactionPool {
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
     }
 
+    /// Returns all transactions in the pool, in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.transactions.values().flatten()
+    }
+
+    /// Returns the transaction with the given hash together with the number of transactions of
+    /// the same access key with a lower nonce, which have to be included in a chunk first.
+    pub fn get_transaction(&self, tx_hash: &CryptoHash) -> Option<(&SignedTransaction, usize)> {
+        let key = self.unique_transactions.get(tx_hash)?;
+        // The group is missing while it's held by a pool iterator.
+        let group = self.transactions.get(key)?;
+        let transaction = group.iter().find(|tx| tx.get_hash() == *tx_hash)?;
+        let nonce = transaction.transaction.nonce();
+        let num_before = group.iter().filter(|tx| tx.transaction.nonce() < nonce).count();
+        Some((transaction, num_before))
+    }
+
     /// Returns the number of unique transactions in the pool.
     pub fn len(&self) -> usize {
         self.unique_transactions.len()
@@ -177,6 +341,18 @@ impl TransactionPool {
     }
 }
 
+/// Creates a group from the transactions of a single pool key, sorted by nonce.
+fn sorted_group(key: PoolKey, mut transactions: Vec<SignedTransaction>) -> TransactionGroup {
+    transactions.sort_by_key(|st| std::cmp::Reverse(st.transaction.nonce()));
+    TransactionGroup {
+        key,
+        signer_id: transactions[0].transaction.signer_id().clone(),
+        transactions,
+        removed_transaction_hashes: vec![],
+        removed_transaction_size: 0,
+    }
+}
+
 /// PoolIterator is a structure to pull transactions from the pool.
 /// It implements `TransactionGroupIterator` trait that iterates over transaction groups one by one.
 /// When the wrapper is dropped the remaining transactions are returned back to the pool.
@@ -186,15 +362,48 @@ pub struct PoolIteratorWrapper<'a> {
 
     /// Queue of transaction groups. Each group there is sorted by nonce.
     sorted_groups: VecDeque<TransactionGroup>,
+
+    /// Picks the next group from `sorted_groups` for orderings other than round robin. All
+    /// groups are taken out of the pool on creation in that case.
+    scheduler: Option<Scheduler>,
+
+    /// Index of the group returned by the previous call to the scheduler.
+    last_scheduled: Option<usize>,
 }
 
 impl<'a> PoolIteratorWrapper<'a> {
     pub fn new(pool: &'a mut TransactionPool) -> Self {
-        Self { pool, sorted_groups: Default::default() }
+        Self::with_shard_layout(pool, None)
+    }
+
+    pub fn with_shard_layout(
+        pool: &'a mut TransactionPool,
+        shard_layout: Option<&ShardLayout>,
+    ) -> Self {
+        let mut sorted_groups = VecDeque::new();
+        let mut scheduler = None;
+        if pool.config.ordering != TransactionPoolOrdering::RoundRobin
+            && !pool.transactions.is_empty()
+        {
+            // Start after the last used key, so that groups with equal priority take turns
+            // across iterations.
+            let keys: Vec<PoolKey> = pool
+                .transactions
+                .range((Bound::Excluded(pool.last_used_key), Bound::Unbounded))
+                .chain(pool.transactions.range(..=pool.last_used_key))
+                .map(|(key, _)| *key)
+                .collect();
+            for key in keys {
+                let transactions = pool.take_group(&key).expect("just listed");
+                sorted_groups.push_back(sorted_group(key, transactions));
+            }
+            scheduler = Scheduler::new(pool.config.ordering, shard_layout, &sorted_groups);
+        }
+        Self { pool, sorted_groups, scheduler, last_scheduled: None }
     }
 }
 
-/// The iterator works with the following algorithm:
+/// With the round robin ordering the iterator works with the following algorithm:
 /// On next(), the iterator tries to get a transaction group from the pool, sorts transactions in
 /// it, and add it to the back of the sorted groups queue.
 /// Remembers the last used key, so it can continue from the next key.
@@ -209,10 +418,20 @@ impl<'a> PoolIteratorWrapper<'a> {
 ///
 /// If the sorted groups queue is empty, the iterator returns None.
 ///
+/// With other orderings all groups are already in the sorted groups queue and the scheduler
+/// decides which of them to return.
+///
 /// When the iterator is dropped, `unique_transactions` in the pool is updated for every group.
 /// And all non-empty group from the sorted groups queue are inserted back into the pool.
 impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
     fn next(&mut self) -> Option<&mut TransactionGroup> {
+        if let Some(scheduler) = &mut self.scheduler {
+            let index = scheduler.next(&self.sorted_groups, self.last_scheduled.take())?;
+            self.last_scheduled = Some(index);
+            let group = &mut self.sorted_groups[index];
+            self.pool.last_used_key = group.key;
+            return Some(group);
+        }
         if !self.pool.transactions.is_empty() {
             let key = *self
                 .pool
@@ -228,30 +447,13 @@ impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
                         .expect("we've just checked that the map is not empty")
                 });
             self.pool.last_used_key = key;
-            let mut transactions =
-                self.pool.transactions.remove(&key).expect("just checked existence");
-            transactions.sort_by_key(|st| std::cmp::Reverse(st.transaction.nonce()));
-            self.sorted_groups.push_back(TransactionGroup {
-                key,
-                transactions,
-                removed_transaction_hashes: vec![],
-                removed_transaction_size: 0,
-            });
+            let transactions = self.pool.take_group(&key).expect("just checked existence");
+            self.sorted_groups.push_back(sorted_group(key, transactions));
             Some(self.sorted_groups.back_mut().expect("just pushed"))
         } else {
             while let Some(sorted_group) = self.sorted_groups.pop_front() {
                 if sorted_group.transactions.is_empty() {
-                    for hash in sorted_group.removed_transaction_hashes {
-                        self.pool.unique_transactions.remove(&hash);
-                    }
-                    // See the comment in `insert_transaction` where we increase the size for reasoning
-                    // why panicing here catches a logic error.
-                    self.pool.total_transaction_size = self
-                        .pool
-                        .total_transaction_size
-                        .checked_sub(sorted_group.removed_transaction_size)
-                        .expect("Total transaction size dropped below zero");
-
+                    self.pool.release_removed_transactions(&sorted_group);
                     self.pool
                         .transaction_pool_count_metric
                         .set(self.pool.unique_transactions.len() as i64);
@@ -271,20 +473,10 @@ impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
 /// removed from the pool's unique_transactions.
 impl<'a> Drop for PoolIteratorWrapper<'a> {
     fn drop(&mut self) {
-        for group in self.sorted_groups.drain(..) {
-            for hash in group.removed_transaction_hashes {
-                self.pool.unique_transactions.remove(&hash);
-            }
-            // See the comment in `insert_transaction` where we increase the size for reasoning
-            // why panicing here catches a logic error.
-            self.pool.total_transaction_size = self
-                .pool
-                .total_transaction_size
-                .checked_sub(group.removed_transaction_size)
-                .expect("Total transaction size dropped below zero");
-
+        for group in std::mem::take(&mut self.sorted_groups) {
+            self.pool.release_removed_transactions(&group);
             if !group.transactions.is_empty() {
-                self.pool.transactions.insert(group.key, group.transactions);
+                self.pool.put_group(group.key, group.transactions);
             }
         }
         // We can update metrics only once for the whole batch of transactions.
@@ -307,6 +499,7 @@ impl TransactionGroupIteratorWrapper {
             .iter()
             .map(|transaction| TransactionGroup {
                 key: PoolKey::default(),
+                signer_id: transaction.transaction.signer_id().clone(),
                 transactions: vec![transaction.clone()],
                 removed_transaction_hashes: vec![],
                 removed_transaction_size: 0,
@@ -337,10 +530,9 @@ mod tests {
     use rand::seq::SliceRandom;
     use rand::thread_rng;
 
-    use near_crypto::{InMemorySigner, KeyType};
+    use near_crypto::{InMemorySigner, KeyType, Signer};
 
     use near_primitives::hash::CryptoHash;
-    use near_primitives::types::Balance;
 
     const TEST_SEED: RngSeed = [3; 32];
 
@@ -372,7 +564,7 @@ mod tests {
         mut transactions: Vec<SignedTransaction>,
         expected_weight: u32,
     ) -> (Vec<u64>, TransactionPool) {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, None, TransactionPoolConfig::default(), "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions {
@@ -482,7 +674,7 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, None, TransactionPoolConfig::default(), "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions.clone() {
@@ -596,7 +788,7 @@ mod tests {
 
     #[test]
     fn test_transaction_pool_size() {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, None, TransactionPoolConfig::default(), "");
         let transactions = generate_transactions("alice.near", "alice.near", 1, 100);
         let mut total_transaction_size = 0;
         // Adding transactions increases the size.
@@ -614,13 +806,36 @@ mod tests {
         assert_eq!(pool.transaction_size(), 0);
     }
 
+    #[test]
+    fn test_get_transaction() {
+        let mut pool = TransactionPool::new(TEST_SEED, None, TransactionPoolConfig::default(), "");
+        let mut transactions = generate_transactions("alice.near", "alice.near", 1, 5);
+        transactions.extend(generate_transactions("bob.near", "bob.near", 1, 2));
+        for tx in transactions.iter().rev().cloned() {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        let (tx, num_before) = pool.get_transaction(&transactions[3].get_hash()).unwrap();
+        assert_eq!(tx, &transactions[3]);
+        assert_eq!(num_before, 3);
+        let (_, num_before) = pool.get_transaction(&transactions[5].get_hash()).unwrap();
+        assert_eq!(num_before, 0);
+
+        pool.remove_transactions(&transactions[3..4]);
+        assert!(pool.get_transaction(&transactions[3].get_hash()).is_none());
+    }
+
     #[test]
     fn test_transaction_pool_size_limit() {
         let transactions = generate_transactions("alice.near", "alice.near", 1, 100);
         // Each transaction is at least 1 byte in size, so the last transaction will not fit.
         let pool_size_limit =
             transactions.iter().map(|tx| tx.get_size()).sum::<u64>().checked_sub(1).unwrap();
-        let mut pool = TransactionPool::new(TEST_SEED, Some(pool_size_limit), "");
+        let mut pool = TransactionPool::new(
+            TEST_SEED,
+            Some(pool_size_limit),
+            TransactionPoolConfig::default(),
+            "",
+        );
         for (i, tx) in transactions.iter().cloned().enumerate() {
             if i + 1 < transactions.len() {
                 assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
@@ -628,5 +843,183 @@ mod tests {
                 assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::NoSpaceLeft);
             }
         }
+        assert_eq!(pool.len(), transactions.len() - 1);
+    }
+
+    fn generate_transactions_with_deposit(
+        signer_id: &str,
+        receiver_id: &str,
+        deposit: Balance,
+        starting_nonce: u64,
+        end_nonce: u64,
+    ) -> Vec<SignedTransaction> {
+        let signer_id: AccountId = signer_id.parse().unwrap();
+        let signer: Signer =
+            InMemorySigner::from_seed(signer_id.clone(), KeyType::ED25519, signer_id.as_str())
+                .into();
+        (starting_nonce..=end_nonce)
+            .map(|i| {
+                SignedTransaction::send_money(
+                    i,
+                    signer_id.clone(),
+                    receiver_id.parse().unwrap(),
+                    &signer,
+                    deposit,
+                    CryptoHash::default(),
+                )
+            })
+            .collect()
+    }
+
+    fn pool_with_config(
+        config: TransactionPoolConfig,
+        size_limit: Option<u64>,
+        transactions: Vec<SignedTransaction>,
+    ) -> TransactionPool {
+        let mut pool = TransactionPool::new(TEST_SEED, size_limit, config, "");
+        for tx in transactions {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        pool
+    }
+
+    /// Groups with a larger deposit go first, groups with equal deposits take turns.
+    #[test]
+    fn test_attached_deposit_ordering() {
+        let mut transactions =
+            generate_transactions_with_deposit("alice.near", "bob.near", 1, 1, 3);
+        transactions.extend(generate_transactions_with_deposit("carol.near", "bob.near", 10, 1, 3));
+        transactions.extend(generate_transactions_with_deposit("dave.near", "bob.near", 1, 1, 3));
+        transactions.shuffle(&mut thread_rng());
+        let config = TransactionPoolConfig {
+            ordering: TransactionPoolOrdering::AttachedDeposit,
+            ..Default::default()
+        };
+        let mut pool = pool_with_config(config, None, transactions);
+
+        let txs = prepare_transactions(&mut pool, 9);
+        let signers: Vec<_> = txs.iter().map(|tx| tx.transaction.signer_id().as_str()).collect();
+        assert_eq!(signers[..3], ["carol.near"; 3]);
+        for pair in signers[3..].chunks_exact(2) {
+            let mut pair = pair.to_vec();
+            pair.sort();
+            assert_eq!(pair, ["alice.near", "dave.near"]);
+        }
+        let nonces: Vec<_> = txs.iter().map(|tx| tx.transaction.nonce()).collect();
+        assert_eq!(nonces, [1, 2, 3, 1, 1, 2, 2, 3, 3]);
+        assert_eq!(pool.len(), 0);
+    }
+
+    /// Receiver shards take turns, even if one shard has more senders than the others.
+    #[test]
+    fn test_receiver_shard_fairness_ordering() {
+        let shard_layout = ShardLayout::get_simple_nightshade_layout();
+        let mut transactions = vec![];
+        for signer_id in ["alice.near", "carol.near", "dave.near"] {
+            transactions.extend(generate_transactions_with_deposit(signer_id, "aaa.near", 0, 1, 2));
+        }
+        transactions.extend(generate_transactions_with_deposit("bob.near", "zzz.near", 0, 1, 2));
+        let config = TransactionPoolConfig {
+            ordering: TransactionPoolOrdering::ReceiverShardFairness,
+            ..Default::default()
+        };
+        let mut pool = pool_with_config(config, None, transactions);
+
+        let mut res = vec![];
+        let mut pool_iter = pool.pool_iterator_with_shard_layout(&shard_layout);
+        while let Some(group) = pool_iter.next() {
+            res.extend(group.next());
+        }
+        drop(pool_iter);
+        let receivers: Vec<_> =
+            res.iter().map(|tx| tx.transaction.receiver_id().as_str()).collect();
+        assert_eq!(receivers[..4], ["aaa.near", "zzz.near", "aaa.near", "zzz.near"]);
+        assert!(receivers[4..].iter().all(|receiver| *receiver == "aaa.near"));
+        assert_eq!(res.len(), 8);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_max_transactions_per_signer() {
+        let config =
+            TransactionPoolConfig { max_transactions_per_signer: Some(5), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, None, config, "");
+        // The quota is shared by all access keys of the signer.
+        let mut transactions = generate_transactions("alice.near", "alice.near", 1, 3);
+        transactions.extend(generate_transactions("alice.near", "bob.near", 4, 10));
+        for (i, tx) in transactions.iter().cloned().enumerate() {
+            let expected = if i < 5 {
+                InsertTransactionResult::Success
+            } else {
+                InsertTransactionResult::SignerQuotaExceeded
+            };
+            assert_eq!(pool.insert_transaction(tx), expected);
+        }
+        for tx in generate_transactions("bob.near", "bob.near", 1, 5) {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+
+        // Transactions leaving the pool free up the quota.
+        pool.remove_transactions(&transactions[..1]);
+        let pulled = prepare_transactions(&mut pool, 2);
+        assert_eq!(pulled.len(), 2);
+        assert_eq!(pool.len(), 7);
+        // One transaction of alice was removed, plus the pulled ones of alice.
+        let freed = 1 + pulled
+            .iter()
+            .filter(|tx| tx.transaction.signer_id().as_str() == "alice.near")
+            .count();
+        let mut remaining = transactions[5..].iter().cloned();
+        for tx in remaining.by_ref().take(freed) {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        assert_eq!(
+            pool.insert_transaction(remaining.next().unwrap()),
+            InsertTransactionResult::SignerQuotaExceeded
+        );
+        assert_eq!(pool.len(), 7 + freed);
+    }
+
+    #[test]
+    fn test_evict_lowest_priority() {
+        let mut transactions =
+            generate_transactions_with_deposit("alice.near", "bob.near", 1, 1, 2);
+        transactions.extend(generate_transactions_with_deposit("carol.near", "bob.near", 5, 1, 2));
+        let pool_size_limit = transactions.iter().map(|tx| tx.get_size()).sum::<u64>();
+        let evicting_config =
+            TransactionPoolConfig { evict_lowest_priority: true, ..Default::default() };
+
+        // Without eviction, a full pool rejects transactions regardless of their priority.
+        let mut pool = pool_with_config(
+            TransactionPoolConfig::default(),
+            Some(pool_size_limit),
+            transactions.clone(),
+        );
+        let tx = generate_transactions_with_deposit("dave.near", "bob.near", 3, 1, 1).remove(0);
+        assert_eq!(pool.insert_transaction(tx.clone()), InsertTransactionResult::NoSpaceLeft);
+
+        // With eviction, the group of alice makes space for the transaction.
+        let mut pool = pool_with_config(evicting_config, Some(pool_size_limit), transactions);
+        assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        assert_eq!(pool.len(), 3);
+        assert!(pool.transaction_size() <= pool_size_limit);
+        let signers: HashSet<_> = prepare_transactions(&mut pool, 3)
+            .iter()
+            .map(|tx| tx.transaction.signer_id().to_string())
+            .collect();
+        assert!(!signers.contains("alice.near"));
+
+        // Groups with a deposit which isn't lower are never evicted.
+        let mut transactions =
+            generate_transactions_with_deposit("alice.near", "bob.near", 5, 1, 2);
+        transactions.extend(generate_transactions_with_deposit("carol.near", "bob.near", 5, 1, 2));
+        let mut pool = pool_with_config(
+            TransactionPoolConfig { evict_lowest_priority: true, ..Default::default() },
+            Some(pool_size_limit),
+            transactions,
+        );
+        let tx = generate_transactions_with_deposit("dave.near", "bob.near", 5, 1, 1).remove(0);
+        assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::NoSpaceLeft);
+        assert_eq!(pool.len(), 4);
     }
 }
@@ -0,0 +1,124 @@
+//! Orderings of transaction groups other than the default round robin.
+//!
+//! For these orderings the pool iterator takes all groups out of the pool at
+//! once and asks the [`Scheduler`] which group to return next.  Every time a
+//! group is returned, the caller may pull transactions from it, so the group
+//! is only rescheduled on the following call, once its next transaction is
+//! known.
+use crate::types::TransactionGroup;
+use near_chain_configs::TransactionPoolOrdering;
+use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{Balance, ShardId};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::ops::Bound;
+
+/// Priority of a transaction, used to order and evict transactions.  It's the
+/// total deposit attached to the actions of the transaction, which the signer
+/// has to pay when the transaction is included.
+pub(crate) fn transaction_priority(transaction: &SignedTransaction) -> Balance {
+    transaction
+        .transaction
+        .actions()
+        .iter()
+        .fold(0, |total, action| total.saturating_add(action.get_deposit_balance()))
+}
+
+pub(crate) enum Scheduler {
+    AttachedDeposit {
+        /// Groups keyed by the priority of their next transaction, then by
+        /// the number of times they have been returned, so that groups with
+        /// equal priority are visited round robin.
+        heap: BinaryHeap<(Balance, Reverse<u64>, Reverse<usize>)>,
+        /// Number of times each group has been returned.
+        served: Vec<u64>,
+    },
+    ReceiverShardFairness {
+        /// Used to find the shard of the receiver.  Without it all receivers
+        /// are considered to be in the same shard.
+        shard_layout: Option<ShardLayout>,
+        /// Groups keyed by the receiver shard of their next transaction.
+        queues: BTreeMap<ShardId, VecDeque<usize>>,
+        /// The shard the previous group was taken from.
+        last_shard: Option<ShardId>,
+    },
+}
+
+impl Scheduler {
+    /// Creates a scheduler for the given groups, which must not be empty.
+    /// Returns `None` for the round robin ordering which is implemented by
+    /// the pool iterator itself.
+    pub fn new(
+        ordering: TransactionPoolOrdering,
+        shard_layout: Option<&ShardLayout>,
+        groups: &VecDeque<TransactionGroup>,
+    ) -> Option<Self> {
+        let mut scheduler = match ordering {
+            TransactionPoolOrdering::RoundRobin => return None,
+            TransactionPoolOrdering::AttachedDeposit => {
+                Self::AttachedDeposit { heap: BinaryHeap::new(), served: vec![0; groups.len()] }
+            }
+            TransactionPoolOrdering::ReceiverShardFairness => Self::ReceiverShardFairness {
+                shard_layout: shard_layout.cloned(),
+                queues: BTreeMap::new(),
+                last_shard: None,
+            },
+        };
+        for index in 0..groups.len() {
+            scheduler.schedule(groups, index);
+        }
+        Some(scheduler)
+    }
+
+    /// Returns the index of the group to return next.  `prev` is the index of
+    /// the previously returned group.
+    pub fn next(
+        &mut self,
+        groups: &VecDeque<TransactionGroup>,
+        prev: Option<usize>,
+    ) -> Option<usize> {
+        if let Some(prev) = prev {
+            self.schedule(groups, prev);
+        }
+        match self {
+            Self::AttachedDeposit { heap, served } => {
+                let (_, _, Reverse(index)) = heap.pop()?;
+                served[index] += 1;
+                Some(index)
+            }
+            Self::ReceiverShardFairness { queues, last_shard, .. } => {
+                let shard_id = match last_shard {
+                    Some(last_shard) => queues
+                        .range((Bound::Excluded(*last_shard), Bound::Unbounded))
+                        .chain(queues.range(..=*last_shard))
+                        .find(|(_, queue)| !queue.is_empty()),
+                    None => queues.iter().find(|(_, queue)| !queue.is_empty()),
+                }
+                .map(|(shard_id, _)| *shard_id)?;
+                *last_shard = Some(shard_id);
+                queues.get_mut(&shard_id).and_then(|queue| queue.pop_front())
+            }
+        }
+    }
+
+    /// Queues the group according to its next transaction, if there is one.
+    fn schedule(&mut self, groups: &VecDeque<TransactionGroup>, index: usize) {
+        let Some(transaction) = groups[index].peek_next() else {
+            return;
+        };
+        match self {
+            Self::AttachedDeposit { heap, served } => heap.push((
+                transaction_priority(transaction),
+                Reverse(served[index]),
+                Reverse(index),
+            )),
+            Self::ReceiverShardFairness { shard_layout, queues, .. } => {
+                let shard_id = shard_layout.as_ref().map_or(0, |shard_layout| {
+                    account_id_to_shard_id(transaction.transaction.receiver_id(), shard_layout)
+                });
+                queues.entry(shard_id).or_default().push_back(index);
+            }
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use near_primitives::hash::CryptoHash;
 use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
 
 /// Trait acts like an iterator. It iterates over transactions groups by returning mutable
 /// references to them. Each transaction group implements a draining iterator to pull transactions.
-/// The order of the transaction groups is round robin scheduling.
+/// The order of the transaction groups is defined by the pool ordering policy,
+/// round robin scheduling by default.
 pub trait TransactionGroupIterator {
     fn next(&mut self) -> Option<&mut TransactionGroup>;
 }
@@ -16,6 +18,8 @@ pub(crate) type PoolKey = CryptoHash;
 pub struct TransactionGroup {
     /// The key of the group.
     pub(crate) key: PoolKey,
+    /// The signer of all transactions in the group.
+    pub(crate) signer_id: AccountId,
     /// Ordered transactions by nonce in non-increasing order (e.g. 3, 2, 2).
     pub(crate) transactions: Vec<SignedTransaction>,
     /// Hashes of the transactions that were pulled from the group using `.next()`.
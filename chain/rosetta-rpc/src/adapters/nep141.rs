@@ -100,6 +100,9 @@ pub(crate) async fn get_fungible_token_balance_for_account(
         account_id: near_account_id::AccountId::from_str(contract_address)?,
         method_name,
         args: args.into(),
+        include_gas_usage: false,
+        include_function_profile: false,
+        state_overrides: vec![],
     };
     let query_response = view_client_addr
         .send(near_client::Query { block_reference, request }.with_span_context())
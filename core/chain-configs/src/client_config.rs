@@ -50,6 +50,28 @@ pub struct GCConfig {
     /// How often gc should be run
     #[serde(with = "near_time::serde_duration_as_std")]
     pub gc_step_period: Duration,
+
+    /// If set, blocks are kept for at least this long after they were
+    /// produced, even if they are older than `gc_num_epochs_to_keep` epochs.
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gc_keep_duration: Option<Duration>,
+
+    /// If set, blocks older than `gc_num_epochs_to_keep` epochs are only
+    /// garbage collected once the hot database grows beyond this size.
+    ///
+    /// The size is only known for RocksDB databases.  Like
+    /// `gc_keep_duration`, it only ever makes the node keep more data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gc_hot_db_size_budget: Option<ByteSize>,
+
+    /// Data kept for longer than the blocks it belongs to.
+    ///
+    /// The data is garbage collected separately from the blocks, once it's
+    /// older than its `keep_duration` and its block has been garbage
+    /// collected.  Only data of blocks on the canonical chain is kept.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub gc_extended_retention: Vec<GCExtendedRetention>,
 }
 
 impl Default for GCConfig {
@@ -59,6 +81,9 @@ impl Default for GCConfig {
             gc_fork_clean_step: 100,
             gc_num_epochs_to_keep: DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             gc_step_period: Duration::seconds(1),
+            gc_keep_duration: None,
+            gc_hot_db_size_budget: None,
+            gc_extended_retention: vec![],
         }
     }
 }
@@ -67,6 +92,127 @@ impl GCConfig {
     pub fn gc_num_epochs_to_keep(&self) -> u64 {
         max(MIN_GC_NUM_EPOCHS_TO_KEEP, self.gc_num_epochs_to_keep)
     }
+
+    /// Returns the extended retention configured for the data, if any.
+    pub fn extended_retention(&self, data: GCRetainedData) -> Option<&GCExtendedRetention> {
+        self.gc_extended_retention.iter().find(|retention| retention.data == data)
+    }
+}
+
+/// Retention of data kept for longer than its block, see
+/// `GCConfig::gc_extended_retention`.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct GCExtendedRetention {
+    pub data: GCRetainedData,
+    /// How long to keep the data after its block was produced.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub keep_duration: Duration,
+}
+
+/// Data which can be kept for longer than the blocks it belongs to.
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GCRetainedData {
+    /// Execution outcomes of the transactions and receipts, stored in
+    /// `DBCol::TransactionResultForBlock` and `DBCol::OutcomeIds`.
+    TransactionResults,
+    /// Changes of the state made by the blocks, stored in
+    /// `DBCol::StateChanges`.
+    StateChanges,
+}
+
+impl GCRetainedData {
+    pub const ALL: [GCRetainedData; 2] =
+        [GCRetainedData::TransactionResults, GCRetainedData::StateChanges];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GCRetainedData::TransactionResults => "transaction_results",
+            GCRetainedData::StateChanges => "state_changes",
+        }
+    }
+}
+
+/// Order in which the transaction pool hands out transaction groups (the
+/// transactions of a single account and access key) to the chunk producer.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionPoolOrdering {
+    /// Groups are visited in a randomized round-robin order, one transaction
+    /// at a time.
+    #[default]
+    RoundRobin,
+    /// The group whose next transaction has the largest total deposit
+    /// attached to its actions goes first.  Groups with equal deposits are
+    /// visited round-robin.
+    AttachedDeposit,
+    /// Receiver shards are visited round-robin, so that transactions to a
+    /// busy shard don't crowd out transactions to other shards.  Within a
+    /// shard, groups are visited round-robin.
+    ReceiverShardFairness,
+}
+
+/// Configuration of the per-shard transaction pool.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TransactionPoolConfig {
+    /// Order in which transactions are taken from the pool when producing
+    /// chunks.
+    pub ordering: TransactionPoolOrdering,
+    /// If set, a signer account can't have more than this many transactions
+    /// in the pool of a shard.  Further transactions are rejected until some
+    /// of the pending ones are included or dropped.
+    pub max_transactions_per_signer: Option<usize>,
+    /// If set, when the pool is full a new transaction evicts the groups with
+    /// the lowest priority, as long as all of their transactions attach a
+    /// smaller deposit than the new one.  Otherwise the new transaction is
+    /// rejected.
+    pub evict_lowest_priority: bool,
+    /// If set, pending transactions are saved to the database periodically
+    /// and on shutdown, and restored into the pool on startup after being
+    /// validated against the current state.
+    pub persist: bool,
+    /// How often the pending transactions are saved if `persist` is set.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub persist_period: Duration,
+}
+
+impl Default for TransactionPoolConfig {
+    fn default() -> Self {
+        Self {
+            ordering: TransactionPoolOrdering::default(),
+            max_transactions_per_signer: None,
+            evict_lowest_priority: false,
+            persist: false,
+            persist_period: Duration::seconds(60),
+        }
+    }
+}
+
+/// Configuration of how chunks of the shards tracked by the node are scheduled
+/// for application when processing a block.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ShardSchedulerConfig {
+    /// Shards whose chunks are applied in a thread pool of their own rather
+    /// than in the pool shared by all other shards.  Useful to keep a busy
+    /// shard from delaying the others, or the other way round.
+    pub dedicated_shards: Vec<ShardId>,
+    /// Number of threads of each dedicated thread pool.
+    pub dedicated_pool_threads: usize,
+    /// If set, chunks of the shards the node validates or produces chunks for
+    /// are started before chunks of the shards it only tracks.
+    pub prioritize_validated_shards: bool,
+}
+
+impl Default for ShardSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            dedicated_shards: vec![],
+            dedicated_pool_threads: 2,
+            prioritize_validated_shards: true,
+        }
+    }
 }
 
 fn default_num_concurrent_requests() -> u32 {
@@ -453,6 +599,10 @@ pub struct ClientConfig {
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
     /// will be unbounded.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Ordering, quotas and eviction policy of the transaction pool.
+    pub transaction_pool: TransactionPoolConfig,
+    /// Thread pools and priorities used to apply the chunks of a block.
+    pub shard_scheduler: ShardSchedulerConfig,
     // Allows more detailed logging, for example a list of orphaned blocks.
     pub enable_multiline_logging: bool,
     // Configuration for resharding.
@@ -485,6 +635,9 @@ pub struct ClientConfig {
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// Save transactions and receipts indexed by account to the database in
+    /// DBCol::AccountActivity.  Set from `StoreConfig::save_account_activity`.
+    pub save_account_activity: bool,
 }
 
 impl ClientConfig {
@@ -559,6 +712,8 @@ impl ClientConfig {
             state_sync_enabled,
             state_sync: StateSyncConfig::default(),
             transaction_pool_size_limit: None,
+            transaction_pool: TransactionPoolConfig::default(),
+            shard_scheduler: ShardSchedulerConfig::default(),
             enable_multiline_logging: false,
             resharding_config: MutableConfigValue::new(
                 ReshardingConfig::default(),
@@ -573,6 +728,7 @@ impl ClientConfig {
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
             save_latest_witnesses: false,
+            save_account_activity: false,
         }
     }
 }
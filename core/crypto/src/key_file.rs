@@ -1,9 +1,48 @@
 use crate::{PublicKey, SecretKey};
 use near_account_id::AccountId;
+use once_cell::sync::OnceCell;
 use std::fs::File;
 use std::io;
 use std::io::{Read, Write};
 use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Environment variable with the passphrase of encrypted key files, used
+/// unless the passphrase has been set with [`set_key_file_passphrase`].
+pub const KEY_FILE_PASSPHRASE_ENV_VAR: &str = "NEAR_KEY_FILE_PASSPHRASE";
+
+static KEY_FILE_PASSPHRASE: OnceCell<Zeroizing<String>> = OnceCell::new();
+
+/// Sets the passphrase used to decrypt key files for the rest of the process.
+/// Fails if the passphrase has already been set.
+pub fn set_key_file_passphrase(passphrase: Zeroizing<String>) -> io::Result<()> {
+    KEY_FILE_PASSPHRASE.set(passphrase).map_err(|_| {
+        io::Error::new(io::ErrorKind::AlreadyExists, "key file passphrase is already set")
+    })
+}
+
+/// Returns the passphrase of encrypted key files configured for the process.
+///
+/// If the passphrase is read from the environment, the variable is removed
+/// afterwards so that it isn't inherited by child processes.
+pub fn key_file_passphrase() -> io::Result<Zeroizing<String>> {
+    KEY_FILE_PASSPHRASE
+        .get_or_try_init(|| {
+            let passphrase =
+                std::env::var(KEY_FILE_PASSPHRASE_ENV_VAR).map(Zeroizing::new).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "key file is encrypted, but no passphrase is given; \
+                             set it in the {KEY_FILE_PASSPHRASE_ENV_VAR} environment variable"
+                        ),
+                    )
+                })?;
+            std::env::remove_var(KEY_FILE_PASSPHRASE_ENV_VAR);
+            Ok(passphrase)
+        })
+        .cloned()
+}
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct KeyFile {
@@ -16,6 +55,18 @@ pub struct KeyFile {
     pub secret_key: SecretKey,
 }
 
+/// Key file as stored on disk, with the secret key either in plain text or
+/// encrypted with a passphrase.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StoredKeyFile {
+    account_id: AccountId,
+    public_key: PublicKey,
+    #[serde(alias = "private_key", default, skip_serializing_if = "Option::is_none")]
+    secret_key: Option<SecretKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_secret_key: Option<EncryptedSecretKey>,
+}
+
 impl KeyFile {
     pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
         let data = serde_json::to_string_pretty(self)?;
@@ -23,6 +74,22 @@ impl KeyFile {
         file.write_all(data.as_bytes())
     }
 
+    /// Writes the key file with the secret key encrypted with the passphrase.
+    #[cfg(feature = "rand")]
+    pub fn write_encrypted_to_file(&self, path: &Path, passphrase: &str) -> io::Result<()> {
+        let encrypted_secret_key =
+            EncryptedSecretKey::encrypt(&self.secret_key, &self.public_key, passphrase)?;
+        let stored = StoredKeyFile {
+            account_id: self.account_id.clone(),
+            public_key: self.public_key.clone(),
+            secret_key: None,
+            encrypted_secret_key: Some(encrypted_secret_key),
+        };
+        let data = serde_json::to_string_pretty(&stored)?;
+        let mut file = Self::create(path)?;
+        file.write_all(data.as_bytes())
+    }
+
     #[cfg(unix)]
     fn create(path: &Path) -> io::Result<File> {
         use std::os::unix::fs::OpenOptionsExt;
@@ -34,15 +101,263 @@ impl KeyFile {
         std::fs::File::create(path)
     }
 
+    /// Reads the key file, decrypting the secret key with the passphrase
+    /// returned by [`key_file_passphrase`] if the key is encrypted.
     pub fn from_file(path: &Path) -> io::Result<Self> {
         let mut file = File::open(path)?;
-        let mut json_config_str = String::new();
+        let mut json_config_str = Zeroizing::new(String::new());
         file.read_to_string(&mut json_config_str)?;
-        let json_str_without_comments: String =
-            near_config_utils::strip_comments_from_json_str(&json_config_str)?;
+        let json_str_without_comments =
+            Zeroizing::new(near_config_utils::strip_comments_from_json_str(&json_config_str)?);
+
+        let stored: StoredKeyFile = serde_json::from_str(&json_str_without_comments)?;
+        let secret_key = match (stored.secret_key, stored.encrypted_secret_key) {
+            (Some(secret_key), None) => secret_key,
+            (None, Some(encrypted)) => {
+                encrypted.decrypt(&stored.public_key, &key_file_passphrase()?)?
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "key file must contain exactly one of secret_key and encrypted_secret_key",
+                ))
+            }
+        };
+        Ok(Self { account_id: stored.account_id, public_key: stored.public_key, secret_key })
+    }
+}
+
+/// Parameters of the Argon2id key derivation function.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory size in KiB, 64 MiB by default.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self { m_cost: 65536, t_cost: 3, p_cost: 1 }
+    }
+}
+
+impl KdfParams {
+    /// Upper bound of the memory size, 4 GiB.  The parameters are read from
+    /// the key file, so they are bounded to keep a corrupted or malicious
+    /// file from exhausting memory or CPU.
+    const MAX_M_COST: u32 = 4 * 1024 * 1024;
+    /// Upper bound of the number of iterations.
+    const MAX_T_COST: u32 = 64;
+    /// Upper bound of the degree of parallelism.
+    const MAX_P_COST: u32 = 16;
+
+    fn validate(&self) -> io::Result<()> {
+        if self.m_cost > Self::MAX_M_COST
+            || self.t_cost > Self::MAX_T_COST
+            || self.p_cost > Self::MAX_P_COST
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("KDF parameters exceed the allowed maximum: {self:?}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Secret key encrypted with a passphrase.
+///
+/// Version 1 derives a 256-bit key from the passphrase with Argon2id and
+/// encrypts the secret key with XChaCha20-Poly1305, authenticating the public
+/// key as associated data.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedSecretKey {
+    pub version: u32,
+    pub kdf_params: KdfParams,
+    #[serde(with = "hex")]
+    pub salt: Vec<u8>,
+    #[serde(with = "hex")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedSecretKey {
+    const VERSION: u32 = 1;
+    const SALT_LEN: usize = 16;
+    const NONCE_LEN: usize = 24;
+
+    #[cfg(feature = "rand")]
+    pub fn encrypt(
+        secret_key: &SecretKey,
+        public_key: &PublicKey,
+        passphrase: &str,
+    ) -> io::Result<Self> {
+        Self::encrypt_with_params(secret_key, public_key, passphrase, KdfParams::default())
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn encrypt_with_params(
+        secret_key: &SecretKey,
+        public_key: &PublicKey,
+        passphrase: &str,
+        kdf_params: KdfParams,
+    ) -> io::Result<Self> {
+        use chacha20poly1305::aead::{Aead, Payload};
+        use secp256k1::rand::rngs::OsRng;
+        use secp256k1::rand::RngCore;
+
+        let mut salt = vec![0; Self::SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = vec![0; Self::NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let cipher = Self::cipher(passphrase, &salt, kdf_params)?;
+        let public_key = public_key.to_string();
+        let secret_key = Zeroizing::new(secret_key.to_string());
+        let payload = Payload { msg: secret_key.as_bytes(), aad: public_key.as_bytes() };
+        let ciphertext = cipher
+            .encrypt(chacha20poly1305::XNonce::from_slice(&nonce), payload)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to encrypt secret key"))?;
+        Ok(Self { version: Self::VERSION, kdf_params, salt, nonce, ciphertext })
+    }
+
+    pub fn decrypt(&self, public_key: &PublicKey, passphrase: &str) -> io::Result<SecretKey> {
+        use chacha20poly1305::aead::{Aead, Payload};
+
+        if self.version != Self::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported version of encrypted secret key: {}", self.version),
+            ));
+        }
+        if self.nonce.len() != Self::NONCE_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid nonce length"));
+        }
+        let cipher = Self::cipher(passphrase, &self.salt, self.kdf_params)?;
+        let public_key_str = public_key.to_string();
+        let payload = Payload { msg: &self.ciphertext, aad: public_key_str.as_bytes() };
+        let plaintext = Zeroizing::new(
+            cipher.decrypt(chacha20poly1305::XNonce::from_slice(&self.nonce), payload).map_err(
+                |_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "failed to decrypt secret key, the passphrase is probably wrong",
+                    )
+                },
+            )?,
+        );
+        let secret_key: SecretKey = std::str::from_utf8(&plaintext)
+            .ok()
+            .and_then(|secret_key| secret_key.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid secret key"))?;
+        if &secret_key.public_key() != public_key {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "secret key doesn't match the public key",
+            ));
+        }
+        Ok(secret_key)
+    }
+
+    fn cipher(
+        passphrase: &str,
+        salt: &[u8],
+        kdf_params: KdfParams,
+    ) -> io::Result<chacha20poly1305::XChaCha20Poly1305> {
+        use chacha20poly1305::KeyInit;
+
+        kdf_params.validate()?;
+        let invalid_params = |err: argon2::Error| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid KDF parameters: {err}"))
+        };
+        let params =
+            argon2::Params::new(kdf_params.m_cost, kdf_params.t_cost, kdf_params.p_cost, Some(32))
+                .map_err(invalid_params)?;
+        let argon2 =
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut key = Zeroizing::new([0; 32]);
+        argon2
+            .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+            .map_err(invalid_params)?;
+        Ok(chacha20poly1305::XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&*key)))
+    }
+}
 
-        Ok(serde_json::from_str(&json_str_without_comments)?)
+/// Encrypts, decrypts or re-encrypts the secret key of a key file.
+///
+/// The updated file is written next to the original, synced and then renamed
+/// over it, so the key is never lost if the process is interrupted.  The
+/// secret key is decrypted with `old_passphrase` if it is encrypted and
+/// encrypted with `new_passphrase` if one is given.  All other fields of the
+/// file are kept as they are, so this also works for node key files which
+/// don't have a valid account id.
+#[cfg(feature = "rand")]
+pub fn update_key_file_encryption(
+    path: &Path,
+    old_passphrase: Option<&str>,
+    new_passphrase: Option<&str>,
+) -> io::Result<()> {
+    let invalid_data = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let contents = Zeroizing::new(std::fs::read_to_string(path)?);
+    let contents = Zeroizing::new(near_config_utils::strip_comments_from_json_str(&contents)?);
+    let mut object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&contents)?;
+    let public_key: PublicKey = serde_json::from_value(
+        object.get("public_key").cloned().ok_or_else(|| invalid_data("missing public_key"))?,
+    )?;
+    let plain = object.remove("secret_key").or_else(|| object.remove("private_key"));
+    let encrypted = object.remove("encrypted_secret_key");
+    let secret_key = match (plain, encrypted) {
+        (Some(secret_key), None) => serde_json::from_value(secret_key)?,
+        (None, Some(encrypted)) => {
+            let encrypted: EncryptedSecretKey = serde_json::from_value(encrypted)?;
+            let old_passphrase = old_passphrase
+                .ok_or_else(|| invalid_data("key file is encrypted, but no passphrase is given"))?;
+            encrypted.decrypt(&public_key, old_passphrase)?
+        }
+        _ => return Err(invalid_data("key file must contain exactly one secret key")),
+    };
+    match new_passphrase {
+        Some(passphrase) => {
+            let encrypted = EncryptedSecretKey::encrypt(&secret_key, &public_key, passphrase)?;
+            object.insert("encrypted_secret_key".to_string(), serde_json::to_value(encrypted)?);
+        }
+        None => {
+            object.insert("secret_key".to_string(), serde_json::to_value(secret_key)?);
+        }
     }
+    let data = Zeroizing::new(serde_json::to_string_pretty(&object)?);
+    replace_file(path, data.as_bytes())
+}
+
+/// Atomically replaces the file at `path` with `data` by writing it to a
+/// temporary file in the same directory and renaming it over the original.
+#[cfg(feature = "rand")]
+fn replace_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file", path.display()))
+    })?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    let result = (|| {
+        let mut file = KeyFile::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result?;
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -111,4 +426,59 @@ mod test {
         let inner_msg = err.into_inner().unwrap().to_string();
         assert!(inner_msg.contains("duplicate field"));
     }
+
+    #[test]
+    fn test_encrypted_secret_key() {
+        let secret_key: SecretKey = SECRET_KEY.parse().unwrap();
+        let public_key = secret_key.public_key();
+        let params = KdfParams { m_cost: 64, t_cost: 1, p_cost: 1 };
+        let encrypted =
+            EncryptedSecretKey::encrypt_with_params(&secret_key, &public_key, "pass", params)
+                .unwrap();
+        assert_eq!(encrypted.decrypt(&public_key, "pass").unwrap(), secret_key);
+        let err = encrypted.decrypt(&public_key, "wrong").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The public key is authenticated, so it can't be swapped.
+        let other_public_key = SecretKey::from_seed(crate::KeyType::ED25519, "other").public_key();
+        assert!(encrypted.decrypt(&other_public_key, "pass").is_err());
+
+        let unsupported = EncryptedSecretKey { version: 2, ..encrypted.clone() };
+        assert!(unsupported.decrypt(&public_key, "pass").is_err());
+
+        // Parameters read from the file are bounded.
+        let kdf_params = KdfParams { m_cost: u32::MAX, ..params };
+        let expensive = EncryptedSecretKey { kdf_params, ..encrypted };
+        let err = expensive.decrypt(&public_key, "pass").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_update_key_file_encryption() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("key-file");
+        // Node key files may have an empty account id.
+        let contents = KEY_FILE_CONTENTS.replace(r#""example""#, r#""""#);
+        std::fs::write(&path, &contents).unwrap();
+
+        update_key_file_encryption(&path, None, Some("old")).unwrap();
+        let encrypted = std::fs::read_to_string(&path).unwrap();
+        assert!(!encrypted.contains(SECRET_KEY));
+        assert!(encrypted.contains("encrypted_secret_key"));
+        assert!(update_key_file_encryption(&path, Some("wrong"), Some("new")).is_err());
+        update_key_file_encryption(&path, Some("old"), Some("new")).unwrap();
+        assert!(update_key_file_encryption(&path, Some("old"), None).is_err());
+        update_key_file_encryption(&path, Some("new"), None).unwrap();
+        assert_eq!(contents, std::fs::read_to_string(&path).unwrap());
+
+        // The file is replaced by a new one, which is only readable by the owner.
+        let entries: Vec<_> = std::fs::read_dir(tmp.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let got = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, got & 0o777);
+        }
+    }
 }
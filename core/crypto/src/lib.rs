@@ -1,7 +1,12 @@
 #![deny(clippy::arithmetic_side_effects)]
 
 pub use errors::{ParseKeyError, ParseKeyTypeError, ParseSignatureError};
-pub use key_file::KeyFile;
+#[cfg(feature = "rand")]
+pub use key_file::update_key_file_encryption;
+pub use key_file::{
+    key_file_passphrase, set_key_file_passphrase, EncryptedSecretKey, KdfParams, KeyFile,
+    KEY_FILE_PASSPHRASE_ENV_VAR,
+};
 pub use signature::{
     ED25519PublicKey, ED25519SecretKey, KeyType, PublicKey, Secp256K1PublicKey, Secp256K1Signature,
     SecretKey, Signature,
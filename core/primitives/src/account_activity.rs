@@ -0,0 +1,185 @@
+//! Index of transactions and receipts by the accounts they touch.
+//!
+//! When enabled, every final block adds a record to `DBCol::AccountActivity`
+//! for each transaction and receipt executed in it, once for every account
+//! taking part in it.  Records are keyed by
+//! `account_id || ',' || block_height || index` with the height and the index
+//! encoded as big endian, so that iterating over the prefix of an account
+//! yields its activity in chronological order.
+//!
+//! Every record also has a row in `DBCol::AccountActivityByHeight` with the
+//! block height moved to the front of the key, so that all records of a block
+//! can be found, and garbage collected, with a single range scan.
+use crate::hash::CryptoHash;
+use crate::types::{AccountId, BlockHeight, ShardId};
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::collections::HashMap;
+
+/// Separates the account id from the rest of the key.  Commas can't appear in
+/// account ids, so the prefix of an account never matches any other account.
+pub const ACCOUNT_ACTIVITY_SEPARATOR: u8 = b',';
+
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountActivityKind {
+    Transaction,
+    Receipt,
+}
+
+/// A transaction or receipt the account took part in, as the signer,
+/// predecessor or receiver.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AccountActivity {
+    pub kind: AccountActivityKind,
+    /// Transaction hash or receipt id.
+    pub id: CryptoHash,
+    /// Block which included the chunk with the transaction or receipt.
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+}
+
+/// Returns the prefix shared by all activity records of the account.
+pub fn get_account_activity_prefix(account_id: &AccountId) -> Vec<u8> {
+    let mut res = Vec::with_capacity(account_id.as_str().len() + 1);
+    res.extend_from_slice(account_id.as_str().as_bytes());
+    res.push(ACCOUNT_ACTIVITY_SEPARATOR);
+    res
+}
+
+pub fn get_account_activity_key(
+    account_id: &AccountId,
+    block_height: BlockHeight,
+    index: u32,
+) -> Vec<u8> {
+    let mut res = get_account_activity_prefix(account_id);
+    res.extend_from_slice(&block_height.to_be_bytes());
+    res.extend_from_slice(&index.to_be_bytes());
+    res
+}
+
+/// Returns the block height and the index stored in an activity record key.
+pub fn get_account_activity_key_rev(key: &[u8]) -> std::io::Result<(BlockHeight, u32)> {
+    if key.len() < 13 || key[key.len() - 13] != ACCOUNT_ACTIVITY_SEPARATOR {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid key"));
+    }
+    let (height, index) = key[key.len() - 12..].split_at(8);
+    Ok((
+        BlockHeight::from_be_bytes(height.try_into().unwrap()),
+        u32::from_be_bytes(index.try_into().unwrap()),
+    ))
+}
+
+/// Returns the prefix shared by all `DBCol::AccountActivityByHeight` rows of
+/// the records of a block.
+pub fn get_account_activity_height_prefix(block_height: BlockHeight) -> Vec<u8> {
+    block_height.to_be_bytes().to_vec()
+}
+
+/// Returns the key of the `DBCol::AccountActivityByHeight` row of a record,
+/// `block_height || account_id || ',' || index`.
+pub fn get_account_activity_height_key(
+    account_id: &AccountId,
+    block_height: BlockHeight,
+    index: u32,
+) -> Vec<u8> {
+    let mut res = get_account_activity_height_prefix(block_height);
+    res.extend_from_slice(&get_account_activity_prefix(account_id));
+    res.extend_from_slice(&index.to_be_bytes());
+    res
+}
+
+/// Converts the key of a `DBCol::AccountActivityByHeight` row into the key of
+/// the record in `DBCol::AccountActivity`.
+pub fn account_activity_key_from_height_key(key: &[u8]) -> std::io::Result<Vec<u8>> {
+    if key.len() < 13 || key[key.len() - 5] != ACCOUNT_ACTIVITY_SEPARATOR {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid key"));
+    }
+    let (height, rest) = key.split_at(8);
+    let (prefix, index) = rest.split_at(rest.len() - 4);
+    Ok([prefix, height, index].concat())
+}
+
+/// An activity record together with the position it is stored at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountActivityRecord {
+    pub account_id: AccountId,
+    pub block_height: BlockHeight,
+    pub index: u32,
+    pub activity: AccountActivity,
+}
+
+impl AccountActivityRecord {
+    /// Key of the record in `DBCol::AccountActivity`.
+    pub fn key(&self) -> Vec<u8> {
+        get_account_activity_key(&self.account_id, self.block_height, self.index)
+    }
+
+    /// Key of the record in `DBCol::AccountActivityByHeight`.
+    pub fn height_key(&self) -> Vec<u8> {
+        get_account_activity_height_key(&self.account_id, self.block_height, self.index)
+    }
+}
+
+/// Computes the activity records of a block.
+///
+/// `executed` lists the transactions and receipts executed in the block, in
+/// execution order, each with the accounts taking part in it.  Every account
+/// gets a single record per transaction or receipt.
+pub fn get_block_account_activity(
+    block_height: BlockHeight,
+    executed: impl IntoIterator<Item = (AccountActivity, Vec<AccountId>)>,
+) -> Vec<AccountActivityRecord> {
+    let mut next_index: HashMap<AccountId, u32> = HashMap::new();
+    let mut res = vec![];
+    for (activity, mut accounts) in executed {
+        accounts.sort();
+        accounts.dedup();
+        for account_id in accounts {
+            let index = next_index.entry(account_id.clone()).or_default();
+            res.push(AccountActivityRecord {
+                account_id,
+                block_height,
+                index: *index,
+                activity: activity.clone(),
+            });
+            *index += 1;
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_activity_key() {
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let key = get_account_activity_key(&alice, 42, 7);
+        assert!(key.starts_with(&get_account_activity_prefix(&alice)));
+        assert_eq!(get_account_activity_key_rev(&key).unwrap(), (42, 7));
+        assert!(get_account_activity_key_rev(b"alice.near").is_err());
+
+        // Records of an account are sorted by height, then by index.
+        assert!(key < get_account_activity_key(&alice, 42, 8));
+        assert!(key < get_account_activity_key(&alice, 256, 0));
+        // Records of another account sharing a prefix don't interleave.
+        let alice_prefix: AccountId = "alice".parse().unwrap();
+        assert!(!key.starts_with(&get_account_activity_prefix(&alice_prefix)));
+
+        let height_key = get_account_activity_height_key(&alice, 42, 7);
+        assert!(height_key.starts_with(&get_account_activity_height_prefix(42)));
+        assert_eq!(account_activity_key_from_height_key(&height_key).unwrap(), key);
+        assert!(account_activity_key_from_height_key(&key).is_err());
+    }
+}
@@ -298,7 +298,7 @@ impl Block {
         block_merkle_root: CryptoHash,
         clock: near_time::Clock,
         sandbox_delta_time: Option<near_time::Duration>,
-    ) -> Self {
+    ) -> Result<Self, crate::validator_signer::ValidatorSignerError> {
         use crate::hash::hash;
         // Collect aggregate of validators and gas usage/limits from chunks.
         let mut prev_validator_proposals = vec![];
@@ -330,12 +330,13 @@ impl Block {
         let new_total_supply = prev.total_supply() + minted_amount.unwrap_or(0) - balance_burnt;
         let now = clock.now_utc().unix_timestamp_nanos() as u64;
         #[cfg(feature = "sandbox")]
-        let now = now + sandbox_delta_time.unwrap().whole_nanoseconds() as u64;
+        let now = u64::try_from(i128::from(now) + sandbox_delta_time.unwrap().whole_nanoseconds())
+            .unwrap_or_default();
         #[cfg(not(feature = "sandbox"))]
         debug_assert!(sandbox_delta_time.is_none());
         let time = if now <= prev.raw_timestamp() { prev.raw_timestamp() + 1 } else { now };
 
-        let (vrf_value, vrf_proof) = signer.compute_vrf_with_proof(prev.random_value().as_ref());
+        let (vrf_value, vrf_proof) = signer.compute_vrf_with_proof(prev.random_value().as_ref())?;
         let random_value = hash(vrf_value.0.as_ref());
 
         let last_ds_final_block =
@@ -398,14 +399,14 @@ impl Block {
             block_merkle_root,
             prev.height(),
             clock,
-        );
+        )?;
 
-        Self::block_from_protocol_version(
+        Ok(Self::block_from_protocol_version(
             this_epoch_protocol_version,
             next_epoch_protocol_version,
             header,
             body,
-        )
+        ))
     }
 
     pub fn verify_total_supply(
@@ -8,6 +8,7 @@ pub use near_primitives_core::hash;
 pub use near_primitives_core::num_rational;
 pub use near_primitives_core::serialize;
 
+pub mod account_activity;
 pub mod action;
 pub mod block;
 pub mod block_body;
@@ -25,6 +26,8 @@ pub mod rand;
 pub mod receipt;
 #[cfg(feature = "solomon")]
 pub mod reed_solomon;
+#[cfg(unix)]
+pub mod remote_signer;
 pub mod runtime;
 pub mod sandbox;
 pub mod shard_layout;
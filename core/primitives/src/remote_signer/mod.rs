@@ -0,0 +1,404 @@
+//! Signing with validator keys held by an external signing daemon.
+//!
+//! [`RemoteValidatorSigner`] doesn't hold any secret key.  Instead, every
+//! message is sent to a signing daemon listening on a Unix socket, which owns
+//! the validator key and refuses to sign conflicting blocks, chunks and
+//! approvals.  The `near-remote-signer` tool is a reference implementation of
+//! such a daemon.
+//!
+//! Requests and responses are borsh-serialized and prefixed by their length
+//! encoded as a little endian `u32`.  A connection can be used for any number
+//! of requests, which are answered one at a time in order.  The node opens
+//! more connections when it signs several messages at once.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::{PublicKey, Signature};
+
+use crate::block::ApprovalInner;
+use crate::challenge::ChallengeBody;
+use crate::hash::CryptoHash;
+use crate::network::PeerId;
+use crate::sharding::{ChunkHash, ShardChunkHeaderInner, ShardChunkHeaderV3};
+use crate::stateless_validation::{
+    ChunkEndorsementInner, EncodedChunkStateWitness, PartialEncodedStateWitnessInner,
+};
+use crate::telemetry::TelemetryInfo;
+use crate::types::{AccountId, BlockHeight, EpochId, ShardId};
+
+/// Maximum size of a single request or response.  Large enough for a state
+/// witness, which is the largest message a validator signs.
+pub const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+/// Maximum number of idle connections kept open to the daemon.
+const MAX_IDLE_CONNECTIONS: usize = 8;
+
+/// Request sent by the node to the signing daemon.
+///
+/// Every signed message has its own request type, so that the daemon knows
+/// what it signs and can check blocks, chunks and approvals for double signing.
+#[derive(BorshSerialize, BorshDeserialize, Clone)]
+pub enum RemoteSignerRequest {
+    /// Asks for the account id and public key of the validator.
+    GetValidatorKey,
+    /// The daemon computes the block hash from the parts and signs it.
+    SignBlockHeader {
+        prev_hash: CryptoHash,
+        inner_lite: Vec<u8>,
+        inner_rest: Vec<u8>,
+    },
+    /// The daemon computes the chunk hash from the inner part and signs it.
+    SignChunkHeader(ShardChunkHeaderInner),
+    SignApproval {
+        inner: ApprovalInner,
+        target_height: BlockHeight,
+    },
+    /// The height and the shard of the endorsed chunk are only used by the
+    /// daemon to check for double signing, they aren't part of the signature.
+    SignChunkEndorsement {
+        inner: ChunkEndorsementInner,
+        height_created: BlockHeight,
+        shard_id: ShardId,
+    },
+    SignChunkStateWitness(EncodedChunkStateWitness),
+    SignPartialEncodedStateWitness(PartialEncodedStateWitnessInner),
+    SignChallenge(ChallengeBody),
+    SignAccountAnnounce {
+        account_id: AccountId,
+        peer_id: PeerId,
+        epoch_id: EpochId,
+    },
+    SignAccountKeyPayload(Vec<u8>),
+    /// JSON-serialized telemetry info.
+    SignTelemetry(String),
+    ComputeVrf(Vec<u8>),
+}
+
+impl RemoteSignerRequest {
+    /// Name of the request used in logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GetValidatorKey => "GetValidatorKey",
+            Self::SignBlockHeader { .. } => "SignBlockHeader",
+            Self::SignChunkHeader(_) => "SignChunkHeader",
+            Self::SignApproval { .. } => "SignApproval",
+            Self::SignChunkEndorsement { .. } => "SignChunkEndorsement",
+            Self::SignChunkStateWitness(_) => "SignChunkStateWitness",
+            Self::SignPartialEncodedStateWitness(_) => "SignPartialEncodedStateWitness",
+            Self::SignChallenge(_) => "SignChallenge",
+            Self::SignAccountAnnounce { .. } => "SignAccountAnnounce",
+            Self::SignAccountKeyPayload(_) => "SignAccountKeyPayload",
+            Self::SignTelemetry(_) => "SignTelemetry",
+            Self::ComputeVrf(_) => "ComputeVrf",
+        }
+    }
+}
+
+/// Response of the signing daemon.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum RemoteSignerResponse {
+    ValidatorKey {
+        account_id: AccountId,
+        public_key: PublicKey,
+    },
+    Signature(Signature),
+    Vrf {
+        value: near_crypto::vrf::Value,
+        proof: near_crypto::vrf::Proof,
+    },
+    /// The daemon refused to handle the request, e.g. because signing it
+    /// would be a double sign.
+    Refused(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RemoteSignerError {
+    #[error("failed to communicate with the remote signer: {0}")]
+    Io(#[from] io::Error),
+    #[error("remote signer refused to sign: {0}")]
+    Refused(String),
+    #[error("unexpected response of the remote signer")]
+    UnexpectedResponse,
+}
+
+pub fn write_message(stream: &mut impl Write, message: &impl BorshSerialize) -> io::Result<()> {
+    let bytes = borsh::to_vec(message)?;
+    if bytes.len() > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "message is too large"));
+    }
+    stream.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+pub fn read_message<T: BorshDeserialize>(stream: &mut impl Read) -> io::Result<T> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message is too large"));
+    }
+    let mut bytes = vec![0; len];
+    stream.read_exact(&mut bytes)?;
+    T::try_from_slice(&bytes)
+}
+
+/// Validator signer which delegates signing to a daemon listening on a Unix
+/// socket.
+///
+/// Signing fails when the daemon is unreachable or refuses to sign, in which
+/// case the node doesn't send the message at all.
+#[derive(Clone)]
+pub struct RemoteValidatorSigner {
+    account_id: AccountId,
+    public_key: PublicKey,
+    socket_path: PathBuf,
+    timeout: Duration,
+    /// Connections which aren't used by any request at the moment.  A request
+    /// takes one of them or opens a new one, so that concurrent requests don't
+    /// wait for each other.
+    idle_connections: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+impl RemoteValidatorSigner {
+    /// Connects to the daemon and fetches the key of the validator.
+    pub fn connect(socket_path: &Path, timeout: Duration) -> Result<Self, RemoteSignerError> {
+        let idle_connections = Arc::new(Mutex::new(Vec::new()));
+        let response = send_request(
+            &idle_connections,
+            socket_path,
+            timeout,
+            &RemoteSignerRequest::GetValidatorKey,
+        )?;
+        match response {
+            RemoteSignerResponse::ValidatorKey { account_id, public_key } => Ok(Self {
+                account_id,
+                public_key,
+                socket_path: socket_path.to_path_buf(),
+                timeout,
+                idle_connections,
+            }),
+            RemoteSignerResponse::Refused(reason) => Err(RemoteSignerError::Refused(reason)),
+            _ => Err(RemoteSignerError::UnexpectedResponse),
+        }
+    }
+
+    pub fn validator_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    fn request(
+        &self,
+        request: &RemoteSignerRequest,
+    ) -> Result<RemoteSignerResponse, RemoteSignerError> {
+        match send_request(&self.idle_connections, &self.socket_path, self.timeout, request)? {
+            RemoteSignerResponse::Refused(reason) => Err(RemoteSignerError::Refused(reason)),
+            response => Ok(response),
+        }
+    }
+
+    fn sign(&self, request: RemoteSignerRequest) -> Result<Signature, RemoteSignerError> {
+        let result = self.request(&request).and_then(|response| match response {
+            RemoteSignerResponse::Signature(signature) => Ok(signature),
+            _ => Err(RemoteSignerError::UnexpectedResponse),
+        });
+        if let Err(err) = &result {
+            tracing::error!(target: "remote_signer", request = request.name(), %err, "Failed to sign");
+        }
+        result
+    }
+
+    pub(crate) fn sign_telemetry(
+        &self,
+        info: &TelemetryInfo,
+    ) -> Result<serde_json::Value, RemoteSignerError> {
+        let mut value = serde_json::to_value(info).expect("Telemetry must serialize to JSON");
+        let content = serde_json::to_string(&value).expect("Telemetry must serialize to JSON");
+        value["signature"] =
+            self.sign(RemoteSignerRequest::SignTelemetry(content))?.to_string().into();
+        Ok(value)
+    }
+
+    pub(crate) fn sign_block_header_parts(
+        &self,
+        prev_hash: CryptoHash,
+        inner_lite: &[u8],
+        inner_rest: &[u8],
+    ) -> Result<(CryptoHash, Signature), RemoteSignerError> {
+        let hash = crate::block::BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
+        let signature = self.sign(RemoteSignerRequest::SignBlockHeader {
+            prev_hash,
+            inner_lite: inner_lite.to_vec(),
+            inner_rest: inner_rest.to_vec(),
+        })?;
+        Ok((hash, signature))
+    }
+
+    pub(crate) fn sign_chunk_header_inner(
+        &self,
+        inner: &ShardChunkHeaderInner,
+    ) -> Result<(ChunkHash, Signature), RemoteSignerError> {
+        let hash = ShardChunkHeaderV3::compute_hash(inner);
+        let signature = self.sign(RemoteSignerRequest::SignChunkHeader(inner.clone()))?;
+        Ok((hash, signature))
+    }
+
+    pub(crate) fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, RemoteSignerError> {
+        self.sign(RemoteSignerRequest::SignApproval { inner: inner.clone(), target_height })
+    }
+
+    pub(crate) fn sign_chunk_endorsement(
+        &self,
+        inner: &ChunkEndorsementInner,
+        height_created: BlockHeight,
+        shard_id: ShardId,
+    ) -> Result<Signature, RemoteSignerError> {
+        self.sign(RemoteSignerRequest::SignChunkEndorsement {
+            inner: inner.clone(),
+            height_created,
+            shard_id,
+        })
+    }
+
+    pub(crate) fn sign_chunk_state_witness(
+        &self,
+        witness_bytes: &EncodedChunkStateWitness,
+    ) -> Result<Signature, RemoteSignerError> {
+        self.sign(RemoteSignerRequest::SignChunkStateWitness(witness_bytes.clone()))
+    }
+
+    pub(crate) fn sign_partial_encoded_state_witness(
+        &self,
+        part: &PartialEncodedStateWitnessInner,
+    ) -> Result<Signature, RemoteSignerError> {
+        self.sign(RemoteSignerRequest::SignPartialEncodedStateWitness(part.clone()))
+    }
+
+    pub(crate) fn sign_challenge(
+        &self,
+        challenge_body: &ChallengeBody,
+    ) -> Result<(CryptoHash, Signature), RemoteSignerError> {
+        let hash = CryptoHash::hash_borsh(challenge_body);
+        Ok((hash, self.sign(RemoteSignerRequest::SignChallenge(challenge_body.clone()))?))
+    }
+
+    pub(crate) fn sign_account_announce(
+        &self,
+        account_id: &AccountId,
+        peer_id: &PeerId,
+        epoch_id: &EpochId,
+    ) -> Result<Signature, RemoteSignerError> {
+        self.sign(RemoteSignerRequest::SignAccountAnnounce {
+            account_id: account_id.clone(),
+            peer_id: peer_id.clone(),
+            epoch_id: *epoch_id,
+        })
+    }
+
+    pub(crate) fn sign_account_key_payload(
+        &self,
+        proto_bytes: &[u8],
+    ) -> Result<Signature, RemoteSignerError> {
+        self.sign(RemoteSignerRequest::SignAccountKeyPayload(proto_bytes.to_vec()))
+    }
+
+    pub(crate) fn compute_vrf_with_proof(
+        &self,
+        data: &[u8],
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), RemoteSignerError> {
+        let request = RemoteSignerRequest::ComputeVrf(data.to_vec());
+        let result = self.request(&request).and_then(|response| match response {
+            RemoteSignerResponse::Vrf { value, proof } => Ok((value, proof)),
+            _ => Err(RemoteSignerError::UnexpectedResponse),
+        });
+        if let Err(err) = &result {
+            tracing::error!(target: "remote_signer", request = request.name(), %err, "Failed to compute VRF");
+        }
+        result
+    }
+}
+
+/// Sends the request over an idle connection, or a new one if there is none.
+/// An idle connection may have been closed by the daemon in the meantime, e.g.
+/// when it restarted, so the request is retried once over a new connection if
+/// it fails.
+fn send_request(
+    idle_connections: &Mutex<Vec<UnixStream>>,
+    socket_path: &Path,
+    timeout: Duration,
+    request: &RemoteSignerRequest,
+) -> io::Result<RemoteSignerResponse> {
+    // The lock is released right away, the request is sent without holding it.
+    let idle = idle_connections.lock().unwrap().pop();
+    let reused = idle.and_then(|mut stream| {
+        send_request_once(&mut stream, request).ok().map(|response| (stream, response))
+    });
+    let (stream, response) = match reused {
+        Some(reused) => reused,
+        None => {
+            let mut stream = open_connection(socket_path, timeout)?;
+            let response = send_request_once(&mut stream, request)?;
+            (stream, response)
+        }
+    };
+    // Streams which failed are dropped, as they may be left in the middle of a
+    // message.
+    let mut idle_connections = idle_connections.lock().unwrap();
+    if idle_connections.len() < MAX_IDLE_CONNECTIONS {
+        idle_connections.push(stream);
+    }
+    Ok(response)
+}
+
+fn open_connection(socket_path: &Path, timeout: Duration) -> io::Result<UnixStream> {
+    let stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(stream)
+}
+
+fn send_request_once(
+    stream: &mut UnixStream,
+    request: &RemoteSignerRequest,
+) -> io::Result<RemoteSignerResponse> {
+    write_message(stream, request)?;
+    read_message(stream)
+}
+
+impl PartialEq for RemoteValidatorSigner {
+    fn eq(&self, other: &Self) -> bool {
+        self.account_id == other.account_id
+            && self.public_key == other.public_key
+            && self.socket_path == other.socket_path
+    }
+}
+
+impl fmt::Debug for RemoteValidatorSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RemoteValidatorSigner(account_id: {}, public_key: {}, socket_path: {})",
+            self.account_id,
+            self.public_key,
+            self.socket_path.display()
+        )
+    }
+}
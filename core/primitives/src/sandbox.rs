@@ -78,3 +78,52 @@ pub mod state_patch {
         }
     }
 }
+
+#[cfg(feature = "sandbox")]
+pub mod impersonation {
+    use crate::types::AccountId;
+    use std::collections::HashSet;
+
+    /// Accounts whose transactions are accepted without checking their
+    /// signature, access key and nonce.
+    ///
+    /// This lets sandbox users act as any account, e.g. one whose state was
+    /// copied from mainnet, without having to patch in an access key of their
+    /// own.  Like `SandboxStatePatch`, the set can be non-empty only if
+    /// `sandbox` feature is enabled.
+    #[derive(Default, Clone, Debug, PartialEq, Eq)]
+    pub struct SandboxImpersonatedAccounts {
+        accounts: HashSet<AccountId>,
+    }
+
+    impl SandboxImpersonatedAccounts {
+        /// Starts impersonating the account.  Returns whether it wasn't already.
+        pub fn insert(&mut self, account_id: AccountId) -> bool {
+            self.accounts.insert(account_id)
+        }
+
+        /// Stops impersonating the account.  Returns whether it was impersonated.
+        pub fn remove(&mut self, account_id: &AccountId) -> bool {
+            self.accounts.remove(account_id)
+        }
+
+        pub fn contains(&self, account_id: &AccountId) -> bool {
+            self.accounts.contains(account_id)
+        }
+    }
+}
+
+#[cfg(not(feature = "sandbox"))]
+pub mod impersonation {
+    use crate::types::AccountId;
+
+    #[derive(Default, Clone, Debug, PartialEq, Eq)]
+    pub struct SandboxImpersonatedAccounts;
+
+    impl SandboxImpersonatedAccounts {
+        #[inline(always)]
+        pub fn contains(&self, _account_id: &AccountId) -> bool {
+            false
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::receipt::Receipt;
 use crate::transaction::SignedTransaction;
 use crate::types::validator_stake::{ValidatorStake, ValidatorStakeIter, ValidatorStakeV1};
 use crate::types::{Balance, BlockHeight, Gas, MerkleHash, ShardId, StateRoot};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{ValidatorSigner, ValidatorSignerError};
 use crate::version::{ProtocolFeature, ProtocolVersion, SHARD_CHUNK_HEADER_UPGRADE_VERSION};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::Signature;
@@ -127,7 +127,7 @@ impl ShardChunkHeaderV2 {
         tx_root: CryptoHash,
         prev_validator_proposals: Vec<ValidatorStakeV1>,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, ValidatorSignerError> {
         let inner = ShardChunkHeaderInnerV1 {
             prev_block_hash,
             prev_state_root,
@@ -144,8 +144,8 @@ impl ShardChunkHeaderV2 {
             prev_validator_proposals,
         };
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_chunk_hash(&hash);
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_chunk_hash(&hash)?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -193,7 +193,7 @@ impl ShardChunkHeaderV3 {
         prev_validator_proposals: Vec<ValidatorStake>,
         congestion_info: Option<CongestionInfo>,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, ValidatorSignerError> {
         let inner = if let Some(congestion_info) = congestion_info {
             assert!(ProtocolFeature::CongestionControl.enabled(protocol_version));
             ShardChunkHeaderInner::V3(ShardChunkHeaderInnerV3 {
@@ -232,10 +232,12 @@ impl ShardChunkHeaderV3 {
         Self::from_inner(inner, signer)
     }
 
-    pub fn from_inner(inner: ShardChunkHeaderInner, signer: &ValidatorSigner) -> Self {
-        let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_chunk_hash(&hash);
-        Self { inner, height_included: 0, signature, hash }
+    pub fn from_inner(
+        inner: ShardChunkHeaderInner,
+        signer: &ValidatorSigner,
+    ) -> Result<Self, ValidatorSignerError> {
+        let (hash, signature) = signer.sign_chunk_header_inner(&inner)?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -505,7 +507,7 @@ impl ShardChunkHeaderV1 {
         tx_root: CryptoHash,
         prev_validator_proposals: Vec<ValidatorStakeV1>,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, ValidatorSignerError> {
         let inner = ShardChunkHeaderInnerV1 {
             prev_block_hash,
             prev_state_root,
@@ -522,8 +524,8 @@ impl ShardChunkHeaderV1 {
             prev_validator_proposals,
         };
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_chunk_hash(&hash);
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_chunk_hash(&hash)?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -1038,7 +1040,7 @@ impl EncodedShardChunk {
         congestion_info: Option<CongestionInfo>,
         signer: &ValidatorSigner,
         protocol_version: ProtocolVersion,
-    ) -> Result<(Self, Vec<MerklePath>), std::io::Error> {
+    ) -> Result<(Self, Vec<MerklePath>), ValidatorSignerError> {
         let (transaction_receipts_parts, encoded_length) = crate::reed_solomon::reed_solomon_encode(
             rs,
             TransactionReceipt(transactions, prev_outgoing_receipts.to_vec()),
@@ -1066,7 +1068,7 @@ impl EncodedShardChunk {
                 tx_root,
                 prev_validator_proposals,
                 signer,
-            );
+            )?;
             let chunk = EncodedShardChunkV1 { header, content };
             Ok((Self::V1(chunk), merkle_paths))
         } else if block_header_v3_version.is_none()
@@ -1089,7 +1091,7 @@ impl EncodedShardChunk {
                 tx_root,
                 validator_proposals,
                 signer,
-            );
+            )?;
             let chunk = EncodedShardChunkV2 { header: ShardChunkHeader::V2(header), content };
             Ok((Self::V2(chunk), merkle_paths))
         } else {
@@ -1110,7 +1112,7 @@ impl EncodedShardChunk {
                 prev_validator_proposals,
                 congestion_info,
                 signer,
-            );
+            )?;
             let chunk = EncodedShardChunkV2 { header: ShardChunkHeader::V3(header), content };
             Ok((Self::V2(chunk), merkle_paths))
         }
@@ -7,7 +7,7 @@ use crate::sharding::{ChunkHash, ReceiptProof, ShardChunkHeader, ShardChunkHeade
 use crate::transaction::SignedTransaction;
 use crate::types::EpochId;
 use crate::utils::io::{CountingRead, CountingWrite};
-use crate::validator_signer::{EmptyValidatorSigner, ValidatorSigner};
+use crate::validator_signer::{EmptyValidatorSigner, ValidatorSigner, ValidatorSignerError};
 use borsh::{BorshDeserialize, BorshSerialize};
 use bytes::{Buf, BufMut};
 use bytesize::ByteSize;
@@ -66,7 +66,7 @@ impl PartialEncodedStateWitness {
         part: Vec<u8>,
         encoded_length: usize,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, ValidatorSignerError> {
         let inner = PartialEncodedStateWitnessInner::new(
             epoch_id,
             chunk_header,
@@ -74,8 +74,8 @@ impl PartialEncodedStateWitness {
             part,
             encoded_length,
         );
-        let signature = signer.sign_partial_encoded_state_witness(&inner);
-        Self { inner, signature }
+        let signature = signer.sign_partial_encoded_state_witness(&inner)?;
+        Ok(Self { inner, signature })
     }
 
     pub fn chunk_production_key(&self) -> ChunkProductionKey {
@@ -367,24 +367,27 @@ impl ChunkStateWitness {
             .enabled(PROTOCOL_VERSION)
             .then_some(CongestionInfo::default());
 
-        let header = ShardChunkHeader::V3(ShardChunkHeaderV3::new(
-            PROTOCOL_VERSION,
-            prev_block_hash,
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            height,
-            shard_id,
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            congestion_info,
-            &EmptyValidatorSigner::default().into(),
-        ));
+        let header = ShardChunkHeader::V3(
+            ShardChunkHeaderV3::new(
+                PROTOCOL_VERSION,
+                prev_block_hash,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                height,
+                shard_id,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                congestion_info,
+                &EmptyValidatorSigner::default().into(),
+            )
+            .expect("Empty signer never fails"),
+        );
         Self::new(
             "alice.near".parse().unwrap(),
             EpochId::default(),
@@ -428,11 +431,18 @@ pub struct ChunkEndorsement {
 }
 
 impl ChunkEndorsement {
-    pub fn new(chunk_hash: ChunkHash, signer: &ValidatorSigner) -> ChunkEndorsement {
-        let inner = ChunkEndorsementInner::new(chunk_hash);
+    pub fn new(
+        chunk_header: &ShardChunkHeader,
+        signer: &ValidatorSigner,
+    ) -> Result<ChunkEndorsement, ValidatorSignerError> {
+        let inner = ChunkEndorsementInner::new(chunk_header.chunk_hash());
         let account_id = signer.validator_id().clone();
-        let signature = signer.sign_chunk_endorsement(&inner);
-        Self { inner, account_id, signature }
+        let signature = signer.sign_chunk_endorsement(
+            &inner,
+            chunk_header.height_created(),
+            chunk_header.shard_id(),
+        )?;
+        Ok(Self { inner, account_id, signature })
     }
 
     pub fn verify(&self, public_key: &PublicKey) -> bool {
@@ -466,6 +476,10 @@ impl ChunkEndorsementInner {
     fn new(chunk_hash: ChunkHash) -> Self {
         Self { chunk_hash, signature_differentiator: "ChunkEndorsement".to_owned() }
     }
+
+    pub fn chunk_hash(&self) -> &ChunkHash {
+        &self.chunk_hash
+    }
 }
 
 /// Stored on disk for each chunk, including missing chunks, in order to
@@ -8,12 +8,14 @@ use crate::block::{Approval, ApprovalInner, BlockHeader};
 use crate::challenge::ChallengeBody;
 use crate::hash::CryptoHash;
 use crate::network::{AnnounceAccount, PeerId};
-use crate::sharding::ChunkHash;
+#[cfg(unix)]
+use crate::remote_signer::{RemoteSignerError, RemoteValidatorSigner};
+use crate::sharding::{ChunkHash, ShardChunkHeaderInner, ShardChunkHeaderV3};
 use crate::stateless_validation::{
     ChunkEndorsementInner, EncodedChunkStateWitness, PartialEncodedStateWitnessInner,
 };
 use crate::telemetry::TelemetryInfo;
-use crate::types::{AccountId, BlockHeight, EpochId};
+use crate::types::{AccountId, BlockHeight, EpochId, ShardId};
 
 /// Enum for validator signer, that holds validator id and key used for signing data.
 #[derive(Clone, Debug, PartialEq)]
@@ -22,6 +24,22 @@ pub enum ValidatorSigner {
     Empty(EmptyValidatorSigner),
     /// Default validator signer that holds data in memory.
     InMemory(InMemoryValidatorSigner),
+    /// Validator signer that delegates signing to an external daemon.
+    #[cfg(unix)]
+    Remote(RemoteValidatorSigner),
+}
+
+/// Error returned when a validator signer fails to sign a message.
+///
+/// Signers holding the key in memory never fail, but a remote signer can be
+/// unreachable or refuse to sign.  Callers must not send the message then.
+#[derive(thiserror::Error, Debug)]
+pub enum ValidatorSignerError {
+    #[cfg(unix)]
+    #[error(transparent)]
+    Remote(#[from] RemoteSignerError),
+    #[error("{0} is not supported by this validator signer")]
+    Unsupported(&'static str),
 }
 
 /// Validator signer that is used to sign blocks and approvals.
@@ -31,6 +49,8 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(signer) => signer.validator_id(),
             ValidatorSigner::InMemory(signer) => signer.validator_id(),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.validator_id(),
         }
     }
 
@@ -39,14 +59,21 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(signer) => signer.public_key(),
             ValidatorSigner::InMemory(signer) => signer.public_key(),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.public_key(),
         }
     }
 
     /// Serializes telemetry info to JSON and signs it, returning JSON with "signature" field.
-    pub fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
+    pub fn sign_telemetry(
+        &self,
+        info: &TelemetryInfo,
+    ) -> Result<serde_json::Value, ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_telemetry(info),
-            ValidatorSigner::InMemory(signer) => signer.sign_telemetry(info),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_telemetry(info)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_telemetry(info)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_telemetry(info)?),
         }
     }
 
@@ -56,46 +83,94 @@ impl ValidatorSigner {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature) {
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
         match self {
             ValidatorSigner::Empty(signer) => {
-                signer.sign_block_header_parts(prev_hash, inner_lite, inner_rest)
+                Ok(signer.sign_block_header_parts(prev_hash, inner_lite, inner_rest))
             }
             ValidatorSigner::InMemory(signer) => {
-                signer.sign_block_header_parts(prev_hash, inner_lite, inner_rest)
+                Ok(signer.sign_block_header_parts(prev_hash, inner_lite, inner_rest))
+            }
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => {
+                Ok(signer.sign_block_header_parts(prev_hash, inner_lite, inner_rest)?)
+            }
+        }
+    }
+
+    /// Signs the hash of a chunk header of one of the legacy versions.
+    pub fn sign_chunk_hash(
+        &self,
+        chunk_hash: &ChunkHash,
+    ) -> Result<Signature, ValidatorSignerError> {
+        match self {
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_chunk_hash(chunk_hash)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_chunk_hash(chunk_hash)),
+            // The remote signer needs the height and the shard of the chunk
+            // to protect from double signing, see `sign_chunk_header_inner`.
+            #[cfg(unix)]
+            ValidatorSigner::Remote(_) => {
+                Err(ValidatorSignerError::Unsupported("signing legacy chunk headers"))
             }
         }
     }
 
-    /// Signs given inner of the chunk header.
-    pub fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Signature {
+    /// Signs given inner of the chunk header, returning its hash and signature.
+    pub fn sign_chunk_header_inner(
+        &self,
+        inner: &ShardChunkHeaderInner,
+    ) -> Result<(ChunkHash, Signature), ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_chunk_hash(chunk_hash),
-            ValidatorSigner::InMemory(signer) => signer.sign_chunk_hash(chunk_hash),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_chunk_header_inner(inner)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_chunk_header_inner(inner)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_chunk_header_inner(inner)?),
         }
     }
 
     /// Signs approval of given parent hash and reference hash.
-    pub fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature {
+    pub fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_approval(inner, target_height),
-            ValidatorSigner::InMemory(signer) => signer.sign_approval(inner, target_height),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_approval(inner, target_height)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_approval(inner, target_height)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_approval(inner, target_height)?),
         }
     }
 
-    /// Signs chunk endorsement to be sent to block producer.
-    pub fn sign_chunk_endorsement(&self, inner: &ChunkEndorsementInner) -> Signature {
+    /// Signs chunk endorsement to be sent to block producer.  The height and
+    /// the shard of the chunk aren't signed, but the remote signer needs them
+    /// to protect from double signing.
+    pub fn sign_chunk_endorsement(
+        &self,
+        inner: &ChunkEndorsementInner,
+        height_created: BlockHeight,
+        shard_id: ShardId,
+    ) -> Result<Signature, ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_chunk_endorsement(inner),
-            ValidatorSigner::InMemory(signer) => signer.sign_chunk_endorsement(inner),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_chunk_endorsement(inner)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_chunk_endorsement(inner)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => {
+                Ok(signer.sign_chunk_endorsement(inner, height_created, shard_id)?)
+            }
         }
     }
 
     /// Signs chunk state witness to be sent to all validators.
-    pub fn sign_chunk_state_witness(&self, witness_bytes: &EncodedChunkStateWitness) -> Signature {
+    pub fn sign_chunk_state_witness(
+        &self,
+        witness_bytes: &EncodedChunkStateWitness,
+    ) -> Result<Signature, ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_chunk_state_witness(witness_bytes),
-            ValidatorSigner::InMemory(signer) => signer.sign_chunk_state_witness(witness_bytes),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_chunk_state_witness(witness_bytes)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_chunk_state_witness(witness_bytes)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_chunk_state_witness(witness_bytes)?),
         }
     }
 
@@ -103,18 +178,27 @@ impl ValidatorSigner {
     pub fn sign_partial_encoded_state_witness(
         &self,
         part: &PartialEncodedStateWitnessInner,
-    ) -> Signature {
+    ) -> Result<Signature, ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_partial_encoded_state_witness(part),
-            ValidatorSigner::InMemory(signer) => signer.sign_partial_encoded_state_witness(part),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_partial_encoded_state_witness(part)),
+            ValidatorSigner::InMemory(signer) => {
+                Ok(signer.sign_partial_encoded_state_witness(part))
+            }
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_partial_encoded_state_witness(part)?),
         }
     }
 
     /// Signs challenge body.
-    pub fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
+    pub fn sign_challenge(
+        &self,
+        challenge_body: &ChallengeBody,
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_challenge(challenge_body),
-            ValidatorSigner::InMemory(signer) => signer.sign_challenge(challenge_body),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_challenge(challenge_body)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_challenge(challenge_body)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_challenge(challenge_body)?),
         }
     }
 
@@ -124,13 +208,17 @@ impl ValidatorSigner {
         account_id: &AccountId,
         peer_id: &PeerId,
         epoch_id: &EpochId,
-    ) -> Signature {
+    ) -> Result<Signature, ValidatorSignerError> {
         match self {
             ValidatorSigner::Empty(signer) => {
-                signer.sign_account_announce(account_id, peer_id, epoch_id)
+                Ok(signer.sign_account_announce(account_id, peer_id, epoch_id))
             }
             ValidatorSigner::InMemory(signer) => {
-                signer.sign_account_announce(account_id, peer_id, epoch_id)
+                Ok(signer.sign_account_announce(account_id, peer_id, epoch_id))
+            }
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => {
+                Ok(signer.sign_account_announce(account_id, peer_id, epoch_id)?)
             }
         }
     }
@@ -146,20 +234,27 @@ impl ValidatorSigner {
     /// used only for networking purposes and are not persisted on chain.
     /// Moving to proto serialization for stuff stored on chain would be way
     /// harder.
-    pub fn sign_account_key_payload(&self, proto_bytes: &[u8]) -> Signature {
+    pub fn sign_account_key_payload(
+        &self,
+        proto_bytes: &[u8],
+    ) -> Result<Signature, ValidatorSignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_account_key_payload(proto_bytes),
-            ValidatorSigner::InMemory(signer) => signer.sign_account_key_payload(proto_bytes),
+            ValidatorSigner::Empty(signer) => Ok(signer.sign_account_key_payload(proto_bytes)),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_account_key_payload(proto_bytes)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.sign_account_key_payload(proto_bytes)?),
         }
     }
 
     pub fn compute_vrf_with_proof(
         &self,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), ValidatorSignerError> {
         match self {
             ValidatorSigner::Empty(_) => unimplemented!(),
-            ValidatorSigner::InMemory(signer) => signer.compute_vrf_with_proof(data),
+            ValidatorSigner::InMemory(signer) => Ok(signer.compute_vrf_with_proof(data)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => Ok(signer.compute_vrf_with_proof(data)?),
         }
     }
 
@@ -168,6 +263,11 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(_) => unimplemented!(),
             ValidatorSigner::InMemory(signer) => signer.write_to_file(path),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "key of a remote signer can't be written to a file",
+            )),
         }
     }
 }
@@ -184,6 +284,13 @@ impl From<InMemoryValidatorSigner> for ValidatorSigner {
     }
 }
 
+#[cfg(unix)]
+impl From<RemoteValidatorSigner> for ValidatorSigner {
+    fn from(signer: RemoteValidatorSigner) -> Self {
+        ValidatorSigner::Remote(signer)
+    }
+}
+
 /// Test-only signer that "signs" everything with 0s.
 /// Don't use in any production or code that requires signature verification.
 #[derive(smart_default::SmartDefault, Clone, Debug, PartialEq)]
@@ -223,6 +330,10 @@ impl EmptyValidatorSigner {
         Signature::default()
     }
 
+    fn sign_chunk_header_inner(&self, inner: &ShardChunkHeaderInner) -> (ChunkHash, Signature) {
+        (ShardChunkHeaderV3::compute_hash(inner), Signature::default())
+    }
+
     fn sign_approval(&self, _inner: &ApprovalInner, _target_height: BlockHeight) -> Signature {
         Signature::default()
     }
@@ -302,10 +413,15 @@ impl InMemoryValidatorSigner {
     fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
         let mut value = serde_json::to_value(info).expect("Telemetry must serialize to JSON");
         let content = serde_json::to_string(&value).expect("Telemetry must serialize to JSON");
-        value["signature"] = self.signer.sign(content.as_bytes()).to_string().into();
+        value["signature"] = self.sign_telemetry_content(&content).to_string().into();
         value
     }
 
+    /// Signs telemetry info already serialized to JSON.
+    pub fn sign_telemetry_content(&self, content: &str) -> Signature {
+        self.signer.sign(content.as_bytes())
+    }
+
     fn sign_block_header_parts(
         &self,
         prev_hash: CryptoHash,
@@ -320,6 +436,12 @@ impl InMemoryValidatorSigner {
         self.signer.sign(chunk_hash.as_ref())
     }
 
+    fn sign_chunk_header_inner(&self, inner: &ShardChunkHeaderInner) -> (ChunkHash, Signature) {
+        let hash = ShardChunkHeaderV3::compute_hash(inner);
+        let signature = self.signer.sign(hash.as_ref());
+        (hash, signature)
+    }
+
     fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature {
         self.signer.sign(&Approval::get_data_for_sig(inner, target_height))
     }
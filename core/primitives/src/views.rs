@@ -4,6 +4,7 @@
 //! type gets changed, the view should preserve the old shape and only re-map the necessary bits
 //! from the source structure in the relevant `From<SourceStruct>` impl.
 use crate::account::{AccessKey, AccessKeyPermission, Account, FunctionCallPermission};
+use crate::account_activity::{AccountActivity, AccountActivityKind};
 use crate::action::delegate::{DelegateAction, SignedDelegateAction};
 use crate::block::{Block, BlockHeader, Tip};
 use crate::block_header::{
@@ -221,6 +222,52 @@ pub struct ViewStateResult {
 pub struct CallResult {
     pub result: Vec<u8>,
     pub logs: Vec<String>,
+    /// Gas used by the call, if requested with `include_gas_usage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_usage: Option<CallGasUsageView>,
+}
+
+/// Gas used by a view call, as it would be charged if the method was called
+/// in a transaction.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct CallGasUsageView {
+    #[serde(with = "dec_format")]
+    pub gas_burnt: Gas,
+    /// Breakdown of the burnt gas between wasm instructions and host
+    /// functions.
+    pub gas_profile: Vec<CostGasUsed>,
+    /// Number of trie nodes touched which are served from disk in the worst
+    /// case.
+    pub trie_nodes_db_reads: u64,
+    /// Number of trie nodes touched which are guaranteed to be served from
+    /// memory.
+    pub trie_nodes_mem_reads: u64,
+    /// Gas burnt by the functions of the contract, if requested with
+    /// `include_function_profile`, as folded stacks that flamegraph tools
+    /// like `inferno-flamegraph` take as input.  The contract is run with a
+    /// different VM to be profiled, so the gas may differ slightly from
+    /// `gas_burnt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_profile: Option<String>,
+}
+
+/// Changes to the state of an account applied before running a view call.
+/// They only affect the call and aren't persisted.
+#[serde_as]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct StateOverride {
+    /// The account is created if it doesn't exist.
+    pub account_id: AccountId,
+    /// Replacement contract code of the account.
+    #[serde(rename = "code_base64", default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<Base64>")]
+    pub code: Option<Vec<u8>>,
+    /// Replacement balance of the account.
+    #[serde(default, with = "dec_format", skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Balance>,
+    /// Contract data to set in the storage of the account.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub state: Vec<StateItem>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -314,6 +361,16 @@ pub enum QueryRequest {
         method_name: String,
         #[serde(rename = "args_base64")]
         args: FunctionArgs,
+        #[serde(default, skip_serializing_if = "is_false")]
+        include_gas_usage: bool,
+        /// Profiles the gas burnt by the functions of the contract, see
+        /// `CallGasUsageView::function_profile`.  This implies
+        /// `include_gas_usage`.  JSON-RPC only accepts it with debug RPC
+        /// enabled.
+        #[serde(default, skip_serializing_if = "is_false")]
+        include_function_profile: bool,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        state_overrides: Vec<StateOverride>,
     },
 }
 
@@ -2216,7 +2273,7 @@ pub struct GasPriceView {
 ///
 /// [serializable view]: ./index.html
 /// [`StateChangesRequest`]: ../types/struct.StateChangesRequest.html
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "changes_type", rename_all = "snake_case")]
 pub enum StateChangesRequestView {
     AccountChanges {
@@ -2439,6 +2496,63 @@ pub struct SplitStorageInfoView {
     pub hot_db_kind: Option<String>,
 }
 
+/// A transaction or receipt the account took part in.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountActivityView {
+    pub kind: AccountActivityKind,
+    /// Transaction hash or receipt id.
+    pub id: CryptoHash,
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+    pub shard_id: ShardId,
+}
+
+impl AccountActivityView {
+    pub fn from_activity(activity: AccountActivity, block_height: BlockHeight) -> Self {
+        Self {
+            kind: activity.kind,
+            id: activity.id,
+            block_hash: activity.block_hash,
+            block_height,
+            shard_id: activity.shard_id,
+        }
+    }
+}
+
+/// A transaction waiting in the pool of the node to be included in a chunk.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MempoolTransactionView {
+    /// Shard of the signer, whose chunk producers include the transaction.
+    pub shard_id: ShardId,
+    pub transaction: SignedTransactionView,
+}
+
+/// Status of a transaction in the pool of the node.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolTransactionStatusView {
+    /// The transaction is waiting to be included in a chunk.
+    Pending {
+        shard_id: ShardId,
+        /// Number of pending transactions of the same access key with a lower nonce, which
+        /// have to be included first.
+        num_pending_before: u64,
+    },
+    /// The transaction has already been included in a chunk, has been dropped, or has never
+    /// been received by this node.
+    NotInPool,
+}
+
+/// Number and total size of the transactions pending in the pool of a shard.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MempoolShardStatsView {
+    pub shard_id: ShardId,
+    pub num_transactions: u64,
+    pub size_bytes: u64,
+    /// Transactions which don't fit within the limit are rejected.  `None` if unlimited.
+    pub size_limit_bytes: Option<u64>,
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct CongestionInfoView {
     #[serde(with = "dec_format")]
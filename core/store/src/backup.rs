@@ -0,0 +1,301 @@
+//! Incremental backups of the node storage.
+//!
+//! A backup directory holds the backups of the hot database in its `hot`
+//! subdirectory and, for nodes with split storage, the backups of the cold
+//! database in `cold`.  Backups of a database share the files they have in
+//! common so each backup only copies what changed since the previous one.
+//!
+//! The databases are backed up together and `backups.json` records, for each
+//! backup, the head of the chain in the backed up hot database.  This allows picking
+//! the backup to restore in order to get the databases back to a given height.
+//!
+//! Both databases are checkpointed before anything is backed up, so that the
+//! backups are made from data as of the same moment, and a backup is refused
+//! if garbage collection of the hot database went past the head of the cold
+//! database, which would leave blocks missing from both.
+use crate::db::rocksdb::backup as rocksdb_backup;
+use crate::db::Database;
+use crate::{DBCol, NodeStorage, COLD_HEAD_KEY, HEAD_KEY, TAIL_KEY};
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use near_primitives::block::Tip;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE_NAME: &str = "backups.json";
+
+/// Description of a backup of the node storage.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BackupInfo {
+    /// Id of the backup of the hot database.
+    pub id: u32,
+    /// Id of the backup of the cold database if the node has one.
+    pub cold_id: Option<u32>,
+    /// Height of the head of the chain in the backed up hot database.
+    pub height: BlockHeight,
+    /// Hash of the head block in the backed up hot database.
+    pub block_hash: CryptoHash,
+    /// Time the backup was made at, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+fn hot_backup_dir(backup_dir: &Path) -> PathBuf {
+    backup_dir.join("hot")
+}
+
+fn cold_backup_dir(backup_dir: &Path) -> PathBuf {
+    backup_dir.join("cold")
+}
+
+/// Returns the backups in the directory, from the oldest to the newest.
+pub fn list_backups(backup_dir: &Path) -> anyhow::Result<Vec<BackupInfo>> {
+    let path = backup_dir.join(MANIFEST_FILE_NAME);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    serde_json::from_slice(&std::fs::read(&path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn save_backups(backup_dir: &Path, backups: &[BackupInfo]) -> anyhow::Result<()> {
+    let path = backup_dir.join(MANIFEST_FILE_NAME);
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec_pretty(backups)?)?;
+    std::fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+/// Returns an error if the databases of the node can’t be backed up, which is
+/// the case unless they use the RocksDB backend.
+pub fn ensure_backups_supported(storage: &NodeStorage) -> anyhow::Result<()> {
+    let cold_supports_backups =
+        storage.cold_storage.as_ref().map_or(true, |cold| cold.supports_backups());
+    anyhow::ensure!(
+        storage.hot_storage.supports_backups() && cold_supports_backups,
+        "backups are only supported by the rocks_db database backend"
+    );
+    Ok(())
+}
+
+/// Backs up the databases of the node to `backup_dir`.
+///
+/// This can be done while the node is running.  If `num_backups_to_keep` is
+/// set, older backups are deleted afterwards.
+pub fn create_backup(
+    storage: &NodeStorage,
+    backup_dir: &Path,
+    num_backups_to_keep: Option<usize>,
+) -> anyhow::Result<BackupInfo> {
+    ensure_backups_supported(storage)?;
+    // The hot database is checkpointed first: the head of the cold database
+    // only moves forward, so it stays at or above the tail of the hot
+    // checkpoint, which garbage collection never moves past the cold head.
+    let hot = storage.hot_storage.create_backup_checkpoint()?;
+    let cold =
+        storage.cold_storage.as_ref().map(|cold| cold.create_backup_checkpoint()).transpose()?;
+    // The heads are read from the checkpoints rather than from the live
+    // databases, which may have moved on while the backup was made.
+    let head =
+        hot.get(DBCol::BlockMisc, HEAD_KEY)?.context("the backed up database has no head")?;
+    let head = Tip::try_from_slice(&head)?;
+    if let Some(cold) = &cold {
+        let tail = match hot.get(DBCol::BlockMisc, TAIL_KEY)? {
+            Some(tail) => BlockHeight::try_from_slice(&tail)?,
+            None => 0,
+        };
+        let cold_head = cold
+            .get(DBCol::BlockMisc, COLD_HEAD_KEY)?
+            .context("the backed up cold database has no head")?;
+        let cold_head = Tip::try_from_slice(&cold_head)?;
+        anyhow::ensure!(
+            cold_head.height >= tail,
+            "the head of the cold database ({}) is below the tail of the hot database ({tail})",
+            cold_head.height,
+        );
+    }
+    let cold_id = cold.map(|cold| cold.backup(&cold_backup_dir(backup_dir))).transpose()?;
+    let id = hot.backup(&hot_backup_dir(backup_dir))?;
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let info = BackupInfo {
+        id,
+        cold_id,
+        height: head.height,
+        block_hash: head.last_block_hash,
+        timestamp,
+    };
+
+    let mut backups = list_backups(backup_dir)?;
+    backups.push(info.clone());
+    if let Some(num_backups_to_keep) = num_backups_to_keep {
+        rocksdb_backup::purge_old_backups(&hot_backup_dir(backup_dir), num_backups_to_keep)?;
+        if cold_id.is_some() {
+            rocksdb_backup::purge_old_backups(&cold_backup_dir(backup_dir), num_backups_to_keep)?;
+        }
+        let ids = rocksdb_backup::list_backups(&hot_backup_dir(backup_dir))?;
+        backups.retain(|backup| ids.contains(&backup.id));
+    }
+    save_backups(backup_dir, &backups)?;
+    tracing::info!(target: "store", backup_dir = %backup_dir.display(), ?info, "Created a backup");
+    Ok(info)
+}
+
+/// Returns the latest backup whose head is at or below `height`, or the latest
+/// backup if `height` is `None`.
+pub fn find_backup(backup_dir: &Path, height: Option<BlockHeight>) -> anyhow::Result<BackupInfo> {
+    list_backups(backup_dir)?
+        .into_iter()
+        .filter(|backup| height.map_or(true, |height| backup.height <= height))
+        .max_by_key(|backup| (backup.height, backup.id))
+        .with_context(|| match height {
+            Some(height) => format!("no backup at or below height {height}"),
+            None => format!("no backup in {}", backup_dir.display()),
+        })
+}
+
+/// Restores the hot database and, if `cold_path` is set, the cold database of
+/// the backup as new databases in the given directories.
+///
+/// The directories must not exist or be empty.
+pub fn restore_backup(
+    backup_dir: &Path,
+    backup: &BackupInfo,
+    hot_path: &Path,
+    cold_path: Option<&Path>,
+) -> anyhow::Result<()> {
+    for path in std::iter::once(hot_path).chain(cold_path) {
+        if path.exists() && path.read_dir()?.next().is_some() {
+            anyhow::bail!("{} is not empty", path.display());
+        }
+    }
+    if let Some(cold_path) = cold_path {
+        let cold_id = backup.cold_id.context("the backup has no cold database")?;
+        rocksdb_backup::restore_backup(&cold_backup_dir(backup_dir), cold_id, cold_path)?;
+    }
+    rocksdb_backup::restore_backup(&hot_backup_dir(backup_dir), backup.id, hot_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DbBackend, Mode, StoreConfig};
+    use near_primitives::types::EpochId;
+
+    fn tip(height: BlockHeight) -> Tip {
+        Tip {
+            height,
+            last_block_hash: CryptoHash::hash_borsh(height),
+            prev_block_hash: CryptoHash::default(),
+            epoch_id: EpochId::default(),
+            next_epoch_id: EpochId::default(),
+        }
+    }
+
+    fn set_tail(storage: &NodeStorage, height: BlockHeight) {
+        let mut update = storage.get_hot_store().store_update();
+        update.set_ser(DBCol::BlockMisc, TAIL_KEY, &height).unwrap();
+        update.commit().unwrap();
+    }
+
+    fn set_head(storage: &NodeStorage, height: BlockHeight) {
+        let mut update = storage.get_hot_store().store_update();
+        update.set_ser(DBCol::BlockMisc, HEAD_KEY, &tip(height)).unwrap();
+        update.insert(DBCol::Block, height.to_le_bytes().to_vec(), b"block".to_vec());
+        update.commit().unwrap();
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let (home_dir, opener) = NodeStorage::test_opener();
+        let backup_dir = home_dir.path().join("backups");
+        let storage = opener.open().unwrap();
+        for height in 1..=4 {
+            set_head(&storage, height);
+            let info = create_backup(&storage, &backup_dir, Some(3)).unwrap();
+            assert_eq!(info.height, height);
+            assert_eq!(info.cold_id, None);
+        }
+        drop(storage);
+
+        // Only the last three backups are kept.
+        let backups = list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.iter().map(|backup| backup.height).collect::<Vec<_>>(), [2, 3, 4]);
+        assert!(find_backup(&backup_dir, Some(1)).is_err());
+        assert_eq!(find_backup(&backup_dir, None).unwrap().height, 4);
+
+        let backup = find_backup(&backup_dir, Some(3)).unwrap();
+        assert_eq!(backup.height, 3);
+        let restore_dir = home_dir.path().join("restored");
+        restore_backup(&backup_dir, &backup, &restore_dir.join("data"), None).unwrap();
+        // Restoring to a non-empty directory fails.
+        assert!(restore_backup(&backup_dir, &backup, &restore_dir.join("data"), None).is_err());
+        // There's no cold database to restore.
+        assert!(restore_backup(
+            &backup_dir,
+            &backup,
+            &home_dir.path().join("hot"),
+            Some(&home_dir.path().join("cold"))
+        )
+        .is_err());
+
+        let config = StoreConfig::test_config();
+        let store = NodeStorage::opener(&restore_dir, false, &config, None)
+            .open_in_mode(Mode::ReadOnly)
+            .unwrap()
+            .get_hot_store();
+        let head = store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY).unwrap().unwrap();
+        assert_eq!(head.height, 3);
+        assert!(store.exists(DBCol::Block, &3u64.to_le_bytes()).unwrap());
+        assert!(!store.exists(DBCol::Block, &4u64.to_le_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_backup_and_restore_split_storage() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let backup_dir = home_dir.path().join("backups");
+        let config = StoreConfig::test_config();
+        let storage =
+            NodeStorage::opener(home_dir.path(), true, &config, Some(&config)).open().unwrap();
+        set_head(&storage, 5);
+        let mut update = storage.get_cold_store().unwrap().store_update();
+        update.set_ser(DBCol::BlockMisc, COLD_HEAD_KEY, &tip(3)).unwrap();
+        update.increment_refcount(DBCol::State, b"key", b"value");
+        update.commit().unwrap();
+        // Blocks below the tail are garbage collected from the hot database,
+        // so the cold one must hold them.
+        set_tail(&storage, 4);
+        assert!(create_backup(&storage, &backup_dir, None).is_err());
+        set_tail(&storage, 3);
+        let backup = create_backup(&storage, &backup_dir, None).unwrap();
+        assert!(backup.cold_id.is_some());
+        drop(storage);
+
+        let restore_dir = home_dir.path().join("restored");
+        restore_backup(
+            &backup_dir,
+            &backup,
+            &restore_dir.join("data"),
+            Some(&restore_dir.join("cold-data")),
+        )
+        .unwrap();
+        let storage = NodeStorage::opener(&restore_dir, true, &config, Some(&config))
+            .open_in_mode(Mode::ReadOnly)
+            .unwrap();
+        let cold_store = storage.get_cold_store().unwrap();
+        assert_eq!(
+            cold_store.get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY).unwrap(),
+            Some(tip(3))
+        );
+        assert_eq!(cold_store.get(DBCol::State, b"key").unwrap().as_deref(), Some(&b"value"[..]));
+    }
+
+    #[test]
+    fn test_backup_unsupported_backend() {
+        let (home_dir, _) = NodeStorage::test_opener();
+        let config = StoreConfig { backend: DbBackend::Redb, ..StoreConfig::test_config() };
+        let storage = NodeStorage::opener(home_dir.path(), false, &config, None).open().unwrap();
+        set_head(&storage, 1);
+        assert!(ensure_backups_supported(&storage).is_err());
+        assert!(create_backup(&storage, &home_dir.path().join("backups"), None).is_err());
+    }
+}
@@ -3,6 +3,9 @@ use crate::db::{ColdDB, COLD_HEAD_KEY, HEAD_KEY};
 use crate::{metrics, DBCol, DBTransaction, Database, Store, TrieChanges};
 
 use borsh::BorshDeserialize;
+use near_primitives::account_activity::{
+    account_activity_key_from_height_key, get_account_activity_height_prefix,
+};
 use near_primitives::block::{Block, BlockHeader, Tip};
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
@@ -391,6 +394,15 @@ fn get_keys_from_store(
         })
         .collect::<io::Result<Vec<ShardChunk>>>()?;
 
+    let account_activity_height_prefix =
+        get_account_activity_height_prefix(block.header().height());
+    let mut account_activity_height_keys = vec![];
+    store.iter_prefix_with_callback_for_cold(
+        DBCol::AccountActivityByHeight,
+        &account_activity_height_prefix,
+        |key| account_activity_height_keys.push(key.to_vec()),
+    )?;
+
     for key_type in DBKeyType::iter() {
         if key_type == DBKeyType::TrieNodeOrValueHash {
             // The TrieNodeOrValueHash is only used in the State column, which is handled separately.
@@ -466,6 +478,11 @@ fn get_keys_from_store(
                         })
                         .collect()
                 }
+                DBKeyType::AccountActivityKey => account_activity_height_keys
+                    .iter()
+                    .map(|key| account_activity_key_from_height_key(key))
+                    .collect::<io::Result<_>>()?,
+                DBKeyType::AccountActivityHeightKey => account_activity_height_keys.clone(),
                 _ => {
                     vec![]
                 }
@@ -293,6 +293,22 @@ pub enum DBCol {
     /// Witnesses with the lowest index are garbage collected first.
     /// u64 -> LatestWitnessesKey
     LatestWitnessesByIndex,
+    /// Transactions and receipts indexed by the accounts taking part in them.
+    /// Only populated if `StoreConfig::save_account_activity` is enabled.
+    /// - *Rows*: `AccountId || ',' || BlockHeight (u64, big endian) || index (u32, big endian)`
+    /// - *Column type*: `near_primitives::account_activity::AccountActivity`
+    AccountActivity,
+    /// Rows of `DBCol::AccountActivity` ordered by block height, used to
+    /// garbage collect the records of a block.
+    /// - *Rows*: `BlockHeight (u64, big endian) || AccountId || ',' || index (u32, big endian)`
+    /// - *Column type*: empty
+    AccountActivityByHeight,
+    /// Pending transactions of the transaction pool, persisted so that they
+    /// survive restarts of the node.  The column is rewritten periodically and
+    /// on shutdown, and read back on startup.
+    /// - *Rows*: transaction hash
+    /// - *Column type*: `SignedTransaction`
+    TransactionPool,
     /// Column to store data for Epoch Sync.
     /// Does not contain data for genesis epoch.
     /// - *Rows*: `epoch_id`
@@ -334,6 +350,8 @@ pub enum DBKeyType {
     ColumnId,
     LatestWitnessesKey,
     LatestWitnessIndex,
+    AccountActivityKey,
+    AccountActivityHeightKey,
 }
 
 impl DBCol {
@@ -445,7 +463,9 @@ impl DBCol {
             | DBCol::StateChangesForSplitStates
             | DBCol::StateHeaders
             | DBCol::TransactionResultForBlock
-            | DBCol::Transactions => true,
+            | DBCol::Transactions
+            | DBCol::AccountActivity
+            | DBCol::AccountActivityByHeight => true,
 
             // TODO
             DBCol::ChallengedBlocks => false,
@@ -471,6 +491,8 @@ impl DBCol {
             // LatestChunkStateWitnesses stores the last N observed witnesses, used only for debugging.
             DBCol::LatestChunkStateWitnesses => false,
             DBCol::LatestWitnessesByIndex => false,
+            // TransactionPool only keeps the pending transactions of this node.
+            DBCol::TransactionPool => false,
             // Deprecated.
             DBCol::_ReceiptIdToShardId => false,
 
@@ -507,7 +529,7 @@ impl DBCol {
     }
 
     /// Whether this column exists in cold storage.
-    pub(crate) const fn is_in_colddb(&self) -> bool {
+    pub const fn is_in_colddb(&self) -> bool {
         matches!(*self, DBCol::DbVersion | DBCol::BlockMisc) || self.is_cold()
     }
 
@@ -574,6 +596,9 @@ impl DBCol {
             DBCol::StateTransitionData => &[DBKeyType::BlockHash, DBKeyType::ShardId],
             DBCol::LatestChunkStateWitnesses => &[DBKeyType::LatestWitnessesKey],
             DBCol::LatestWitnessesByIndex => &[DBKeyType::LatestWitnessIndex],
+            DBCol::AccountActivity => &[DBKeyType::AccountActivityKey],
+            DBCol::AccountActivityByHeight => &[DBKeyType::AccountActivityHeightKey],
+            DBCol::TransactionPool => &[DBKeyType::TransactionHash],
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => &[DBKeyType::EpochId],
         }
@@ -13,6 +13,11 @@ pub struct StoreConfig {
     /// database.
     pub path: Option<std::path::PathBuf>,
 
+    /// Storage engine of the database.  Defaults to RocksDB.  An existing
+    /// database can be converted to another backend with the `neard database
+    /// convert-backend` command.
+    pub backend: DbBackend,
+
     /// Collect internal storage layer statistics.
     /// Minor performance impact is expected.
     pub enable_statistics: bool,
@@ -69,6 +74,14 @@ pub struct StoreConfig {
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     /// If true, load mem trie for each shard being tracked; this has priority over `load_mem_tries_for_shards`.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// If true, in-memory tries are saved to `memtrie_snapshot` in the
+    /// database directory on shutdown and loaded from there on the next start,
+    /// instead of being rebuilt from flat storage.
+    pub save_mem_trie_snapshots: bool,
+    /// If true, identical in-memory trie nodes are stored only once.  This
+    /// saves memory when updates recreate nodes which already exist, at the
+    /// cost of an index of 20 to 40 bytes per node.
+    pub deduplicate_mem_trie_nodes: bool,
 
     /// Path where to create RocksDB checkpoints during database migrations or
     /// `false` to disable that feature.
@@ -99,6 +112,12 @@ pub struct StoreConfig {
 
     // TODO (#9989): To be phased out in favor of state_snapshot_config
     pub state_snapshot_enabled: bool,
+
+    /// Index transactions and receipts by the accounts taking part in them in
+    /// `DBCol::AccountActivity`, which backs the `EXPERIMENTAL_account_activity`
+    /// RPC method.  Meant for archival nodes, on other nodes the index is
+    /// garbage collected together with the blocks.
+    pub save_account_activity: bool,
 }
 
 /// Config used to control state snapshot creation. This is used for state sync and resharding.
@@ -126,6 +145,32 @@ pub enum MigrationSnapshot {
     Path(std::path::PathBuf),
 }
 
+/// Storage engine backing a database.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumString,
+    strum::IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DbBackend {
+    /// RocksDB, an LSM tree.
+    #[default]
+    RocksDb,
+    /// redb, a copy-on-write B-tree stored in a single file.  It has no
+    /// background compactions, which makes read latencies more predictable
+    /// at the cost of slower writes.
+    Redb,
+}
+
 /// Mode in which to open the storage.
 #[derive(Clone, Copy)]
 pub enum Mode {
@@ -189,6 +234,7 @@ impl Default for StoreConfig {
     fn default() -> Self {
         Self {
             path: None,
+            backend: DbBackend::default(),
             enable_statistics: false,
             enable_statistics_export: true,
 
@@ -273,6 +319,8 @@ impl Default for StoreConfig {
             // requires more RAM and takes several minutes on startup.
             load_mem_tries_for_shards: Default::default(),
             load_mem_tries_for_tracked_shards: false,
+            save_mem_trie_snapshots: false,
+            deduplicate_mem_trie_nodes: false,
 
             migration_snapshot: Default::default(),
 
@@ -280,6 +328,8 @@ impl Default for StoreConfig {
 
             // TODO: To be phased out in favor of state_snapshot_config
             state_snapshot_enabled: false,
+
+            save_account_activity: false,
         }
     }
 }
@@ -6,6 +6,7 @@ pub(crate) mod rocksdb;
 
 mod colddb;
 mod mixeddb;
+mod redb;
 mod splitdb;
 
 pub mod refcount;
@@ -16,6 +17,8 @@ mod database_tests;
 
 pub use self::colddb::ColdDB;
 pub use self::mixeddb::{MixedDB, ReadOrder};
+pub use self::redb::RedbDB;
+pub use self::rocksdb::backup::BackupCheckpoint;
 pub use self::rocksdb::RocksDB;
 pub use self::splitdb::SplitDB;
 
@@ -245,6 +248,11 @@ pub trait Database: Sync + Send {
     /// Returns statistics about the database if available.
     fn get_store_statistics(&self) -> Option<StoreStatistics>;
 
+    /// Returns the total size of the data stored in the database if known.
+    fn get_live_data_size(&self) -> Option<u64> {
+        None
+    }
+
     /// Create checkpoint in provided path
     fn create_checkpoint(
         &self,
@@ -252,6 +260,21 @@ pub trait Database: Sync + Send {
         columns_to_keep: Option<&[DBCol]>,
     ) -> anyhow::Result<()>;
 
+    /// Creates a checkpoint of the database from which incremental backups
+    /// can be made.
+    ///
+    /// Backups stored in the same directory share their data, so only what
+    /// changed since the previous backup is copied.  Returns an error if the
+    /// database doesn’t support backups, see [`Database::supports_backups`].
+    fn create_backup_checkpoint(&self) -> anyhow::Result<BackupCheckpoint> {
+        anyhow::bail!("the database doesn’t support backups")
+    }
+
+    /// Whether [`Database::create_backup_checkpoint`] is supported.
+    fn supports_backups(&self) -> bool {
+        false
+    }
+
     /// If this is a test database, return a copy of the entire database.
     /// Otherwise return None.
     fn copy_if_test(&self) -> Option<Arc<dyn Database>> {
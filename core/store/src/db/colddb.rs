@@ -1,7 +1,7 @@
 use near_o11y::{log_assert, log_assert_fail};
 
 use crate::db::refcount::set_refcount;
-use crate::db::{DBIterator, DBOp, DBSlice, DBTransaction, Database};
+use crate::db::{BackupCheckpoint, DBIterator, DBOp, DBSlice, DBTransaction, Database};
 use crate::DBCol;
 
 /// A database which provides access to the cold storage.
@@ -111,6 +111,10 @@ impl Database for ColdDB {
         self.cold.get_store_statistics()
     }
 
+    fn get_live_data_size(&self) -> Option<u64> {
+        self.cold.get_live_data_size()
+    }
+
     fn create_checkpoint(
         &self,
         path: &std::path::Path,
@@ -118,6 +122,14 @@ impl Database for ColdDB {
     ) -> anyhow::Result<()> {
         self.cold.create_checkpoint(path, columns_to_keep)
     }
+
+    fn create_backup_checkpoint(&self) -> anyhow::Result<BackupCheckpoint> {
+        self.cold.create_backup_checkpoint()
+    }
+
+    fn supports_backups(&self) -> bool {
+        self.cold.supports_backups()
+    }
 }
 
 /// Adjust database operation to be performed on cold storage.
@@ -6,21 +6,23 @@ mod tests {
     use std::sync::Arc;
 
     use crate::{
-        db::{DBTransaction, Database, TestDB},
-        DBCol, NodeStorage,
+        db::{DBTransaction, Database, RedbDB, TestDB},
+        DBCol, Mode, NodeStorage,
     };
 
-    // Returns test & rocksDB databases.
-    fn test_and_rocksdb() -> Vec<Arc<dyn Database>> {
+    // Returns test, rocksDB & redb databases.
+    fn test_databases() -> Vec<Arc<dyn Database>> {
         let (_tmp_dir, opener) = NodeStorage::test_opener();
         let store = opener.open().unwrap().get_hot_store();
-        vec![TestDB::new(), store.storage.clone()]
+        let redb_dir = tempfile::tempdir().unwrap();
+        let redb = RedbDB::open(redb_dir.path(), Mode::Create).unwrap();
+        vec![TestDB::new(), store.storage.clone(), Arc::new(redb)]
     }
 
     /// Tests the behavior of the iterators. Iterators don't really work over cold storage, so we're not testing it here.
     #[test]
     fn test_db_iter() {
-        for db in test_and_rocksdb() {
+        for db in test_databases() {
             let mut transaction = DBTransaction::new();
             transaction.insert(DBCol::Block, "a".into(), "val_a".into());
             transaction.insert(DBCol::Block, "aa".into(), "val_aa".into());
@@ -0,0 +1,409 @@
+use crate::config::Mode;
+use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError};
+use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database};
+use crate::{metadata, metrics, DBCol, StoreConfig, StoreStatistics};
+use ::redb::{ReadableTable, TableDefinition};
+use anyhow::Context;
+use once_cell::sync::OnceCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use strum::IntoEnumIterator;
+
+/// Name of the file holding the database inside of the database directory.
+const DB_FILE_NAME: &str = "data.redb";
+
+type Table<'txn> = redb::Table<'txn, &'static [u8], &'static [u8]>;
+type ReadOnlyTable = redb::ReadOnlyTable<&'static [u8], &'static [u8]>;
+
+/// Returns definition of the table holding given column.
+///
+/// Every column is stored in a separate table named after the column.
+fn table_definition(col: DBCol) -> TableDefinition<'static, &'static [u8], &'static [u8]> {
+    TableDefinition::new(col.into())
+}
+
+/// Database backed by [redb](https://www.redb.org), a copy-on-write B-tree
+/// stored in a single file.
+///
+/// Unlike RocksDB, redb has no merge operator so reference counts are merged
+/// when writing.  Transactions are committed with immediate durability, so
+/// every successful write is persisted.  Point reads share a read transaction
+/// which is replaced after each write.
+pub struct RedbDB {
+    db: redb::Database,
+    read_only: bool,
+    read_cache: RwLock<ReadCache>,
+}
+
+struct ReadCache {
+    /// Incremented by every write, so that a read transaction started before
+    /// a write isn't cached after it.
+    generation: u64,
+    snapshot: Option<Arc<ReadSnapshot>>,
+}
+
+/// Read transaction with the tables opened in it so far.
+struct ReadSnapshot {
+    txn: Mutex<redb::ReadTransaction>,
+    tables: enum_map::EnumMap<DBCol, OnceCell<Option<ReadOnlyTable>>>,
+}
+
+impl ReadSnapshot {
+    fn table(&self, col: DBCol) -> io::Result<Option<&ReadOnlyTable>> {
+        let table =
+            self.tables[col].get_or_try_init(|| open_read_table(&self.txn.lock().unwrap(), col))?;
+        Ok(table.as_ref())
+    }
+}
+
+/// Opens the table of given column for reading.
+///
+/// Returns `None` if the table doesn’t exist, which may happen if the
+/// database was created by an older version and opened in read-only mode.
+fn open_read_table(txn: &redb::ReadTransaction, col: DBCol) -> io::Result<Option<ReadOnlyTable>> {
+    match txn.open_table(table_definition(col)) {
+        Ok(table) => Ok(Some(table)),
+        Err(redb::TableError::TableDoesNotExist(_)) => Ok(None),
+        Err(err) => Err(io::Error::other(err)),
+    }
+}
+
+impl RedbDB {
+    /// Opens the database.
+    ///
+    /// `path` is the database directory, as for [`crate::db::RocksDB::open`].
+    /// The directory is created if missing and `mode` allows creating the
+    /// database.  In read-only mode all writes fail.  Note that, unlike
+    /// RocksDB, redb doesn’t allow opening a database which is open elsewhere,
+    /// even for reading.
+    pub fn open(path: &Path, mode: Mode) -> io::Result<Self> {
+        let file = path.join(DB_FILE_NAME);
+        let db = if mode.can_create() {
+            std::fs::create_dir_all(path)?;
+            redb::Builder::new().create(&file)
+        } else {
+            redb::Builder::new().open(&file)
+        }
+        .map_err(io::Error::other)?;
+        let db = Self {
+            db,
+            read_only: mode.read_only(),
+            read_cache: RwLock::new(ReadCache { generation: 0, snapshot: None }),
+        };
+        if !db.read_only {
+            // Create tables of all the columns so that readers don’t have to
+            // deal with missing tables.
+            let txn = db.db.begin_write().map_err(io::Error::other)?;
+            for col in DBCol::iter() {
+                txn.open_table(table_definition(col)).map_err(io::Error::other)?;
+            }
+            txn.commit().map_err(io::Error::other)?;
+        }
+        Ok(db)
+    }
+
+    /// Returns whether there’s a redb database in given directory.
+    pub fn exists(path: &Path) -> bool {
+        path.join(DB_FILE_NAME).is_file()
+    }
+
+    /// Returns metadata of the database or `None` if the db doesn’t exist.
+    pub(crate) fn get_metadata(path: &Path) -> io::Result<Option<metadata::DbMetadata>> {
+        if !Self::exists(path) {
+            return Ok(None);
+        }
+        let db = Self::open(path, Mode::ReadOnly)?;
+        Some(metadata::DbMetadata::read(&db)).transpose()
+    }
+
+    /// Possibly creates a new migration snapshot of the database.
+    ///
+    /// This is the redb counterpart of [`Snapshot::new`]; the snapshot is
+    /// a copy of the database made with [`Database::create_checkpoint`].
+    pub(crate) fn snapshot(
+        db_path: &Path,
+        config: &StoreConfig,
+    ) -> Result<Snapshot, SnapshotError> {
+        let snapshot_path = match config.migration_snapshot.get_path(db_path) {
+            Some(snapshot_path) => snapshot_path,
+            None => return Ok(Snapshot::none()),
+        };
+
+        tracing::info!(target: "db", snapshot_path=%snapshot_path.display(),
+                       "Creating database snapshot");
+        if snapshot_path.exists() {
+            return Err(SnapshotError::AlreadyExists(snapshot_path));
+        }
+
+        let db = Self::open(db_path, Mode::ReadWriteExisting)?;
+        db.create_checkpoint(&snapshot_path, None).map_err(io::Error::other)?;
+        Ok(Snapshot(Some(snapshot_path)))
+    }
+
+    /// Returns the read transaction shared by point reads, starting a new
+    /// one if the database was written to since the last one started.
+    fn read_snapshot(&self) -> io::Result<Arc<ReadSnapshot>> {
+        let generation = {
+            let cache = self.read_cache.read().unwrap();
+            if let Some(snapshot) = &cache.snapshot {
+                return Ok(snapshot.clone());
+            }
+            cache.generation
+        };
+        let txn = self.db.begin_read().map_err(io::Error::other)?;
+        let snapshot = Arc::new(ReadSnapshot { txn: Mutex::new(txn), tables: Default::default() });
+        let mut cache = self.read_cache.write().unwrap();
+        if cache.generation == generation {
+            cache.snapshot = Some(snapshot.clone());
+        }
+        Ok(snapshot)
+    }
+
+    /// Iterators use a read transaction of their own, so that they see a
+    /// consistent view of the database however long they live.
+    fn iter_raw_bytes_internal(
+        &self,
+        col: DBCol,
+        lower_bound: Bound<&[u8]>,
+        upper_bound: Bound<&[u8]>,
+    ) -> DBIterator<'static> {
+        let table = match self
+            .db
+            .begin_read()
+            .map_err(io::Error::other)
+            .and_then(|txn| open_read_table(&txn, col))
+        {
+            Ok(Some(table)) => table,
+            Ok(None) => return Box::new(std::iter::empty()),
+            Err(err) => return Box::new(std::iter::once(Err(err))),
+        };
+        let range = match table.range::<&[u8]>((lower_bound, upper_bound)) {
+            Ok(range) => range,
+            Err(err) => return Box::new(std::iter::once(Err(io::Error::other(err)))),
+        };
+        Box::new(range.map(|item| {
+            let (key, value) = item.map_err(io::Error::other)?;
+            Ok((Box::from(key.value()), Box::from(value.value())))
+        }))
+    }
+
+    fn apply_op(table: &mut Table, op: DBOp) -> Result<(), redb::StorageError> {
+        match op {
+            DBOp::Set { key, value, .. } => {
+                table.insert(key.as_slice(), value.as_slice())?;
+            }
+            DBOp::Insert { col, key, value } => {
+                if cfg!(debug_assertions) {
+                    if let Some(old_value) = table.get(key.as_slice())? {
+                        super::assert_no_overwrite(col, &key, &value, old_value.value())
+                    }
+                }
+                table.insert(key.as_slice(), value.as_slice())?;
+            }
+            DBOp::UpdateRefcount { key, value, .. } => {
+                let existing = table.get(key.as_slice())?.map(|value| value.value().to_vec());
+                let operands = [value.as_slice()];
+                let merged = refcount::refcount_merge(existing.as_deref(), operands);
+                if merged.is_empty() {
+                    table.remove(key.as_slice())?;
+                } else {
+                    debug_assert!(
+                        refcount::decode_value_with_rc(&merged).1 > 0,
+                        "Inserting value with non-positive refcount"
+                    );
+                    table.insert(key.as_slice(), merged.as_slice())?;
+                }
+            }
+            DBOp::Delete { key, .. } => {
+                table.remove(key.as_slice())?;
+            }
+            DBOp::DeleteAll { .. } => table.retain(|_, _| false)?,
+            DBOp::DeleteRange { from, to, .. } => {
+                table.retain_in(from.as_slice()..to.as_slice(), |_, _| false)?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Database for RedbDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let timer =
+            metrics::DATABASE_OP_LATENCY_HIST.with_label_values(&["get", col.into()]).start_timer();
+        let snapshot = self.read_snapshot()?;
+        let Some(table) = snapshot.table(col)? else {
+            return Ok(None);
+        };
+        let value = table.get(key).map_err(io::Error::other)?;
+        let result = value.map(|value| DBSlice::from_vec(value.value().to_vec()));
+        timer.observe_duration();
+        Ok(result)
+    }
+
+    fn iter_raw_bytes(&self, col: DBCol) -> DBIterator {
+        self.iter_raw_bytes_internal(col, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    fn iter(&self, col: DBCol) -> DBIterator {
+        let iter = self.iter_raw_bytes_internal(col, Bound::Unbounded, Bound::Unbounded);
+        refcount::iter_with_rc_logic(col, iter)
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        let iter = self
+            .iter_raw_bytes_internal(col, Bound::Included(key_prefix), Bound::Unbounded)
+            .take_while(move |item| {
+                item.as_ref().map_or(true, |(key, _)| key.starts_with(key_prefix))
+            });
+        refcount::iter_with_rc_logic(col, iter)
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        let lower = lower_bound.map_or(Bound::Unbounded, Bound::Included);
+        let upper = upper_bound.map_or(Bound::Unbounded, Bound::Excluded);
+        refcount::iter_with_rc_logic(col, self.iter_raw_bytes_internal(col, lower, upper))
+    }
+
+    #[tracing::instrument(target = "store::db::redb", level = "trace", "RedbDB::write", skip_all)]
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::Error::other("database is open in read-only mode"));
+        }
+        let mut txn = self.db.begin_write().map_err(io::Error::other)?;
+        txn.set_durability(redb::Durability::Immediate);
+        {
+            // A table can be open only once per transaction so keep the tables
+            // open until all operations are applied.
+            let mut tables = HashMap::new();
+            for op in transaction.ops {
+                let table = match tables.entry(op.col()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(
+                        txn.open_table(table_definition(op.col())).map_err(io::Error::other)?,
+                    ),
+                };
+                Self::apply_op(table, op).map_err(io::Error::other)?;
+            }
+        }
+        txn.commit().map_err(io::Error::other)?;
+        let mut cache = self.read_cache.write().unwrap();
+        cache.generation += 1;
+        cache.snapshot = None;
+        Ok(())
+    }
+
+    /// redb reuses freed pages in place so there’s no need for compaction.
+    /// Shrinking the file requires exclusive access to the database which the
+    /// store cannot provide thus this is a no-op.
+    fn compact(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Every transaction is persisted when committed, so there’s nothing to
+    /// flush.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        None
+    }
+
+    /// Copies the database into a new one in `path`.
+    ///
+    /// The copy is made from a single read transaction so it’s consistent
+    /// even if the database is written to in the meantime.
+    #[tracing::instrument(
+        target = "store::db::redb",
+        level = "debug",
+        "RedbDB::create_checkpoint",
+        skip_all,
+        fields(path = %path.display()),
+    )]
+    fn create_checkpoint(
+        &self,
+        path: &std::path::Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        std::fs::create_dir_all(path)?;
+        let file = path.join(DB_FILE_NAME);
+        anyhow::ensure!(!file.exists(), "checkpoint at {} already exists", path.display());
+        let checkpoint = redb::Database::create(&file)
+            .with_context(|| format!("failed to create checkpoint at {}", path.display()))?;
+        let read_txn = self.db.begin_read()?;
+        for col in DBCol::iter() {
+            // We need to keep DbVersion because it's expected to be there when
+            // we check the metadata in DBOpener::get_metadata()
+            if col != DBCol::DbVersion && columns_to_keep.is_some_and(|cols| !cols.contains(&col)) {
+                continue;
+            }
+            let source = match read_txn.open_table(table_definition(col)) {
+                Ok(table) => table,
+                Err(redb::TableError::TableDoesNotExist(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let write_txn = checkpoint.begin_write()?;
+            {
+                let mut target = write_txn.open_table(table_definition(col))?;
+                for item in source.iter()? {
+                    let (key, value) = item?;
+                    target.insert(key.value(), value.value())?;
+                }
+            }
+            write_txn.commit()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refcount_and_delete_range() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let db = RedbDB::open(tmp_dir.path(), Mode::ReadWrite).unwrap();
+        let value = |rc: i64| [&b"value"[..], &rc.to_le_bytes()].concat();
+
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(DBCol::State, b"key".to_vec(), value(1));
+        transaction.update_refcount(DBCol::State, b"key".to_vec(), value(2));
+        db.write(transaction).unwrap();
+        assert_eq!(db.get_raw_bytes(DBCol::State, b"key").unwrap().as_deref(), Some(&value(3)[..]));
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(DBCol::State, b"key".to_vec(), value(-3));
+        db.write(transaction).unwrap();
+        assert_eq!(db.get_raw_bytes(DBCol::State, b"key").unwrap(), None);
+
+        let mut transaction = DBTransaction::new();
+        for key in [b"a", b"b", b"c", b"d"] {
+            transaction.set(DBCol::Block, key.to_vec(), key.to_vec());
+        }
+        transaction.delete_range(DBCol::Block, b"b".to_vec(), b"d".to_vec());
+        db.write(transaction).unwrap();
+        let keys: Vec<_> = db.iter(DBCol::Block).map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, [&b"a"[..], &b"d"[..]].map(Box::from));
+
+        // Reads see the latest write.
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, b"a".to_vec(), b"new".to_vec());
+        db.write(transaction).unwrap();
+        assert_eq!(db.get_raw_bytes(DBCol::Block, b"a").unwrap().as_deref(), Some(&b"new"[..]));
+
+        // The database is persisted and can't be written to in read-only mode.
+        drop(db);
+        let db = RedbDB::open(tmp_dir.path(), Mode::ReadOnly).unwrap();
+        assert_eq!(db.iter(DBCol::Block).count(), 2);
+        assert!(db.write(DBTransaction::new()).is_err());
+    }
+}
@@ -13,6 +13,7 @@ use std::path::Path;
 use strum::IntoEnumIterator;
 use tracing::warn;
 
+pub(crate) mod backup;
 mod instance_tracker;
 pub(crate) mod snapshot;
 
@@ -54,6 +55,11 @@ pub struct RocksDB {
     /// want.
     cf_handles: enum_map::EnumMap<DBCol, Option<std::ptr::NonNull<ColumnFamily>>>,
 
+    /// Configuration the database was opened with, needed to open checkpoints
+    /// of the database the same way.
+    store_config: StoreConfig,
+    temp: Temperature,
+
     // RAII-style of keeping track of the number of instances of RocksDB and
     // counting total sum of max_open_files.
     _instance_tracker: instance_tracker::InstanceTracker,
@@ -119,7 +125,14 @@ impl RocksDB {
             .map_err(io::Error::other)?;
         let (db, db_opt) = Self::open_db(path, store_config, mode, temp, columns)?;
         let cf_handles = Self::get_cf_handles(&db, columns);
-        Ok(Self { db, db_opt, cf_handles, _instance_tracker: counter })
+        Ok(Self {
+            db,
+            db_opt,
+            cf_handles,
+            store_config: store_config.clone(),
+            temp,
+            _instance_tracker: counter,
+        })
     }
 
     /// Opens the database with given column families configured.
@@ -448,6 +461,18 @@ impl Database for RocksDB {
         }
     }
 
+    /// Returns the total size of the live SST files of all the columns.
+    fn get_live_data_size(&self) -> Option<u64> {
+        let mut size = 0;
+        for (_, handle) in self.cf_handles() {
+            size += self
+                .db
+                .property_int_value_cf(handle, ::rocksdb::properties::LIVE_SST_FILES_SIZE)
+                .ok()??;
+        }
+        Some(size)
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "debug",
@@ -494,6 +519,27 @@ impl Database for RocksDB {
         }
         Ok(())
     }
+
+    #[tracing::instrument(
+        target = "store::db::rocksdb",
+        level = "info",
+        "RocksDB::create_backup_checkpoint",
+        skip_all
+    )]
+    fn create_backup_checkpoint(&self) -> anyhow::Result<backup::BackupCheckpoint> {
+        let columns =
+            self.cf_handles.iter().filter(|(_, cf)| cf.is_some()).map(|(column, _)| column);
+        backup::BackupCheckpoint::new(
+            &self.db,
+            &columns.collect_vec(),
+            &self.store_config,
+            self.temp,
+        )
+    }
+
+    fn supports_backups(&self) -> bool {
+        true
+    }
 }
 
 fn cf_descriptors(
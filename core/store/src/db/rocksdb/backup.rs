@@ -0,0 +1,126 @@
+//! Incremental backups of RocksDB databases.
+//!
+//! Backups are made with RocksDB’s backup engine.  All the backups of
+//! a database live in the same directory and share the SST files they have in
+//! common, so creating a backup only copies the files created since the
+//! previous one.
+use super::{cf_descriptors, col_name, common_rocksdb_options};
+use crate::{DBCol, StoreConfig, Temperature};
+use ::rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
+use ::rocksdb::checkpoint::Checkpoint;
+use ::rocksdb::{Env, DB};
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+fn open_engine(backup_dir: &Path) -> anyhow::Result<BackupEngine> {
+    let options = BackupEngineOptions::new(backup_dir)?;
+    let env = Env::new()?;
+    BackupEngine::open(&options, &env)
+        .with_context(|| format!("failed to open backups at {}", backup_dir.display()))
+}
+
+/// Checkpoint of a database, from which a consistent backup can be made even
+/// though the database keeps being written to.
+///
+/// The checkpoint is created next to the database to hard link its files and
+/// is deleted when dropped.
+pub struct BackupCheckpoint {
+    db: Option<DB>,
+    dir: PathBuf,
+}
+
+impl BackupCheckpoint {
+    /// Creates a checkpoint of the database and opens it with the `columns`,
+    /// `store_config` and `temp` the database has been opened with.
+    pub(crate) fn new(
+        db: &DB,
+        columns: &[DBCol],
+        store_config: &StoreConfig,
+        temp: Temperature,
+    ) -> anyhow::Result<Self> {
+        let dir = db.path().with_extension("backup-checkpoint");
+        if dir.exists() {
+            // Left over by a backup which was interrupted.
+            std::fs::remove_dir_all(&dir)?;
+        }
+        Checkpoint::new(db)?
+            .create_checkpoint(&dir)
+            .with_context(|| format!("failed to create checkpoint at {}", dir.display()))?;
+        // From now on the directory is deleted on error by `drop`.
+        let mut checkpoint = Self { db: None, dir };
+        let cfs = cf_descriptors(columns, store_config, temp);
+        checkpoint.db = Some(
+            DB::open_cf_descriptors(&common_rocksdb_options(), &checkpoint.dir, cfs).with_context(
+                || format!("failed to open checkpoint at {}", checkpoint.dir.display()),
+            )?,
+        );
+        Ok(checkpoint)
+    }
+
+    fn db(&self) -> &DB {
+        self.db.as_ref().unwrap()
+    }
+
+    /// Returns the value of `key` in `col` in the checkpoint.
+    pub fn get(&self, col: DBCol, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let cf = self.db().cf_handle(col_name(col)).with_context(|| format!("no column {col}"))?;
+        Ok(self.db().get_cf(cf, key)?)
+    }
+
+    /// Creates a new backup of the checkpoint in `backup_dir` and returns its
+    /// id.
+    pub fn backup(&self, backup_dir: &Path) -> anyhow::Result<u32> {
+        std::fs::create_dir_all(backup_dir)?;
+        let mut engine = open_engine(backup_dir)?;
+        // Memtables are flushed beforehand so that the backup doesn’t depend on
+        // the write-ahead log.
+        engine
+            .create_new_backup_flush(self.db(), true)
+            .with_context(|| format!("failed to create backup at {}", backup_dir.display()))?;
+        backup_ids(&engine).into_iter().max().context("no backup was created")
+    }
+}
+
+impl Drop for BackupCheckpoint {
+    fn drop(&mut self) {
+        // The database must be closed before its files are deleted.
+        drop(self.db.take());
+        if let Err(err) = std::fs::remove_dir_all(&self.dir) {
+            tracing::warn!(target: "store", dir = %self.dir.display(), ?err, "Failed to delete backup checkpoint");
+        }
+    }
+}
+
+fn backup_ids(engine: &BackupEngine) -> Vec<u32> {
+    engine.get_backup_info().iter().map(|info| info.backup_id).collect()
+}
+
+/// Returns ids of the backups stored in the directory.
+pub(crate) fn list_backups(backup_dir: &Path) -> anyhow::Result<Vec<u32>> {
+    Ok(backup_ids(&open_engine(backup_dir)?))
+}
+
+/// Deletes all but the `num_backups_to_keep` latest backups, along with the
+/// files no other backup needs.
+pub(crate) fn purge_old_backups(
+    backup_dir: &Path,
+    num_backups_to_keep: usize,
+) -> anyhow::Result<()> {
+    open_engine(backup_dir)?.purge_old_backups(num_backups_to_keep)?;
+    Ok(())
+}
+
+/// Checks the backup and restores it as a new database in `db_path`.
+pub(crate) fn restore_backup(
+    backup_dir: &Path,
+    backup_id: u32,
+    db_path: &Path,
+) -> anyhow::Result<()> {
+    let mut engine = open_engine(backup_dir)?;
+    engine
+        .verify_backup(backup_id)
+        .with_context(|| format!("backup {backup_id} at {} is corrupted", backup_dir.display()))?;
+    engine
+        .restore_from_backup(db_path, db_path, &RestoreOptions::default(), backup_id)
+        .with_context(|| format!("failed to restore backup {backup_id} to {}", db_path.display()))
+}
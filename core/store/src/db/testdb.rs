@@ -128,6 +128,12 @@ impl Database for TestDB {
         self.stats.read().unwrap().clone()
     }
 
+    fn get_live_data_size(&self) -> Option<u64> {
+        let db = self.db.read().unwrap();
+        let size = db.values().flatten().map(|(key, value)| key.len() + value.len()).sum::<usize>();
+        Some(size as u64)
+    }
+
     fn create_checkpoint(
         &self,
         _path: &std::path::Path,
@@ -57,6 +57,30 @@ impl FlatStorageChunkView {
         store_helper::iter_flat_state_entries(self.flat_storage.shard_uid(), &self.store, from, to)
     }
 
+    /// Returns iterator over the state corresponding to
+    /// `FlatStorageChunkView::block_hash` in the range of keys `[from, to)`,
+    /// ordered by key.
+    ///
+    /// Unlike `iter_flat_state_entries`, this takes into account changes of
+    /// the blocks after flat head.
+    pub fn iter_range<'a>(
+        &'a self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+    ) -> Result<FlatStateIterator<'a>, crate::StorageError> {
+        self.flat_storage.iter_range(&self.store, &self.block_hash, from, to)
+    }
+
+    /// Returns iterator over the keys starting with `prefix` in the state
+    /// corresponding to `FlatStorageChunkView::block_hash`, ordered by key.
+    pub fn iter_prefix<'a>(
+        &'a self,
+        prefix: &[u8],
+    ) -> Result<FlatStateIterator<'a>, crate::StorageError> {
+        let to = prefix_upper_bound(prefix);
+        self.iter_range(Some(prefix), to.as_deref())
+    }
+
     pub fn get_head_hash(&self) -> CryptoHash {
         self.flat_storage.get_head_hash()
     }
@@ -65,3 +89,16 @@ impl FlatStorageChunkView {
         self.flat_storage.shard_uid()
     }
 }
+
+/// Returns the smallest key greater than all keys starting with `prefix`, or
+/// `None` if there is no such key.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bound = prefix.to_vec();
+    while let Some(byte) = bound.pop() {
+        if byte != u8::MAX {
+            bound.push(byte + 1);
+            return Some(bound);
+        }
+    }
+    None
+}
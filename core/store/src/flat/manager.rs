@@ -85,6 +85,19 @@ impl FlatStorageManager {
         Ok(())
     }
 
+    /// Recreates the flat storages of all shards from the contents of the
+    /// database, dropping their cached deltas.  Used when the database was
+    /// changed underneath them, e.g. when a sandbox node is reverted to a
+    /// snapshot.
+    pub fn reload_flat_storages(&self) -> Result<(), StorageError> {
+        let mut flat_storages = self.0.flat_storages.lock().expect(POISONED_LOCK_ERR);
+        for (shard_uid, flat_storage) in flat_storages.iter_mut() {
+            tracing::debug!(target: "store", ?shard_uid, "Reloading flat storage for shard");
+            *flat_storage = FlatStorage::new(self.0.store.clone(), *shard_uid)?;
+        }
+        Ok(())
+    }
+
     /// Update flat storage for given processed or caught up block, which includes:
     /// - merge deltas from current flat storage head to new one given in
     /// `new_flat_head`;
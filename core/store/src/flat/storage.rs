@@ -1,6 +1,9 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
 
+use itertools::{EitherOrBoth, Itertools};
+
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
@@ -16,7 +19,7 @@ use crate::{Store, StoreUpdate};
 use super::delta::{CachedFlatStateDelta, FlatStateDelta};
 use super::metrics::FlatStorageMetrics;
 use super::store_helper;
-use super::types::FlatStorageError;
+use super::types::{FlatStateIterator, FlatStorageError};
 
 /// FlatStorage stores information on which blocks flat storage current supports key lookups on.
 /// Note that this struct is shared by multiple threads, the chain thread, threads that apply chunks,
@@ -342,6 +345,41 @@ impl FlatStorage {
         })?)
     }
 
+    /// Returns iterator over the state at `block_hash` in the range of keys
+    /// `[from, to)`, ordered by key.
+    ///
+    /// Flat state entries are merged with the changes of blocks between flat
+    /// head and `block_hash`.  Cached deltas only store key hashes, so full
+    /// changes are read from disk; they are few compared to the flat state.
+    pub(crate) fn iter_range<'a>(
+        &self,
+        store: &'a Store,
+        block_hash: &CryptoHash,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+    ) -> Result<FlatStateIterator<'a>, StorageError> {
+        let (blocks_to_head, flat_state) = self.iter_range_at_head(store, block_hash, from, to)?;
+        merge_block_changes(store, self.shard_uid(), &blocks_to_head, flat_state, from, to)
+    }
+
+    /// Returns the blocks between `block_hash` and flat head along with the
+    /// iterator over the flat state at flat head in the range of keys
+    /// `[from, to)`.  Both are taken under the lock, so that the iterator
+    /// observes the flat state at the same flat head as the blocks.
+    fn iter_range_at_head<'a>(
+        &self,
+        store: &'a Store,
+        block_hash: &CryptoHash,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+    ) -> Result<(Vec<CryptoHash>, FlatStateIterator<'a>), StorageError> {
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        let blocks_to_head =
+            guard.get_blocks_to_head(block_hash).map_err(|e| StorageError::from(e))?;
+        let flat_state = store_helper::iter_flat_state_entries(guard.shard_uid, store, from, to);
+        Ok((blocks_to_head, flat_state))
+    }
+
     // TODO(#11601): Direct call is DEPRECATED, consider removing non-strict mode.
     /// Update the head of the flat storage, including updating the flat state
     /// in memory and on disk and updating the flat state to reflect the state
@@ -505,6 +543,50 @@ impl FlatStorage {
     }
 }
 
+/// Merges the changes of `blocks_to_head` into `flat_state` in the range of
+/// keys `[from, to)`.
+///
+/// The deltas are read without holding the flat storage lock, so they may be
+/// removed meanwhile if flat head moves past them.  The block isn't supported
+/// by flat storage anymore then, and callers fall back to the trie.
+fn merge_block_changes<'a>(
+    store: &Store,
+    shard_uid: ShardUId,
+    blocks_to_head: &[CryptoHash],
+    flat_state: FlatStateIterator<'a>,
+    from: Option<&[u8]>,
+    to: Option<&[u8]>,
+) -> Result<FlatStateIterator<'a>, StorageError> {
+    let in_range =
+        |key: &[u8]| from.map_or(true, |from| key >= from) && to.map_or(true, |to| key < to);
+    // Blocks are applied from the oldest one, so that the most recent change
+    // of a key wins.
+    let mut changes = BTreeMap::new();
+    for block_hash in blocks_to_head.iter().rev() {
+        let block_changes = store_helper::get_delta_changes(store, shard_uid, *block_hash)?
+            .ok_or_else(|| {
+                StorageError::FlatStorageBlockNotSupported(format!(
+                    "delta for block {block_hash} was removed as flat head moved"
+                ))
+            })?;
+        changes.extend(block_changes.0.into_iter().filter(|(key, _)| in_range(key)));
+    }
+
+    let iter = flat_state
+        .merge_join_by(changes, |item, (key, _)| match item {
+            Ok((flat_state_key, _)) => flat_state_key.cmp(key),
+            // Errors are returned as soon as they are encountered.
+            Err(_) => Ordering::Less,
+        })
+        .filter_map(|item| match item {
+            EitherOrBoth::Left(item) => Some(item),
+            EitherOrBoth::Right((key, value)) | EitherOrBoth::Both(_, (key, value)) => {
+                value.map(|value| Ok((key, value)))
+            }
+        });
+    Ok(Box::new(iter))
+}
+
 fn missing_delta_error(block_hash: &CryptoHash) -> FlatStorageError {
     FlatStorageError::StorageInternalError(format!("delta does not exist for block {block_hash}"))
 }
@@ -515,7 +597,7 @@ mod tests {
         BlockWithChangesInfo, FlatStateChanges, FlatStateDelta, FlatStateDeltaMetadata,
     };
     use crate::flat::manager::FlatStorageManager;
-    use crate::flat::storage::FlatStorageInner;
+    use crate::flat::storage::{merge_block_changes, FlatStorageInner};
     use crate::flat::test_utils::MockChain;
     use crate::flat::types::FlatStorageError;
     use crate::flat::{store_helper, FlatStorageReadyStatus, FlatStorageStatus};
@@ -821,6 +903,160 @@ mod tests {
         );
     }
 
+    #[test]
+    fn flat_storage_iter_range() {
+        // 1. Create a chain with 3 blocks with no forks. Set flat head to be at block 0
+        //    and fill flat state with a few keys.
+        let chain = MockChain::linear_chain(3);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        for key in [vec![1], vec![2, 0], vec![2, 1], vec![3]] {
+            store_helper::set_flat_state_value(
+                &mut store_update,
+                shard_uid,
+                key,
+                Some(FlatStateValue::value_ref(&[0])),
+            );
+        }
+        // 2. Block 1 deletes &[2, 0] and adds &[2, 2]. Block 2 overwrites &[1], adds &[2, 0]
+        //    back and adds &[4].
+        let changes = [
+            FlatStateChanges::from([
+                (vec![2, 0], None),
+                (vec![2, 2], Some(FlatStateValue::value_ref(&[1]))),
+            ]),
+            FlatStateChanges::from([
+                (vec![1], Some(FlatStateValue::value_ref(&[2]))),
+                (vec![2, 0], Some(FlatStateValue::value_ref(&[2]))),
+                (vec![4], Some(FlatStateValue::value_ref(&[2]))),
+            ]),
+        ];
+        for (i, changes) in changes.into_iter().enumerate() {
+            let delta = FlatStateDelta {
+                changes,
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(i as BlockHeight + 1),
+                    prev_block_with_changes: None,
+                },
+            };
+            store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        }
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::new(store);
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let collect = |height: BlockHeight, from: Option<&[u8]>, to: Option<&[u8]>| {
+            let chunk_view =
+                flat_storage_manager.chunk_view(shard_uid, chain.get_block_hash(height)).unwrap();
+            chunk_view
+                .iter_range(from, to)
+                .unwrap()
+                .map(|item| {
+                    let (key, value) = item.unwrap();
+                    (key, value.to_value_ref().hash)
+                })
+                .collect::<Vec<_>>()
+        };
+        let entry = |key: &[u8], value: u8| (key.to_vec(), hash(&[value]));
+
+        // 3. Check that iteration at each block reflects the changes of all blocks up to it.
+        assert_eq!(
+            collect(0, None, None),
+            vec![entry(&[1], 0), entry(&[2, 0], 0), entry(&[2, 1], 0), entry(&[3], 0)]
+        );
+        assert_eq!(
+            collect(1, None, None),
+            vec![entry(&[1], 0), entry(&[2, 1], 0), entry(&[2, 2], 1), entry(&[3], 0)]
+        );
+        assert_eq!(
+            collect(2, None, None),
+            vec![
+                entry(&[1], 2),
+                entry(&[2, 0], 2),
+                entry(&[2, 1], 0),
+                entry(&[2, 2], 1),
+                entry(&[3], 0),
+                entry(&[4], 2)
+            ]
+        );
+
+        // 4. Check that the range bounds apply to both flat state and changes.
+        assert_eq!(
+            collect(2, Some(&[2, 1]), Some(&[4])),
+            vec![entry(&[2, 1], 0), entry(&[2, 2], 1), entry(&[3], 0)]
+        );
+        let chunk_view =
+            flat_storage_manager.chunk_view(shard_uid, chain.get_block_hash(1)).unwrap();
+        let keys =
+            chunk_view.iter_prefix(&[2]).unwrap().map(|item| item.unwrap().0).collect::<Vec<_>>();
+        assert_eq!(keys, vec![vec![2, 1], vec![2, 2]]);
+    }
+
+    #[test]
+    fn flat_storage_iter_range_flat_head_moved() {
+        // 1. Create a chain with 3 blocks with no forks. Set flat head to be at block 0,
+        //    and let blocks 1 and 2 overwrite the only key.
+        let chain = MockChain::linear_chain(3);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_helper::set_flat_state_value(
+            &mut store_update,
+            shard_uid,
+            vec![1],
+            Some(FlatStateValue::value_ref(&[0])),
+        );
+        for height in 1..=2 {
+            let delta = FlatStateDelta {
+                changes: FlatStateChanges::from([(
+                    vec![1],
+                    Some(FlatStateValue::value_ref(&[height as u8])),
+                )]),
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(height),
+                    prev_block_with_changes: None,
+                },
+            };
+            store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        }
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+
+        // 2. Move flat head past block 1 after the flat state iterator is taken but before
+        //    the deltas are read. Iteration falls back to the trie then.
+        let block_hash = chain.get_block_hash(2);
+        let (blocks_to_head, flat_state) =
+            flat_storage.iter_range_at_head(&store, &block_hash, None, None).unwrap();
+        flat_storage.update_flat_head(&chain.get_block_hash(1)).unwrap();
+        assert_matches!(
+            merge_block_changes(&store, shard_uid, &blocks_to_head, flat_state, None, None).err(),
+            Some(StorageError::FlatStorageBlockNotSupported(_))
+        );
+
+        // 3. Iterating again observes the new flat head.
+        let chunk_view = flat_storage_manager.chunk_view(shard_uid, block_hash).unwrap();
+        let values = chunk_view
+            .iter_range(None, None)
+            .unwrap()
+            .map(|item| item.unwrap().1.to_value_ref().hash)
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![hash(&[2])]);
+    }
+
     #[test]
     fn flat_storage_with_hops() {
         init_test_logger();
@@ -40,7 +40,9 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::{fmt, io};
 use strum;
+use strum::IntoEnumIterator;
 
+pub mod backup;
 pub mod cold_storage;
 mod columns;
 pub mod config;
@@ -56,7 +58,7 @@ mod sync_utils;
 pub mod test_utils;
 pub mod trie;
 
-pub use crate::config::{Mode, StoreConfig};
+pub use crate::config::{DbBackend, Mode, StoreConfig};
 pub use crate::opener::{
     checkpoint_hot_storage_and_cleanup_columns, StoreMigrator, StoreOpener, StoreOpenerError,
 };
@@ -93,6 +95,7 @@ const STATE_FILE_END_MARK: u8 = 255;
 ///
 /// Provides access to hot storage, cold storage and split storage. Typically
 /// users will want to use one of the above via the Store abstraction.
+#[derive(Clone)]
 pub struct NodeStorage {
     hot_storage: Arc<dyn Database>,
     cold_storage: Option<Arc<crate::db::ColdDB>>,
@@ -121,14 +124,11 @@ impl NodeStorage {
         StoreOpener::new(home_dir, archive, config, cold_config)
     }
 
-    /// Constructs new object backed by given database.
-    fn from_rocksdb(
-        hot_storage: crate::db::RocksDB,
-        cold_storage: Option<crate::db::RocksDB>,
+    /// Constructs new object backed by given databases.
+    fn from_databases(
+        hot_storage: Arc<dyn Database>,
+        cold_storage: Option<Arc<dyn Database>>,
     ) -> Self {
-        let hot_storage = Arc::new(hot_storage);
-        let cold_storage = cold_storage.map(|storage| Arc::new(storage));
-
         let cold_db = if let Some(cold_storage) = cold_storage {
             Some(Arc::new(crate::db::ColdDB::new(cold_storage)))
         } else {
@@ -386,6 +386,67 @@ impl Store {
         self.storage.write(transaction)
     }
 
+    /// Takes a snapshot of the hot database, which it can be reverted to with
+    /// [`Self::restore_snapshot`].
+    ///
+    /// The snapshot is a checkpoint of the database created in `path`, which
+    /// hard links the files of the database rather than copying them.  It is
+    /// deleted when the snapshot is dropped.
+    #[cfg(feature = "sandbox")]
+    pub fn snapshot(&self, path: &Path) -> io::Result<StoreSnapshot> {
+        let storage = checkpoint_hot_storage_and_cleanup_columns(self, path, None)
+            .map_err(io::Error::other)?;
+        Ok(StoreSnapshot { store: Some(storage.get_hot_store()), path: path.to_path_buf() })
+    }
+
+    /// Reverts the contents of the database to the ones of the snapshot.
+    ///
+    /// Only the entries which differ from the snapshot are written.  Caches
+    /// built on top of the store (for example in the chain store or in flat
+    /// storage) aren’t aware of the change and must be rebuilt by the caller.
+    #[cfg(feature = "sandbox")]
+    pub fn restore_snapshot(&self, snapshot: &StoreSnapshot) -> io::Result<()> {
+        let source = snapshot.store.as_ref().unwrap();
+        let mut transaction = DBTransaction::new();
+        for column in DBCol::iter() {
+            // Both iterators are sorted by key, so the entries to change are
+            // found by walking them side by side.
+            let mut current = self.storage.iter_raw_bytes(column);
+            let mut wanted = source.storage.iter_raw_bytes(column);
+            let mut current_entry = current.next().transpose()?;
+            let mut wanted_entry = wanted.next().transpose()?;
+            loop {
+                let ordering = match (&current_entry, &wanted_entry) {
+                    (None, None) => break,
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (Some((key, _)), Some((wanted_key, _))) => key.cmp(wanted_key),
+                };
+                match ordering {
+                    std::cmp::Ordering::Less => {
+                        let (key, _) = current_entry.unwrap();
+                        transaction.delete(column, key.into_vec());
+                        current_entry = current.next().transpose()?;
+                    }
+                    std::cmp::Ordering::Greater => {
+                        let (key, value) = wanted_entry.unwrap();
+                        transaction.set(column, key.into_vec(), value.into_vec());
+                        wanted_entry = wanted.next().transpose()?;
+                    }
+                    std::cmp::Ordering::Equal => {
+                        let (key, value) = wanted_entry.unwrap();
+                        if current_entry.unwrap().1 != value {
+                            transaction.set(column, key.into_vec(), value.into_vec());
+                        }
+                        current_entry = current.next().transpose()?;
+                        wanted_entry = wanted.next().transpose()?;
+                    }
+                }
+            }
+        }
+        self.storage.write(transaction)
+    }
+
     /// If the storage is backed by disk, flushes any in-memory data to disk.
     pub fn flush(&self) -> io::Result<()> {
         self.storage.flush()
@@ -399,6 +460,11 @@ impl Store {
     pub fn get_store_statistics(&self) -> Option<StoreStatistics> {
         self.storage.get_store_statistics()
     }
+
+    /// Returns the total size of the data stored in the database if known.
+    pub fn get_live_data_size(&self) -> Option<u64> {
+        self.storage.get_live_data_size()
+    }
 }
 
 impl Store {
@@ -423,6 +489,26 @@ impl Store {
     }
 }
 
+/// Checkpoint of a database, see [`Store::snapshot`].
+#[cfg(feature = "sandbox")]
+pub struct StoreSnapshot {
+    store: Option<Store>,
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "sandbox")]
+impl Drop for StoreSnapshot {
+    fn drop(&mut self) {
+        // The checkpoint must be closed before its files are deleted.
+        drop(self.store.take());
+        if self.path.exists() {
+            if let Err(err) = std::fs::remove_dir_all(&self.path) {
+                tracing::warn!(target: "store", path = %self.path.display(), ?err, "Failed to delete store snapshot");
+            }
+        }
+    }
+}
+
 /// Keeps track of current changes to the database and can commit all of them to the database.
 pub struct StoreUpdate {
     transaction: DBTransaction,
@@ -1311,4 +1397,42 @@ mod tests {
             store.load_state_from_file(tmp.path()).unwrap_err().kind()
         );
     }
+
+    #[cfg(feature = "sandbox")]
+    #[test]
+    fn test_snapshot_and_restore() {
+        let (home_dir, opener) = crate::NodeStorage::test_opener();
+        let store = opener.open().unwrap().get_hot_store();
+        let mut store_update = store.store_update();
+        store_update.increment_refcount(DBCol::State, &[1], &[1]);
+        store_update.increment_refcount(DBCol::State, &[1], &[1]);
+        store_update.set(DBCol::BlockMisc, &[1], &[1]);
+        store_update.commit().unwrap();
+        let snapshot_path = home_dir.path().join("snapshot");
+        let snapshot = store.snapshot(&snapshot_path).unwrap();
+
+        let mut store_update = store.store_update();
+        store_update.decrement_refcount(DBCol::State, &[1]);
+        store_update.increment_refcount(DBCol::State, &[2], &[2]);
+        store_update.set(DBCol::BlockMisc, &[1], &[3]);
+        store_update.set(DBCol::BlockMisc, &[2], &[2]);
+        store_update.commit().unwrap();
+
+        store.restore_snapshot(&snapshot).unwrap();
+        assert_eq!(Some(&[1u8][..]), store.get(DBCol::State, &[1]).unwrap().as_deref());
+        assert_eq!(None, store.get(DBCol::State, &[2]).unwrap());
+        assert_eq!(Some(&[1u8][..]), store.get(DBCol::BlockMisc, &[1]).unwrap().as_deref());
+        assert_eq!(None, store.get(DBCol::BlockMisc, &[2]).unwrap());
+
+        // The refcount is restored as well.
+        let mut store_update = store.store_update();
+        store_update.decrement_refcount(DBCol::State, &[1]);
+        store_update.commit().unwrap();
+        assert_eq!(Some(&[1u8][..]), store.get(DBCol::State, &[1]).unwrap().as_deref());
+
+        // The checkpoint is deleted along with the snapshot.
+        assert!(snapshot_path.exists());
+        drop(snapshot);
+        assert!(!snapshot_path.exists());
+    }
 }
@@ -1,7 +1,8 @@
 use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError, SnapshotRemoveError};
 use crate::db::rocksdb::RocksDB;
+use crate::db::{Database, RedbDB};
 use crate::metadata::{DbKind, DbMetadata, DbVersion, DB_VERSION};
-use crate::{DBCol, DBTransaction, Mode, NodeStorage, Store, StoreConfig, Temperature};
+use crate::{DBCol, DBTransaction, DbBackend, Mode, NodeStorage, Store, StoreConfig, Temperature};
 use std::sync::Arc;
 
 #[derive(Debug, thiserror::Error)]
@@ -162,10 +163,10 @@ fn is_valid_kind_archive(kind: DbKind, archive: bool) -> bool {
 ///     .open();
 /// ```
 pub struct StoreOpener<'a> {
-    /// Opener for an instance of RPC or Hot RocksDB store.
+    /// Opener for an instance of RPC or Hot store.
     hot: DBOpener<'a>,
 
-    /// Opener for an instance of Cold RocksDB store if one was configured.
+    /// Opener for an instance of Cold store if one was configured.
     cold: Option<DBOpener<'a>>,
 
     /// Whether the opener should expect archival db or not.
@@ -176,7 +177,7 @@ pub struct StoreOpener<'a> {
     migrator: Option<&'a dyn StoreMigrator>,
 }
 
-/// Opener for a single RocksDB instance.
+/// Opener for a single database instance.
 struct DBOpener<'a> {
     /// Path to the database.
     ///
@@ -221,13 +222,20 @@ impl<'a> StoreOpener<'a> {
         self
     }
 
-    /// Returns path to the underlying RocksDB database.
+    /// Returns path to the underlying database.
     ///
     /// Does not check whether the database actually exists.
     pub fn path(&self) -> &std::path::Path {
         &self.hot.path
     }
 
+    /// Returns path to the cold database if one is configured.
+    ///
+    /// Does not check whether the database actually exists.
+    pub fn cold_path(&self) -> Option<&std::path::Path> {
+        self.cold.as_ref().map(|cold| cold.path.as_path())
+    }
+
     #[cfg(test)]
     pub(crate) fn config(&self) -> &StoreConfig {
         self.hot.config
@@ -240,7 +248,7 @@ impl<'a> StoreOpener<'a> {
         self.open_in_mode(Mode::ReadWrite)
     }
 
-    /// Opens the RocksDB database(s) for hot and cold (if configured) storages.
+    /// Opens the database(s) for hot and cold (if configured) storages.
     ///
     /// When opening in read-only mode, verifies that the database version is
     /// what the node expects and fails if it isn’t.  If database doesn’t exist,
@@ -279,7 +287,7 @@ impl<'a> StoreOpener<'a> {
             .transpose()?
             .map(|(db, _)| db);
 
-        let storage = NodeStorage::from_rocksdb(hot_db, cold_db);
+        let storage = NodeStorage::from_databases(hot_db, cold_db);
 
         hot_snapshot.remove()?;
         cold_snapshot.remove()?;
@@ -330,6 +338,12 @@ impl<'a> StoreOpener<'a> {
         match meta {
             Some(_) if !mode.must_create() => {
                 tracing::info!(target: "db_opener", path=%opener.path.display(), "The database exists.");
+                let backend = opener.backend();
+                if backend != opener.config.backend {
+                    tracing::warn!(target: "db_opener", path=%opener.path.display(), %backend, configured=%opener.config.backend,
+                                   "The database uses a different backend than configured; opening it with its own backend. \
+                                    Use `neard database convert-backend` to convert it.");
+                }
                 return Ok(());
             }
             Some(_) => {
@@ -339,7 +353,7 @@ impl<'a> StoreOpener<'a> {
                 tracing::info!(target: "db_opener", path=%opener.path.display(), "The database doesn't exist, creating it.");
 
                 let db = opener.create()?;
-                let store = Store { storage: Arc::new(db) };
+                let store = Store { storage: db };
                 store.set_db_version(DB_VERSION)?;
                 return Ok(());
             }
@@ -467,19 +481,19 @@ impl<'a> StoreOpener<'a> {
         version: DbVersion,
     ) -> Result<Store, StoreOpenerError> {
         let (db, _) = opener.open(mode, version)?;
-        let store = Store { storage: Arc::new(db) };
+        let store = Store { storage: db };
         Ok(store)
     }
 
     fn open_store_unsafe(mode: Mode, opener: &DBOpener) -> Result<Store, StoreOpenerError> {
         let db = opener.open_unsafe(mode)?;
-        let store = Store { storage: Arc::new(db) };
+        let store = Store { storage: db };
         Ok(store)
     }
 }
 
 impl<'a> DBOpener<'a> {
-    /// Constructs new opener for a single RocksDB builder.
+    /// Constructs new opener for a single database.
     ///
     /// The path to the database is resolved based on the path in config with
     /// given home_dir as base directory for resolving relative paths.
@@ -499,7 +513,36 @@ impl<'a> DBOpener<'a> {
     /// introduced, the kind is returned as `None`.  Otherwise, it’s also
     /// fetched and if it’s not there error is returned.
     fn get_metadata(&self) -> std::io::Result<Option<DbMetadata>> {
-        RocksDB::get_metadata(&self.path, self.config)
+        match self.backend() {
+            DbBackend::RocksDb => RocksDB::get_metadata(&self.path, self.config),
+            DbBackend::Redb => RedbDB::get_metadata(&self.path),
+        }
+    }
+
+    /// Returns the backend of the database.
+    ///
+    /// If the database exists, this is the backend it was created with,
+    /// whatever the configuration says.  This way databases created by
+    /// checkpointing (which use default configuration) can be opened.
+    /// Otherwise, it’s the configured backend.
+    fn backend(&self) -> DbBackend {
+        if RedbDB::exists(&self.path) {
+            DbBackend::Redb
+        } else if self.path.join("CURRENT").is_file() {
+            DbBackend::RocksDb
+        } else {
+            self.config.backend
+        }
+    }
+
+    /// Opens the database with its backend.
+    fn open_db(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        Ok(match self.backend() {
+            DbBackend::RocksDb => {
+                Arc::new(RocksDB::open(&self.path, &self.config, mode, self.temp)?)
+            }
+            DbBackend::Redb => Arc::new(RedbDB::open(&self.path, mode)?),
+        })
     }
 
     /// Opens the database in given mode checking expected version and kind.
@@ -518,9 +561,13 @@ impl<'a> DBOpener<'a> {
     /// new version.
     ///
     /// Use [`Self::create`] to create a new database.
-    fn open(&self, mode: Mode, want_version: DbVersion) -> std::io::Result<(RocksDB, DbMetadata)> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        let metadata = DbMetadata::read(&db)?;
+    fn open(
+        &self,
+        mode: Mode,
+        want_version: DbVersion,
+    ) -> std::io::Result<(Arc<dyn Database>, DbMetadata)> {
+        let db = self.open_db(mode)?;
+        let metadata = DbMetadata::read(db.as_ref())?;
         if want_version != metadata.version {
             let msg = format!("unexpected DbVersion {}; expected {want_version}", metadata.version);
             Err(std::io::Error::other(msg))
@@ -533,19 +580,21 @@ impl<'a> DBOpener<'a> {
     ///
     /// This is only suitable when creating the database or setting the version
     /// and kind for the first time.
-    fn open_unsafe(&self, mode: Mode) -> std::io::Result<RocksDB> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        Ok(db)
+    fn open_unsafe(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        self.open_db(mode)
     }
 
     /// Creates a new database.
-    fn create(&self) -> std::io::Result<RocksDB> {
-        RocksDB::open(&self.path, &self.config, Mode::Create, self.temp)
+    fn create(&self) -> std::io::Result<Arc<dyn Database>> {
+        self.open_db(Mode::Create)
     }
 
     /// Creates a new snapshot for the database.
     fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
-        Snapshot::new(&self.path, &self.config, self.temp)
+        match self.backend() {
+            DbBackend::RocksDb => Snapshot::new(&self.path, &self.config, self.temp),
+            DbBackend::Redb => RedbDB::snapshot(&self.path, &self.config),
+        }
     }
 }
 
@@ -636,6 +685,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_open_redb() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let config = StoreConfig { backend: DbBackend::Redb, ..StoreConfig::test_config() };
+        {
+            let storage =
+                NodeStorage::opener(home_dir.path(), false, &config, None).open().unwrap();
+            let mut update = storage.get_hot_store().store_update();
+            update.set_raw_bytes(DBCol::BlockMisc, b"key", b"value");
+            update.commit().unwrap();
+        }
+        assert!(RedbDB::exists(&home_dir.path().join("data")));
+
+        // The existing database is opened with its backend even if another one
+        // is configured.
+        let config = StoreConfig::test_config();
+        let storage = NodeStorage::opener(home_dir.path(), false, &config, None)
+            .open_in_mode(Mode::ReadOnly)
+            .unwrap();
+        let store = storage.get_hot_store();
+        assert_eq!(store.get_db_kind().unwrap(), Some(DbKind::RPC));
+        assert_eq!(store.get(DBCol::BlockMisc, b"key").unwrap().as_deref(), Some(&b"value"[..]));
+    }
+
     #[test]
     fn test_checkpoint_hot_storage_and_cleanup_columns() {
         let (home_dir, opener) = NodeStorage::test_opener();
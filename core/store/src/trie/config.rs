@@ -2,6 +2,7 @@ use crate::config::{PrefetchConfig, TrieCacheConfig};
 use crate::StoreConfig;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::AccountId;
+use std::path::PathBuf;
 use std::str::FromStr;
 use tracing::error;
 
@@ -38,6 +39,11 @@ pub struct TrieConfig {
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     /// Whether mem-trie should be loaded for each tracked shard.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// Directory of in-memory trie snapshots, if they are enabled.  Snapshots
+    /// are used when loading in-memory tries and written on shutdown.
+    pub mem_trie_snapshot_dir: Option<PathBuf>,
+    /// Whether identical in-memory trie nodes are shared.
+    pub deduplicate_mem_trie_nodes: bool,
 }
 
 impl TrieConfig {
@@ -65,6 +71,7 @@ impl TrieConfig {
         this.kaiching_prefetch_config.clone_from(&config.kaiching_prefetch_config);
         this.load_mem_tries_for_shards.clone_from(&config.load_mem_tries_for_shards);
         this.load_mem_tries_for_tracked_shards = config.load_mem_tries_for_tracked_shards;
+        this.deduplicate_mem_trie_nodes = config.deduplicate_mem_trie_nodes;
 
         this
     }
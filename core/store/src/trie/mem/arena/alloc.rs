@@ -4,6 +4,7 @@ use crate::trie::mem::arena::metrics::{
     MEM_TRIE_ARENA_ACTIVE_ALLOCS_BYTES, MEM_TRIE_ARENA_MEMORY_USAGE_BYTES,
 };
 use crate::trie::mem::flexible_data::encoding::BorshFixedSize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_o11y::metrics::IntGauge;
 
 /// Simple bump allocator with freelists.
@@ -101,6 +102,45 @@ impl Allocator {
         allocator
     }
 
+    /// Writes the freelists, the bump position and the stats, so that the
+    /// allocator can be restored with `deserialize_state` together with the
+    /// memory it manages.
+    pub fn serialize_state(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        self.freelists.serialize(writer)?;
+        self.next_alloc_pos.serialize(writer)?;
+        (self.active_allocs_bytes as u64).serialize(writer)?;
+        (self.active_allocs_count as u64).serialize(writer)
+    }
+
+    /// Restores an allocator written with `serialize_state`, checking that
+    /// all positions point into the given memory.
+    pub fn deserialize_state(
+        name: String,
+        memory: &STArenaMemory,
+        reader: &mut impl std::io::Read,
+    ) -> std::io::Result<Self> {
+        let freelists = <[ArenaPos; NUM_ALLOCATION_CLASSES]>::deserialize_reader(reader)?;
+        let next_alloc_pos = ArenaPos::deserialize_reader(reader)?;
+        let active_allocs_bytes = u64::deserialize_reader(reader)? as usize;
+        let active_allocs_count = u64::deserialize_reader(reader)? as usize;
+        for pos in freelists.iter().chain(std::iter::once(&next_alloc_pos)) {
+            if !pos.is_invalid()
+                && memory.chunks.get(pos.chunk()).map_or(true, |chunk| chunk.len() < pos.pos())
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Allocator position {} is out of the arena", pos),
+                ));
+            }
+        }
+        let mut allocator =
+            Self::new_with_initial_stats(name, active_allocs_bytes, active_allocs_count);
+        allocator.freelists = freelists;
+        allocator.next_alloc_pos = next_alloc_pos;
+        allocator.update_memory_usage_gauge(memory);
+        Ok(allocator)
+    }
+
     pub fn update_memory_usage_gauge(&self, memory: &STArenaMemory) {
         self.memory_usage_gauge.set(memory.chunks.len() as i64 * CHUNK_SIZE as i64);
     }
@@ -172,6 +172,32 @@ impl STArena {
         arena
     }
 
+    /// Writes the memory of the arena together with the state of its
+    /// allocator.
+    pub(crate) fn serialize_snapshot(
+        &self,
+        writer: &mut impl std::io::Write,
+    ) -> std::io::Result<()> {
+        self.memory.chunks.serialize(writer)?;
+        self.allocator.serialize_state(writer)
+    }
+
+    /// Restores an arena written with `serialize_snapshot`.  Positions of
+    /// previously allocated memory remain valid.
+    pub(crate) fn deserialize_snapshot(
+        name: String,
+        reader: &mut impl std::io::Read,
+    ) -> std::io::Result<Self> {
+        let memory = STArenaMemory { chunks: Vec::<Vec<u8>>::deserialize_reader(reader)? };
+        let allocator = Allocator::deserialize_state(name, &memory, reader)?;
+        Ok(Self { memory, allocator })
+    }
+
+    /// Whether the position points into memory allocated by the arena.
+    pub(crate) fn contains(&self, pos: ArenaPos) -> bool {
+        self.memory.chunks.get(pos.chunk()).map_or(false, |chunk| pos.pos() < chunk.len())
+    }
+
     /// Number of active allocations (alloc calls minus dealloc calls).
     #[cfg(test)]
     pub fn num_active_allocs(&self) -> usize {
@@ -0,0 +1,229 @@
+use super::arena::{Arena, ArenaMemory, STArena, STArenaMemory};
+use super::metrics::{
+    MEM_TRIE_DEDUP_BYTES_SAVED, MEM_TRIE_DEDUP_INDEX_SIZE, MEM_TRIE_DEDUP_NUM_NODES,
+};
+use super::node::{MemTrieNodeId, MemTrieNodePtr};
+use near_primitives::shard_layout::ShardUId;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Index of the nodes of `MemTries` by their content, used to share identical
+/// nodes (and therefore identical subtrees) instead of storing them several
+/// times.
+///
+/// Nodes are keyed by a hash of their encoding, excluding the refcount. Since
+/// children are referenced by position, two nodes can only have the same
+/// content if their children were deduplicated before, so nodes must be
+/// deduplicated bottom-up. The encodings are compared on lookup, so a hash
+/// collision only means that a duplicate is kept.
+///
+/// Only one node per key is indexed; it is removed from the index when it is
+/// deallocated, which requires deallocating nodes with `remove_ref_with` and
+/// `MemTrieNodeDedup::on_dealloc`.
+pub(crate) struct MemTrieNodeDedup {
+    nodes: HashMap<u64, MemTrieNodeId>,
+    shard_uid: String,
+}
+
+impl MemTrieNodeDedup {
+    pub(crate) fn new(shard_uid: ShardUId) -> Self {
+        Self { nodes: HashMap::new(), shard_uid: shard_uid.to_string() }
+    }
+
+    fn key(node: MemTrieNodePtr<STArenaMemory>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        node.encoded_content().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Looks up a node identical to the given one. If there is none, the
+    /// given node is indexed, unless another node with the same key is.
+    fn find_or_insert(&mut self, memory: &STArenaMemory, node: MemTrieNodeId) -> MemTrieNodeId {
+        let ptr = node.as_ptr(memory);
+        let existing = *self.nodes.entry(Self::key(ptr)).or_insert(node);
+        if existing != node && existing.as_ptr(memory).encoded_content() == ptr.encoded_content() {
+            existing
+        } else {
+            node
+        }
+    }
+
+    /// Must be called for every node of the tries right before it is
+    /// deallocated.
+    pub(crate) fn on_dealloc(&mut self, node: MemTrieNodePtr<STArenaMemory>) {
+        let key = Self::key(node);
+        if self.nodes.get(&key) == Some(&node.id()) {
+            self.nodes.remove(&key);
+        }
+    }
+
+    /// Drops a reference to a duplicate node which was replaced by an
+    /// identical one, and records the memory saved.
+    fn release_duplicate(&mut self, arena: &mut STArena, node: MemTrieNodeId) {
+        let mut num_nodes = 0;
+        let mut bytes_saved = 0;
+        node.remove_ref_with(arena, &mut |node| {
+            num_nodes += 1;
+            bytes_saved += node.size_of_allocation();
+            self.on_dealloc(node);
+        });
+        MEM_TRIE_DEDUP_NUM_NODES.with_label_values(&[&self.shard_uid]).inc_by(num_nodes);
+        MEM_TRIE_DEDUP_BYTES_SAVED.with_label_values(&[&self.shard_uid]).inc_by(bytes_saved as u64);
+    }
+
+    /// Deduplicates a node which was just created and isn't referenced yet,
+    /// whose children are already deduplicated. Returns the node that should
+    /// be used in its place; the given node is freed if it is a duplicate.
+    pub(crate) fn deduplicate_new_node(
+        &mut self,
+        arena: &mut STArena,
+        node: MemTrieNodeId,
+    ) -> MemTrieNodeId {
+        let result = self.find_or_insert(arena.memory(), node);
+        if result != node {
+            node.add_ref(arena.memory_mut());
+            self.release_duplicate(arena, node);
+        }
+        self.update_index_size();
+        result
+    }
+
+    /// Deduplicates all the descendants of the node in place, and indexes the
+    /// node itself unless an identical node is indexed already. Used for
+    /// tries that were constructed without deduplication.
+    pub(crate) fn deduplicate_subtree(&mut self, arena: &mut STArena, root: MemTrieNodeId) {
+        self.deduplicate_subtree_impl(arena, root);
+        self.update_index_size();
+    }
+
+    fn deduplicate_subtree_impl(
+        &mut self,
+        arena: &mut STArena,
+        node: MemTrieNodeId,
+    ) -> MemTrieNodeId {
+        if self.nodes.get(&Self::key(node.as_ptr(arena.memory()))) == Some(&node) {
+            // Already deduplicated through another parent.
+            return node;
+        }
+        for offset in node.child_pointer_offsets(arena.memory()) {
+            let child =
+                MemTrieNodeId { pos: arena.memory().ptr(node.pos.offset_by(offset)).read_pos() };
+            let new_child = self.deduplicate_subtree_impl(arena, child);
+            if new_child != child {
+                node.replace_child_at(arena.memory_mut(), offset, new_child);
+                new_child.add_ref(arena.memory_mut());
+                self.release_duplicate(arena, child);
+            }
+        }
+        self.find_or_insert(arena.memory(), node)
+    }
+
+    fn update_index_size(&self) {
+        MEM_TRIE_DEDUP_INDEX_SIZE
+            .with_label_values(&[&self.shard_uid])
+            .set(self.nodes.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::mem::lookup::memtrie_lookup;
+    use crate::trie::mem::updating::apply_memtrie_changes;
+    use crate::trie::mem::MemTries;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::FlatStateValue;
+    use near_primitives::types::BlockHeight;
+
+    fn apply(
+        mem: &mut MemTries,
+        root: CryptoHash,
+        height: BlockHeight,
+        changes: &[(Vec<u8>, Vec<u8>)],
+    ) -> CryptoHash {
+        let mut update = mem.update(root, false).unwrap();
+        for (key, value) in changes {
+            update.insert_memtrie_only(key, FlatStateValue::on_disk(value));
+        }
+        let changes = update.to_mem_trie_changes_only();
+        apply_memtrie_changes(mem, &changes, height)
+    }
+
+    fn test_changes() -> Vec<(Vec<u8>, Vec<u8>)> {
+        (0..100u8).map(|i| (vec![i, i.wrapping_mul(7)], vec![i; i as usize])).collect()
+    }
+
+    #[test]
+    fn test_deduplicate_updates() {
+        let changes = test_changes();
+        let mut num_allocs = Vec::new();
+        for deduplicate in [false, true] {
+            let mut mem = MemTries::new(ShardUId::single_shard());
+            if deduplicate {
+                mem.enable_deduplication();
+            }
+            let root = apply(&mut mem, CryptoHash::default(), 1, &changes);
+            let allocs_after_first_root = mem.arena.num_active_allocs();
+            // Rewriting a key with the same value recreates the same nodes.
+            let same_root = apply(&mut mem, root, 2, &changes[5..6]);
+            assert_eq!(same_root, root);
+            // Constructing an identical trie from scratch.
+            let same_root = apply(&mut mem, CryptoHash::default(), 3, &changes);
+            assert_eq!(same_root, root);
+            assert_eq!(mem.num_roots(), 3);
+            if deduplicate {
+                assert_eq!(mem.arena.num_active_allocs(), allocs_after_first_root);
+            }
+            num_allocs.push(mem.arena.num_active_allocs());
+
+            for (key, value) in &changes {
+                let result = memtrie_lookup(mem.get_root(&root).unwrap(), key, None);
+                assert_eq!(result.map(|v| v.to_flat_value()), Some(FlatStateValue::on_disk(value)));
+            }
+            mem.delete_until_height(4);
+            assert_eq!(mem.arena.num_active_allocs(), 0);
+            if deduplicate {
+                assert!(mem.node_dedup.as_ref().unwrap().nodes.is_empty());
+            }
+        }
+        assert!(num_allocs[1] < num_allocs[0]);
+    }
+
+    #[test]
+    fn test_deduplicate_existing_tries() {
+        // The subtrees under keys starting with 0 and 1 are identical.
+        let changes = test_changes()
+            .into_iter()
+            .flat_map(|(key, value)| {
+                [0u8, 1].map(|prefix| ([&[prefix][..], &key[..]].concat(), value.clone()))
+            })
+            .collect::<Vec<_>>();
+        let mut mem = MemTries::new(ShardUId::single_shard());
+        let root = apply(&mut mem, CryptoHash::default(), 1, &changes);
+        let num_allocs = mem.arena.num_active_allocs();
+
+        mem.enable_deduplication();
+        assert!(mem.arena.num_active_allocs() < num_allocs / 2 + 10);
+        let root_node = mem.get_root(&root).unwrap();
+        assert_eq!(root_node.view().node_hash(), root);
+        for (key, value) in &changes {
+            let result = memtrie_lookup(root_node, key, None);
+            assert_eq!(result.map(|v| v.to_flat_value()), Some(FlatStateValue::on_disk(value)));
+        }
+
+        // Updates of one of the copies don't affect the other one.
+        let new_root = apply(&mut mem, root, 2, &[(changes[0].0.clone(), b"new".to_vec())]);
+        let new_root_node = mem.get_root(&new_root).unwrap();
+        assert_eq!(
+            memtrie_lookup(new_root_node, &changes[0].0, None).map(|v| v.to_flat_value()),
+            Some(FlatStateValue::on_disk(b"new"))
+        );
+        assert_eq!(
+            memtrie_lookup(new_root_node, &changes[1].0, None).map(|v| v.to_flat_value()),
+            Some(FlatStateValue::on_disk(&changes[1].1))
+        );
+        mem.delete_until_height(3);
+        assert_eq!(mem.arena.num_active_allocs(), 0);
+    }
+}
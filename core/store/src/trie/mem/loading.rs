@@ -3,10 +3,11 @@ use super::MemTries;
 use crate::flat::store_helper::{
     decode_flat_state_db_key, get_all_deltas_metadata, get_delta_changes, get_flat_storage_status,
 };
-use crate::flat::{FlatStorageError, FlatStorageStatus};
+use crate::flat::{BlockInfo, FlatStorageError, FlatStorageStatus};
 use crate::trie::mem::arena::Arena;
 use crate::trie::mem::construction::TrieConstructor;
 use crate::trie::mem::parallel_loader::load_memtrie_in_parallel;
+use crate::trie::mem::snapshot::mem_trie_snapshot_path;
 use crate::trie::mem::updating::apply_memtrie_changes;
 use crate::{DBCol, NibbleSlice, Store};
 use near_primitives::errors::StorageError;
@@ -16,8 +17,9 @@ use near_primitives::state::FlatStateValue;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, StateRoot};
 use std::collections::BTreeSet;
+use std::path::Path;
 use std::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Loads a trie from the FlatState column. The returned `MemTries` contains
 /// exactly one trie root.
@@ -107,7 +109,7 @@ pub fn load_trie_from_flat_state(
     Ok(tries)
 }
 
-fn get_state_root(
+pub(crate) fn get_state_root(
     store: &Store,
     block_hash: CryptoHash,
     shard_uid: ShardUId,
@@ -135,11 +137,14 @@ fn get_state_root(
 /// flat storage currently has, i.e. one for the final block, and one for each
 /// block that flat storage has a delta for, possibly in more than one fork.
 /// `state_root` parameter is required if `ChunkExtra` is not available, e.g. on catchup.
+/// If `snapshot_dir` contains a snapshot of the shard taken at the current flat head, the base
+/// trie is loaded from it instead of the flat state.
 pub fn load_trie_from_flat_state_and_delta(
     store: &Store,
     shard_uid: ShardUId,
     state_root: Option<StateRoot>,
     parallelize: bool,
+    snapshot_dir: Option<&Path>,
 ) -> Result<MemTries, StorageError> {
     debug!(target: "memtrie", %shard_uid, "Loading base trie from flat state...");
     let flat_head = match get_flat_storage_status(&store, shard_uid)? {
@@ -157,9 +162,15 @@ pub fn load_trie_from_flat_state_and_delta(
         None => get_state_root(store, flat_head.hash, shard_uid)?,
     };
 
-    let mut mem_tries =
-        load_trie_from_flat_state(&store, shard_uid, state_root, flat_head.height, parallelize)
-            .unwrap();
+    let snapshot = snapshot_dir
+        .and_then(|dir| load_trie_from_snapshot(dir, shard_uid, &flat_head, state_root));
+    let mut mem_tries = match snapshot {
+        Some(mem_tries) => mem_tries,
+        None => {
+            load_trie_from_flat_state(&store, shard_uid, state_root, flat_head.height, parallelize)
+                .unwrap()
+        }
+    };
 
     debug!(target: "memtrie", %shard_uid, "Loading flat state deltas...");
     // We load the deltas in order of height, so that we always have the previous state root
@@ -175,6 +186,10 @@ pub fn load_trie_from_flat_state_and_delta(
         if let Some(changes) = delta {
             let old_state_root = get_state_root(store, prev_hash, shard_uid)?;
             let new_state_root = get_state_root(store, hash, shard_uid)?;
+            if mem_tries.has_root_at_height(height, &new_state_root) {
+                // Already applied before the snapshot was taken.
+                continue;
+            }
 
             let mut trie_update = mem_tries.update(old_state_root, false)?;
             for (key, value) in changes.0 {
@@ -198,6 +213,32 @@ pub fn load_trie_from_flat_state_and_delta(
     Ok(mem_tries)
 }
 
+/// Loads the snapshot of the shard from `dir`, if there is one taken at the given flat head.
+fn load_trie_from_snapshot(
+    dir: &Path,
+    shard_uid: ShardUId,
+    flat_head: &BlockInfo,
+    state_root: StateRoot,
+) -> Option<MemTries> {
+    let path = mem_trie_snapshot_path(dir, shard_uid);
+    info!(target: "memtrie", %shard_uid, ?path, "Loading trie from snapshot...");
+    let load_start = Instant::now();
+    match MemTries::load_snapshot(&path, shard_uid, flat_head, state_root) {
+        Ok(mem_tries) => {
+            info!(target: "memtrie", %shard_uid, "Done loading trie from snapshot, took {:?}", load_start.elapsed());
+            Some(mem_tries)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!(target: "memtrie", %shard_uid, "No snapshot found, loading trie from flat state");
+            None
+        }
+        Err(err) => {
+            warn!(target: "memtrie", %shard_uid, ?err, "Cannot use snapshot, loading trie from flat state");
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::load_trie_from_flat_state_and_delta;
@@ -209,6 +250,7 @@ mod tests {
     };
     use crate::trie::mem::loading::load_trie_from_flat_state;
     use crate::trie::mem::lookup::memtrie_lookup;
+    use crate::trie::mem::snapshot::mem_trie_snapshot_path;
     use crate::{DBCol, KeyLookupMode, NibbleSlice, ShardTries, Store, Trie, TrieUpdate};
     use near_primitives::congestion_info::CongestionInfo;
     use near_primitives::hash::CryptoHash;
@@ -499,7 +541,8 @@ mod tests {
         // Load into memory. It should load the base flat state (block 0), plus all
         // four deltas. We'll check against the state roots at each block; they should
         // all exist in the loaded memtrie.
-        let mem_tries = load_trie_from_flat_state_and_delta(&store, shard_uid, None, true).unwrap();
+        let mem_tries =
+            load_trie_from_flat_state_and_delta(&store, shard_uid, None, true, None).unwrap();
 
         assert_eq!(
             memtrie_lookup(mem_tries.get_root(&state_root_0).unwrap(), &test_key.to_vec(), None)
@@ -526,6 +569,23 @@ mod tests {
                 .map(|v| v.to_flat_value()),
             Some(FlatStateValue::inlined(&test_val4))
         );
+
+        // Loading from a snapshot taken at the flat head yields the same tries. The deltas
+        // already applied to the snapshot are not applied again.
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = mem_trie_snapshot_path(dir.path(), shard_uid);
+        mem_tries.save_snapshot(&snapshot_path, chain.get_block(0), state_root_0).unwrap();
+        let mem_tries_from_snapshot =
+            load_trie_from_flat_state_and_delta(&store, shard_uid, None, false, Some(dir.path()))
+                .unwrap();
+        assert_eq!(mem_tries_from_snapshot.num_roots(), mem_tries.num_roots());
+        for (state_root, test_val) in [(state_root_3, &test_val3), (state_root_4, &test_val4)] {
+            let root = mem_tries_from_snapshot.get_root(&state_root).unwrap();
+            assert_eq!(
+                memtrie_lookup(root, &test_key.to_vec(), None).map(|v| v.to_flat_value()),
+                Some(FlatStateValue::inlined(test_val))
+            );
+        }
     }
 
     /// Makes the given changes to both the trie and flat storage.
@@ -29,3 +29,30 @@ pub static MEM_TRIE_NUM_LOOKUPS: Lazy<IntCounter> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub static MEM_TRIE_DEDUP_NUM_NODES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_mem_trie_dedup_num_nodes",
+        "Number of in-memory trie nodes freed because an identical node already existed",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
+
+pub static MEM_TRIE_DEDUP_BYTES_SAVED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_mem_trie_dedup_bytes_saved",
+        "Total size in bytes of in-memory trie nodes freed because an identical node already existed",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
+
+pub static MEM_TRIE_DEDUP_INDEX_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_mem_trie_dedup_index_size",
+        "Number of in-memory trie nodes in the deduplication index",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
@@ -1,4 +1,5 @@
 use self::arena::{Arena, STArena, STArenaMemory};
+use self::dedup::MemTrieNodeDedup;
 use self::metrics::MEM_TRIE_NUM_ROOTS;
 use self::node::{MemTrieNodeId, MemTrieNodePtr};
 use self::updating::MemTrieUpdate;
@@ -10,6 +11,7 @@ use std::collections::{BTreeMap, HashMap};
 
 mod arena;
 mod construction;
+mod dedup;
 pub(crate) mod flexible_data;
 mod freelist;
 pub mod iter;
@@ -18,6 +20,7 @@ pub mod lookup;
 pub mod metrics;
 pub mod node;
 mod parallel_loader;
+pub mod snapshot;
 pub mod updating;
 
 /// Check this, because in the code we conveniently assume usize is 8 bytes.
@@ -33,10 +36,11 @@ compile_error!("In-memory trie requires a 64 bit platform");
 pub struct MemTries {
     arena: STArena,
     /// Maps a state root to a list of nodes that have the same root hash.
-    /// The reason why this is a list is because we do not have a node
-    /// deduplication mechanism so we can't guarantee that nodes of the
-    /// same hash are unique. During lookup, any of these nodes can be provided
-    /// as they all logically represent the same trie.
+    /// The reason why this is a list is because node deduplication is
+    /// optional, and doesn't apply to roots that existed before it was
+    /// enabled, so we can't guarantee that nodes of the same hash are unique.
+    /// During lookup, any of these nodes can be provided as they all logically
+    /// represent the same trie.
     roots: HashMap<StateRoot, Vec<MemTrieNodeId>>,
     /// Maps a block height to a list of state roots present at that height.
     /// This is used for GC. The invariant is that for any state root, the
@@ -45,6 +49,8 @@ pub struct MemTries {
     heights: BTreeMap<BlockHeight, Vec<StateRoot>>,
     /// Shard UID, for exporting metrics only.
     shard_uid: ShardUId,
+    /// Index used to share identical nodes, if deduplication is enabled.
+    node_dedup: Option<MemTrieNodeDedup>,
 }
 
 impl MemTries {
@@ -54,6 +60,7 @@ impl MemTries {
             roots: HashMap::new(),
             heights: Default::default(),
             shard_uid,
+            node_dedup: None,
         }
     }
 
@@ -63,8 +70,13 @@ impl MemTries {
         arena: STArena,
         root: MemTrieNodeId,
     ) -> Self {
-        let mut tries =
-            Self { arena, roots: HashMap::new(), heights: Default::default(), shard_uid };
+        let mut tries = Self {
+            arena,
+            roots: HashMap::new(),
+            heights: Default::default(),
+            shard_uid,
+            node_dedup: None,
+        };
         tries.insert_root(root.as_ptr(tries.arena.memory()).view().node_hash(), root, block_height);
         tries
     }
@@ -78,15 +90,38 @@ impl MemTries {
         f: impl FnOnce(&mut STArena) -> Result<Option<MemTrieNodeId>, Error>,
     ) -> Result<CryptoHash, Error> {
         let root = f(&mut self.arena)?;
+        Ok(self.insert_constructed_root(block_height, root))
+    }
+
+    /// Inserts the root of a newly constructed trie, returning its state root.
+    fn insert_constructed_root(
+        &mut self,
+        block_height: BlockHeight,
+        root: Option<MemTrieNodeId>,
+    ) -> CryptoHash {
         if let Some(root) = root {
             let state_root = root.as_ptr(self.arena.memory()).view().node_hash();
             self.insert_root(state_root, root, block_height);
-            Ok(state_root)
+            state_root
         } else {
-            Ok(CryptoHash::default())
+            CryptoHash::default()
         }
     }
 
+    /// Enables deduplication of nodes: identical nodes created by later
+    /// updates are shared instead of being stored several times. The nodes of
+    /// the existing tries are deduplicated in place.
+    pub fn enable_deduplication(&mut self) {
+        if self.node_dedup.is_some() {
+            return;
+        }
+        let mut node_dedup = MemTrieNodeDedup::new(self.shard_uid);
+        for root in self.roots.values().flatten() {
+            node_dedup.deduplicate_subtree(&mut self.arena, *root);
+        }
+        self.node_dedup = Some(node_dedup);
+    }
+
     fn insert_root(
         &mut self,
         state_root: StateRoot,
@@ -135,7 +170,12 @@ impl MemTries {
     fn delete_root(&mut self, state_root: &CryptoHash) {
         if let Some(ids) = self.roots.get_mut(state_root) {
             let last_id = ids.last().unwrap();
-            let new_ref = last_id.remove_ref(&mut self.arena);
+            let node_dedup = &mut self.node_dedup;
+            let new_ref = last_id.remove_ref_with(&mut self.arena, &mut |node| {
+                if let Some(node_dedup) = node_dedup.as_mut() {
+                    node_dedup.on_dealloc(node);
+                }
+            });
             if new_ref == 0 {
                 ids.pop();
                 if ids.is_empty() {
@@ -150,6 +190,13 @@ impl MemTries {
             .set(self.roots.len() as i64);
     }
 
+    /// Whether the state root has been inserted at the given height.
+    fn has_root_at_height(&self, block_height: BlockHeight, state_root: &StateRoot) -> bool {
+        self.heights
+            .get(&block_height)
+            .map_or(false, |state_roots| state_roots.contains(state_root))
+    }
+
     /// Used for unit testing and integration testing.
     pub fn num_roots(&self) -> usize {
         self.heights.iter().map(|(_, v)| v.len()).sum()
@@ -211,7 +258,7 @@ mod tests {
         //  - Each height possibly having multiple state roots (due to forks)
         //    (and possibly with the same state roots)
         //  - Each state root possibly having multiple actual nodes that have
-        //    the same hash (as we don't deduplicate in general)
+        //    the same hash (as we don't deduplicate by default)
         //  - A state root being possibly the same as another of a different
         //    height.
         //
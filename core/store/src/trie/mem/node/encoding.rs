@@ -231,6 +231,16 @@ impl MemTrieNodeId {
     /// Decrements the refcount, deallocating the node if it reaches zero.
     /// Returns the new refcount.
     pub(crate) fn remove_ref(&self, arena: &mut impl ArenaWithDealloc) -> u32 {
+        self.remove_ref_with(arena, &mut |_| {})
+    }
+
+    /// Same as `remove_ref`, but calls `on_dealloc` with each node (this one
+    /// or a descendant) right before it is deallocated.
+    pub(crate) fn remove_ref_with<A: ArenaWithDealloc>(
+        &self,
+        arena: &mut A,
+        on_dealloc: &mut impl FnMut(MemTrieNodePtr<A::Memory>),
+    ) -> u32 {
         // Refcount is always encoded as the first four bytes of the node memory.
         let refcount_memory = arena.memory_mut().raw_slice_mut(self.pos, size_of::<u32>());
         let refcount = u32::from_le_bytes(refcount_memory.try_into().unwrap());
@@ -243,13 +253,51 @@ impl MemTrieNodeId {
                 children_to_unref.push(child.id().pos);
             }
             let alloc_size = node_ptr.size_of_allocation();
+            on_dealloc(node_ptr);
             arena.dealloc(self.pos, alloc_size);
             for child in children_to_unref.iter() {
-                MemTrieNodeId { pos: *child }.remove_ref(arena);
+                MemTrieNodeId { pos: *child }.remove_ref_with(arena, on_dealloc);
             }
         }
         new_refcount
     }
+
+    /// Offsets of the pointers to the children of the node, relative to the
+    /// start of the node's allocation, in the order of the children.
+    pub(crate) fn child_pointer_offsets(&self, memory: &impl ArenaMemory) -> SmallVec<[usize; 16]> {
+        let mut decoder = self.as_ptr(memory).decoder();
+        match decoder.peek::<CommonHeader>().kind {
+            NodeKind::Leaf => SmallVec::new(),
+            NodeKind::Extension => SmallVec::from_slice(&[
+                CommonHeader::SERIALIZED_SIZE + NonLeafHeader::SERIALIZED_SIZE
+            ]),
+            NodeKind::Branch => {
+                let header = decoder.decode::<BranchHeader>();
+                (0..header.children.flexible_data_length() / size_of::<usize>())
+                    .map(|i| BranchHeader::SERIALIZED_SIZE + i * size_of::<usize>())
+                    .collect()
+            }
+            NodeKind::BranchWithValue => {
+                let header = decoder.decode::<BranchWithValueHeader>();
+                (0..header.children.flexible_data_length() / size_of::<usize>())
+                    .map(|i| BranchWithValueHeader::SERIALIZED_SIZE + i * size_of::<usize>())
+                    .collect()
+            }
+        }
+    }
+
+    /// Replaces the child pointer at the given offset (see
+    /// `child_pointer_offsets`) with `child`, without touching refcounts.
+    /// The new child must be identical to the old one, since the hash of the
+    /// node is not recomputed.
+    pub(crate) fn replace_child_at(
+        &self,
+        memory: &mut impl ArenaMemory,
+        offset: usize,
+        child: MemTrieNodeId,
+    ) {
+        memory.slice_mut(self.pos.offset_by(offset), size_of::<usize>()).write_pos_at(0, child.pos);
+    }
 }
 
 impl<'a, M: ArenaMemory> MemTrieNodePtr<'a, M> {
@@ -301,9 +349,18 @@ impl<'a, M: ArenaMemory> MemTrieNodePtr<'a, M> {
         }
     }
 
+    /// The encoded node without its refcount.  Nodes with the same content are
+    /// interchangeable.
+    pub(crate) fn encoded_content(&self) -> &'a [u8] {
+        let size = self.size_of_allocation();
+        self.ptr
+            .arena()
+            .raw_slice(self.ptr.raw_pos().offset_by(size_of::<u32>()), size - size_of::<u32>())
+    }
+
     /// Calculates the size of the allocation with only a pointer to the start
     /// of the trie node's allocation.
-    fn size_of_allocation(&self) -> usize {
+    pub(crate) fn size_of_allocation(&self) -> usize {
         let mut decoder = self.decoder();
         let kind = decoder.peek::<CommonHeader>().kind;
         match kind {
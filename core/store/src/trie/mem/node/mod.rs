@@ -3,6 +3,7 @@ use super::flexible_data::children::ChildrenView;
 use super::flexible_data::value::ValueView;
 use crate::trie::{Children, TRIE_COSTS};
 use crate::{RawTrieNode, RawTrieNodeWithSize};
+use borsh::{BorshDeserialize, BorshSerialize};
 use derive_where::derive_where;
 use near_primitives::hash::CryptoHash;
 use near_primitives::state::FlatStateValue;
@@ -22,7 +23,7 @@ mod view;
 /// references in the case of multiple state roots (trie roots), and are
 /// internally refcounted. See `MemTries` for more details on the lifecycle
 /// of trie nodes.
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, BorshSerialize, BorshDeserialize)]
 pub struct MemTrieNodeId {
     pub(crate) pos: ArenaPos,
 }
@@ -0,0 +1,298 @@
+//! On-disk snapshots of in-memory tries.
+//!
+//! Loading in-memory tries from flat storage rebuilds the whole arena and
+//! takes many minutes per shard on mainnet.  Instead, the arena of a shard
+//! can be written to a file together with its roots, tied to the flat head at
+//! the time the snapshot is taken.  On the next start the snapshot is used if
+//! the flat head hasn't moved since, after which the flat storage deltas are
+//! applied as usual.
+//!
+//! The header of a snapshot contains the checksum of the payload following
+//! it, which is verified before anything is decoded from the payload, so that
+//! a corrupted snapshot is rejected and the trie is loaded from flat storage
+//! instead.
+use super::arena::STArena;
+use super::metrics::MEM_TRIE_NUM_ROOTS;
+use super::node::MemTrieNodeId;
+use super::MemTries;
+use crate::flat::BlockInfo;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{BlockHeight, StateRoot};
+use sha2::Digest;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Version of the snapshot format.  Must be bumped whenever the encoding of
+/// the arena, the nodes or the snapshot itself changes; snapshots of other
+/// versions are ignored.
+pub const MEM_TRIE_SNAPSHOT_VERSION: u32 = 2;
+
+const MEM_TRIE_SNAPSHOT_MAGIC: &[u8; 8] = b"MEMTRIE\0";
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+struct MemTrieSnapshotHeader {
+    version: u32,
+    shard_uid: ShardUId,
+    /// Flat head at the time the snapshot was taken.
+    flat_head: BlockInfo,
+    /// State root of the shard at the flat head.
+    state_root: StateRoot,
+    /// SHA-256 of the payload, i.e. the arena, the roots and the heights.
+    /// Must be the last field, as it is filled in after the payload is written.
+    payload_checksum: CryptoHash,
+}
+
+/// Writer computing the checksum of the data written through it.
+struct ChecksumWriter<W> {
+    inner: W,
+    hasher: sha2::Sha256,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, hasher: sha2::Sha256::new() }
+    }
+
+    fn finish(self) -> (W, CryptoHash) {
+        (self.inner, CryptoHash(self.hasher.finalize().into()))
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the path of the snapshot of the shard in the given directory.
+pub fn mem_trie_snapshot_path(dir: &Path, shard_uid: ShardUId) -> PathBuf {
+    dir.join(format!("{}.snapshot", shard_uid))
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+impl MemTries {
+    /// Writes the tries to a snapshot at `path`.  `state_root` is the state
+    /// root of the shard at `flat_head` and must be one of the roots of the
+    /// tries.  The snapshot is written to a temporary file first, so an
+    /// interrupted write never leaves a partial snapshot behind.
+    pub fn save_snapshot(
+        &self,
+        path: &Path,
+        flat_head: BlockInfo,
+        state_root: StateRoot,
+    ) -> std::io::Result<()> {
+        if state_root != CryptoHash::default() && !self.roots.contains_key(&state_root) {
+            return Err(invalid_data(format!(
+                "State root {} of flat head {:?} is not in the in-memory trie",
+                state_root, flat_head
+            )));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MEM_TRIE_SNAPSHOT_MAGIC)?;
+        let mut header = MemTrieSnapshotHeader {
+            version: MEM_TRIE_SNAPSHOT_VERSION,
+            shard_uid: self.shard_uid,
+            flat_head,
+            state_root,
+            payload_checksum: CryptoHash::default(),
+        };
+        header.serialize(&mut writer)?;
+        let payload_start = writer.stream_position()?;
+
+        let mut payload_writer = ChecksumWriter::new(writer);
+        self.arena.serialize_snapshot(&mut payload_writer)?;
+        self.roots.serialize(&mut payload_writer)?;
+        self.heights.serialize(&mut payload_writer)?;
+        let (mut writer, payload_checksum) = payload_writer.finish();
+        header.payload_checksum = payload_checksum;
+        writer.seek(SeekFrom::Start(payload_start - CryptoHash::LENGTH as u64))?;
+        header.payload_checksum.serialize(&mut writer)?;
+
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Loads the tries from the snapshot at `path`.  Fails unless the
+    /// snapshot has the current version and was taken for the shard at the
+    /// given flat head with the given state root.  Roots older than the flat
+    /// head are dropped.
+    ///
+    /// The payload is only decoded once its checksum matches the header, and
+    /// all decoding errors are returned as `InvalidData` errors.  The nodes in
+    /// the arena are trusted afterwards, as decoding them can't fail
+    /// gracefully.
+    pub fn load_snapshot(
+        path: &Path,
+        shard_uid: ShardUId,
+        flat_head: &BlockInfo,
+        state_root: StateRoot,
+    ) -> std::io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; MEM_TRIE_SNAPSHOT_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MEM_TRIE_SNAPSHOT_MAGIC {
+            return Err(invalid_data("Not an in-memory trie snapshot".to_string()));
+        }
+        let header = MemTrieSnapshotHeader::deserialize_reader(&mut reader)?;
+        if header.version != MEM_TRIE_SNAPSHOT_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported snapshot version {}, expected {}",
+                header.version, MEM_TRIE_SNAPSHOT_VERSION
+            )));
+        }
+        if header.shard_uid != shard_uid
+            || header.flat_head != *flat_head
+            || header.state_root != state_root
+        {
+            return Err(invalid_data(format!(
+                "Snapshot of shard {} was taken at flat head {:?} with state root {}, \
+                 but shard {} is at flat head {:?} with state root {}",
+                header.shard_uid,
+                header.flat_head,
+                header.state_root,
+                shard_uid,
+                flat_head,
+                state_root
+            )));
+        }
+
+        let payload_start = reader.stream_position()?;
+        let mut checksum_writer = ChecksumWriter::new(std::io::sink());
+        std::io::copy(&mut reader, &mut checksum_writer)?;
+        let (_, payload_checksum) = checksum_writer.finish();
+        if payload_checksum != header.payload_checksum {
+            return Err(invalid_data(format!(
+                "Snapshot payload has checksum {}, expected {}",
+                payload_checksum, header.payload_checksum
+            )));
+        }
+        reader.seek(SeekFrom::Start(payload_start))?;
+
+        let arena = STArena::deserialize_snapshot(shard_uid.to_string(), &mut reader)?;
+        let roots = HashMap::<StateRoot, Vec<MemTrieNodeId>>::deserialize_reader(&mut reader)?;
+        let heights = BTreeMap::<BlockHeight, Vec<StateRoot>>::deserialize_reader(&mut reader)?;
+        if !reader.fill_buf()?.is_empty() {
+            return Err(invalid_data("Unexpected data at the end of the snapshot".to_string()));
+        }
+        for (root, ids) in &roots {
+            for id in ids {
+                if !arena.contains(id.pos) {
+                    return Err(invalid_data(format!("Invalid node {:?} of root {}", id, root)));
+                }
+            }
+        }
+        if let Some(root) = heights.values().flatten().find(|root| !roots.contains_key(root)) {
+            return Err(invalid_data(format!("Unknown root {} in heights", root)));
+        }
+        if state_root != CryptoHash::default() && !roots.contains_key(&state_root) {
+            return Err(invalid_data(format!("State root {} is not in the snapshot", state_root)));
+        }
+
+        let mut tries = Self { arena, roots, heights, shard_uid, node_dedup: None };
+        MEM_TRIE_NUM_ROOTS
+            .with_label_values(&[&shard_uid.to_string()])
+            .set(tries.roots.len() as i64);
+        tries.delete_until_height(flat_head.height);
+        Ok(tries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mem_trie_snapshot_path;
+    use crate::flat::BlockInfo;
+    use crate::test_utils::{
+        simplify_changes, test_populate_flat_storage, test_populate_trie, TestTriesBuilder,
+    };
+    use crate::trie::mem::loading::load_trie_from_flat_state;
+    use crate::trie::mem::lookup::memtrie_lookup;
+    use crate::trie::mem::MemTries;
+    use crate::Trie;
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::FlatStateValue;
+
+    #[test]
+    fn test_save_and_load_snapshot() {
+        let shard_tries = TestTriesBuilder::new().with_flat_storage(true).build();
+        let shard_uid = ShardUId::single_shard();
+        let keys: Vec<Vec<u8>> =
+            (0..1000u32).map(|i| hash(&i.to_le_bytes()).as_ref().to_vec()).collect();
+        let changes = keys.iter().map(|key| (key.clone(), Some(key.clone()))).collect::<Vec<_>>();
+        let changes = simplify_changes(&changes);
+        test_populate_flat_storage(
+            &shard_tries,
+            shard_uid,
+            &CryptoHash::default(),
+            &CryptoHash::default(),
+            &changes,
+        );
+        let state_root = test_populate_trie(&shard_tries, &Trie::EMPTY_ROOT, shard_uid, changes);
+        let mem_tries =
+            load_trie_from_flat_state(&shard_tries.get_store(), shard_uid, state_root, 123, false)
+                .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = mem_trie_snapshot_path(dir.path(), shard_uid);
+        let flat_head = BlockInfo { hash: hash(b"head"), height: 123, prev_hash: hash(b"prev") };
+        mem_tries.save_snapshot(&path, flat_head, state_root).unwrap();
+        // Saving a root which isn't in the tries fails.
+        assert!(mem_tries.save_snapshot(&path, flat_head, hash(b"other root")).is_err());
+
+        let loaded = MemTries::load_snapshot(&path, shard_uid, &flat_head, state_root).unwrap();
+        assert_eq!(loaded.num_roots(), 1);
+        let root = loaded.get_root(&state_root).unwrap();
+        for key in &keys {
+            let value = memtrie_lookup(root, key, None).map(|v| v.to_flat_value());
+            assert_eq!(value, Some(FlatStateValue::inlined(key)));
+        }
+        // The loaded arena keeps allocating and freeing memory correctly.
+        let mut loaded = loaded;
+        loaded.delete_until_height(124);
+        assert_eq!(loaded.num_roots(), 0);
+        assert_eq!(loaded.arena.num_active_allocs(), 0);
+
+        // Snapshots taken at another flat head or for another shard are rejected.
+        let other_head = BlockInfo { height: 124, ..flat_head };
+        assert!(MemTries::load_snapshot(&path, shard_uid, &other_head, state_root).is_err());
+        let other_shard = ShardUId { version: 1, shard_id: 1 };
+        assert!(MemTries::load_snapshot(&path, other_shard, &flat_head, state_root).is_err());
+        assert!(MemTries::load_snapshot(
+            &dir.path().join("missing"),
+            shard_uid,
+            &flat_head,
+            state_root
+        )
+        .is_err());
+
+        // Corrupted and truncated snapshots are rejected.
+        let data = std::fs::read(&path).unwrap();
+        let mut corrupted = data.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, &corrupted).unwrap();
+        let err = MemTries::load_snapshot(&path, shard_uid, &flat_head, state_root).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+        assert!(MemTries::load_snapshot(&path, shard_uid, &flat_head, state_root).is_err());
+        std::fs::write(&path, &data).unwrap();
+        assert!(MemTries::load_snapshot(&path, shard_uid, &flat_head, state_root).is_ok());
+    }
+}
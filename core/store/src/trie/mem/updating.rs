@@ -858,61 +858,58 @@ pub fn apply_memtrie_changes(
     changes: &MemTrieChanges,
     block_height: BlockHeight,
 ) -> CryptoHash {
-    memtries
-        .construct_root(block_height, |arena| {
-            let mut last_node_id: Option<MemTrieNodeId> = None;
-            let map_to_new_node_id = |node_id: OldOrUpdatedNodeId,
-                                      old_to_new_map: &HashMap<
-                UpdatedMemTrieNodeId,
-                MemTrieNodeId,
-            >|
-             -> MemTrieNodeId {
-                match node_id {
-                    OldOrUpdatedNodeId::Updated(node_id) => *old_to_new_map.get(&node_id).unwrap(),
-                    OldOrUpdatedNodeId::Old(node_id) => node_id,
-                }
-            };
-
-            let mut updated_to_new_map = HashMap::<UpdatedMemTrieNodeId, MemTrieNodeId>::new();
-            let updated_nodes = &changes.updated_nodes;
-            let node_ids_with_hashes = &changes.node_ids_with_hashes;
-            for (node_id, node_hash) in node_ids_with_hashes.iter() {
-                let node = updated_nodes.get(*node_id).unwrap().clone().unwrap();
-                let node = match &node {
-                    UpdatedMemTrieNode::Empty => unreachable!(),
-                    UpdatedMemTrieNode::Branch { children, value } => {
-                        let mut new_children = [None; 16];
-                        for i in 0..16 {
-                            if let Some(child) = children[i] {
-                                new_children[i] =
-                                    Some(map_to_new_node_id(child, &updated_to_new_map));
-                            }
-                        }
-                        match value {
-                            Some(value) => {
-                                InputMemTrieNode::BranchWithValue { children: new_children, value }
-                            }
-                            None => InputMemTrieNode::Branch { children: new_children },
-                        }
-                    }
-                    UpdatedMemTrieNode::Extension { extension, child } => {
-                        InputMemTrieNode::Extension {
-                            extension,
-                            child: map_to_new_node_id(*child, &updated_to_new_map),
-                        }
+    let arena = &mut memtries.arena;
+    let mut node_dedup = memtries.node_dedup.as_mut();
+    let mut last_node_id: Option<MemTrieNodeId> = None;
+    let map_to_new_node_id = |node_id: OldOrUpdatedNodeId,
+                              old_to_new_map: &HashMap<UpdatedMemTrieNodeId, MemTrieNodeId>|
+     -> MemTrieNodeId {
+        match node_id {
+            OldOrUpdatedNodeId::Updated(node_id) => *old_to_new_map.get(&node_id).unwrap(),
+            OldOrUpdatedNodeId::Old(node_id) => node_id,
+        }
+    };
+
+    let mut updated_to_new_map = HashMap::<UpdatedMemTrieNodeId, MemTrieNodeId>::new();
+    let updated_nodes = &changes.updated_nodes;
+    let node_ids_with_hashes = &changes.node_ids_with_hashes;
+    for (node_id, node_hash) in node_ids_with_hashes.iter() {
+        let node = updated_nodes.get(*node_id).unwrap().clone().unwrap();
+        let node = match &node {
+            UpdatedMemTrieNode::Empty => unreachable!(),
+            UpdatedMemTrieNode::Branch { children, value } => {
+                let mut new_children = [None; 16];
+                for i in 0..16 {
+                    if let Some(child) = children[i] {
+                        new_children[i] = Some(map_to_new_node_id(child, &updated_to_new_map));
                     }
-                    UpdatedMemTrieNode::Leaf { extension, value } => {
-                        InputMemTrieNode::Leaf { value, extension }
+                }
+                match value {
+                    Some(value) => {
+                        InputMemTrieNode::BranchWithValue { children: new_children, value }
                     }
-                };
-                let mem_node_id = MemTrieNodeId::new_with_hash(arena, node, *node_hash);
-                updated_to_new_map.insert(*node_id, mem_node_id);
-                last_node_id = Some(mem_node_id);
+                    None => InputMemTrieNode::Branch { children: new_children },
+                }
             }
+            UpdatedMemTrieNode::Extension { extension, child } => InputMemTrieNode::Extension {
+                extension,
+                child: map_to_new_node_id(*child, &updated_to_new_map),
+            },
+            UpdatedMemTrieNode::Leaf { extension, value } => {
+                InputMemTrieNode::Leaf { value, extension }
+            }
+        };
+        let mut mem_node_id = MemTrieNodeId::new_with_hash(arena, node, *node_hash);
+        if let Some(node_dedup) = node_dedup.as_mut() {
+            // Nodes are created bottom-up, so the children are already
+            // deduplicated.
+            mem_node_id = node_dedup.deduplicate_new_node(arena, mem_node_id);
+        }
+        updated_to_new_map.insert(*node_id, mem_node_id);
+        last_node_id = Some(mem_node_id);
+    }
 
-            Ok::<Option<MemTrieNodeId>, ()>(last_node_id)
-        })
-        .unwrap() // cannot fail
+    memtries.insert_constructed_root(block_height, last_node_id)
 }
 
 #[cfg(test)]
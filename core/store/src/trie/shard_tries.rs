@@ -1,16 +1,18 @@
 use super::mem::MemTries;
 use super::state_snapshot::{StateSnapshot, StateSnapshotConfig};
 use super::TrieRefcountSubtraction;
-use crate::flat::store_helper::remove_all_state_values;
+use crate::flat::store_helper::{get_flat_storage_status, remove_all_state_values};
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::trie::config::TrieConfig;
-use crate::trie::mem::loading::load_trie_from_flat_state_and_delta;
+use crate::trie::mem::loading::{get_state_root, load_trie_from_flat_state_and_delta};
+use crate::trie::mem::snapshot::mem_trie_snapshot_path;
 use crate::trie::mem::updating::apply_memtrie_changes;
 use crate::trie::prefetching_trie_storage::PrefetchingThreadsHandle;
 use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
 use crate::trie::{TrieRefcountAddition, POISONED_LOCK_ERR};
 use crate::{metrics, DBCol, PrefetchApi};
 use crate::{Store, StoreUpdate, Trie, TrieChanges, TrieUpdate};
+use anyhow::Context;
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::{self, ShardUId};
@@ -21,7 +23,7 @@ use near_primitives::types::{
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
-use tracing::info;
+use tracing::{info, warn};
 
 struct ShardTriesInner {
     store: Store,
@@ -417,17 +419,31 @@ impl ShardTries {
         parallelize: bool,
     ) -> Result<(), StorageError> {
         info!(target: "memtrie", "Loading trie to memory for shard {:?}...", shard_uid);
-        let mem_tries = load_trie_from_flat_state_and_delta(
+        let mut mem_tries = load_trie_from_flat_state_and_delta(
             &self.0.store,
             *shard_uid,
             state_root,
             parallelize,
+            self.0.trie_config.mem_trie_snapshot_dir.as_deref(),
         )?;
+        if self.0.trie_config.deduplicate_mem_trie_nodes {
+            mem_tries.enable_deduplication();
+        }
         self.0.mem_tries.write().unwrap().insert(*shard_uid, Arc::new(RwLock::new(mem_tries)));
         info!(target: "memtrie", "Memtrie loading complete for shard {:?}", shard_uid);
         Ok(())
     }
 
+    /// Reloads all the loaded memtries from flat storage, e.g. after the
+    /// database was reverted to a snapshot.
+    pub fn reload_mem_tries(&self) -> Result<(), StorageError> {
+        let shard_uids = self.0.mem_tries.read().unwrap().keys().copied().collect::<Vec<_>>();
+        for shard_uid in &shard_uids {
+            self.load_mem_trie(shard_uid, None, true)?;
+        }
+        Ok(())
+    }
+
     /// Loads in-memory trie upon catchup, if it is enabled.
     /// Requires state root because `ChunkExtra` is not available at the time mem-trie is being loaded.
     pub fn load_mem_trie_on_catchup(
@@ -474,6 +490,37 @@ impl ShardTries {
         Ok(())
     }
 
+    /// Saves the loaded in-memory tries to snapshots, if they are enabled, so
+    /// that they can be loaded quickly on the next start.  Must be called once
+    /// block processing has stopped, as the snapshot of a shard is only usable
+    /// if its flat head doesn't move afterwards.
+    pub fn save_mem_trie_snapshots(&self) -> Result<(), anyhow::Error> {
+        let Some(dir) = &self.0.trie_config.mem_trie_snapshot_dir else {
+            return Ok(());
+        };
+        let mem_tries = self.0.mem_tries.read().unwrap().clone();
+        for (shard_uid, mem_tries) in mem_tries {
+            let _span =
+                tracing::info_span!(target: "memtrie", "save_snapshot", %shard_uid).entered();
+            let start = std::time::Instant::now();
+            let mem_tries = mem_tries.read().unwrap();
+            let flat_head = match get_flat_storage_status(&self.0.store, shard_uid)? {
+                FlatStorageStatus::Ready(status) => status.flat_head,
+                status => {
+                    warn!(target: "memtrie", ?status, "Flat storage is not ready, not saving snapshot");
+                    continue;
+                }
+            };
+            let state_root = get_state_root(&self.0.store, flat_head.hash, shard_uid)?;
+            let path = mem_trie_snapshot_path(dir, shard_uid);
+            mem_tries.save_snapshot(&path, flat_head, state_root).with_context(|| {
+                format!("failed to save memtrie snapshot to {}", path.display())
+            })?;
+            info!(target: "memtrie", ?path, elapsed = ?start.elapsed(), "Saved memtrie snapshot");
+        }
+        Ok(())
+    }
+
     /// Retrieves the in-memory tries for the shard.
     pub fn get_mem_tries(&self, shard_uid: ShardUId) -> Option<Arc<RwLock<MemTries>>> {
         let guard = self.0.mem_tries.read().unwrap();
@@ -0,0 +1,94 @@
+use near_chain::ChainStoreAccess;
+use near_chain_configs::Genesis;
+use near_client::test_utils::TestEnv;
+use near_client::ProcessTxResponse;
+use near_crypto::{InMemorySigner, KeyType};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::account_activity::{AccountActivity, AccountActivityKind};
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, BlockHeight};
+use near_store::DBCol;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+fn get_activity(
+    env: &TestEnv,
+    account_id: &AccountId,
+) -> Vec<((BlockHeight, u32), AccountActivity)> {
+    env.clients[0].chain.chain_store().get_account_activity(account_id, (0, 0), 100).unwrap()
+}
+
+fn contains(
+    records: &[((BlockHeight, u32), AccountActivity)],
+    kind: AccountActivityKind,
+    id: &CryptoHash,
+) -> bool {
+    records.iter().any(|(_, activity)| activity.kind == kind && &activity.id == id)
+}
+
+/// Sends money between two accounts and checks that the transaction and the
+/// transfer receipt are indexed for both of them once their blocks are final.
+/// Then disables the index and checks that the records are still garbage
+/// collected together with their blocks.
+#[test]
+fn test_account_activity_indexing() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let sender: AccountId = "test0".parse().unwrap();
+    let receiver: AccountId = "test1".parse().unwrap();
+    let mut genesis = Genesis::test(vec![sender.clone(), receiver.clone()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    env.clients[0].chain.mut_chain_store().set_save_account_activity(true);
+
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer = InMemorySigner::from_seed(sender.clone(), KeyType::ED25519, "test0").into();
+    let tx = SignedTransaction::send_money(
+        1,
+        sender.clone(),
+        receiver.clone(),
+        &signer,
+        100,
+        genesis_hash,
+    );
+    let tx_hash = tx.get_hash();
+    assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    for height in 1..10 {
+        env.produce_block(0, height);
+    }
+
+    let outcomes = env.clients[0].chain.chain_store().get_outcomes_by_id(&tx_hash).unwrap();
+    let receipt_id = outcomes[0].outcome_with_id.outcome.receipt_ids[0];
+    let sender_activity = get_activity(&env, &sender);
+    let receiver_activity = get_activity(&env, &receiver);
+    for activity in [&sender_activity, &receiver_activity] {
+        assert!(contains(activity, AccountActivityKind::Transaction, &tx_hash));
+        // The transfer receipt is applied from the incoming receipts of the
+        // next chunk, with the sender as its predecessor.
+        assert!(contains(activity, AccountActivityKind::Receipt, &receipt_id));
+    }
+    let (tx_height, _) = sender_activity
+        .iter()
+        .find(|(_, activity)| activity.id == tx_hash)
+        .map(|(position, _)| *position)
+        .unwrap();
+    let (receipt_height, _) = receiver_activity
+        .iter()
+        .find(|(_, activity)| activity.id == receipt_id)
+        .map(|(position, _)| *position)
+        .unwrap();
+    assert!(receipt_height > tx_height);
+
+    env.clients[0].chain.mut_chain_store().set_save_account_activity(false);
+    let max_height = env.clients[0].config.gc.gc_num_epochs_to_keep * epoch_length * 3;
+    for height in 10..max_height {
+        env.produce_block(0, height);
+    }
+    assert!(env.clients[0].chain.get_block_by_height(receipt_height).is_err());
+    assert!(get_activity(&env, &sender).is_empty());
+    assert!(get_activity(&env, &receiver).is_empty());
+    let store = env.clients[0].chain.chain_store().store();
+    assert_eq!(store.iter(DBCol::AccountActivity).count(), 0);
+    assert_eq!(store.iter(DBCol::AccountActivityByHeight).count(), 0);
+}
@@ -1,16 +1,27 @@
+use actix::{Addr, System};
+use assert_matches::assert_matches;
 use borsh::BorshDeserialize;
-use near_chain::Provenance;
-use near_chain_configs::{Genesis, MutableConfigValue};
+use near_actix_test_utils::run_actix;
+use near_async::messaging::{noop, IntoMultiSender};
+use near_async::time::Clock;
+use near_chain::{ChainGenesis, Provenance};
+use near_chain_configs::{ClientConfig, Genesis, MutableConfigValue};
 use near_client::test_utils::TestEnv;
-use near_client::ProcessTxResponse;
+use near_client::{
+    ColdStoreFallback, ProcessTxResponse, Query, QueryError, ViewClientActor, ViewClientActorInner,
+};
 use near_crypto::{InMemorySigner, KeyType, Signer};
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
 use near_epoch_manager::EpochManager;
 use near_o11y::testonly::init_test_logger;
+use near_o11y::WithSpanContextExt;
 use near_primitives::block::Tip;
 use near_primitives::sharding::{PartialEncodedChunk, ShardChunk};
 use near_primitives::transaction::{
     Action, DeployContractAction, FunctionCallAction, SignedTransaction,
 };
+use near_primitives::types::{BlockId, BlockReference};
+use near_primitives::views::{QueryRequest, QueryResponseKind};
 use near_primitives_core::hash::CryptoHash;
 use near_primitives_core::types::AccountId;
 use near_store::cold_storage::{
@@ -22,8 +33,9 @@ use near_store::metadata::DB_VERSION;
 use near_store::test_utils::create_test_node_storage_with_cold;
 use near_store::{DBCol, Store, COLD_HEAD_KEY, HEAD_KEY};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
-use nearcore::{cold_storage::spawn_cold_store_loop, NearConfig};
+use nearcore::{cold_storage::spawn_cold_store_loop, NearConfig, NightshadeRuntime};
 use std::collections::HashSet;
+use std::path::Path;
 use std::str::FromStr;
 use strum::IntoEnumIterator;
 
@@ -508,3 +520,128 @@ fn test_cold_loop_on_gc_boundary() {
         end_cold_head
     );
 }
+
+/// Producing blocks on an archival node with split storage until the first
+/// blocks are garbage collected from the hot storage. Then checking that the
+/// balance of an account at such a height can still be viewed by a view
+/// client reading from the split store and by a view client reading from the
+/// hot storage with a fallback to the split store, while a view client reading
+/// only from the hot storage reports the block as garbage collected.
+#[test]
+fn test_view_account_at_garbage_collected_height() {
+    init_test_logger();
+
+    let epoch_length = 5;
+
+    let mut genesis = Genesis::test(vec![test0(), test1()], 1);
+    genesis.config.epoch_length = epoch_length;
+
+    let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Hot);
+    let hot_store = &storage.get_hot_store();
+    let cold_db = storage.cold_db().unwrap();
+    let mut env = TestEnv::builder(&genesis.config)
+        .archive(true)
+        .save_trie_changes(true)
+        .stores(vec![hot_store.clone()])
+        .nightshade_runtimes(&genesis)
+        .build();
+
+    test_cold_genesis_update(&cold_db, hot_store).unwrap();
+
+    let max_height = env.clients[0].config.gc.gc_num_epochs_to_keep * epoch_length * 2;
+    let mut last_hash = *env.clients[0].chain.genesis().hash();
+    let mut first_header = None;
+    for height in 1..max_height {
+        if height == 1 {
+            let signer = InMemorySigner::from_seed(test0(), KeyType::ED25519, "test0").into();
+            let tx = create_tx_send_money(height, &signer, last_hash);
+            assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+        }
+
+        let block = env.clients[0].produce_block(height).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+
+        let epoch_id = block.header().epoch_id();
+        let shard_layout = env.clients[0].epoch_manager.get_shard_layout(epoch_id).unwrap();
+        update_cold_db(&cold_db, hot_store, &shard_layout, &height, 1).unwrap();
+        update_cold_head(&cold_db, hot_store, &height).unwrap();
+
+        first_header.get_or_insert_with(|| block.header().clone());
+        last_hash = *block.hash();
+    }
+
+    let header = first_header.unwrap();
+    assert!(env.clients[0].chain.get_block(header.hash()).is_err());
+
+    let config = env.clients[0].config.clone();
+    let split_store = storage.get_split_store().unwrap();
+    let hot_store = hot_store.clone();
+    let query = Query::new(
+        BlockReference::BlockId(BlockId::Hash(*header.hash())),
+        QueryRequest::ViewAccount { account_id: test0() },
+    );
+    let query_by_height = Query::new(
+        BlockReference::BlockId(BlockId::Height(header.height())),
+        QueryRequest::ViewAccount { account_id: test0() },
+    );
+    run_actix(async move {
+        let home_dir = tempfile::tempdir().unwrap();
+        let split_parts = view_client_parts(split_store, &genesis, &home_dir.path().join("split"));
+        let hot_parts = view_client_parts(hot_store, &genesis, &home_dir.path().join("hot"));
+        let split_view_client =
+            spawn_view_client(split_parts.clone(), &genesis, config.clone(), None);
+        let fallback_view_client =
+            spawn_view_client(hot_parts.clone(), &genesis, config.clone(), Some(split_parts));
+        let hot_view_client = spawn_view_client(hot_parts, &genesis, config, None);
+        actix::spawn(async move {
+            for (view_client, query) in [
+                (&split_view_client, &query),
+                (&fallback_view_client, &query),
+                (&fallback_view_client, &query_by_height),
+            ] {
+                let response =
+                    view_client.send(query.clone().with_span_context()).await.unwrap().unwrap();
+                assert_eq!(response.block_hash, *header.hash());
+                assert_matches!(response.kind, QueryResponseKind::ViewAccount(_));
+            }
+
+            // Without the cold storage the state of the block is gone.
+            let err = hot_view_client.send(query.with_span_context()).await.unwrap().unwrap_err();
+            assert_matches!(err, QueryError::GarbageCollectedBlock { .. });
+            drop(home_dir);
+            System::current().stop();
+        });
+    });
+}
+
+/// Creates the epoch manager, shard tracker and runtime of a view client
+/// reading from `store`.
+fn view_client_parts(store: Store, genesis: &Genesis, home_dir: &Path) -> ColdStoreFallback {
+    std::fs::create_dir_all(home_dir).unwrap();
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &genesis.config);
+    let shard_tracker = ShardTracker::new(TrackedConfig::AllShards, epoch_manager.clone());
+    let runtime = NightshadeRuntime::test(home_dir, store, &genesis.config, epoch_manager.clone());
+    ColdStoreFallback { epoch_manager, shard_tracker, runtime }
+}
+
+/// Starts a view client the way archival nodes start it, optionally falling
+/// back to the split store for garbage collected blocks.
+fn spawn_view_client(
+    parts: ColdStoreFallback,
+    genesis: &Genesis,
+    config: ClientConfig,
+    cold_store_fallback: Option<ColdStoreFallback>,
+) -> Addr<ViewClientActor> {
+    ViewClientActorInner::spawn_actix_actor(
+        Clock::real(),
+        MutableConfigValue::new(None, "validator_signer"),
+        ChainGenesis::new(&genesis.config),
+        parts.epoch_manager,
+        parts.shard_tracker,
+        parts.runtime,
+        noop().into_multi_sender(),
+        config,
+        Default::default(),
+        cold_store_fallback,
+    )
+}
@@ -1,3 +1,4 @@
+mod account_activity;
 mod benchmarks;
 mod block_corruption;
 mod challenges;
@@ -18,6 +18,7 @@ use near_chain::ChainStore;
 use near_chain::{Block, BlockProcessingArtifact, ChainStoreAccess, Error, Provenance};
 use near_chain_configs::test_utils::{TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
 use near_chain_configs::{Genesis, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP, NEAR_BASE};
+use near_chunks::client::ShardedTransactionPool;
 use near_client::test_utils::{
     create_chunk_on_height, setup_mock, setup_mock_all_validators, TestEnv,
 };
@@ -25,7 +26,7 @@ use near_client::{
     BlockApproval, BlockResponse, GetBlockWithMerkleTree, ProcessTxResponse, ProduceChunkResult,
     SetNetworkInfo,
 };
-use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature};
+use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
 use near_network::test_utils::{wait_or_panic, MockPeerManagerAdapter};
 use near_network::types::{
     BlockInfo, ConnectedPeerInfo, HighestHeightPeerInfo, NetworkInfo, PeerChainInfo,
@@ -351,7 +352,8 @@ fn receive_network_block() {
                 block_merkle_tree.root(),
                 Clock::real(),
                 None,
-            );
+            )
+            .unwrap();
             actor_handles.client_actor.do_send(
                 BlockResponse { block, peer_id: PeerInfo::random().id, was_requested: false }
                     .with_span_context(),
@@ -438,7 +440,8 @@ fn produce_block_with_approvals() {
                 block_merkle_tree.root(),
                 Clock::real(),
                 None,
-            );
+            )
+            .unwrap();
             actor_handles.client_actor.do_send(
                 BlockResponse {
                     block: block.clone(),
@@ -461,7 +464,8 @@ fn produce_block_with_approvals() {
                     block.header().height(),
                     10, // the height at which "test1" is producing
                     &signer,
-                );
+                )
+                .unwrap();
                 actor_handles
                     .client_actor
                     .do_send(BlockApproval(approval, PeerInfo::random().id).with_span_context());
@@ -655,7 +659,8 @@ fn invalid_blocks_common(is_requested: bool) {
                 block_merkle_tree.root(),
                 Clock::real(),
                 None,
-            );
+            )
+            .unwrap();
             // Send block with invalid chunk mask
             let mut block = valid_block.clone();
             block.mut_header().get_mut().inner_rest.chunk_mask = vec![];
@@ -1064,6 +1069,54 @@ fn test_process_invalid_tx() {
     );
 }
 
+/// Persisted transactions are inserted back into an empty pool, except for the invalid ones.
+#[test]
+fn test_restore_transaction_pool() {
+    init_test_logger();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    env.clients[0].config.transaction_pool.persist = true;
+    let signer: Signer =
+        InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0").into();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let send_money = |nonce, block_hash| {
+        SignedTransaction::send_money(
+            nonce,
+            "test0".parse().unwrap(),
+            "test1".parse().unwrap(),
+            &signer,
+            100,
+            block_hash,
+        )
+    };
+    for nonce in 1..=2 {
+        let tx = send_money(nonce, genesis_hash);
+        assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    }
+    env.clients[0].persist_transaction_pool().unwrap();
+    let mut persisted = env.clients[0].chain.chain_store().load_transaction_pool().unwrap();
+    assert_eq!(persisted.len(), 2);
+
+    // A transaction referring to an unknown block is dropped on restore.
+    persisted.push(send_money(3, hash(&[1])));
+    // So is a transaction whose signature doesn't match.
+    let tx = send_money(4, genesis_hash);
+    persisted.push(SignedTransaction::new(Signature::empty(KeyType::ED25519), tx.transaction));
+    env.clients[0].chain.chain_store().save_transaction_pool(&persisted).unwrap();
+
+    let config = env.clients[0].config.clone();
+    env.clients[0].sharded_tx_pool = ShardedTransactionPool::new(
+        [0; 32],
+        config.transaction_pool_size_limit,
+        config.transaction_pool,
+    );
+    env.clients[0].restore_transaction_pool().unwrap();
+    let mut nonces: Vec<_> =
+        env.clients[0].sharded_tx_pool.transactions().map(|tx| tx.transaction.nonce()).collect();
+    nonces.sort();
+    assert_eq!(nonces, vec![1, 2]);
+}
+
 /// If someone produce a block with Utc::now() + 1 min, we should produce a block with valid timestamp
 #[test]
 fn test_time_attack() {
@@ -2274,7 +2327,7 @@ fn test_validate_chunk_extra() {
         block.mut_header().get_mut().inner_rest.chunk_mask = vec![true];
         block.mut_header().get_mut().inner_lite.prev_outcome_root =
             Block::compute_outcome_root(block.chunks().iter());
-        let endorsement = ChunkEndorsement::new(chunk_header.chunk_hash(), &validator_signer);
+        let endorsement = ChunkEndorsement::new(&chunk_header, &validator_signer).unwrap();
         block.set_chunk_endorsements(vec![vec![Some(Box::new(endorsement.signature))]]);
         block.mut_header().get_mut().inner_rest.block_body_hash =
             block.compute_block_body_hash().unwrap();
@@ -3326,11 +3379,13 @@ fn test_header_version_downgrade() {
             BlockHeader::BlockHeaderV1(header) => {
                 let header = Arc::make_mut(header);
                 header.inner_rest.latest_protocol_version = PROTOCOL_VERSION;
-                let (hash, signature) = validator_signer.sign_block_header_parts(
-                    header.prev_hash,
-                    &borsh::to_vec(&header.inner_lite).expect("Failed to serialize"),
-                    &borsh::to_vec(&header.inner_rest).expect("Failed to serialize"),
-                );
+                let (hash, signature) = validator_signer
+                    .sign_block_header_parts(
+                        header.prev_hash,
+                        &borsh::to_vec(&header.inner_lite).expect("Failed to serialize"),
+                        &borsh::to_vec(&header.inner_rest).expect("Failed to serialize"),
+                    )
+                    .unwrap();
                 header.hash = hash;
                 header.signature = signature;
             }
@@ -3708,7 +3763,9 @@ mod contract_precompilation_tests {
                 &"test0".parse().unwrap(),
                 "log_something",
                 &[],
+                &[],
                 &mut logs,
+                None,
                 &MockEpochInfoProvider::default(),
             )
             .unwrap();
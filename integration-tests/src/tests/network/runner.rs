@@ -124,6 +124,7 @@ fn setup_network_node(
         network_adapter.as_multi_sender(),
         client_config.clone(),
         adv,
+        None,
     );
     let (shards_manager_actor, _) = start_shards_manager(
         epoch_manager.clone(),
@@ -3,13 +3,14 @@ use std::{collections::HashMap, io, sync::Arc};
 use borsh::BorshDeserialize;
 
 use crate::runtime_utils::{get_runtime_and_trie, get_test_trie_viewer, TEST_SHARD_UID};
+use near_parameters::RuntimeConfigStore;
 use near_primitives::{
     account::Account,
     hash::{hash as sha256, CryptoHash},
     serialize::to_base64,
     trie_key::trie_key_parsers,
     types::{AccountId, StateRoot},
-    views::StateItem,
+    views::{CallGasUsageView, StateItem, StateOverride},
 };
 use near_primitives::{
     test_utils::MockEpochInfoProvider,
@@ -18,6 +19,7 @@ use near_primitives::{
     version::PROTOCOL_VERSION,
 };
 use near_store::{set_account, NibbleSlice, RawTrieNode, RawTrieNodeWithSize, ShardUId};
+use near_vm_runner::{ContractRuntimeCache, MockContractRuntimeCache};
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
 use testlib::runtime_utils::alice_account;
@@ -122,7 +124,9 @@ fn test_view_call() {
         &"test.contract".parse().unwrap(),
         "run_test",
         &[],
+        &[],
         &mut logs,
+        None,
         &MockEpochInfoProvider::default(),
     );
 
@@ -151,7 +155,9 @@ fn test_view_call_try_changing_storage() {
         &"test.contract".parse().unwrap(),
         "run_test_with_storage_change",
         &[],
+        &[],
         &mut logs,
+        None,
         &MockEpochInfoProvider::default(),
     );
     let err = result.unwrap_err();
@@ -184,10 +190,157 @@ fn test_view_call_with_args() {
         &"test.contract".parse().unwrap(),
         "sum_with_input",
         &args,
+        &[],
         &mut logs,
+        None,
+        &MockEpochInfoProvider::default(),
+    );
+    assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
+}
+
+#[test]
+fn test_view_call_gas_usage() {
+    let (viewer, root) = get_test_trie_viewer();
+    let args: Vec<_> = [1u64, 2u64].iter().flat_map(|x| (*x).to_le_bytes().to_vec()).collect();
+    let mut logs = vec![];
+    let mut gas_usage = CallGasUsageView::default();
+    let view_state = ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        shard_id: ShardUId::single_shard().shard_id(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    };
+    let view_call_result = viewer.call_function(
+        root,
+        view_state,
+        &"test.contract".parse().unwrap(),
+        "sum_with_input",
+        &args,
+        &[],
+        &mut logs,
+        Some(&mut gas_usage),
+        &MockEpochInfoProvider::default(),
+    );
+    assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
+    assert!(gas_usage.gas_burnt > 0);
+    let profile_gas = |cost: &str| {
+        gas_usage.gas_profile.iter().find(|item| item.cost == cost).map(|item| item.gas_used)
+    };
+    assert!(profile_gas("WASM_INSTRUCTION").unwrap() > 0);
+    assert!(profile_gas("INPUT_BASE").unwrap() > 0);
+    let total_gas = gas_usage.gas_profile.iter().map(|item| item.gas_used).sum::<u64>();
+    assert_eq!(total_gas, gas_usage.gas_burnt);
+}
+
+#[test]
+fn test_view_call_function_profile() {
+    let (viewer, root) = get_test_trie_viewer();
+    let args: Vec<_> = [1u64, 2u64].iter().flat_map(|x| (*x).to_le_bytes().to_vec()).collect();
+    let mut gas_usage =
+        CallGasUsageView { function_profile: Some(String::new()), ..Default::default() };
+    let view_state = ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        shard_id: ShardUId::single_shard().shard_id(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    };
+    let view_call_result = viewer.call_function(
+        root,
+        view_state,
+        &"test.contract".parse().unwrap(),
+        "sum_with_input",
+        &args,
+        &[],
+        &mut vec![],
+        Some(&mut gas_usage),
         &MockEpochInfoProvider::default(),
     );
     assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
+    let function_profile = gas_usage.function_profile.unwrap();
+    let mut total_gas = 0;
+    for line in function_profile.lines() {
+        let (stack, gas) = line.rsplit_once(' ').unwrap();
+        assert!(stack.starts_with("test.contract;"), "{line}");
+        total_gas += gas.parse::<u64>().unwrap();
+    }
+    assert!(total_gas > 0);
+    assert!(function_profile.contains(";[host] "), "{function_profile}");
+}
+
+#[test]
+fn test_view_call_with_state_overrides() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let viewer = TrieViewer::default();
+    let account_id: AccountId = "carol.near".parse().unwrap();
+    let state_overrides = vec![StateOverride {
+        account_id: account_id.clone(),
+        code: Some(near_test_contracts::rs_contract().to_vec()),
+        balance: Some(42),
+        state: vec![StateItem {
+            key: 1u64.to_le_bytes().to_vec().into(),
+            value: 2u64.to_le_bytes().to_vec().into(),
+        }],
+    }];
+    let cache = MockContractRuntimeCache::default();
+    let call = |method_name: &str, args: &[u8], state_overrides: &[StateOverride]| {
+        let view_state = ViewApplyState {
+            block_height: 1,
+            prev_block_hash: CryptoHash::default(),
+            block_hash: CryptoHash::default(),
+            shard_id: ShardUId::single_shard().shard_id(),
+            epoch_id: EpochId::default(),
+            epoch_height: 0,
+            block_timestamp: 1,
+            current_protocol_version: PROTOCOL_VERSION,
+            cache: Some(cache.handle()),
+        };
+        viewer.call_function(
+            tries.new_trie_update(TEST_SHARD_UID, root),
+            view_state,
+            &account_id,
+            method_name,
+            args,
+            state_overrides,
+            &mut vec![],
+            None,
+            &MockEpochInfoProvider::default(),
+        )
+    };
+    assert_eq!(
+        call("read_value", &1u64.to_le_bytes(), &state_overrides).unwrap(),
+        2u64.to_le_bytes()
+    );
+    assert_eq!(call("ext_account_balance", &[], &state_overrides).unwrap(), 42u128.to_le_bytes());
+    // The overrides aren't persisted, not even the compiled code.
+    let state_update = tries.new_trie_update(TEST_SHARD_UID, root);
+    let account = viewer.view_account(&state_update, &account_id).unwrap();
+    assert_eq!(account.code_hash(), CryptoHash::default());
+    assert_eq!(cache.len(), 0);
+
+    // The code is subject to the contract size limit.
+    let max_contract_size = RuntimeConfigStore::new(None)
+        .get_config(PROTOCOL_VERSION)
+        .wasm_config
+        .limit_config
+        .max_contract_size;
+    let state_overrides = vec![StateOverride {
+        account_id: account_id.clone(),
+        code: Some(vec![0; max_contract_size as usize + 1]),
+        balance: None,
+        state: vec![],
+    }];
+    let err = call("read_value", &1u64.to_le_bytes(), &state_overrides).unwrap_err();
+    assert!(matches!(err, errors::CallFunctionError::VMError { .. }), "{err:?}");
 }
 
 fn assert_view_state(
@@ -407,7 +560,9 @@ fn test_log_when_panic() {
             &"test.contract".parse().unwrap(),
             "panic_after_logging",
             &[],
+            &[],
             &mut logs,
+            None,
             &MockEpochInfoProvider::default(),
         )
         .unwrap_err();
@@ -113,6 +113,9 @@ impl User for RpcUser {
             account_id: account_id.clone(),
             method_name: method_name.to_string(),
             args: args.to_vec().into(),
+            include_gas_usage: false,
+            include_function_profile: false,
+            state_overrides: vec![],
         };
         match self.query(query)?.kind {
             QueryResponseKind::CallResult(call_result) => Ok(call_result),
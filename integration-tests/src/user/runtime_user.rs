@@ -182,6 +182,7 @@ impl RuntimeUser {
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info,
+            impersonated_accounts: Default::default(),
         }
     }
 
@@ -317,7 +318,9 @@ impl User for RuntimeUser {
                 account_id,
                 method_name,
                 args,
+                &[],
                 &mut result.logs,
+                None,
                 &self.epoch_info_provider,
             )
             .map_err(|err| err.to_string())?;
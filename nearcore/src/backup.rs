@@ -0,0 +1,77 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use near_async::time::{Duration, Instant};
+use near_store::NodeStorage;
+
+use crate::config::BackupConfig;
+use crate::metrics;
+
+/// A handle to the thread that periodically backs up the databases of the
+/// node, used to stop it.
+pub struct BackupLoopHandle {
+    join_handle: std::thread::JoinHandle<()>,
+    keep_going: Arc<AtomicBool>,
+}
+
+impl BackupLoopHandle {
+    pub fn stop(self) {
+        self.keep_going.store(false, Ordering::Relaxed);
+        match self.join_handle.join() {
+            Ok(_) => {
+                tracing::debug!(target: "backup", "Joined the backup loop thread");
+            }
+            Err(_) => {
+                tracing::error!(target: "backup", "Failed to join the backup loop thread");
+            }
+        }
+    }
+}
+
+/// Spawns a thread making a backup of the databases every `config.period`,
+/// starting one period after the node starts so that restarts don’t each
+/// trigger a backup.
+///
+/// Returns an error if the databases don’t support backups.
+pub fn spawn_backup_loop(
+    home_dir: &Path,
+    config: &BackupConfig,
+    storage: &NodeStorage,
+) -> anyhow::Result<BackupLoopHandle> {
+    near_store::backup::ensure_backups_supported(storage)?;
+    let backup_dir = home_dir.join(&config.path);
+    let period = config.period;
+    let num_backups_to_keep = config.num_backups_to_keep;
+    let storage = storage.clone();
+    let keep_going = Arc::new(AtomicBool::new(true));
+    let keep_going_clone = keep_going.clone();
+
+    tracing::info!(target: "backup", backup_dir = %backup_dir.display(), "Spawning the backup loop");
+    let join_handle = std::thread::Builder::new().name("backup".to_string()).spawn(move || {
+        let mut start = Instant::now();
+        loop {
+            // Sleep in short steps so that stopping the loop isn't delayed by
+            // a whole period.
+            while keep_going_clone.load(Ordering::Relaxed) && start.elapsed() < period {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+            if !keep_going_clone.load(Ordering::Relaxed) {
+                break;
+            }
+            start = Instant::now();
+            match near_store::backup::create_backup(&storage, &backup_dir, num_backups_to_keep) {
+                Ok(info) => {
+                    metrics::BACKUP_RESULT.with_label_values(&["ok"]).inc();
+                    metrics::BACKUP_HEIGHT.set(info.height as i64);
+                }
+                Err(err) => {
+                    metrics::BACKUP_RESULT.with_label_values(&["error"]).inc();
+                    tracing::error!(target: "backup", ?err, "Failed to create a backup");
+                }
+            }
+        }
+        tracing::debug!(target: "backup", "Stopping the backup loop");
+    })?;
+    Ok(BackupLoopHandle { join_handle, keep_going })
+}
@@ -20,13 +20,13 @@ use near_chain_configs::{
     default_tx_routing_height_horizon, default_view_client_threads,
     default_view_client_throttle_period, get_initial_supply, ChunkDistributionNetworkConfig,
     ClientConfig, GCConfig, Genesis, GenesisConfig, GenesisValidationMode, LogSummaryStyle,
-    MutableConfigValue, MutableValidatorSigner, ReshardingConfig, StateSyncConfig,
-    BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
-    CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD, EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD,
-    GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME, INITIAL_GAS_LIMIT, MAX_INFLATION_RATE,
-    MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE, NUM_BLOCKS_PER_YEAR,
-    NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE, PROTOCOL_UPGRADE_STAKE_THRESHOLD,
-    TRANSACTION_VALIDITY_PERIOD,
+    MutableConfigValue, MutableValidatorSigner, ReshardingConfig, ShardSchedulerConfig,
+    StateSyncConfig, TransactionPoolConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD,
+    CHUNK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD,
+    EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD, GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME,
+    INITIAL_GAS_LIMIT, MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE,
+    NUM_BLOCKS_PER_YEAR, NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE,
+    PROTOCOL_UPGRADE_STAKE_THRESHOLD, TRANSACTION_VALIDITY_PERIOD,
 };
 use near_config_utils::{ValidationError, ValidationErrors};
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey};
@@ -36,6 +36,7 @@ use near_jsonrpc::RpcConfig;
 use near_network::config::NetworkConfig;
 use near_network::tcp;
 use near_o11y::log_config::LogConfig;
+use near_parameters::RuntimeConfigStore;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::test_utils::create_test_signer;
@@ -113,6 +114,27 @@ fn default_doomslug_step_period() -> Duration {
     Duration::milliseconds(100)
 }
 
+fn default_backup_period() -> Duration {
+    Duration::hours(6)
+}
+
+/// Configuration of the periodic backups of the node databases, see
+/// `near_store::backup`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupConfig {
+    /// Directory the backups are stored in.  Relative paths are resolved
+    /// against the home directory.
+    pub path: PathBuf,
+    /// Time between two consecutive backups.
+    #[serde(default = "default_backup_period")]
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub period: Duration,
+    /// Number of the latest backups to keep.  Older backups are deleted after
+    /// each new backup.  If not set, all backups are kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_backups_to_keep: Option<usize>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct Consensus {
     /// Minimum number of peers to start syncing.
@@ -278,6 +300,10 @@ pub struct Config {
     /// Setting this value too low (<1MB) on the validator might lead to production of smaller
     /// chunks and underutilizing the capacity of the network.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Ordering, per-signer quotas and eviction policy of the transaction pool.
+    pub transaction_pool: TransactionPoolConfig,
+    /// Thread pools and priorities used to apply the chunks of a block.
+    pub shard_scheduler: ShardSchedulerConfig,
     // Configuration for resharding.
     pub resharding_config: ReshardingConfig,
     /// If the node is not a chunk producer within that many blocks, then route
@@ -318,6 +344,14 @@ pub struct Config {
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// If set, the node signs with a validator key held by an external
+    /// signing daemon instead of the key in `validator_key_file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSignerConfig>,
+    /// If set, the node periodically makes incremental backups of its
+    /// databases.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<BackupConfig>,
 }
 
 fn is_false(value: &bool) -> bool {
@@ -358,6 +392,8 @@ impl Default for Config {
             state_sync: default_state_sync(),
             state_sync_enabled: default_state_sync_enabled(),
             transaction_pool_size_limit: default_transaction_pool_size_limit(),
+            transaction_pool: TransactionPoolConfig::default(),
+            shard_scheduler: ShardSchedulerConfig::default(),
             enable_multiline_logging: default_enable_multiline_logging(),
             resharding_config: ReshardingConfig::default(),
             tx_routing_height_horizon: default_tx_routing_height_horizon(),
@@ -368,6 +404,8 @@ impl Default for Config {
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
             max_loaded_contracts: 256,
             save_latest_witnesses: false,
+            remote_signer: None,
+            backup: None,
         }
     }
 }
@@ -392,8 +430,29 @@ fn default_cold_store_loop_sleep_duration() -> Duration {
     Duration::seconds(1)
 }
 
+fn default_remote_signer_timeout() -> Duration {
+    Duration::seconds(1)
+}
+
+/// Configuration of the connection to an external signing daemon, see
+/// `near_primitives::remote_signer`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RemoteSignerConfig {
+    /// Unix socket the daemon listens on.  Relative paths are resolved
+    /// against the home directory.
+    pub socket_path: PathBuf,
+    /// Timeout of a single request to the daemon.
+    #[serde(default = "default_remote_signer_timeout")]
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub timeout: Duration,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct SplitStorageConfig {
+    /// Whether the view client of an archival node should read from both the
+    /// hot and the cold storage for all requests.  Disabled by default, in
+    /// which case it reads from the cold storage only to answer queries about
+    /// blocks already garbage collected from the hot storage.
     #[serde(default = "default_enable_split_storage_view_client")]
     pub enable_split_storage_view_client: bool,
 
@@ -577,6 +636,8 @@ impl NearConfig {
                 state_sync_enabled: config.state_sync_enabled,
                 state_sync: config.state_sync.unwrap_or_default(),
                 transaction_pool_size_limit: config.transaction_pool_size_limit,
+                transaction_pool: config.transaction_pool,
+                shard_scheduler: config.shard_scheduler,
                 enable_multiline_logging: config.enable_multiline_logging.unwrap_or(true),
                 resharding_config: MutableConfigValue::new(
                     config.resharding_config,
@@ -591,6 +652,7 @@ impl NearConfig {
                 orphan_state_witness_pool_size: config.orphan_state_witness_pool_size,
                 orphan_state_witness_max_size: config.orphan_state_witness_max_size,
                 save_latest_witnesses: config.save_latest_witnesses,
+                save_account_activity: config.store.save_account_activity,
             },
             network_config: NetworkConfig::new(
                 config.network,
@@ -650,6 +712,19 @@ impl NightshadeRuntime {
         store: Store,
         config: &NearConfig,
         epoch_manager: Arc<EpochManagerHandle>,
+    ) -> std::io::Result<Arc<NightshadeRuntime>> {
+        Self::from_config_with_runtime_config_store(home_dir, store, config, epoch_manager, None)
+    }
+
+    /// Like `from_config` but applies chunks with the runtime configs of
+    /// `runtime_config_store` if it's set rather than with the ones of the
+    /// chain.
+    pub fn from_config_with_runtime_config_store(
+        home_dir: &Path,
+        store: Store,
+        config: &NearConfig,
+        epoch_manager: Arc<EpochManagerHandle>,
+        runtime_config_store: Option<RuntimeConfigStore>,
     ) -> std::io::Result<Arc<NightshadeRuntime>> {
         // TODO (#9989): directly use the new state snapshot config once the migration is done.
         let mut state_snapshot_type =
@@ -668,6 +743,11 @@ impl NightshadeRuntime {
                 .unwrap_or_else(|| PathBuf::from("data")),
             state_snapshot_subdir: PathBuf::from("state_snapshot"),
         };
+        let mut trie_config = TrieConfig::from_store_config(&config.config.store);
+        if config.config.store.save_mem_trie_snapshots {
+            trie_config.mem_trie_snapshot_dir =
+                Some(home_dir.join(&state_snapshot_config.hot_store_path).join("memtrie_snapshot"));
+        }
         // FIXME: this (and other contract runtime resources) should probably get constructed by
         // the caller and passed into this `NightshadeRuntime::from_config` here. But that's a big
         // refactor...
@@ -683,9 +763,9 @@ impl NightshadeRuntime {
             epoch_manager,
             config.client_config.trie_viewer_state_size_limit,
             config.client_config.max_gas_burnt_view,
-            None,
+            runtime_config_store,
             config.config.gc.gc_num_epochs_to_keep(),
-            TrieConfig::from_store_config(&config.config.store),
+            trie_config,
             state_snapshot_config,
         ))
     }
@@ -1196,7 +1276,10 @@ pub fn download_config(url: &str, path: &Path) -> Result<(), FileDownloadError>
 struct NodeKeyFile {
     account_id: String,
     public_key: PublicKey,
-    secret_key: near_crypto::SecretKey,
+    #[serde(default)]
+    secret_key: Option<near_crypto::SecretKey>,
+    #[serde(default)]
+    encrypted_secret_key: Option<near_crypto::EncryptedSecretKey>,
 }
 
 impl NodeKeyFile {
@@ -1208,7 +1291,18 @@ impl NodeKeyFile {
 
         let json_str_without_comments = near_config_utils::strip_comments_from_json_str(&json_str)?;
 
-        Ok(serde_json::from_str(&json_str_without_comments)?)
+        let mut node_key: Self = serde_json::from_str(&json_str_without_comments)?;
+        if let Some(encrypted) = node_key.encrypted_secret_key.take() {
+            let passphrase = near_crypto::key_file_passphrase()?;
+            node_key.secret_key = Some(encrypted.decrypt(&node_key.public_key, &passphrase)?);
+        }
+        if node_key.secret_key.is_none() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "node key file doesn't contain the secret key",
+            ));
+        }
+        Ok(node_key)
     }
 }
 
@@ -1223,7 +1317,7 @@ impl From<NodeKeyFile> for KeyFile {
             .try_into()
             .unwrap(),
             public_key: this.public_key,
-            secret_key: this.secret_key,
+            secret_key: this.secret_key.expect("checked in NodeKeyFile::from_file"),
         }
     }
 }
@@ -1242,6 +1336,32 @@ pub fn load_validator_key(validator_file: &Path) -> anyhow::Result<Option<Arc<Va
     }
 }
 
+/// Loads the validator signer configured for the node, which is either the
+/// remote signer or the key stored in `validator_key_file`.
+pub fn load_validator_signer(
+    dir: &Path,
+    config: &Config,
+) -> anyhow::Result<Option<Arc<ValidatorSigner>>> {
+    let Some(remote_signer) = &config.remote_signer else {
+        return load_validator_key(&dir.join(&config.validator_key_file));
+    };
+    let socket_path = dir.join(&remote_signer.socket_path);
+    #[cfg(unix)]
+    {
+        let signer = near_primitives::remote_signer::RemoteValidatorSigner::connect(
+            &socket_path,
+            remote_signer.timeout.unsigned_abs(),
+        )
+        .with_context(|| {
+            format!("Failed connecting to the remote signer at {}", socket_path.display())
+        })?;
+        info!(target: "neard", validator_id = %signer.validator_id(), socket_path = %socket_path.display(), "Using remote signer");
+        Ok(Some(Arc::new(signer.into())))
+    }
+    #[cfg(not(unix))]
+    bail!("Remote signer at {} is only supported on Unix", socket_path.display())
+}
+
 pub fn load_config(
     dir: &Path,
     genesis_validation: GenesisValidationMode,
@@ -1255,8 +1375,15 @@ pub fn load_config(
         validation_errors.push_errors(e)
     };
 
-    let validator_file: PathBuf = dir.join(&config.validator_key_file);
-    let validator_signer = match load_validator_key(&validator_file) {
+    // The remote signer is only connected to when the node starts, see
+    // `start_with_config`, so that commands which just read the config don't
+    // need the signing daemon to be running.
+    let validator_signer = if config.remote_signer.is_some() {
+        Ok(None)
+    } else {
+        load_validator_key(&dir.join(&config.validator_key_file))
+    };
+    let validator_signer = match validator_signer {
         Ok(validator_signer) => validator_signer,
         Err(e) => {
             validation_errors.push_validator_key_file_error(e.to_string());
@@ -1503,6 +1630,7 @@ mod tests {
                     gc_fork_clean_step: 420,
                     gc_num_epochs_to_keep: 24,
                     gc_step_period: Duration::seconds(1),
+                    ..GCConfig::default()
                 }
             } else {
                 GCConfig {
@@ -1510,6 +1638,7 @@ mod tests {
                     gc_fork_clean_step: 100,
                     gc_num_epochs_to_keep: 5,
                     gc_step_period: Duration::seconds(1),
+                    ..GCConfig::default()
                 }
             };
             assert_eq!(want_gc, config.gc);
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::config::Config;
+use crate::config::{BackupConfig, Config};
 use near_jsonrpc::RpcConfig;
 use near_network::config_json::{ExperimentalConfig, NetworkConfigOverrides};
 use near_o11y::testonly::init_test_logger;
@@ -27,6 +27,11 @@ fn test_config_duration_all_std() {
     // of the serialization is checked. It's still not perfect, I suppose,
     // because there are Vec's. So it's best-effort.
     let config = Config {
+        backup: Some(BackupConfig {
+            path: Default::default(),
+            period: Default::default(),
+            num_backups_to_keep: Some(Default::default()),
+        }),
         chunk_distribution_network: Some(Default::default()),
         store: StoreConfig { path: Some(Default::default()), ..Default::default() },
         cold_store: Some(StoreConfig { path: Some(Default::default()), ..Default::default() }),
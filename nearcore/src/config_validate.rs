@@ -148,6 +148,14 @@ impl<'a> ConfigValidator<'a> {
             }
         }
 
+        let shard_scheduler = &self.config.shard_scheduler;
+        if !shard_scheduler.dedicated_shards.is_empty()
+            && shard_scheduler.dedicated_pool_threads == 0
+        {
+            let error_message = "'config.shard_scheduler.dedicated_pool_threads' should be greater than 0 when dedicated shards are configured.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
         let tx_routing_height_horizon = self.config.tx_routing_height_horizon;
         if tx_routing_height_horizon < 2 {
             let error_message = format!("'config.tx_routing_height_horizon' needs to be at least 2, got {tx_routing_height_horizon}.");
@@ -183,6 +191,17 @@ mod tests {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(
+        expected = "'config.shard_scheduler.dedicated_pool_threads' should be greater than 0"
+    )]
+    fn test_shard_scheduler_dedicated_pool_threads_nonzero() {
+        let mut config = Config::default();
+        config.shard_scheduler.dedicated_shards = vec![0];
+        config.shard_scheduler.dedicated_pool_threads = 0;
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(
         expected = "Configuration with archive = false and save_trie_changes = false is not supported"
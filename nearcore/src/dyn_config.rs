@@ -106,8 +106,11 @@ fn read_validator_key(
     home_dir: &Path,
     config: &Config,
 ) -> Result<Option<Arc<ValidatorSigner>>, UpdateableConfigLoaderError> {
-    let validator_file: PathBuf = home_dir.join(&config.validator_key_file);
-    match crate::config::load_validator_key(&validator_file) {
+    let validator_file: PathBuf = match &config.remote_signer {
+        Some(remote_signer) => home_dir.join(&remote_signer.socket_path),
+        None => home_dir.join(&config.validator_key_file),
+    };
+    match crate::config::load_validator_signer(home_dir, config) {
         Ok(Some(validator_signer)) => {
             tracing::info!(target: "neard", "Hot loading validator key {}.", validator_file.display());
             Ok(Some(validator_signer))
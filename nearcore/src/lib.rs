@@ -3,6 +3,7 @@ pub use crate::config::{init_configs, load_config, load_test_config, NearConfig}
 use crate::entity_debug::EntityDebugHandlerImpl;
 use crate::metrics::spawn_trie_metrics_loop;
 
+use crate::backup::{spawn_backup_loop, BackupLoopHandle};
 use crate::cold_storage::spawn_cold_store_loop;
 use crate::state_sync::StateSyncDumper;
 use actix::{Actor, Addr};
@@ -26,8 +27,8 @@ use near_client::adapter::client_sender_for_network;
 use near_client::gc_actor::GCActor;
 use near_client::sync::adapter::SyncAdapter;
 use near_client::{
-    start_client, ClientActor, ConfigUpdater, PartialWitnessActor, StartClientResult,
-    ViewClientActor, ViewClientActorInner,
+    start_client, ClientActor, ColdStoreFallback, ConfigUpdater, PartialWitnessActor,
+    StartClientResult, ViewClientActor, ViewClientActorInner,
 };
 use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
 use near_epoch_manager::EpochManager;
@@ -39,7 +40,7 @@ use near_store::flat::FlatStateValuesInliningMigrationHandle;
 use near_store::genesis::initialize_sharded_genesis_state;
 use near_store::metadata::DbKind;
 use near_store::metrics::spawn_db_metrics_loop;
-use near_store::{DBCol, Mode, NodeStorage, Store, StoreOpenerError};
+use near_store::{DBCol, Mode, NodeStorage, ShardTries, Store, StoreOpenerError};
 use near_telemetry::TelemetryActor;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
@@ -47,6 +48,7 @@ use tokio::sync::broadcast;
 use tracing::info;
 
 pub mod append_only_map;
+pub mod backup;
 pub mod cold_storage;
 pub mod config;
 #[cfg(test)]
@@ -196,11 +198,6 @@ fn get_split_store(config: &NearConfig, storage: &NodeStorage) -> anyhow::Result
         return Ok(None);
     }
 
-    // SplitStore should only be used in the view client if it is enabled.
-    if !config.config.split_storage.as_ref().is_some_and(|c| c.enable_split_storage_view_client) {
-        return Ok(None);
-    }
-
     // SplitStore should only be used if the migration is finished. The
     // migration to cold store is finished when the db kind of the hot store is
     // changed from Archive to Hot.
@@ -215,10 +212,15 @@ pub struct NearNode {
     pub client: Addr<ClientActor>,
     pub view_client: Addr<ViewClientActor>,
     pub arbiters: Vec<ArbiterHandle>,
+    /// The arbiter running the client actor, which processes the blocks.
+    pub client_arbiter_handle: ArbiterHandle,
     pub rpc_servers: Vec<(&'static str, actix_web::dev::ServerHandle)>,
     /// The cold_store_loop_handle will only be set if the cold store is configured.
     /// It's a handle to a background thread that copies data from the hot store to the cold store.
     pub cold_store_loop_handle: Option<ColdStoreLoopHandle>,
+    /// Set if backups are configured.  It's a handle to a background thread
+    /// that periodically backs up the databases.
+    pub backup_loop_handle: Option<BackupLoopHandle>,
     /// Contains handles to background threads that may be dumping state to S3.
     pub state_sync_dumper: StateSyncDumper,
     /// A handle to control background flat state values inlining migration.
@@ -227,6 +229,8 @@ pub struct NearNode {
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
+    /// Tries of the node, used to save in-memory trie snapshots on shutdown.
+    pub shard_tries: ShardTries,
 }
 
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> anyhow::Result<NearNode> {
@@ -241,6 +245,12 @@ pub fn start_with_config_and_synchronization(
     shutdown_signal: Option<broadcast::Sender<()>>,
     config_updater: Option<ConfigUpdater>,
 ) -> anyhow::Result<NearNode> {
+    // `load_config` doesn't connect to the remote signer, the node does it
+    // only when it starts.
+    if config.config.remote_signer.is_some() {
+        let validator_signer = crate::config::load_validator_signer(home_dir, &config.config)?;
+        config.validator_signer.update(validator_signer);
+    }
     let storage = open_storage(home_dir, &mut config)?;
     let db_metrics_arbiter = if config.client_config.enable_statistics_export {
         let period = config.client_config.log_summary_period;
@@ -278,12 +288,16 @@ pub fn start_with_config_and_synchronization(
         epoch_manager.clone(),
     )
     .context("could not create the transaction runtime")?;
+    let shard_tries = runtime.get_tries();
 
     // Get the split store. If split store is some then create a new set of structures for
-    // the view client. Otherwise just re-use the existing ones.
+    // the view client. If the view client should read from the split store then it uses
+    // them for all requests. Otherwise it re-uses the existing ones and falls back to the
+    // split store only for queries about blocks garbage collected from the hot store.
     let split_store = get_split_store(&config, &storage)?;
-    let (view_epoch_manager, view_shard_tracker, view_runtime) =
-        if let Some(split_store) = &split_store {
+    let split_store_view = split_store
+        .as_ref()
+        .map(|split_store| -> anyhow::Result<_> {
             let view_epoch_manager =
                 EpochManager::new_arc_handle(split_store.clone(), &config.genesis.config);
             let view_shard_tracker = ShardTracker::new(
@@ -297,12 +311,30 @@ pub fn start_with_config_and_synchronization(
                 view_epoch_manager.clone(),
             )
             .context("could not create the transaction runtime")?;
-            (view_epoch_manager, view_shard_tracker, view_runtime)
-        } else {
-            (epoch_manager.clone(), shard_tracker.clone(), runtime.clone())
+            Ok(ColdStoreFallback {
+                epoch_manager: view_epoch_manager,
+                shard_tracker: view_shard_tracker,
+                runtime: view_runtime,
+            })
+        })
+        .transpose()?;
+    let enable_split_storage_view_client =
+        config.config.split_storage.as_ref().is_some_and(|c| c.enable_split_storage_view_client);
+    let (view_epoch_manager, view_shard_tracker, view_runtime, cold_store_fallback) =
+        match split_store_view {
+            Some(view) if enable_split_storage_view_client => {
+                (view.epoch_manager, view.shard_tracker, view.runtime, None)
+            }
+            view => (epoch_manager.clone(), shard_tracker.clone(), runtime.clone(), view),
         };
 
     let cold_store_loop_handle = spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?;
+    let backup_loop_handle = config
+        .config
+        .backup
+        .as_ref()
+        .map(|backup_config| spawn_backup_loop(home_dir, backup_config, &storage))
+        .transpose()?;
 
     let telemetry = ActixWrapper::new(TelemetryActor::new(config.telemetry_config.clone())).start();
     let chain_genesis = ChainGenesis::new(&config.genesis.config);
@@ -339,6 +371,7 @@ pub fn start_with_config_and_synchronization(
         network_adapter.as_multi_sender(),
         config.client_config.clone(),
         adv.clone(),
+        cold_store_fallback,
     );
 
     let state_snapshot_sender = LateBoundSender::new();
@@ -411,7 +444,9 @@ pub fn start_with_config_and_synchronization(
         network_adapter.as_sender(),
         client_adapter_for_shards_manager.as_sender(),
         config.validator_signer.clone(),
-        split_store.unwrap_or_else(|| storage.get_hot_store()),
+        split_store
+            .filter(|_| enable_split_storage_view_client)
+            .unwrap_or_else(|| storage.get_hot_store()),
         config.client_config.chunk_request_retry_period,
     );
     shards_manager_adapter.bind(shards_manager_actor.with_auto_span_context());
@@ -491,7 +526,7 @@ pub fn start_with_config_and_synchronization(
     tracing::trace!(target: "diagnostic", key = "log", "Starting NEAR node with diagnostic activated");
 
     let mut arbiters = vec![
-        client_arbiter_handle,
+        client_arbiter_handle.clone(),
         shards_manager_arbiter_handle,
         trie_metrics_arbiter,
         state_snapshot_arbiter,
@@ -507,10 +542,13 @@ pub fn start_with_config_and_synchronization(
         view_client: view_client_addr,
         rpc_servers,
         arbiters,
+        client_arbiter_handle,
         cold_store_loop_handle,
+        backup_loop_handle,
         state_sync_dumper,
         flat_state_migration_handle,
         resharding_handle,
+        shard_tries,
     })
 }
 
@@ -30,6 +30,20 @@ pub(crate) static CONFIG_CORRECT: Lazy<IntGauge> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static BACKUP_RESULT: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_backup_result",
+        "The result of a database backup made by the backup loop.",
+        &["result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static BACKUP_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge("near_backup_height", "Height of the head in the latest database backup")
+        .unwrap()
+});
+
 pub(crate) static COLD_STORE_COPY_RESULT: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_cold_store_copy_result",
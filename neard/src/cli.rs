@@ -1,8 +1,7 @@
-#[cfg(unix)]
 use anyhow::Context;
 use near_amend_genesis::AmendGenesisCommand;
 use near_chain_configs::GenesisValidationMode;
-use near_client::ConfigUpdater;
+use near_client::{ConfigUpdater, PersistTransactionPool};
 use near_cold_store_tool::ColdStoreCommand;
 use near_database_tool::commands::DatabaseCommand;
 use near_dyn_configs::{UpdateableConfigLoader, UpdateableConfigLoaderError, UpdateableConfigs};
@@ -16,7 +15,7 @@ use near_network::tcp;
 use near_o11y::tracing_subscriber::EnvFilter;
 use near_o11y::{
     default_subscriber, default_subscriber_with_opentelemetry, BuildEnvFilterError,
-    EnvFilterBuilder,
+    EnvFilterBuilder, WithSpanContextExt,
 };
 use near_ping::PingCommand;
 use near_primitives::hash::CryptoHash;
@@ -37,6 +36,7 @@ use std::sync::Arc;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::Receiver;
 use tracing::{debug, error, info, warn};
+use zeroize::Zeroizing;
 
 /// NEAR Protocol Node
 #[derive(clap::Parser)]
@@ -146,6 +146,9 @@ impl NeardCmd {
             NeardSubCommand::StatePartsDumpCheck(cmd) => {
                 cmd.run()?;
             }
+            NeardSubCommand::KeyFile(cmd) => {
+                cmd.run(&home_dir)?;
+            }
             #[cfg(feature = "new_epoch_sync")]
             NeardSubCommand::EpochSync(cmd) => {
                 cmd.run(&home_dir)?;
@@ -279,6 +282,10 @@ pub(super) enum NeardSubCommand {
     /// Check completeness of dumped state parts of an epoch
     StatePartsDumpCheck(StatePartsDumpCheckCommand),
 
+    /// Encrypts, decrypts or changes the passphrase of key files such as
+    /// node_key.json and validator_key.json.
+    KeyFile(KeyFileCommand),
+
     #[cfg(feature = "new_epoch_sync")]
     /// Testing tool for epoch sync
     EpochSync(EpochSyncCommand),
@@ -445,8 +452,17 @@ pub(super) struct RunCmd {
     /// configuration will be taken.
     #[clap(long)]
     max_gas_burnt_view: Option<Gas>,
+    /// Read the passphrase of encrypted key files from the given file
+    /// descriptor.  If not given, the passphrase is read from the
+    /// NEAR_KEY_FILE_PASSPHRASE environment variable.
+    #[clap(long)]
+    key_file_passphrase_fd: Option<i32>,
 }
 
+/// How long to wait on shutdown for the client actor to stop before saving the
+/// memtrie snapshots.
+const CLIENT_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
 impl RunCmd {
     pub(super) fn run(
         self,
@@ -455,6 +471,12 @@ impl RunCmd {
         verbose_target: Option<&str>,
         o11y_opts: &near_o11y::Options,
     ) {
+        if let Some(fd) = self.key_file_passphrase_fd {
+            let passphrase = read_passphrase_from_fd(fd)
+                .unwrap_or_else(|e| panic!("Error reading key file passphrase: {:#}", e));
+            near_crypto::set_key_file_passphrase(passphrase)
+                .expect("Key file passphrase is set only once");
+        }
         // Load configs from home.
         let mut near_config = nearcore::config::load_config(home_dir, genesis_validation)
             .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
@@ -549,11 +571,15 @@ impl RunCmd {
             let config_updater = ConfigUpdater::new(rx_config_update);
 
             let nearcore::NearNode {
+                client,
+                client_arbiter_handle,
                 rpc_servers,
                 cold_store_loop_handle,
+                backup_loop_handle,
                 mut state_sync_dumper,
                 flat_state_migration_handle,
                 resharding_handle,
+                shard_tries,
                 ..
             } = nearcore::start_with_config_and_synchronization(
                 home_dir,
@@ -577,6 +603,9 @@ impl RunCmd {
             if let Some(handle) = cold_store_loop_handle {
                 handle.stop()
             }
+            if let Some(handle) = backup_loop_handle {
+                handle.stop()
+            }
             state_sync_dumper.stop();
             resharding_handle.stop();
             flat_state_migration_handle.stop();
@@ -585,6 +614,22 @@ impl RunCmd {
                 debug!(target: "neard", "{} server stopped", name);
             }))
             .await;
+            if let Err(err) = client.send(PersistTransactionPool.with_span_context()).await {
+                error!(target: "neard", ?err, "Failed to persist the transaction pool");
+            }
+            // A memtrie snapshot is only usable if the flat head of its shard
+            // doesn't move afterwards, so block processing is stopped along
+            // with the client actor before the snapshots are written.
+            client_arbiter_handle.stop();
+            let deadline = std::time::Instant::now() + CLIENT_STOP_TIMEOUT;
+            while client.connected() && std::time::Instant::now() < deadline {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            if client.connected() {
+                warn!(target: "neard", "Client actor didn't stop, not saving memtrie snapshots");
+            } else if let Err(err) = shard_tries.save_mem_trie_snapshots() {
+                error!(target: "neard", ?err, "Failed to save memtrie snapshots");
+            }
             actix::System::current().stop();
             // Disable the subscriber to properly shutdown the tracer.
             near_o11y::reload(Some("error"), None, Some("off")).unwrap();
@@ -839,6 +884,125 @@ impl ValidateConfigCommand {
     }
 }
 
+/// Environment variable with the new passphrase for `neard key-file change-passphrase`.
+const KEY_FILE_NEW_PASSPHRASE_ENV_VAR: &str = "NEAR_KEY_FILE_NEW_PASSPHRASE";
+
+/// Reads a passphrase from the file descriptor, e.g. a pipe set up by the
+/// process starting neard, ignoring the trailing newline.
+fn read_passphrase_from_fd(fd: i32) -> anyhow::Result<Zeroizing<String>> {
+    let passphrase = Zeroizing::new(
+        std::fs::read_to_string(format!("/dev/fd/{fd}"))
+            .with_context(|| format!("failed to read passphrase from file descriptor {fd}"))?,
+    );
+    Ok(Zeroizing::new(passphrase.trim_end_matches(&['\n', '\r'][..]).to_string()))
+}
+
+/// Reads a passphrase from the file descriptor if given, otherwise from the
+/// environment variable, which is then removed from the environment.
+fn read_passphrase(fd: Option<i32>, env_var: &str) -> anyhow::Result<Zeroizing<String>> {
+    match fd {
+        Some(fd) => read_passphrase_from_fd(fd),
+        None => {
+            let passphrase = std::env::var(env_var).map(Zeroizing::new).map_err(|_| {
+                anyhow::anyhow!("passphrase must be given with a file descriptor or in {env_var}")
+            })?;
+            std::env::remove_var(env_var);
+            Ok(passphrase)
+        }
+    }
+}
+
+#[derive(clap::Parser)]
+pub(super) struct KeyFileCommand {
+    #[clap(subcommand)]
+    subcmd: KeyFileSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum KeyFileSubCommand {
+    /// Encrypts the secret key of plain text key files with a passphrase.
+    Encrypt(KeyFileArgs),
+    /// Decrypts the secret key of encrypted key files and stores it in
+    /// plain text.
+    Decrypt(KeyFileArgs),
+    /// Re-encrypts the secret key of encrypted key files with a new passphrase.
+    ChangePassphrase {
+        #[clap(flatten)]
+        args: KeyFileArgs,
+        /// Read the new passphrase from the given file descriptor.  If not
+        /// given, it is read from the NEAR_KEY_FILE_NEW_PASSPHRASE environment
+        /// variable.
+        #[clap(long)]
+        new_passphrase_fd: Option<i32>,
+    },
+}
+
+#[derive(clap::Args)]
+struct KeyFileArgs {
+    /// Key files to process, relative to the home directory.  Defaults to the
+    /// node and validator key files configured in config.json which exist.
+    #[clap(long = "file")]
+    files: Vec<PathBuf>,
+    /// Read the passphrase from the given file descriptor.  If not given, it
+    /// is read from the NEAR_KEY_FILE_PASSPHRASE environment variable.
+    #[clap(long)]
+    passphrase_fd: Option<i32>,
+}
+
+impl KeyFileArgs {
+    fn key_files(&self, home_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        if !self.files.is_empty() {
+            return Ok(self.files.iter().map(|file| home_dir.join(file)).collect());
+        }
+        let config =
+            nearcore::config::Config::from_file(&home_dir.join(nearcore::config::CONFIG_FILENAME))?;
+        Ok([config.node_key_file, config.validator_key_file]
+            .into_iter()
+            .map(|file| home_dir.join(file))
+            .filter(|path| path.exists())
+            .collect())
+    }
+
+    fn passphrase(&self) -> anyhow::Result<Zeroizing<String>> {
+        read_passphrase(self.passphrase_fd, near_crypto::KEY_FILE_PASSPHRASE_ENV_VAR)
+    }
+}
+
+impl KeyFileCommand {
+    pub(super) fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let (args, old_passphrase, new_passphrase) = match self.subcmd {
+            KeyFileSubCommand::Encrypt(args) => {
+                let passphrase = args.passphrase()?;
+                (args, None, Some(passphrase))
+            }
+            KeyFileSubCommand::Decrypt(args) => {
+                let passphrase = args.passphrase()?;
+                (args, Some(passphrase), None)
+            }
+            KeyFileSubCommand::ChangePassphrase { args, new_passphrase_fd } => {
+                let old_passphrase = args.passphrase()?;
+                let new_passphrase =
+                    read_passphrase(new_passphrase_fd, KEY_FILE_NEW_PASSPHRASE_ENV_VAR)?;
+                (args, Some(old_passphrase), Some(new_passphrase))
+            }
+        };
+        let key_files = args.key_files(home_dir)?;
+        if key_files.is_empty() {
+            anyhow::bail!("no key files found in {}", home_dir.display());
+        }
+        for path in key_files {
+            near_crypto::update_key_file_encryption(
+                &path,
+                old_passphrase.as_ref().map(|passphrase| passphrase.as_str()),
+                new_passphrase.as_ref().map(|passphrase| passphrase.as_str()),
+            )
+            .with_context(|| format!("failed to update {}", path.display()))?;
+            info!(target: "neard", path = %path.display(), "Updated key file");
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{CryptoHash, NeardCmd, NeardSubCommand, VerifyProofError, VerifyProofSubCommand};
@@ -0,0 +1,85 @@
+#!/usr/bin/env python3
+# Send transactions on behalf of an account without access keys by
+# impersonating it in a sandbox node.
+
+import sys
+import pathlib
+
+sys.path.append(str(pathlib.Path(__file__).resolve().parents[2] / 'lib'))
+
+import utils
+from cluster import start_cluster
+from key import Key
+from transaction import sign_payment_tx
+
+CONFIG = utils.figure_out_sandbox_binary()
+
+# start node
+nodes = start_cluster(1, 0, 1, CONFIG, [["epoch_length", 10]], {})
+
+# create an account without any access key
+ACCOUNT_ID = "dao.test0"
+BALANCE = 10**25
+res = nodes[0].json_rpc(
+    'sandbox_patch_state', {
+        "records": [{
+            "Account": {
+                "account_id": ACCOUNT_ID,
+                "account": {
+                    "amount": str(BALANCE),
+                    "locked": "0",
+                    "code_hash": "11111111111111111111111111111111",
+                    "storage_usage": 182
+                }
+            }
+        }]
+    })
+assert 'error' not in res, res
+
+# The transactions are signed with a key the account doesn't have.
+key = Key(ACCOUNT_ID, nodes[0].signer_key.pk, nodes[0].signer_key.sk)
+receiver_balance = int(nodes[0].get_account('test0')['result']['amount'])
+
+
+def send_money(amount, nonce):
+    hash_ = nodes[0].get_latest_block().hash_bytes
+    tx = sign_payment_tx(key, 'test0', amount, nonce, hash_)
+    return nodes[0].send_tx_and_wait(tx, 20)
+
+
+res = send_money(10**24, 1)
+assert 'AccessKeyNotFound' in str(res['error']), res
+
+res = nodes[0].json_rpc('sandbox_impersonate_account',
+                        {'account_id': ACCOUNT_ID})
+assert 'error' not in res, res
+res = send_money(10**24, 1)
+assert 'SuccessValue' in res['result']['status'], res
+# Nonces aren't checked either.
+res = send_money(10**24 + 1, 1)
+assert 'SuccessValue' in res['result']['status'], res
+
+balance = int(nodes[0].get_account(ACCOUNT_ID)['result']['amount'])
+assert balance < BALANCE - 2 * 10**24, balance
+balance = int(nodes[0].get_account('test0')['result']['amount'])
+assert balance >= receiver_balance + 2 * 10**24, balance
+
+res = nodes[0].json_rpc('sandbox_stop_impersonating_account',
+                        {'account_id': ACCOUNT_ID})
+assert 'error' not in res, res
+res = send_money(10**24, 2)
+assert 'AccessKeyNotFound' in str(res['error']), res
+
+# Reverting to a snapshot restores the accounts impersonated when it was taken.
+res = nodes[0].json_rpc('sandbox_impersonate_account',
+                        {'account_id': ACCOUNT_ID})
+assert 'error' not in res, res
+res = nodes[0].json_rpc('sandbox_snapshot', {})
+snapshot_id = res['result']['snapshot_id']
+res = nodes[0].json_rpc('sandbox_stop_impersonating_account',
+                        {'account_id': ACCOUNT_ID})
+assert 'error' not in res, res
+res = nodes[0].json_rpc('sandbox_revert', {'snapshot_id': snapshot_id})
+assert 'error' not in res, res
+res = send_money(10**24, 3)
+assert 'SuccessValue' in res['result']['status'], res
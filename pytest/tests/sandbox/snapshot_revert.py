@@ -0,0 +1,115 @@
+#!/usr/bin/env python3
+# Take a snapshot of a sandbox node, change its state and revert it back to the
+# snapshot, also across an epoch boundary.  Then check that the timestamp of the
+# next block can be set.
+
+import sys, time
+import base64
+import pathlib
+
+sys.path.append(str(pathlib.Path(__file__).resolve().parents[2] / 'lib'))
+
+import utils
+from cluster import start_cluster
+from transaction import sign_deploy_contract_tx, sign_function_call_tx
+
+CONFIG = utils.figure_out_sandbox_binary()
+
+# start node
+EPOCH_LENGTH = 10
+nodes = start_cluster(1, 0, 1, CONFIG, [["epoch_length", EPOCH_LENGTH]], {})
+
+# deploy contract
+hash_ = nodes[0].get_latest_block().hash_bytes
+tx = sign_deploy_contract_tx(nodes[0].signer_key, utils.load_test_contract(),
+                             10, hash_)
+nodes[0].send_tx(tx)
+time.sleep(3)
+
+k = (10).to_bytes(8, byteorder="little")
+
+
+def write_value(value, nonce):
+    hash_ = nodes[0].get_latest_block().hash_bytes
+    tx = sign_function_call_tx(nodes[0].signer_key,
+                               nodes[0].signer_key.account_id,
+                               'write_key_value', k + value, 1000000000000, 0,
+                               nonce, hash_)
+    res = nodes[0].send_tx_and_wait(tx, 20)
+    assert 'SuccessValue' in res['result']['status'], res
+
+
+def read_value():
+    res = nodes[0].call_function("test0", "read_value",
+                                 base64.b64encode(k).decode('ascii'))
+    return bytes(res['result']['result'])
+
+
+v = (20).to_bytes(8, byteorder="little")
+write_value(v, 20)
+assert read_value() == v
+
+res = nodes[0].json_rpc('sandbox_snapshot', {})
+snapshot_id = res['result']['snapshot_id']
+snapshot_height = nodes[0].get_latest_block().height
+
+new_v = (30).to_bytes(8, byteorder="little")
+write_value(new_v, 30)
+assert read_value() == new_v
+assert nodes[0].get_latest_block().height > snapshot_height
+
+res = nodes[0].json_rpc('sandbox_revert', {'snapshot_id': snapshot_id})
+assert 'error' not in res, res
+assert read_value() == v
+assert nodes[0].get_latest_block().height < snapshot_height + 2
+
+# The chain keeps going from the snapshot.
+utils.wait_for_blocks(nodes[0], target=snapshot_height + 5, timeout=20)
+assert read_value() == v
+write_value(new_v, 40)
+assert read_value() == new_v
+
+
+def check_validators():
+    validators = nodes[0].get_validators()['result']
+    height = nodes[0].get_latest_block().height
+    # Blocks of the reverted epochs aren't counted in the current one.
+    for validator in validators['current_validators']:
+        assert validator['num_produced_blocks'] <= height - validators[
+            'epoch_start_height'] + 1, (height, validators)
+    return validators['epoch_height']
+
+
+# Revert across an epoch boundary and keep going through the next epochs.
+res = nodes[0].json_rpc('sandbox_snapshot', {})
+epoch_snapshot_id = res['result']['snapshot_id']
+epoch_height = check_validators()
+height = nodes[0].get_latest_block().height
+utils.wait_for_blocks(nodes[0], target=height + 2 * EPOCH_LENGTH, timeout=60)
+assert check_validators() > epoch_height
+
+res = nodes[0].json_rpc('sandbox_revert', {'snapshot_id': epoch_snapshot_id})
+assert 'error' not in res, res
+assert nodes[0].get_latest_block().height < height + 2
+assert check_validators() <= epoch_height + 1
+utils.wait_for_blocks(nodes[0], target=height + 3 * EPOCH_LENGTH, timeout=60)
+assert check_validators() > epoch_height + 1
+assert read_value() == new_v
+
+# Reverting to a snapshot which doesn't exist fails.
+res = nodes[0].json_rpc('sandbox_revert', {'snapshot_id': snapshot_id + 1})
+assert 'error' in res, res
+
+# Time travel a year into the future.
+latest_timestamp = nodes[0].json_rpc(
+    'block', {'finality': 'optimistic'})['result']['header']['timestamp']
+timestamp = latest_timestamp + 365 * 24 * 3600 * 10**9
+res = nodes[0].json_rpc('sandbox_set_block_timestamp',
+                        {'timestamp': str(timestamp)})
+assert 'error' not in res, res
+height = nodes[0].get_latest_block().height
+utils.wait_for_blocks(nodes[0], target=height + 2, timeout=20)
+new_timestamp = nodes[0].json_rpc(
+    'block', {'finality': 'optimistic'})['result']['header']['timestamp']
+assert timestamp <= new_timestamp < timestamp + 60 * 10**9, (timestamp,
+                                                            new_timestamp)
@@ -0,0 +1,215 @@
+//! Profiler attributing the gas burnt by contracts to the functions of their code.
+//!
+//! Profiling is opt-in and enabled for the current thread by [`profile_functions`]. While it is
+//! enabled, contracts are run with the wasmtime runtime (which thus needs the `wasmtime_vm`
+//! feature) and their functions are instrumented with hooks reporting when they are entered and
+//! left, see `instrument::gas::inject_profiling_hooks`. The gas burnt in between two hooks is
+//! attributed to the function on top of the call stack: the gas of host functions and actions, as
+//! recorded in the gas profile, goes to a `[host]` frame above it and the rest to the function
+//! itself. The stacks of each call start with the account of the contract.
+//!
+//! Contracts are always instrumented the way `ContractPrepareVersion::V1` does it while profiling,
+//! so the gas they burn may differ slightly from the gas burnt by the same calls on chain. The
+//! profile is meant to find out which functions are expensive rather than for exact accounting.
+
+use crate::logic::gas_counter::GasCounter;
+use crate::logic::VMOutcome;
+use near_primitives_core::types::{AccountId, Gas};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+thread_local! {
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// Gas burnt by contracts, attributed to their call stacks.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    /// Gas burnt by each call stack, frames being separated by `;`.
+    stacks: BTreeMap<String, Gas>,
+}
+
+impl FunctionProfile {
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    /// Gas burnt by each call stack, frames being separated by `;`.
+    pub fn stacks(&self) -> impl Iterator<Item = (&str, Gas)> {
+        self.stacks.iter().map(|(stack, gas)| (stack.as_str(), *gas))
+    }
+
+    pub fn total_gas(&self) -> Gas {
+        self.stacks.values().copied().fold(0, Gas::saturating_add)
+    }
+
+    pub fn merge(&mut self, other: &FunctionProfile) {
+        for (stack, gas) in other.stacks() {
+            self.add(stack.to_string(), gas);
+        }
+    }
+
+    /// Formats the profile as folded stacks, one `stack gas` line per call stack, as consumed by
+    /// flamegraph tools like `inferno-flamegraph` or `flamegraph.pl`.
+    pub fn to_folded_stacks(&self) -> String {
+        self.stacks.iter().map(|(stack, gas)| format!("{stack} {gas}\n")).collect()
+    }
+
+    fn add(&mut self, stack: String, gas: Gas) {
+        let total = self.stacks.entry(stack).or_default();
+        *total = total.saturating_add(gas);
+    }
+}
+
+/// Runs `f` with the function profiler enabled on the current thread and returns the profile of
+/// the contract calls it made.
+pub fn profile_functions<T>(f: impl FnOnce() -> T) -> (T, FunctionProfile) {
+    let previous = PROFILER.with(|profiler| profiler.replace(Some(Profiler::default())));
+    let result = f();
+    let profiler = PROFILER.with(|profiler| profiler.replace(previous));
+    (result, profiler.map(|profiler| profiler.profile).unwrap_or_default())
+}
+
+pub(crate) fn is_enabled() -> bool {
+    PROFILER.with(|profiler| profiler.borrow().is_some())
+}
+
+/// Starts profiling a contract call, `function_names` being the names of the functions defined
+/// by the contract.
+pub(crate) fn start_call(function_names: Vec<String>) {
+    with_profiler(|profiler| profiler.call = Some(CallProfile::new(function_names)));
+}
+
+/// Called by the `profile_function_enter` hook.
+pub(crate) fn enter(function: u32, gas_counter: &GasCounter) {
+    with_call(|call| {
+        call.charge(gas_counter.burnt_gas(), gas_counter.profiled_gas());
+        call.enter(function);
+    });
+}
+
+/// Called by the `profile_function_exit` hook.
+pub(crate) fn exit(gas_counter: &GasCounter) {
+    with_call(|call| {
+        call.charge(gas_counter.burnt_gas(), gas_counter.profiled_gas());
+        call.current = call.frames[call.current].parent;
+    });
+}
+
+/// Finishes profiling the current contract call, made to the contract of `account_id`, and adds
+/// it to the profile.
+pub(crate) fn finish_call(account_id: &AccountId, outcome: &VMOutcome) {
+    with_profiler(|profiler| {
+        if let Some(mut call) = profiler.call.take() {
+            call.charge(outcome.burnt_gas, outcome.profile.non_wasm_gas());
+            call.fold_into(account_id.as_str(), &mut profiler.profile);
+        }
+    });
+}
+
+fn with_profiler(f: impl FnOnce(&mut Profiler)) {
+    PROFILER.with(|profiler| {
+        if let Some(profiler) = profiler.borrow_mut().as_mut() {
+            f(profiler)
+        }
+    });
+}
+
+fn with_call(f: impl FnOnce(&mut CallProfile)) {
+    with_profiler(|profiler| {
+        if let Some(call) = profiler.call.as_mut() {
+            f(call)
+        }
+    });
+}
+
+#[derive(Default)]
+struct Profiler {
+    profile: FunctionProfile,
+    /// The contract call being profiled.
+    call: Option<CallProfile>,
+}
+
+/// Call tree of a single contract call.
+struct CallProfile {
+    function_names: Vec<String>,
+    /// Frames of the call tree, the first one being its root.
+    frames: Vec<Frame>,
+    /// Index of the frame of the function being executed.
+    current: usize,
+    /// Gas burnt when the last hook was called.
+    burnt_gas: Gas,
+    /// Gas burnt by host functions and actions when the last hook was called.
+    host_gas: Gas,
+}
+
+struct Frame {
+    parent: usize,
+    function: u32,
+    children: HashMap<u32, usize>,
+    wasm_gas: Gas,
+    host_gas: Gas,
+}
+
+impl Frame {
+    fn new(parent: usize, function: u32) -> Self {
+        Self { parent, function, children: HashMap::new(), wasm_gas: 0, host_gas: 0 }
+    }
+}
+
+impl CallProfile {
+    fn new(function_names: Vec<String>) -> Self {
+        Self {
+            function_names,
+            frames: vec![Frame::new(0, u32::MAX)],
+            current: 0,
+            burnt_gas: 0,
+            host_gas: 0,
+        }
+    }
+
+    /// Attributes the gas burnt since the last hook to the current frame.
+    fn charge(&mut self, burnt_gas: Gas, host_gas: Gas) {
+        let host_delta = host_gas.saturating_sub(self.host_gas);
+        let wasm_delta = burnt_gas.saturating_sub(self.burnt_gas).saturating_sub(host_delta);
+        let frame = &mut self.frames[self.current];
+        frame.wasm_gas = frame.wasm_gas.saturating_add(wasm_delta);
+        frame.host_gas = frame.host_gas.saturating_add(host_delta);
+        self.burnt_gas = burnt_gas;
+        self.host_gas = host_gas;
+    }
+
+    fn enter(&mut self, function: u32) {
+        let next = self.frames.len();
+        let child = *self.frames[self.current].children.entry(function).or_insert(next);
+        if child == next {
+            self.frames.push(Frame::new(self.current, function));
+        }
+        self.current = child;
+    }
+
+    fn function_name(&self, function: u32) -> &str {
+        // Functions added by the instrumentation have no name.
+        self.function_names.get(function as usize).map_or("[instrumentation]", String::as_str)
+    }
+
+    fn fold_into(&self, root: &str, profile: &mut FunctionProfile) {
+        for (index, frame) in self.frames.iter().enumerate() {
+            let mut names = vec![];
+            let mut ancestor = index;
+            while ancestor != 0 {
+                names.push(self.function_name(self.frames[ancestor].function));
+                ancestor = self.frames[ancestor].parent;
+            }
+            names.push(root);
+            names.reverse();
+            if frame.wasm_gas > 0 {
+                profile.add(names.join(";"), frame.wasm_gas);
+            }
+            if frame.host_gas > 0 {
+                names.push("[host]");
+                profile.add(names.join(";"), frame.host_gas);
+            }
+        }
+    }
+}
@@ -91,6 +91,11 @@ imports! {
     @in internal: finite_wasm_gas<[gas: u64] -> []>,
     @in internal: finite_wasm_stack<[operand_size: u64, frame_size: u64] -> []>,
     @in internal: finite_wasm_unstack<[operand_size: u64, frame_size: u64] -> []>,
+    // #####################
+    // # Function profiler #
+    // #####################
+    @in internal: profile_function_enter<[function: u32] -> []>,
+    @in internal: profile_function_exit<[] -> []>,
     // #############
     // # Registers #
     // #############
@@ -310,6 +315,8 @@ pub(crate) const fn should_trace_host_function(host_function: &str) -> bool {
     match host_function {
         _ if str_eq(host_function, "gas") => false,
         _ if str_eq(host_function, "finite_wasm_gas") => false,
+        _ if str_eq(host_function, "profile_function_enter") => false,
+        _ if str_eq(host_function, "profile_function_exit") => false,
         _ => true,
     }
 }
@@ -510,6 +510,125 @@ pub fn inject_gas_counter<R: Rules>(
     }
 }
 
+/// Injects calls to the profiling hooks into every function defined by the module.
+///
+/// Two functions are imported from `hooks_module_name`: `profile_function_enter`, which takes
+/// the index of the function in the code section as an `i32`, and `profile_function_exit`. The
+/// former is called when a function is entered and the latter whenever it returns, be it with a
+/// `return`, a branch to the outermost label or by reaching the end of its body. To cover all of
+/// these, the original body is wrapped into a `block` of the function's result type.
+///
+/// Indices passed to `profile_function_enter` stay the same when functions are imported after
+/// the hooks have been injected (e.g. by [`inject_gas_counter`]), as they only count the
+/// functions defined by the module.
+///
+/// The function fails if the module contains a function with several results, returning the
+/// original module as an Err.
+pub fn inject_profiling_hooks(
+    module: elements::Module,
+    hooks_module_name: &str,
+) -> Result<elements::Module, elements::Module> {
+    let mut mbuilder = builder::from_module(module);
+    let enter_sig =
+        mbuilder.push_signature(builder::signature().with_param(ValueType::I32).build_sig());
+    let exit_sig = mbuilder.push_signature(builder::signature().build_sig());
+    mbuilder.push_import(
+        builder::import()
+            .module(hooks_module_name)
+            .field("profile_function_enter")
+            .external()
+            .func(enter_sig)
+            .build(),
+    );
+    mbuilder.push_import(
+        builder::import()
+            .module(hooks_module_name)
+            .field("profile_function_exit")
+            .external()
+            .func(exit_sig)
+            .build(),
+    );
+    let mut module = mbuilder.build();
+
+    let exit_func = module.import_count(elements::ImportCountType::Function) as u32 - 1;
+    let enter_func = exit_func - 1;
+
+    let types = module.type_section().map(elements::TypeSection::types).unwrap_or(&[]);
+    let block_types = module
+        .function_section()
+        .map(elements::FunctionSection::entries)
+        .unwrap_or(&[])
+        .iter()
+        .map(|func| match types.get(func.type_ref() as usize)? {
+            elements::Type::Function(func_type) => match func_type.results() {
+                [] => Some(elements::BlockType::NoResult),
+                [result] => Some(elements::BlockType::Value(*result)),
+                _ => None,
+            },
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some(block_types) = block_types else {
+        return Err(module);
+    };
+
+    for section in module.sections_mut() {
+        match section {
+            elements::Section::Code(code_section) => {
+                for (index, func_body) in code_section.bodies_mut().iter_mut().enumerate() {
+                    // Calls to functions at index >= `enter_func` are shifted by the two imports.
+                    update_call_index(func_body.code_mut(), enter_func);
+                    update_call_index(func_body.code_mut(), exit_func);
+                    let code = mem::take(func_body.code_mut().elements_mut());
+                    let mut instrumented = Vec::with_capacity(code.len() + 6);
+                    instrumented.extend([
+                        elements::Instruction::I32Const(index as i32),
+                        elements::Instruction::Call(enter_func),
+                        elements::Instruction::Block(block_types[index]),
+                    ]);
+                    for instruction in code {
+                        if matches!(instruction, elements::Instruction::Return) {
+                            instrumented.push(elements::Instruction::Call(exit_func));
+                        }
+                        instrumented.push(instruction);
+                    }
+                    // The `end` of the original body now closes the wrapping block.
+                    instrumented.extend([
+                        elements::Instruction::Call(exit_func),
+                        elements::Instruction::End,
+                    ]);
+                    *func_body.code_mut().elements_mut() = instrumented;
+                }
+            }
+            elements::Section::Export(export_section) => {
+                for export in export_section.entries_mut() {
+                    if let elements::Internal::Function(func_index) = export.internal_mut() {
+                        if *func_index >= enter_func {
+                            *func_index += 2
+                        }
+                    }
+                }
+            }
+            elements::Section::Element(elements_section) => {
+                for segment in elements_section.entries_mut() {
+                    for func_index in segment.members_mut() {
+                        if *func_index >= enter_func {
+                            *func_index += 2
+                        }
+                    }
+                }
+            }
+            elements::Section::Start(start_idx) => {
+                if *start_idx >= enter_func {
+                    *start_idx += 2
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(module)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -666,6 +785,61 @@ mod tests {
         elements::deserialize_buffer(module_bytes.as_ref()).expect("failed to parse module")
     }
 
+    #[test]
+    fn profiling_hooks() {
+        let module = parse_wat(
+            r#"
+            (module
+                (import "env" "ext" (func))
+                (func (export "main")
+                    call 0
+                    call 2
+                    drop
+                    return)
+                (func (result i32)
+                    i32.const 1))
+            "#,
+        );
+        let injected_module = inject_profiling_hooks(module, "internal").unwrap();
+
+        assert_eq!(
+            get_function_body(&injected_module, 0).unwrap(),
+            &[
+                I32Const(0),
+                Call(1),
+                Block(elements::BlockType::NoResult),
+                Call(0),
+                Call(4),
+                Drop,
+                Call(2),
+                Return,
+                End,
+                Call(2),
+                End
+            ][..]
+        );
+        assert_eq!(
+            get_function_body(&injected_module, 1).unwrap(),
+            &[
+                I32Const(1),
+                Call(1),
+                Block(elements::BlockType::Value(ValueType::I32)),
+                I32Const(1),
+                End,
+                Call(2),
+                End
+            ][..]
+        );
+        let export = &injected_module.export_section().unwrap().entries()[0];
+        assert_eq!(export.internal(), &elements::Internal::Function(3));
+
+        // Gas metering can be applied on top of the hooks.
+        let injected_module =
+            inject_gas_counter(injected_module, &rules::Set::default(), "env").unwrap();
+        let binary = serialize(injected_module).expect("serialization failed");
+        wasmparser::validate(&binary).unwrap();
+    }
+
     macro_rules! test_gas_counter_injection {
         (name = $name:ident; input = $input:expr; expected = $expected:expr) => {
             #[test]
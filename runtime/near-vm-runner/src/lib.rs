@@ -4,6 +4,7 @@ mod cache;
 mod code;
 mod errors;
 mod features;
+mod function_profiler;
 mod imports;
 #[cfg(feature = "prepare")]
 mod instrument;
@@ -33,6 +34,7 @@ pub use cache::{
     NoContractRuntimeCache,
 };
 pub use code::ContractCode;
+pub use function_profiler::{profile_functions, FunctionProfile};
 #[cfg(feature = "metrics")]
 pub use metrics::{report_metrics, reset_metrics};
 pub use profile::ProfileDataV3;
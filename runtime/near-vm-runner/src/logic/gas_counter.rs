@@ -275,6 +275,11 @@ impl GasCounter {
         self.prepaid_gas - self.used_gas()
     }
 
+    /// Gas burnt by host functions and actions so far, as recorded in the profile.
+    pub fn profiled_gas(&self) -> Gas {
+        self.profile.non_wasm_gas()
+    }
+
     pub fn profile_data(&self) -> ProfileDataV3 {
         self.profile.clone()
     }
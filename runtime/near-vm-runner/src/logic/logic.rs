@@ -377,6 +377,26 @@ impl<'a> VMLogic<'a> {
         Ok(())
     }
 
+    // #####################
+    // # Function profiler #
+    // #####################
+
+    /// Called when a function of the contract is entered while profiling it, see
+    /// [`crate::profile_functions`].
+    ///
+    /// `function` is the index of the function among the ones defined by the contract.
+    pub fn profile_function_enter(&mut self, function: u32) -> Result<()> {
+        crate::function_profiler::enter(function, &self.result_state.gas_counter);
+        Ok(())
+    }
+
+    /// Called when a function of the contract returns while profiling it, see
+    /// [`crate::profile_functions`].
+    pub fn profile_function_exit(&mut self) -> Result<()> {
+        crate::function_profiler::exit(&self.result_state.gas_counter);
+        Ok(())
+    }
+
     // #################
     // # Registers API #
     // #################
@@ -47,6 +47,21 @@ pub fn prepare_contract(
     }
 }
 
+/// Prepares the contract for profiling its execution with the function profiler.
+///
+/// The contract is validated as by [`prepare_contract`], but is always instrumented the way
+/// `ContractPrepareVersion::V1` does, with the profiling hooks injected on top. Returns the
+/// prepared code along with the names of the functions defined by the contract.
+pub fn prepare_contract_for_profiling(
+    original_code: &[u8],
+    config: &Config,
+) -> Result<(Vec<u8>, Vec<String>), PrepareError> {
+    let features =
+        crate::features::WasmFeatures::from(config.limit_config.contract_prepare_version);
+    prepare_v1::validate_contract(original_code, features, config)?;
+    prepare_v1::prepare_contract_for_profiling(original_code, config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
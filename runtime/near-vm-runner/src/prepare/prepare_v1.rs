@@ -29,6 +29,28 @@ pub(crate) fn prepare_contract(
         .into_wasm_code()
 }
 
+/// Like [`prepare_contract`], but also injects the profiling hooks into the functions of the
+/// contract.
+///
+/// Returns the prepared code along with the names of the functions defined by the contract,
+/// indexed like the argument of the `profile_function_enter` hook.
+pub(crate) fn prepare_contract_for_profiling(
+    original_code: &[u8],
+    config: &Config,
+) -> Result<(Vec<u8>, Vec<String>), PrepareError> {
+    let (module, function_names) = ContractModule::init(original_code, config)?.function_names();
+    let code = module
+        .scan_imports()?
+        .standardize_mem()
+        .ensure_no_internal_memory()?
+        .inject_gas_metering()?
+        // Injected after gas metering so that the hooks themselves aren't charged for.
+        .inject_profiling_hooks()?
+        .inject_stack_height_metering()?
+        .into_wasm_code()?;
+    Ok((code, function_names))
+}
+
 pub(crate) struct ContractModule<'a> {
     module: elements::Module,
     config: &'a Config,
@@ -91,6 +113,35 @@ impl<'a> ContractModule<'a> {
         Ok(Self { module, config })
     }
 
+    fn inject_profiling_hooks(self) -> Result<Self, PrepareError> {
+        let Self { module, config } = self;
+        let module = crate::instrument::gas::inject_profiling_hooks(module, "internal")
+            .map_err(|_| PrepareError::GasInstrumentation)?;
+        Ok(Self { module, config })
+    }
+
+    /// Names of the functions defined by the module, taken from its name section.
+    ///
+    /// Functions without a name are called `wasm-function[N]`, `N` being the index of the
+    /// function in the module.
+    fn function_names(self) -> (Self, Vec<String>) {
+        let Self { module, config } = self;
+        let module = module.parse_names().unwrap_or_else(|(_, module)| module);
+        let imported = module.import_count(elements::ImportCountType::Function);
+        let defined = module.function_section().map_or(0, |s| s.entries().len());
+        let names =
+            module.names_section().and_then(elements::NameSection::functions).map(|f| f.names());
+        let function_names = (imported..imported + defined)
+            .map(|index| {
+                names
+                    .and_then(|names| names.get(index as u32))
+                    .cloned()
+                    .unwrap_or_else(|| format!("wasm-function[{index}]"))
+            })
+            .collect();
+        (Self { module, config }, function_names)
+    }
+
     fn inject_stack_height_metering(self) -> Result<Self, PrepareError> {
         let Self { module, config } = self;
         let module = crate::instrument::stack_height::inject_limiter(
@@ -102,6 +102,11 @@ impl ProfileDataV3 {
         self.actions_profile.as_slice().iter().copied().fold(0, Gas::saturating_add)
     }
 
+    /// Gas spent outside the WASM VM, on host functions and actions.
+    pub(crate) fn non_wasm_gas(&self) -> Gas {
+        self.host_gas().saturating_add(self.action_gas())
+    }
+
     /// Returns total compute usage of host calls.
     pub fn total_compute_usage(&self, ext_costs_config: &ExtCostsConfig) -> Compute {
         let ext_compute_cost = self
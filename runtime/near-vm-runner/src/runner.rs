@@ -36,6 +36,13 @@ pub fn prepare(
     wasm_config: Arc<Config>,
     cache: Option<&dyn ContractRuntimeCache>,
 ) -> Box<dyn crate::PreparedContract> {
+    #[cfg(feature = "wasmtime_vm")]
+    if crate::function_profiler::is_enabled() {
+        // Only wasmtime runs the contracts instrumented with the profiling hooks, which are
+        // compiled anew for every call rather than being cached.
+        let runtime = Box::new(crate::wasmtime_runner::WasmtimeVM::new(wasm_config));
+        return runtime.prepare(ext, context, None);
+    }
     let vm_kind = wasm_config.vm_kind;
     let runtime = vm_kind
         .runtime(wasm_config)
@@ -80,6 +87,7 @@ pub fn run(
         Ok(o) => o,
         e @ Err(_) => return e,
     };
+    crate::function_profiler::finish_call(&context.current_account_id, &outcome);
 
     span.record("burnt_gas", outcome.burnt_gas);
     span.record("compute_usage", outcome.compute_usage);
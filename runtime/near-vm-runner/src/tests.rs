@@ -1,5 +1,7 @@
 mod cache;
 mod compile_errors;
+#[cfg(feature = "wasmtime_vm")]
+mod function_profiler;
 mod fuzzers;
 mod regression_tests;
 mod rs_contract;
@@ -0,0 +1,33 @@
+use crate::function_profiler::profile_functions;
+use crate::logic::mocks::mock_external::MockedExternal;
+use crate::tests::{create_context, test_vm_config};
+use crate::ContractCode;
+use near_parameters::RuntimeFeesConfig;
+use std::sync::Arc;
+
+#[test]
+fn test_profile_functions() {
+    let code = wat::parse_str(
+        r#"
+        (module
+            (import "env" "input" (func $input (param i64)))
+            (func $helper (call $input (i64.const 0)))
+            (func $main (export "main") (call $helper) (call $helper)))
+        "#,
+    )
+    .unwrap();
+    let mut ext = MockedExternal::with_code(ContractCode::new(code, None));
+    let context = create_context("main", vec![]);
+    let config = Arc::new(test_vm_config());
+    let fees = Arc::new(RuntimeFeesConfig::test());
+    let (outcome, profile) =
+        profile_functions(|| crate::run(&mut ext, &context, config, fees, None).unwrap());
+    assert_eq!(outcome.aborted, None);
+
+    let stacks = profile.stacks().map(|(stack, _)| stack).collect::<Vec<_>>();
+    for stack in ["alice;main", "alice;main;helper", "alice;main;helper;[host]"] {
+        assert!(stacks.contains(&stack), "{stack} not in {stacks:?}");
+    }
+    assert_eq!(profile.total_gas(), outcome.burnt_gas);
+    assert!(profile.to_folded_stacks().lines().all(|line| line.rsplit_once(' ').is_some()));
+}
@@ -155,8 +155,16 @@ impl WasmtimeVM {
     #[tracing::instrument(target = "vm", level = "debug", "WasmtimeVM::compile_uncached", skip_all)]
     fn compile_uncached(&self, code: &ContractCode) -> Result<Vec<u8>, CompilationError> {
         let start = std::time::Instant::now();
-        let prepared_code = prepare::prepare_contract(code.code(), &self.config, VMKind::Wasmtime)
-            .map_err(CompilationError::PrepareError)?;
+        let prepared_code = if crate::function_profiler::is_enabled() {
+            let (prepared_code, function_names) =
+                prepare::prepare_contract_for_profiling(code.code(), &self.config)
+                    .map_err(CompilationError::PrepareError)?;
+            crate::function_profiler::start_call(function_names);
+            prepared_code
+        } else {
+            prepare::prepare_contract(code.code(), &self.config, VMKind::Wasmtime)
+                .map_err(CompilationError::PrepareError)?
+        };
         let serialized = self.engine.precompile_module(&prepared_code).map_err(|err| {
             tracing::error!(?err, "wasmtime failed to compile the prepared code (this is defense-in-depth, the error was recovered from but should be reported to the developers)");
             CompilationError::WasmtimeCompileError { msg: err.to_string() }
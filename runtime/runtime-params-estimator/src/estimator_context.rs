@@ -177,6 +177,7 @@ impl<'c> EstimatorContext<'c> {
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info,
+            impersonated_accounts: Default::default(),
         }
     }
 
@@ -408,6 +409,7 @@ impl Testbed<'_> {
             gas_price,
             tx,
             verify_signature,
+            &Default::default(),
             block_height,
             PROTOCOL_VERSION,
         )
@@ -1405,6 +1405,7 @@ mod tests {
             migration_data: Arc::default(),
             migration_flags: MigrationFlags::default(),
             congestion_info: BlockCongestionInfo::default(),
+            impersonated_accounts: Default::default(),
         }
     }
 
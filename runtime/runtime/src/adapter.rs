@@ -6,7 +6,7 @@ use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::ViewStateResult;
+use near_primitives::views::{CallGasUsageView, StateOverride, ViewStateResult};
 use near_vm_runner::ContractCode;
 
 /// Adapter for querying runtime.
@@ -38,7 +38,9 @@ pub trait ViewRuntimeAdapter {
         contract_id: &AccountId,
         method_name: &str,
         args: &[u8],
+        state_overrides: &[StateOverride],
         logs: &mut Vec<String>,
+        gas_usage: Option<&mut CallGasUsageView>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, crate::state_viewer::errors::CallFunctionError>;
@@ -58,10 +60,14 @@ pub trait ViewRuntimeAdapter {
         account_id: &AccountId,
     ) -> Result<Vec<(PublicKey, AccessKey)>, crate::state_viewer::errors::ViewAccessKeyError>;
 
+    /// Returns the contract data of the account at the state after
+    /// `block_hash`, iterated over in flat storage if it is available for the
+    /// block.
     fn view_state(
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
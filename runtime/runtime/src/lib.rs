@@ -30,6 +30,7 @@ use near_primitives::receipt::{
     Receipt, ReceiptEnum, ReceiptV0, ReceivedData,
 };
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
+use near_primitives::sandbox::impersonation::SandboxImpersonatedAccounts;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::state_record::StateRecord;
 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
@@ -63,6 +64,7 @@ pub use near_vm_runner::with_ext_cost_counter;
 use near_vm_runner::ContractCode;
 use near_vm_runner::ContractRuntimeCache;
 use near_vm_runner::ProfileDataV3;
+pub use near_vm_runner::{profile_functions, FunctionProfile};
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -78,6 +80,7 @@ pub mod ext;
 mod metrics;
 mod prefetch;
 pub mod receipt_manager;
+pub mod simulation;
 pub mod state_viewer;
 mod verifier;
 
@@ -129,6 +132,9 @@ pub struct ApplyState {
     /// the congestion info needs to be computed while applying receipts.
     /// TODO(congestion_info) - verify performance of initialization when congested
     pub congestion_info: BlockCongestionInfo,
+    /// Accounts impersonated in sandbox, whose transactions are accepted
+    /// without checking their signature, access key and nonce.
+    pub impersonated_accounts: SandboxImpersonatedAccounts,
 }
 
 /// Contains information to update validators accounts at the first block of a new epoch.
@@ -278,6 +284,7 @@ impl Runtime {
     /// `ExecutionOutcomeWithId` for the transaction.
     /// In case of an error, returns either `InvalidTxError` if the transaction verification failed
     /// or a `StorageError` wrapped into `RuntimeError`.
+    /// `verify_signature` is only ever false when simulating transactions.
     #[instrument(target = "runtime", level = "debug", "process_transaction", skip_all, fields(
         tx_hash = %signed_transaction.get_hash(),
         gas_burnt = tracing::field::Empty,
@@ -288,6 +295,7 @@ impl Runtime {
         state_update: &mut TrieUpdate,
         apply_state: &ApplyState,
         signed_transaction: &SignedTransaction,
+        verify_signature: bool,
         stats: &mut ApplyStats,
     ) -> Result<(Receipt, ExecutionOutcomeWithId), InvalidTxError> {
         let span = tracing::Span::current();
@@ -298,7 +306,8 @@ impl Runtime {
             state_update,
             apply_state.gas_price,
             signed_transaction,
-            true,
+            verify_signature,
+            &apply_state.impersonated_accounts,
             Some(apply_state.block_height),
             apply_state.current_protocol_version,
         ) {
@@ -1517,6 +1526,7 @@ impl Runtime {
                 state_update,
                 apply_state,
                 signed_transaction,
+                true,
                 &mut processing_state.stats,
             )?;
             if receipt.receiver_id() == signed_transaction.transaction.signer_id() {
@@ -2312,7 +2322,7 @@ struct ApplyProcessingReceiptState<'a> {
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
-    use near_crypto::{InMemorySigner, KeyType, PublicKey, Signer};
+    use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
     use near_parameters::{ExtCosts, ParameterCost, RuntimeConfig};
     use near_primitives::account::AccessKey;
     use near_primitives::action::delegate::{
@@ -2471,6 +2481,7 @@ mod tests {
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info,
+            impersonated_accounts: Default::default(),
         };
 
         (runtime, tries, root, apply_state, signer, MockEpochInfoProvider::default())
@@ -3236,6 +3247,56 @@ mod tests {
         assert_eq!(initial_account_state.storage_usage(), final_account_state.storage_usage());
     }
 
+    #[test]
+    fn test_simulate_transaction() {
+        let (runtime, tries, root, mut apply_state, signer, epoch_info_provider) =
+            setup_runtime(to_yocto(1_000_000), 0, 10u64.pow(15));
+        let shard_uid = ShardUId::single_shard();
+        let initial_account =
+            get_account(&tries.new_trie_update(shard_uid, root), &alice_account()).unwrap();
+
+        let mut transaction = SignedTransaction::send_money(
+            1,
+            alice_account(),
+            alice_account(),
+            &*signer,
+            to_yocto(1),
+            CryptoHash::default(),
+        );
+        let simulate = |apply_state: &mut ApplyState, transaction: &SignedTransaction, verify| {
+            let state_updates = [(shard_uid.shard_id(), tries.new_trie_update(shard_uid, root))];
+            runtime.simulate_transaction(
+                state_updates.into(),
+                &|_| shard_uid.shard_id(),
+                apply_state,
+                transaction,
+                verify,
+                &epoch_info_provider,
+            )
+        };
+
+        let result = simulate(&mut apply_state, &transaction, true).unwrap();
+        assert_eq!(result.transaction_outcome.id, transaction.get_hash());
+        assert!(!result.receipts_outcome.is_empty());
+        assert_eq!(
+            result.receipts_outcome[0].outcome.status,
+            ExecutionStatus::SuccessValue(vec![])
+        );
+        assert!(result.pending_receipts.is_empty());
+        assert_eq!(result.state_changes.len(), 1);
+        // Nothing has been committed to the storage.
+        let account =
+            get_account(&tries.new_trie_update(shard_uid, root), &alice_account()).unwrap();
+        assert_eq!(account, initial_account);
+
+        transaction.signature = Signature::empty(KeyType::ED25519);
+        assert_eq!(
+            simulate(&mut apply_state, &transaction, true).unwrap_err(),
+            RuntimeError::InvalidTxError(InvalidTxError::InvalidSignature)
+        );
+        assert!(simulate(&mut apply_state, &transaction, false).is_ok());
+    }
+
     #[test]
     fn test_delete_key_underflow() {
         let initial_locked = to_yocto(500_000);
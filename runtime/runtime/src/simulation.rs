@@ -0,0 +1,150 @@
+//! Dry-run execution of transactions.
+//!
+//! A simulated transaction is converted into a receipt and the whole tree of
+//! receipts it produces is executed right away, regardless of the shard the
+//! receipts belong to and without waiting for the next block.  All changes are
+//! accumulated in the given [`TrieUpdate`]s and are never committed to
+//! storage.
+
+use crate::congestion_control::{ReceiptSink, ReceiptSinkV1};
+use crate::{ApplyState, ApplyStats, Runtime};
+use near_primitives::errors::{RuntimeError, StorageError};
+use near_primitives::receipt::Receipt;
+use near_primitives::transaction::{ExecutionOutcomeWithId, SignedTransaction};
+use near_primitives::types::{
+    AccountId, EpochInfoProvider, Gas, RawStateChangesWithTrieKey, ShardId,
+};
+use near_store::TrieUpdate;
+use std::collections::{HashMap, VecDeque};
+
+/// Upper bound on the number of receipts executed in a single simulation.
+/// Guards the node against transactions spawning an unbounded number of
+/// promises.  Receipts beyond the limit are reported as pending.
+pub const MAX_SIMULATED_RECEIPTS: usize = 1_000;
+
+/// Upper bound on the depth of the executed receipts, the receipt created
+/// from the transaction having depth 0.  Deeper receipts are reported as
+/// pending.
+pub const MAX_SIMULATED_RECEIPT_DEPTH: usize = 64;
+
+/// Upper bound on the gas burnt by a single simulation, 3 PGas being several
+/// times the gas a chunk may burn.  Once it is reached the remaining receipts
+/// are reported as pending.
+pub const MAX_SIMULATED_GAS_BURNT: Gas = 3_000_000_000_000_000;
+
+/// Result of [`Runtime::simulate_transaction`].
+#[derive(Debug)]
+pub struct SimulationResult {
+    /// Outcome of converting the transaction into a receipt.
+    pub transaction_outcome: ExecutionOutcomeWithId,
+    /// Outcomes of the executed receipts in execution order.  Receipts which
+    /// wait for data that never arrives (e.g. a yield which isn't resumed)
+    /// have no outcome.
+    pub receipts_outcome: Vec<ExecutionOutcomeWithId>,
+    /// Receipts which haven't been executed because [`MAX_SIMULATED_RECEIPTS`],
+    /// [`MAX_SIMULATED_RECEIPT_DEPTH`] or [`MAX_SIMULATED_GAS_BURNT`] has been
+    /// reached.
+    pub pending_receipts: Vec<Receipt>,
+    /// All state changes made by the transaction, grouped by shard.
+    pub state_changes: Vec<(ShardId, Vec<RawStateChangesWithTrieKey>)>,
+}
+
+impl Runtime {
+    /// Executes the transaction and all the receipts it produces on top of
+    /// `state_updates`, which must contain an update for every shard of the
+    /// current shard layout.
+    ///
+    /// `apply_state.shard_id` is overwritten with the shard of each executed
+    /// receipt.  State changes are only finalized in memory and the returned
+    /// trie changes are dropped, so nothing is ever written to the database.
+    pub fn simulate_transaction(
+        &self,
+        mut state_updates: HashMap<ShardId, TrieUpdate>,
+        account_id_to_shard_id: &dyn Fn(&AccountId) -> ShardId,
+        apply_state: &mut ApplyState,
+        signed_transaction: &SignedTransaction,
+        verify_signature: bool,
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<SimulationResult, RuntimeError> {
+        let _span = tracing::debug_span!(
+            target: "runtime",
+            "simulate_transaction",
+            tx_hash = %signed_transaction.get_hash())
+        .entered();
+        let mut stats = ApplyStats::default();
+        let mut validator_proposals = vec![];
+
+        let signer_shard_id = account_id_to_shard_id(signed_transaction.transaction.signer_id());
+        apply_state.shard_id = signer_shard_id;
+        let (receipt, transaction_outcome) = self.process_transaction(
+            state_update_for_shard(&mut state_updates, signer_shard_id)?,
+            apply_state,
+            signed_transaction,
+            verify_signature,
+            &mut stats,
+        )?;
+
+        let mut receipts_outcome = vec![];
+        let mut num_processed_receipts = 0;
+        let mut gas_burnt = transaction_outcome.outcome.gas_burnt;
+        // Receipts are executed in breadth-first order, so once the receipt at
+        // the front of the queue is too deep all the others are as well.
+        let mut queue = VecDeque::from([(receipt, 0)]);
+        while let Some((receipt, depth)) = queue.pop_front() {
+            if num_processed_receipts >= MAX_SIMULATED_RECEIPTS
+                || depth > MAX_SIMULATED_RECEIPT_DEPTH
+                || gas_burnt >= MAX_SIMULATED_GAS_BURNT
+            {
+                queue.push_front((receipt, depth));
+                break;
+            }
+            let shard_id = account_id_to_shard_id(receipt.receiver_id());
+            apply_state.shard_id = shard_id;
+            let mut outgoing_receipts = vec![];
+            let mut receipt_sink =
+                ReceiptSink::V1(ReceiptSinkV1 { outgoing_receipts: &mut outgoing_receipts });
+            let outcome = self.process_receipt(
+                state_update_for_shard(&mut state_updates, shard_id)?,
+                apply_state,
+                &receipt,
+                &mut receipt_sink,
+                &mut validator_proposals,
+                &mut stats,
+                epoch_info_provider,
+            )?;
+            num_processed_receipts += 1;
+            queue.extend(outgoing_receipts.into_iter().map(|receipt| (receipt, depth + 1)));
+            if let Some(outcome) = &outcome {
+                gas_burnt = gas_burnt.saturating_add(outcome.outcome.gas_burnt);
+            }
+            receipts_outcome.extend(outcome);
+        }
+
+        let mut state_changes = vec![];
+        for (shard_id, state_update) in state_updates {
+            let (_, _, changes) = state_update.finalize()?;
+            if !changes.is_empty() {
+                state_changes.push((shard_id, changes));
+            }
+        }
+        state_changes.sort_by_key(|(shard_id, _)| *shard_id);
+
+        Ok(SimulationResult {
+            transaction_outcome,
+            receipts_outcome,
+            pending_receipts: queue.into_iter().map(|(receipt, _)| receipt).collect(),
+            state_changes,
+        })
+    }
+}
+
+fn state_update_for_shard(
+    state_updates: &mut HashMap<ShardId, TrieUpdate>,
+    shard_id: ShardId,
+) -> Result<&mut TrieUpdate, StorageError> {
+    state_updates.get_mut(&shard_id).ok_or_else(|| {
+        StorageError::StorageInconsistentState(format!(
+            "state of shard {shard_id} is not available for simulation"
+        ))
+    })
+}
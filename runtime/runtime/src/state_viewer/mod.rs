@@ -6,18 +6,23 @@ use near_crypto::{KeyType, PublicKey};
 use near_parameters::RuntimeConfigStore;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::borsh::BorshDeserialize;
+use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::ActionReceipt;
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
-use near_primitives::transaction::FunctionCallAction;
-use near_primitives::trie_key::trie_key_parsers;
+use near_primitives::state::FlatStateValue;
+use near_primitives::transaction::{ExecutionMetadata, FunctionCallAction};
+use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, ShardId,
 };
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{StateItem, ViewStateResult};
+use near_primitives::views::{
+    CallGasUsageView, ExecutionMetadataView, StateItem, StateOverride, ViewStateResult,
+};
 use near_primitives_core::config::ViewConfig;
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
+use near_store::flat::FlatStorageChunkView;
+use near_store::{get_access_key, get_account, get_code, set_account, set_code, TrieUpdate};
 use near_vm_runner::logic::{ProtocolVersion, ReturnData};
 use near_vm_runner::{ContractCode, ContractRuntimeCache};
 use std::{str, sync::Arc, time::Instant};
@@ -143,6 +148,21 @@ impl TrieViewer {
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
+    ) -> Result<ViewStateResult, errors::ViewStateError> {
+        self.view_state_with_flat_storage(state_update, None, account_id, prefix, include_proof)
+    }
+
+    /// Same as `view_state`, but the contract data is iterated over in flat
+    /// storage if `flat_storage_chunk_view` is set, it supports the block of
+    /// the view and no proof is requested.  The account is always read from
+    /// `state_update`.
+    pub fn view_state_with_flat_storage(
+        &self,
+        state_update: &TrieUpdate,
+        flat_storage_chunk_view: Option<&FlatStorageChunkView>,
+        account_id: &AccountId,
+        prefix: &[u8],
+        include_proof: bool,
     ) -> Result<ViewStateResult, errors::ViewStateError> {
         match get_account(state_update, account_id)? {
             Some(account) => {
@@ -167,6 +187,31 @@ impl TrieViewer {
         let mut values = vec![];
         let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
         let acc_sep_len = query.len() - prefix.len();
+        // Proofs consist of trie nodes, so only queries without them can be
+        // served by flat storage.
+        if let (false, Some(chunk_view)) = (include_proof, flat_storage_chunk_view) {
+            match chunk_view.iter_prefix(&query) {
+                Ok(iter) => {
+                    for item in iter {
+                        let (key, value) = item.map_err(StorageError::from)?;
+                        let value = match value {
+                            FlatStateValue::Ref(value_ref) => {
+                                state_update.trie().retrieve_value(&value_ref.hash)?
+                            }
+                            FlatStateValue::Inlined(value) => value,
+                        };
+                        values.push(StateItem {
+                            key: key[acc_sep_len..].to_vec().into(),
+                            value: value.into(),
+                        });
+                    }
+                    return Ok(ViewStateResult { values, proof: vec![] });
+                }
+                // Blocks before the flat head are only available in the trie.
+                Err(StorageError::FlatStorageBlockNotSupported(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
         let mut iter = state_update.trie().disk_iter()?;
         iter.remember_visited_nodes(include_proof);
         iter.seek_prefix(&query)?;
@@ -178,6 +223,12 @@ impl TrieViewer {
         Ok(ViewStateResult { values, proof })
     }
 
+    /// Calls the method of the contract without persisting any changes.
+    ///
+    /// The state overrides are applied to the state before the call.  If
+    /// `gas_usage` is set, it's filled with the gas used by the call, even if
+    /// the execution of the method fails.  The call is run with the function
+    /// profiler if its `function_profile` is set.
     pub fn call_function(
         &self,
         mut state_update: TrieUpdate,
@@ -185,11 +236,21 @@ impl TrieViewer {
         contract_id: &AccountId,
         method_name: &str,
         args: &[u8],
+        state_overrides: &[StateOverride],
         logs: &mut Vec<String>,
+        gas_usage: Option<&mut CallGasUsageView>,
         epoch_info_provider: &(dyn EpochInfoProvider),
     ) -> Result<Vec<u8>, errors::CallFunctionError> {
         let now = Instant::now();
         let root = *state_update.get_root();
+        let config_store = RuntimeConfigStore::new(None);
+        let config = config_store.get_config(PROTOCOL_VERSION);
+        apply_state_overrides(
+            &mut state_update,
+            state_overrides,
+            config.wasm_config.limit_config.max_contract_size,
+            view_state.current_protocol_version,
+        )?;
         let account = get_account(&state_update, contract_id)?.ok_or_else(|| {
             errors::CallFunctionError::AccountDoesNotExist {
                 requested_account_id: contract_id.clone(),
@@ -199,6 +260,8 @@ impl TrieViewer {
         let originator_id = contract_id;
         let public_key = PublicKey::empty(KeyType::ED25519);
         let empty_hash = CryptoHash::default();
+        // The trie nodes touched to read the account aren't counted.
+        let trie_nodes_before = state_update.trie().get_trie_nodes_count();
         let mut receipt_manager = ReceiptManager::default();
         let mut runtime_ext = RuntimeExt::new(
             &mut state_update,
@@ -212,8 +275,13 @@ impl TrieViewer {
             epoch_info_provider,
             view_state.current_protocol_version,
         );
-        let config_store = RuntimeConfigStore::new(None);
-        let config = config_store.get_config(PROTOCOL_VERSION);
+        // Code from the overrides is compiled without the cache so that it
+        // doesn't end up in the cache of the node.
+        let cache = if state_overrides.iter().any(|state_override| state_override.code.is_some()) {
+            None
+        } else {
+            view_state.cache
+        };
         let apply_state = ApplyState {
             apply_reason: None,
             block_height: view_state.block_height,
@@ -229,11 +297,12 @@ impl TrieViewer {
             random_seed: root,
             current_protocol_version: view_state.current_protocol_version,
             config: config.clone(),
-            cache: view_state.cache,
+            cache,
             is_new_chunk: false,
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info: Default::default(),
+            impersonated_accounts: Default::default(),
         };
         let action_receipt = ActionReceipt {
             signer_id: originator_id.clone(),
@@ -249,19 +318,42 @@ impl TrieViewer {
             gas: self.max_gas_burnt_view,
             deposit: 0,
         };
-        let outcome = execute_function_call(
-            &apply_state,
-            &mut runtime_ext,
-            originator_id,
-            &action_receipt,
-            [].into(),
-            &function_call,
-            &empty_hash,
-            config,
-            true,
-            Some(ViewConfig { max_gas_burnt: self.max_gas_burnt_view }),
-        )
-        .map_err(|e| errors::CallFunctionError::InternalError { error_message: e.to_string() })?;
+        let profile_functions =
+            gas_usage.as_ref().is_some_and(|gas_usage| gas_usage.function_profile.is_some());
+        let call = || {
+            execute_function_call(
+                &apply_state,
+                &mut runtime_ext,
+                originator_id,
+                &action_receipt,
+                [].into(),
+                &function_call,
+                &empty_hash,
+                config,
+                true,
+                Some(ViewConfig { max_gas_burnt: self.max_gas_burnt_view }),
+            )
+        };
+        let (outcome, function_profile) = if profile_functions {
+            let (outcome, profile) = near_vm_runner::profile_functions(call);
+            (outcome, Some(profile.to_folded_stacks()))
+        } else {
+            (call(), None)
+        };
+        let outcome = outcome.map_err(|e| errors::CallFunctionError::InternalError {
+            error_message: e.to_string(),
+        })?;
+        if let Some(gas_usage) = gas_usage {
+            let trie_nodes = state_update.trie().get_trie_nodes_count();
+            let metadata = ExecutionMetadata::V3(Box::new(outcome.profile.clone()));
+            *gas_usage = CallGasUsageView {
+                gas_burnt: outcome.burnt_gas,
+                gas_profile: ExecutionMetadataView::from(metadata).gas_profile.unwrap_or_default(),
+                trie_nodes_db_reads: trie_nodes.db_reads - trie_nodes_before.db_reads,
+                trie_nodes_mem_reads: trie_nodes.mem_reads - trie_nodes_before.mem_reads,
+                function_profile,
+            };
+        }
         let elapsed = now.elapsed();
         let time_ms =
             (elapsed.as_secs() as f64 / 1_000.0) + f64::from(elapsed.subsec_nanos()) / 1_000_000.0;
@@ -283,3 +375,44 @@ impl TrieViewer {
         }
     }
 }
+
+/// Applies the overrides to the state of a view call.  Since the changes are
+/// only used for the call, the storage usage of the accounts isn't updated.
+/// The code of the overrides is subject to the same size limit as deployed
+/// contracts.
+fn apply_state_overrides(
+    state_update: &mut TrieUpdate,
+    state_overrides: &[StateOverride],
+    max_contract_size: u64,
+    protocol_version: ProtocolVersion,
+) -> Result<(), errors::CallFunctionError> {
+    for state_override in state_overrides {
+        let account_id = &state_override.account_id;
+        let mut account = get_account(state_update, account_id)?
+            .unwrap_or_else(|| Account::new(0, 0, 0, CryptoHash::default(), 0, protocol_version));
+        if let Some(code) = &state_override.code {
+            if code.len() as u64 > max_contract_size {
+                return Err(errors::CallFunctionError::VMError {
+                    error_message: format!(
+                        "code override of {account_id} is {} bytes, over the limit of {} bytes",
+                        code.len(),
+                        max_contract_size
+                    ),
+                });
+            }
+            let code = ContractCode::new(code.clone(), None);
+            account.set_code_hash(*code.hash());
+            set_code(state_update, account_id.clone(), &code);
+        }
+        if let Some(balance) = state_override.balance {
+            account.set_amount(balance);
+        }
+        for item in &state_override.state {
+            let key =
+                TrieKey::ContractData { account_id: account_id.clone(), key: item.key.to_vec() };
+            state_update.set(key, item.value.to_vec());
+        }
+        set_account(state_update, account_id.clone(), &account);
+    }
+    Ok(())
+}
@@ -3,13 +3,14 @@ use crate::near_primitives::account::Account;
 use crate::VerificationResult;
 use near_crypto::key_conversion::is_valid_staking_key;
 use near_parameters::RuntimeConfig;
-use near_primitives::account::AccessKeyPermission;
+use near_primitives::account::{AccessKey, AccessKeyPermission};
 use near_primitives::action::delegate::SignedDelegateAction;
 use near_primitives::checked_feature;
 use near_primitives::errors::{
     ActionsValidationError, InvalidAccessKeyError, InvalidTxError, ReceiptValidationError,
 };
 use near_primitives::receipt::{ActionReceipt, DataReceipt, Receipt, ReceiptEnum};
+use near_primitives::sandbox::impersonation::SandboxImpersonatedAccounts;
 use near_primitives::transaction::DeleteAccountAction;
 use near_primitives::transaction::{
     Action, AddKeyAction, DeployContractAction, FunctionCallAction, SignedTransaction, StakeAction,
@@ -90,11 +91,14 @@ fn is_zero_balance_account(account: &Account) -> bool {
 
 /// Validates the transaction without using the state. It allows any node to validate a
 /// transaction before forwarding it to the node that tracks the `signer_id` account.
+///
+/// The signature of transactions of `impersonated_accounts` isn't verified.
 pub fn validate_transaction(
     config: &RuntimeConfig,
     gas_price: Balance,
     signed_transaction: &SignedTransaction,
     verify_signature: bool,
+    impersonated_accounts: &SandboxImpersonatedAccounts,
     current_protocol_version: ProtocolVersion,
 ) -> Result<TransactionCost, InvalidTxError> {
     // Don't allow V1 currently. This will be changed when the new protocol version is introduced.
@@ -105,6 +109,7 @@ pub fn validate_transaction(
     let signer_id = transaction.signer_id();
 
     if verify_signature
+        && !impersonated_accounts.contains(signer_id)
         && !signed_transaction
             .signature
             .verify(signed_transaction.get_hash().as_ref(), transaction.public_key())
@@ -137,12 +142,16 @@ pub fn validate_transaction(
 
 /// Verifies the signed transaction on top of given state, charges transaction fees
 /// and balances, and updates the state for the used account and access keys.
+///
+/// Transactions of `impersonated_accounts` are accepted without checking their
+/// signature, access key and nonce.
 pub fn verify_and_charge_transaction(
     config: &RuntimeConfig,
     state_update: &mut TrieUpdate,
     gas_price: Balance,
     signed_transaction: &SignedTransaction,
     verify_signature: bool,
+    impersonated_accounts: &SandboxImpersonatedAccounts,
     block_height: Option<BlockHeight>,
     current_protocol_version: ProtocolVersion,
 ) -> Result<VerificationResult, InvalidTxError> {
@@ -153,6 +162,7 @@ pub fn verify_and_charge_transaction(
             gas_price,
             signed_transaction,
             verify_signature,
+            impersonated_accounts,
             current_protocol_version,
         )?;
 
@@ -165,27 +175,34 @@ pub fn verify_and_charge_transaction(
             return Err(InvalidTxError::SignerDoesNotExist { signer_id: signer_id.clone() });
         }
     };
-    let mut access_key = match get_access_key(state_update, signer_id, transaction.public_key())? {
-        Some(access_key) => access_key,
-        None => {
-            return Err(InvalidTxError::InvalidAccessKeyError(
-                InvalidAccessKeyError::AccessKeyNotFound {
-                    account_id: signer_id.clone(),
-                    public_key: transaction.public_key().clone().into(),
-                },
-            )
-            .into());
+    // Transactions of accounts impersonated in sandbox act with full access
+    // regardless of the key they claim to be signed with, which is left as is.
+    let impersonated = impersonated_accounts.contains(signer_id);
+    let mut access_key = if impersonated {
+        AccessKey::full_access()
+    } else {
+        match get_access_key(state_update, signer_id, transaction.public_key())? {
+            Some(access_key) => access_key,
+            None => {
+                return Err(InvalidTxError::InvalidAccessKeyError(
+                    InvalidAccessKeyError::AccessKeyNotFound {
+                        account_id: signer_id.clone(),
+                        public_key: transaction.public_key().clone().into(),
+                    },
+                )
+                .into());
+            }
         }
     };
 
-    if transaction.nonce() <= access_key.nonce {
+    if !impersonated && transaction.nonce() <= access_key.nonce {
         return Err(InvalidTxError::InvalidNonce {
             tx_nonce: transaction.nonce(),
             ak_nonce: access_key.nonce,
         }
         .into());
     }
-    if checked_feature!("stable", AccessKeyNonceRange, current_protocol_version) {
+    if !impersonated && checked_feature!("stable", AccessKeyNonceRange, current_protocol_version) {
         if let Some(height) = block_height {
             let upper_bound =
                 height * near_primitives::account::AccessKey::ACCESS_KEY_NONCE_RANGE_MULTIPLIER;
@@ -282,7 +299,14 @@ pub fn verify_and_charge_transaction(
         }
     };
 
-    set_access_key(state_update, signer_id.clone(), transaction.public_key().clone(), &access_key);
+    if !impersonated {
+        set_access_key(
+            state_update,
+            signer_id.clone(),
+            transaction.public_key().clone(),
+            &access_key,
+        );
+    }
     set_account(state_update, signer_id.clone(), &signer);
 
     Ok(VerificationResult { gas_burnt, gas_remaining, receipt_gas_price, burnt_amount })
@@ -723,8 +747,15 @@ mod tests {
         expected_err: InvalidTxError,
     ) {
         assert_eq!(
-            validate_transaction(config, gas_price, signed_transaction, true, PROTOCOL_VERSION)
-                .expect_err("expected an error"),
+            validate_transaction(
+                config,
+                gas_price,
+                signed_transaction,
+                true,
+                &Default::default(),
+                PROTOCOL_VERSION
+            )
+            .expect_err("expected an error"),
             expected_err,
         );
         assert_eq!(
@@ -734,6 +765,7 @@ mod tests {
                 gas_price,
                 signed_transaction,
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -857,14 +889,22 @@ mod tests {
             deposit,
             CryptoHash::default(),
         );
-        validate_transaction(&config, gas_price, &transaction, true, PROTOCOL_VERSION)
-            .expect("valid transaction");
+        validate_transaction(
+            &config,
+            gas_price,
+            &transaction,
+            true,
+            &Default::default(),
+            PROTOCOL_VERSION,
+        )
+        .expect("valid transaction");
         let verification_result = verify_and_charge_transaction(
             &config,
             &mut state_update,
             gas_price,
             &transaction,
             true,
+            &Default::default(),
             None,
             PROTOCOL_VERSION,
         )
@@ -937,6 +977,7 @@ mod tests {
                     CryptoHash::default(),
                 ),
                 false,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -948,6 +989,81 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "sandbox")]
+    fn test_validate_transaction_impersonated_account() {
+        let config = RuntimeConfig::test();
+        let account_id: AccountId = "impersonated.near".parse().unwrap();
+        let (signer, mut state_update, gas_price) = setup_accounts(vec![(
+            account_id.clone(),
+            TESTING_INIT_BALANCE,
+            0,
+            vec![],
+            false,
+            false,
+        )]);
+        let mut tx = SignedTransaction::send_money(
+            1,
+            account_id.clone(),
+            bob_account(),
+            &*signer,
+            100,
+            CryptoHash::default(),
+        );
+        tx.signature = signer.sign(CryptoHash::default().as_ref());
+        assert_err_both_validations(
+            &config,
+            &mut state_update,
+            gas_price,
+            &tx,
+            InvalidTxError::InvalidSignature,
+        );
+
+        let mut impersonated_accounts = SandboxImpersonatedAccounts::default();
+        assert!(impersonated_accounts.insert(account_id.clone()));
+        // The same nonce can be used again since the account has no access key
+        // and none is added.
+        for _ in 0..2 {
+            validate_transaction(
+                &config,
+                gas_price,
+                &tx,
+                true,
+                &impersonated_accounts,
+                PROTOCOL_VERSION,
+            )
+            .expect("valid transaction");
+            verify_and_charge_transaction(
+                &config,
+                &mut state_update,
+                gas_price,
+                &tx,
+                true,
+                &impersonated_accounts,
+                Some(1),
+                PROTOCOL_VERSION,
+            )
+            .expect("valid transaction");
+        }
+        assert!(get_access_key(&state_update, &account_id, &signer.public_key())
+            .unwrap()
+            .is_none());
+
+        assert!(impersonated_accounts.remove(&account_id));
+        assert_eq!(
+            validate_transaction(
+                &config,
+                gas_price,
+                &tx,
+                true,
+                &impersonated_accounts,
+                PROTOCOL_VERSION
+            )
+            .expect_err("expected an error"),
+            InvalidTxError::InvalidSignature,
+        );
+    }
+
     #[test]
     fn test_validate_transaction_invalid_bad_action() {
         let mut config = RuntimeConfig::test();
@@ -1002,6 +1118,7 @@ mod tests {
                     CryptoHash::default(),
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1033,6 +1150,7 @@ mod tests {
                     CryptoHash::default(),
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1105,6 +1223,7 @@ mod tests {
                 CryptoHash::default(),
             ),
             true,
+            &Default::default(),
             None,
             PROTOCOL_VERSION,
         )
@@ -1153,6 +1272,7 @@ mod tests {
                 0,
             ),
             true,
+            &Default::default(),
             None,
             PROTOCOL_VERSION,
         )
@@ -1199,6 +1319,7 @@ mod tests {
                 CryptoHash::default(),
             ),
             true,
+            &Default::default(),
             None,
             PROTOCOL_VERSION,
         );
@@ -1239,6 +1360,7 @@ mod tests {
                 CryptoHash::default(),
             ),
             true,
+            &Default::default(),
             None,
             PROTOCOL_VERSION,
         )
@@ -1294,6 +1416,7 @@ mod tests {
                     0
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1316,6 +1439,7 @@ mod tests {
                     0
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1338,6 +1462,7 @@ mod tests {
                     0
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1382,6 +1507,7 @@ mod tests {
                     0
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1429,6 +1555,7 @@ mod tests {
                     0
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1475,6 +1602,7 @@ mod tests {
                     0
                 ),
                 true,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1511,6 +1639,7 @@ mod tests {
                 gas_price,
                 &transaction,
                 false,
+                &Default::default(),
                 None,
                 PROTOCOL_VERSION,
             )
@@ -1529,6 +1658,7 @@ mod tests {
             gas_price,
             &transaction,
             false,
+            &Default::default(),
             None,
             PROTOCOL_VERSION,
         )
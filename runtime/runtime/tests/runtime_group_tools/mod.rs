@@ -122,6 +122,7 @@ impl StandaloneRuntime {
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info,
+            impersonated_accounts: Default::default(),
         };
 
         Self {
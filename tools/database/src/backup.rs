@@ -0,0 +1,155 @@
+use near_chain::store_validator::StoreValidator;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
+use near_primitives::types::BlockHeight;
+use near_store::backup::{create_backup, find_backup, list_backups, restore_backup};
+use near_store::{Mode, NodeStorage};
+use nearcore::{NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args)]
+pub(crate) struct BackupCommand {
+    #[clap(subcommand)]
+    subcmd: BackupSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum BackupSubCommand {
+    /// Back up the databases of a stopped node.  Running nodes make backups
+    /// on their own if `backup` is set in their config.
+    Create(CreateBackupCommand),
+    /// List the backups in a directory.
+    List(ListBackupsCommand),
+    /// Restore the databases of the node from a backup and validate them.
+    Restore(RestoreBackupCommand),
+}
+
+#[derive(clap::Args)]
+struct CreateBackupCommand {
+    /// Directory the backups are stored in.
+    #[clap(long)]
+    backup_dir: PathBuf,
+    /// Delete all but this number of latest backups afterwards.
+    #[clap(long)]
+    num_backups_to_keep: Option<usize>,
+}
+
+#[derive(clap::Args)]
+struct ListBackupsCommand {
+    /// Directory the backups are stored in.
+    #[clap(long)]
+    backup_dir: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct RestoreBackupCommand {
+    /// Directory the backups are stored in.
+    #[clap(long)]
+    backup_dir: PathBuf,
+    /// Restore the latest backup whose head is at or below this height.  The
+    /// databases are restored as they were backed up, with that backup's head,
+    /// rather than rolled back to exactly this height.  The latest backup is
+    /// restored if not set.
+    #[clap(long)]
+    max_height: Option<BlockHeight>,
+    /// Restore the cold database as well.
+    #[clap(long)]
+    cold: bool,
+    /// Don't validate the restored databases.
+    #[clap(long)]
+    skip_validation: bool,
+}
+
+impl BackupCommand {
+    pub(crate) fn run(&self, home_dir: &Path, near_config: NearConfig) -> anyhow::Result<()> {
+        match &self.subcmd {
+            BackupSubCommand::Create(cmd) => cmd.run(home_dir, &near_config),
+            BackupSubCommand::List(cmd) => cmd.run(),
+            BackupSubCommand::Restore(cmd) => cmd.run(home_dir, near_config),
+        }
+    }
+}
+
+fn opener<'a>(home_dir: &Path, near_config: &'a NearConfig) -> near_store::StoreOpener<'a> {
+    NodeStorage::opener(
+        home_dir,
+        near_config.config.archive,
+        &near_config.config.store,
+        near_config.config.cold_store.as_ref(),
+    )
+}
+
+impl CreateBackupCommand {
+    fn run(&self, home_dir: &Path, near_config: &NearConfig) -> anyhow::Result<()> {
+        let storage = opener(home_dir, near_config).open_in_mode(Mode::ReadWriteExisting)?;
+        let info = create_backup(&storage, &self.backup_dir, self.num_backups_to_keep)?;
+        println!("Created backup {} at height {}", info.id, info.height);
+        Ok(())
+    }
+}
+
+impl ListBackupsCommand {
+    fn run(&self) -> anyhow::Result<()> {
+        for info in list_backups(&self.backup_dir)? {
+            println!(
+                "id: {} cold id: {:?} height: {} block: {} timestamp: {}",
+                info.id, info.cold_id, info.height, info.block_hash, info.timestamp
+            );
+        }
+        Ok(())
+    }
+}
+
+impl RestoreBackupCommand {
+    fn run(&self, home_dir: &Path, near_config: NearConfig) -> anyhow::Result<()> {
+        let backup = find_backup(&self.backup_dir, self.max_height)?;
+        let opener = opener(home_dir, &near_config);
+        let cold_path = if self.cold {
+            Some(opener.cold_path().ok_or_else(|| anyhow::anyhow!("cold store isn't configured"))?)
+        } else {
+            None
+        };
+        restore_backup(&self.backup_dir, &backup, opener.path(), cold_path)?;
+        println!("Restored backup {} at height {}", backup.id, backup.height);
+        if !self.skip_validation {
+            validate(home_dir, near_config)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the store validator on the restored hot database.
+fn validate(home_dir: &Path, near_config: NearConfig) -> anyhow::Result<()> {
+    let store = opener(home_dir, &near_config).open_in_mode(Mode::ReadOnly)?.get_hot_store();
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+    let shard_tracker = ShardTracker::new(
+        TrackedConfig::from_config(&near_config.client_config),
+        epoch_manager.clone(),
+    );
+    let runtime = NightshadeRuntime::from_config(
+        home_dir,
+        store.clone(),
+        &near_config,
+        epoch_manager.clone(),
+    )?;
+    let mut store_validator = StoreValidator::new(
+        near_config.validator_signer.get().map(|signer| signer.validator_id().clone()),
+        near_config.genesis.config,
+        epoch_manager,
+        shard_tracker,
+        runtime,
+        store,
+        near_config.config.archive,
+    );
+    store_validator.validate();
+    for error in &store_validator.errors {
+        eprintln!("{} {} {}", error.col, error.key, error.err);
+    }
+    anyhow::ensure!(
+        !store_validator.is_failed(),
+        "validation of the restored database failed with {} errors",
+        store_validator.num_failed()
+    );
+    println!("Validated {} conditions", store_validator.tests_done());
+    Ok(())
+}
@@ -4,7 +4,9 @@ use crate::analyse_gas_usage::AnalyseGasUsageCommand;
 use crate::analyse_high_load::HighLoadStatsCommand;
 use crate::analyze_contract_sizes::AnalyzeContractSizesCommand;
 use crate::analyze_delayed_receipt::AnalyzeDelayedReceiptCommand;
+use crate::backup::BackupCommand;
 use crate::compact::RunCompactionCommand;
+use crate::convert_backend::ConvertBackendCommand;
 use crate::corrupt::CorruptStateSnapshotCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
 use crate::memtrie::LoadMemTrieCommand;
@@ -29,12 +31,18 @@ enum SubCommand {
     /// Analyse gas usage in a chosen sequnce of blocks
     AnalyseGasUsage(AnalyseGasUsageCommand),
 
+    /// Back up the databases or restore them from a backup
+    Backup(BackupCommand),
+
     /// Change DbKind of hot or cold db.
     ChangeDbKind(ChangeDbKindCommand),
 
     /// Run SST file compaction on database
     CompactDatabase(RunCompactionCommand),
 
+    /// Copy the database to a new one using another storage backend
+    ConvertBackend(ConvertBackendCommand),
+
     /// Corrupt the state snapshot.
     CorruptStateSnapshot(CorruptStateSnapshotCommand),
 
@@ -66,8 +74,29 @@ impl DatabaseCommand {
         match &self.subcmd {
             SubCommand::AnalyseDataSizeDistribution(cmd) => cmd.run(home),
             SubCommand::AnalyseGasUsage(cmd) => cmd.run(home),
+            SubCommand::Backup(cmd) => {
+                let near_config = nearcore::config::load_config(
+                    &home,
+                    near_chain_configs::GenesisValidationMode::UnsafeFast,
+                )
+                .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+                cmd.run(home, near_config)
+            }
             SubCommand::ChangeDbKind(cmd) => cmd.run(home),
             SubCommand::CompactDatabase(cmd) => cmd.run(home),
+            SubCommand::ConvertBackend(cmd) => {
+                let near_config = nearcore::config::load_config(
+                    &home,
+                    near_chain_configs::GenesisValidationMode::UnsafeFast,
+                )
+                .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+                cmd.run(
+                    home,
+                    near_config.config.archive,
+                    &near_config.config.store,
+                    near_config.config.cold_store.as_ref(),
+                )
+            }
             SubCommand::CorruptStateSnapshot(cmd) => cmd.run(home),
             SubCommand::MakeSnapshot(cmd) => {
                 let near_config = nearcore::config::load_config(
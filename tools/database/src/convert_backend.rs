@@ -0,0 +1,129 @@
+use near_store::db::{DBTransaction, Database, RedbDB, RocksDB};
+use near_store::{DBCol, DbBackend, Mode, NodeStorage, StoreConfig, Temperature};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+
+#[derive(clap::Args)]
+pub(crate) struct ConvertBackendCommand {
+    /// Backend of the converted database.
+    #[clap(long)]
+    backend: DbBackend,
+    /// Destination directory.  The converted database is created in its
+    /// `data` (or `cold-data`) subdirectory, so that it can replace the
+    /// database of the node as is.
+    #[clap(long)]
+    destination: PathBuf,
+    /// Convert the cold database instead of the hot one.
+    #[clap(long)]
+    cold: bool,
+    /// Maximal size of the data written to the converted database at once.
+    #[clap(long, default_value = "64MiB")]
+    batch_size: bytesize::ByteSize,
+}
+
+impl ConvertBackendCommand {
+    pub(crate) fn run(
+        &self,
+        home_dir: &Path,
+        archive: bool,
+        store_config: &StoreConfig,
+        cold_store_config: Option<&StoreConfig>,
+    ) -> anyhow::Result<()> {
+        let opener = NodeStorage::opener(home_dir, archive, store_config, cold_store_config);
+        let storage = opener.open_in_mode(Mode::ReadOnly)?;
+        let (temp, config, path) = if self.cold {
+            anyhow::ensure!(storage.has_cold(), "the node has no cold database");
+            (Temperature::Cold, cold_store_config.unwrap(), self.destination.join("cold-data"))
+        } else {
+            (Temperature::Hot, store_config, self.destination.join("data"))
+        };
+        if path.exists() && path.read_dir()?.next().is_some() {
+            anyhow::bail!("{} is not empty", path.display());
+        }
+        let source = storage.into_inner(temp);
+        let destination: Arc<dyn Database> = match self.backend {
+            DbBackend::RocksDb => Arc::new(RocksDB::open(&path, config, Mode::Create, temp)?),
+            DbBackend::Redb => Arc::new(RedbDB::open(&path, Mode::Create)?),
+        };
+
+        for col in DBCol::iter() {
+            if temp == Temperature::Cold && !col.is_in_colddb() {
+                continue;
+            }
+            // Values are copied as they are, including reference counts.
+            let mut transaction = DBTransaction::new();
+            let mut batch_size = 0;
+            let mut num_keys = 0;
+            for item in source.iter_raw_bytes(col) {
+                let (key, value) = item?;
+                batch_size += key.len() + value.len();
+                num_keys += 1;
+                transaction.set(col, key.into_vec(), value.into_vec());
+                if batch_size as u64 >= self.batch_size.as_u64() {
+                    destination.write(std::mem::take(&mut transaction))?;
+                    batch_size = 0;
+                }
+            }
+            destination.write(transaction)?;
+            eprintln!("Copied {num_keys} keys of column {col}");
+        }
+        destination.flush()?;
+        eprintln!("Converted the database to {} in {}", self.backend, path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert_backend::ConvertBackendCommand;
+    use near_store::db::RedbDB;
+    use near_store::{DBCol, DbBackend, Mode, NodeStorage, StoreConfig};
+
+    #[test]
+    fn test() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let store_config = StoreConfig::test_config();
+        let opener = NodeStorage::opener(home_dir.path(), false, &store_config, None);
+
+        let keys = vec![vec![0], vec![1], vec![2], vec![3]];
+        {
+            let node_storage = opener.open().unwrap();
+            let mut store_update = node_storage.get_hot_store().store_update();
+            for key in &keys {
+                store_update.insert(DBCol::Block, key.clone(), vec![42]);
+                store_update.increment_refcount(DBCol::State, key, &[42]);
+            }
+            store_update.commit().unwrap();
+        }
+
+        let destination = home_dir.path().join("converted");
+        for backend in [DbBackend::Redb, DbBackend::RocksDb] {
+            // Convert to redb first and back to RocksDB from the redb copy.
+            let (source, destination) = match backend {
+                DbBackend::Redb => (home_dir.path().to_path_buf(), destination.join("redb")),
+                DbBackend::RocksDb => (destination.join("redb"), destination.join("rocksdb")),
+            };
+            let cmd = ConvertBackendCommand {
+                backend,
+                destination: destination.clone(),
+                cold: false,
+                batch_size: bytesize::ByteSize::b(10),
+            };
+            cmd.run(&source, false, &store_config, None).unwrap();
+            assert_eq!(RedbDB::exists(&destination.join("data")), backend == DbBackend::Redb);
+            // Converting to a non-empty directory fails.
+            assert!(cmd.run(&source, false, &store_config, None).is_err());
+
+            let store = NodeStorage::opener(&destination, false, &store_config, None)
+                .open_in_mode(Mode::ReadOnly)
+                .unwrap()
+                .get_hot_store();
+            assert_eq!(store.get_db_version().unwrap(), Some(near_store::metadata::DB_VERSION));
+            for key in &keys {
+                assert!(store.exists(DBCol::Block, key).unwrap());
+                assert_eq!(store.get(DBCol::State, key).unwrap().as_deref(), Some(&[42][..]));
+            }
+        }
+    }
+}
@@ -4,9 +4,11 @@ mod analyse_gas_usage;
 mod analyse_high_load;
 mod analyze_contract_sizes;
 mod analyze_delayed_receipt;
+mod backup;
 mod block_iterators;
 pub mod commands;
 mod compact;
+mod convert_backend;
 mod corrupt;
 mod make_snapshot;
 mod memtrie;
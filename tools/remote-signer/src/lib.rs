@@ -0,0 +1,624 @@
+//! Reference implementation of the signing daemon used by validators
+//! configured with a remote signer, see `near_primitives::remote_signer`.
+//!
+//! [`RemoteSignerServer`] answers requests of `RemoteValidatorSigner`s with an
+//! in-memory key on background threads.  It is run by the `near-remote-signer`
+//! binary, by tests and documents the behaviour expected from production
+//! daemons.
+//!
+//! Before signing a block, a chunk, a chunk endorsement or an approval the
+//! daemon records it in a slashing protection file and refuses to sign a
+//! different one at the same height later on.  The record is persisted before
+//! the signature is returned, so the protection survives restarts of both the
+//! node and the daemon.  Approvals far above the last signed one are refused
+//! too, as signing them would prune all the records.
+//!
+//! Payloads which are signed as they are (state witnesses and account key
+//! payloads) are decoded first, so that a compromised node can't use these
+//! requests to get a signature over e.g. a block hash.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::Signature;
+use near_network::types::validate_account_key_payload;
+use near_primitives::block::ApprovalInner;
+use near_primitives::block_header::{BlockHeader, BlockHeaderInnerLite};
+use near_primitives::hash::CryptoHash;
+use near_primitives::remote_signer::{
+    read_message, write_message, RemoteSignerRequest, RemoteSignerResponse,
+};
+use near_primitives::sharding::{ChunkHash, ShardChunkHeaderV3};
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::validator_signer::{
+    InMemoryValidatorSigner, ValidatorSigner, ValidatorSignerError,
+};
+
+/// Number of heights below the highest signed one for which the signed blocks,
+/// chunks and approvals are remembered.  Nothing below this window can be
+/// signed.
+pub const SLASHING_PROTECTION_WINDOW: BlockHeight = 10_000;
+
+/// Maximum distance between the target heights of the last signed approval and
+/// the next one.  Allowing larger jumps would let a single approval prune the
+/// records of all the approvals signed so far.
+pub const MAX_APPROVAL_HEIGHT_JUMP: BlockHeight = SLASHING_PROTECTION_WINDOW;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SlashingProtectionError {
+    #[error("a different block has already been signed at height {height}")]
+    DoubleSignedBlock { height: BlockHeight },
+    #[error("a different chunk has already been signed at height {height} in shard {shard_id}")]
+    DoubleSignedChunk { height: BlockHeight, shard_id: ShardId },
+    #[error("a different chunk has already been endorsed at height {height} in shard {shard_id}")]
+    DoubleSignedChunkEndorsement { height: BlockHeight, shard_id: ShardId },
+    #[error("a different approval has already been signed for height {target_height}")]
+    DoubleSignedApproval { target_height: BlockHeight },
+    #[error(
+        "approval for height {target_height} is too far above the last signed one, \
+         restart the daemon to sign it anyway (max height: {max_height})"
+    )]
+    ApprovalTooFarAhead { target_height: BlockHeight, max_height: BlockHeight },
+    #[error("height {height} is below the lowest height which can be signed ({min_height})")]
+    HeightTooLow { height: BlockHeight, min_height: BlockHeight },
+    #[error("failed to persist the slashing protection record: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+struct SlashingProtectionRecords {
+    /// Heights below this one have been pruned and can't be signed anymore.
+    min_height: BlockHeight,
+    blocks: BTreeMap<BlockHeight, CryptoHash>,
+    chunks: BTreeMap<(BlockHeight, ShardId), ChunkHash>,
+    chunk_endorsements: BTreeMap<(BlockHeight, ShardId), ChunkHash>,
+    approvals: BTreeMap<BlockHeight, ApprovalInner>,
+}
+
+/// Blocks, chunks, chunk endorsements and approvals signed by the daemon,
+/// persisted in a file.
+pub struct SlashingProtection {
+    path: PathBuf,
+    records: SlashingProtectionRecords,
+    /// Highest target height of the approvals signed since the daemon started.
+    /// The first approval after a restart isn't limited, so that a validator
+    /// which was offline for a long time can sign again.
+    last_approval_height: Option<BlockHeight>,
+}
+
+impl SlashingProtection {
+    /// Loads the records from the file, starting from scratch if it doesn't
+    /// exist yet.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let records = match std::fs::read(path) {
+            Ok(bytes) => SlashingProtectionRecords::try_from_slice(&bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Default::default(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self { path: path.to_path_buf(), records, last_approval_height: None })
+    }
+
+    /// Records the block, failing if a different block has been signed at the
+    /// same height.  Signing the same block again is allowed.
+    pub fn check_block(
+        &mut self,
+        height: BlockHeight,
+        hash: CryptoHash,
+    ) -> Result<(), SlashingProtectionError> {
+        self.check_height(height)?;
+        match self.records.blocks.get(&height) {
+            Some(signed) if *signed == hash => return Ok(()),
+            Some(_) => return Err(SlashingProtectionError::DoubleSignedBlock { height }),
+            None => {}
+        }
+        self.records.blocks.insert(height, hash);
+        self.prune_and_save(height)
+    }
+
+    /// Records the chunk, failing if a different chunk has been signed at the
+    /// same height in the same shard.  Signing the same chunk again is allowed.
+    pub fn check_chunk(
+        &mut self,
+        height: BlockHeight,
+        shard_id: ShardId,
+        hash: &ChunkHash,
+    ) -> Result<(), SlashingProtectionError> {
+        self.check_height(height)?;
+        match self.records.chunks.get(&(height, shard_id)) {
+            Some(signed) if signed == hash => return Ok(()),
+            Some(_) => return Err(SlashingProtectionError::DoubleSignedChunk { height, shard_id }),
+            None => {}
+        }
+        self.records.chunks.insert((height, shard_id), hash.clone());
+        self.prune_and_save(height)
+    }
+
+    /// Records the chunk endorsement, failing if a different chunk has been
+    /// endorsed at the same height in the same shard.  Endorsing the same chunk
+    /// again is allowed.
+    pub fn check_chunk_endorsement(
+        &mut self,
+        height: BlockHeight,
+        shard_id: ShardId,
+        hash: &ChunkHash,
+    ) -> Result<(), SlashingProtectionError> {
+        self.check_height(height)?;
+        match self.records.chunk_endorsements.get(&(height, shard_id)) {
+            Some(signed) if signed == hash => return Ok(()),
+            Some(_) => {
+                return Err(SlashingProtectionError::DoubleSignedChunkEndorsement {
+                    height,
+                    shard_id,
+                })
+            }
+            None => {}
+        }
+        self.records.chunk_endorsements.insert((height, shard_id), hash.clone());
+        self.prune_and_save(height)
+    }
+
+    /// Records the approval, failing if a different approval has been signed
+    /// for the same target height or if the target height is more than
+    /// `MAX_APPROVAL_HEIGHT_JUMP` above the last signed one.  Signing the same
+    /// approval again is allowed.
+    pub fn check_approval(
+        &mut self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<(), SlashingProtectionError> {
+        self.check_height(target_height)?;
+        if let Some(last_approval_height) = self.last_approval_height {
+            let max_height = last_approval_height.saturating_add(MAX_APPROVAL_HEIGHT_JUMP);
+            if target_height > max_height {
+                return Err(SlashingProtectionError::ApprovalTooFarAhead {
+                    target_height,
+                    max_height,
+                });
+            }
+        }
+        match self.records.approvals.get(&target_height) {
+            Some(signed) if signed == inner => return Ok(()),
+            Some(_) => return Err(SlashingProtectionError::DoubleSignedApproval { target_height }),
+            None => {}
+        }
+        self.records.approvals.insert(target_height, inner.clone());
+        self.prune_and_save(target_height)?;
+        self.last_approval_height = self.last_approval_height.max(Some(target_height));
+        Ok(())
+    }
+
+    fn check_height(&self, height: BlockHeight) -> Result<(), SlashingProtectionError> {
+        let min_height = self.records.min_height;
+        if height < min_height {
+            return Err(SlashingProtectionError::HeightTooLow { height, min_height });
+        }
+        Ok(())
+    }
+
+    fn prune_and_save(&mut self, height: BlockHeight) -> Result<(), SlashingProtectionError> {
+        let min_height = height.saturating_sub(SLASHING_PROTECTION_WINDOW);
+        if min_height > self.records.min_height {
+            self.records.min_height = min_height;
+            self.records.blocks = self.records.blocks.split_off(&min_height);
+            self.records.chunks = self.records.chunks.split_off(&(min_height, 0));
+            self.records.chunk_endorsements =
+                self.records.chunk_endorsements.split_off(&(min_height, 0));
+            self.records.approvals = self.records.approvals.split_off(&min_height);
+        }
+        // Write to a temporary file first so that a crash never leaves a
+        // truncated file behind.
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(&borsh::to_vec(&self.records)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+struct Daemon {
+    in_memory_signer: InMemoryValidatorSigner,
+    signer: ValidatorSigner,
+    slashing_protection: SlashingProtection,
+}
+
+fn signature_response(result: Result<Signature, ValidatorSignerError>) -> RemoteSignerResponse {
+    match result {
+        Ok(signature) => RemoteSignerResponse::Signature(signature),
+        Err(err) => RemoteSignerResponse::Refused(err.to_string()),
+    }
+}
+
+impl Daemon {
+    fn handle(&mut self, request: RemoteSignerRequest) -> RemoteSignerResponse {
+        use RemoteSignerRequest::*;
+        use RemoteSignerResponse::Refused;
+
+        let signer = &self.signer;
+        match request {
+            GetValidatorKey => RemoteSignerResponse::ValidatorKey {
+                account_id: signer.validator_id().clone(),
+                public_key: signer.public_key(),
+            },
+            SignBlockHeader { prev_hash, inner_lite, inner_rest } => {
+                let height = match BlockHeaderInnerLite::try_from_slice(&inner_lite) {
+                    Ok(inner_lite) => inner_lite.height,
+                    Err(err) => return Refused(format!("invalid block header: {err}")),
+                };
+                let hash = BlockHeader::compute_hash(prev_hash, &inner_lite, &inner_rest);
+                if let Err(err) = self.slashing_protection.check_block(height, hash) {
+                    return Refused(err.to_string());
+                }
+                signature_response(
+                    signer
+                        .sign_block_header_parts(prev_hash, &inner_lite, &inner_rest)
+                        .map(|(_, signature)| signature),
+                )
+            }
+            SignChunkHeader(inner) => {
+                let hash = ShardChunkHeaderV3::compute_hash(&inner);
+                if let Err(err) = self.slashing_protection.check_chunk(
+                    inner.height_created(),
+                    inner.shard_id(),
+                    &hash,
+                ) {
+                    return Refused(err.to_string());
+                }
+                signature_response(
+                    signer.sign_chunk_header_inner(&inner).map(|(_, signature)| signature),
+                )
+            }
+            SignApproval { inner, target_height } => {
+                if let Err(err) = self.slashing_protection.check_approval(&inner, target_height) {
+                    return Refused(err.to_string());
+                }
+                signature_response(signer.sign_approval(&inner, target_height))
+            }
+            SignChunkEndorsement { inner, height_created, shard_id } => {
+                if let Err(err) = self.slashing_protection.check_chunk_endorsement(
+                    height_created,
+                    shard_id,
+                    inner.chunk_hash(),
+                ) {
+                    return Refused(err.to_string());
+                }
+                signature_response(signer.sign_chunk_endorsement(&inner, height_created, shard_id))
+            }
+            SignChunkStateWitness(witness_bytes) => {
+                // The bytes are signed as they are, so make sure they really
+                // are a witness and not e.g. a block or chunk hash.
+                if witness_bytes.size_bytes() == CryptoHash::LENGTH {
+                    return Refused(
+                        "invalid chunk state witness: has the size of a hash".to_string(),
+                    );
+                }
+                if let Err(err) = witness_bytes.decode() {
+                    return Refused(format!("invalid chunk state witness: {err}"));
+                }
+                signature_response(signer.sign_chunk_state_witness(&witness_bytes))
+            }
+            SignPartialEncodedStateWitness(part) => {
+                signature_response(signer.sign_partial_encoded_state_witness(&part))
+            }
+            SignChallenge(challenge_body) => signature_response(
+                signer.sign_challenge(&challenge_body).map(|(_, signature)| signature),
+            ),
+            SignAccountAnnounce { account_id, peer_id, epoch_id } => {
+                signature_response(signer.sign_account_announce(&account_id, &peer_id, &epoch_id))
+            }
+            SignAccountKeyPayload(proto_bytes) => {
+                if let Err(err) = validate_account_key_payload(&proto_bytes) {
+                    return Refused(format!("invalid account key payload: {err}"));
+                }
+                signature_response(signer.sign_account_key_payload(&proto_bytes))
+            }
+            SignTelemetry(content) => {
+                if serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content)
+                    .is_err()
+                {
+                    return Refused("telemetry must be a JSON object".to_string());
+                }
+                RemoteSignerResponse::Signature(
+                    self.in_memory_signer.sign_telemetry_content(&content),
+                )
+            }
+            ComputeVrf(data) => match signer.compute_vrf_with_proof(&data) {
+                Ok((value, proof)) => RemoteSignerResponse::Vrf { value, proof },
+                Err(err) => Refused(err.to_string()),
+            },
+        }
+    }
+}
+
+/// Signing daemon serving requests on a Unix socket until dropped.
+pub struct RemoteSignerServer {
+    socket_path: PathBuf,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RemoteSignerServer {
+    /// Starts listening on `socket_path`, signing with `signer`.  Signed
+    /// blocks, chunks, chunk endorsements and approvals are recorded in
+    /// `slashing_protection_path`.
+    pub fn spawn(
+        socket_path: &Path,
+        signer: InMemoryValidatorSigner,
+        slashing_protection_path: &Path,
+    ) -> io::Result<Self> {
+        let daemon = Arc::new(Mutex::new(Daemon {
+            signer: signer.clone().into(),
+            in_memory_signer: signer,
+            slashing_protection: SlashingProtection::open(slashing_protection_path)?,
+        }));
+        let listener = UnixListener::bind(socket_path)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = std::thread::Builder::new().name("remote_signer".to_string()).spawn({
+            let stop = stop.clone();
+            move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            let daemon = daemon.clone();
+                            std::thread::spawn(move || serve_connection(stream, &daemon));
+                        }
+                        Err(err) => {
+                            tracing::warn!(target: "remote_signer", %err, "Failed to accept connection")
+                        }
+                    }
+                }
+            }
+        })?;
+        Ok(Self { socket_path: socket_path.to_path_buf(), stop, handle: Some(handle) })
+    }
+
+    /// Blocks until the daemon stops accepting connections.
+    pub fn join(mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve_connection(mut stream: UnixStream, daemon: &Mutex<Daemon>) {
+    loop {
+        let request = match read_message::<RemoteSignerRequest>(&mut stream) {
+            Ok(request) => request,
+            // The node has closed the connection.
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(err) => {
+                tracing::warn!(target: "remote_signer", %err, "Failed to read request");
+                return;
+            }
+        };
+        let request_name = request.name();
+        let response = daemon.lock().unwrap().handle(request);
+        if let RemoteSignerResponse::Refused(reason) = &response {
+            tracing::warn!(target: "remote_signer", request = request_name, %reason, "Refused to sign");
+        }
+        if let Err(err) = write_message(&mut stream, &response) {
+            tracing::warn!(target: "remote_signer", %err, "Failed to write response");
+            return;
+        }
+    }
+}
+
+impl Drop for RemoteSignerServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake up the listener blocked on accepting connections.
+        let _ = UnixStream::connect(&self.socket_path);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RemoteSignerServer, SlashingProtection, SlashingProtectionError, MAX_APPROVAL_HEIGHT_JUMP,
+    };
+    use near_crypto::KeyType;
+    use near_primitives::block::{Approval, ApprovalInner};
+    use near_primitives::block_header::BlockHeaderInnerLite;
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::remote_signer::RemoteValidatorSigner;
+    use near_primitives::sharding::{
+        ShardChunkHeader, ShardChunkHeaderInner, ShardChunkHeaderInnerV2, ShardChunkHeaderV3,
+    };
+    use near_primitives::stateless_validation::{
+        ChunkEndorsement, ChunkStateWitness, EncodedChunkStateWitness,
+    };
+    use near_primitives::types::{BlockHeight, ShardId};
+    use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+    use std::time::Duration;
+
+    fn chunk_header_inner(
+        prev_block_hash: CryptoHash,
+        height: BlockHeight,
+        shard_id: ShardId,
+    ) -> ShardChunkHeaderInner {
+        ShardChunkHeaderInner::V2(ShardChunkHeaderInnerV2 {
+            prev_block_hash,
+            prev_state_root: CryptoHash::default(),
+            prev_outcome_root: CryptoHash::default(),
+            encoded_merkle_root: CryptoHash::default(),
+            encoded_length: 0,
+            height_created: height,
+            shard_id,
+            prev_gas_used: 0,
+            gas_limit: 0,
+            prev_balance_burnt: 0,
+            prev_outgoing_receipts_root: CryptoHash::default(),
+            tx_root: CryptoHash::default(),
+            prev_validator_proposals: vec![],
+        })
+    }
+
+    fn signed_chunk_header(
+        inner: ShardChunkHeaderInner,
+        signer: &ValidatorSigner,
+    ) -> ShardChunkHeader {
+        ShardChunkHeader::V3(ShardChunkHeaderV3::from_inner(inner, signer).unwrap())
+    }
+
+    #[test]
+    fn test_slashing_protection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slashing_protection");
+        let mut protection = SlashingProtection::open(&path).unwrap();
+        let block = hash(b"block");
+        protection.check_block(10, block).unwrap();
+        protection.check_block(10, block).unwrap();
+        assert_matches::assert_matches!(
+            protection.check_block(10, hash(b"fork")),
+            Err(SlashingProtectionError::DoubleSignedBlock { height: 10 })
+        );
+        let chunk = hash(b"chunk").into();
+        protection.check_chunk(10, 0, &chunk).unwrap();
+        protection.check_chunk(10, 1, &hash(b"other shard").into()).unwrap();
+        assert_matches::assert_matches!(
+            protection.check_chunk(10, 0, &hash(b"fork").into()),
+            Err(SlashingProtectionError::DoubleSignedChunk { height: 10, shard_id: 0 })
+        );
+        protection.check_chunk_endorsement(10, 0, &chunk).unwrap();
+        protection.check_chunk_endorsement(10, 0, &chunk).unwrap();
+        assert_matches::assert_matches!(
+            protection.check_chunk_endorsement(10, 0, &hash(b"fork").into()),
+            Err(SlashingProtectionError::DoubleSignedChunkEndorsement { height: 10, shard_id: 0 })
+        );
+        let approval = ApprovalInner::Endorsement(block);
+        protection.check_approval(&approval, 11).unwrap();
+        assert_matches::assert_matches!(
+            protection.check_approval(&ApprovalInner::Skip(9), 11),
+            Err(SlashingProtectionError::DoubleSignedApproval { target_height: 11 })
+        );
+        let far_ahead = 11 + MAX_APPROVAL_HEIGHT_JUMP + 1;
+        assert_matches::assert_matches!(
+            protection.check_approval(&ApprovalInner::Skip(11), far_ahead),
+            Err(SlashingProtectionError::ApprovalTooFarAhead { .. })
+        );
+
+        // The records survive restarts.
+        let mut protection = SlashingProtection::open(&path).unwrap();
+        assert!(protection.check_block(10, hash(b"fork")).is_err());
+        assert!(protection.check_chunk(10, 0, &hash(b"fork").into()).is_err());
+        assert!(protection.check_chunk_endorsement(10, 0, &hash(b"fork").into()).is_err());
+        protection.check_approval(&approval, 11).unwrap();
+        // The first approval after a restart can be far ahead.
+        protection.check_approval(&ApprovalInner::Skip(11), far_ahead).unwrap();
+
+        // Old records are pruned, but nothing can be signed below them.
+        protection.check_block(100_000, block).unwrap();
+        assert!(protection.records.blocks.get(&10).is_none());
+        assert!(protection.records.chunks.is_empty());
+        assert!(protection.records.chunk_endorsements.is_empty());
+        assert_matches::assert_matches!(
+            protection.check_block(10, block),
+            Err(SlashingProtectionError::HeightTooLow { height: 10, .. })
+        );
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("signer.sock");
+        let in_memory_signer =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let _server = RemoteSignerServer::spawn(
+            &socket_path,
+            in_memory_signer.clone(),
+            &dir.path().join("slashing_protection"),
+        )
+        .unwrap();
+        let in_memory_signer: ValidatorSigner = in_memory_signer.into();
+        let signer: ValidatorSigner =
+            RemoteValidatorSigner::connect(&socket_path, Duration::from_secs(10)).unwrap().into();
+        assert_eq!(signer.validator_id(), in_memory_signer.validator_id());
+        assert_eq!(signer.public_key(), in_memory_signer.public_key());
+
+        // Signatures made remotely are identical to the ones made locally.
+        let prev_hash = hash(b"prev");
+        let inner_lite = BlockHeaderInnerLite { height: 10, ..Default::default() };
+        let inner_lite = borsh::to_vec(&inner_lite).unwrap();
+        assert_eq!(
+            signer.sign_block_header_parts(prev_hash, &inner_lite, b"rest").unwrap(),
+            in_memory_signer.sign_block_header_parts(prev_hash, &inner_lite, b"rest").unwrap(),
+        );
+        let chunk = chunk_header_inner(prev_hash, 10, 0);
+        assert_eq!(
+            signer.sign_chunk_header_inner(&chunk).unwrap(),
+            in_memory_signer.sign_chunk_header_inner(&chunk).unwrap(),
+        );
+        let chunk_header = signed_chunk_header(chunk.clone(), &in_memory_signer);
+        assert_eq!(
+            ChunkEndorsement::new(&chunk_header, &signer).unwrap(),
+            ChunkEndorsement::new(&chunk_header, &in_memory_signer).unwrap()
+        );
+        let inner = ApprovalInner::Endorsement(prev_hash);
+        assert_eq!(
+            signer.sign_approval(&inner, 11).unwrap(),
+            in_memory_signer.sign_approval(&inner, 11).unwrap()
+        );
+        let data = CryptoHash::default();
+        assert_eq!(
+            signer.compute_vrf_with_proof(data.as_ref()).unwrap().0,
+            in_memory_signer.compute_vrf_with_proof(data.as_ref()).unwrap().0
+        );
+
+        // Double signing is refused and reported as an error.
+        assert!(signer.sign_block_header_parts(prev_hash, &inner_lite, b"fork").is_err());
+        let fork = chunk_header_inner(hash(b"fork"), 10, 0);
+        assert!(signer.sign_chunk_header_inner(&fork).is_err());
+        assert!(
+            ChunkEndorsement::new(&signed_chunk_header(fork, &in_memory_signer), &signer).is_err()
+        );
+        assert!(signer.sign_approval(&ApprovalInner::Skip(9), 11).is_err());
+    }
+
+    #[test]
+    fn test_remote_signer_refuses_raw_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("signer.sock");
+        let in_memory_signer =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let _server = RemoteSignerServer::spawn(
+            &socket_path,
+            in_memory_signer.clone(),
+            &dir.path().join("slashing_protection"),
+        )
+        .unwrap();
+        let in_memory_signer: ValidatorSigner = in_memory_signer.into();
+        let signer: ValidatorSigner =
+            RemoteValidatorSigner::connect(&socket_path, Duration::from_secs(10)).unwrap().into();
+
+        let (witness, _) =
+            EncodedChunkStateWitness::encode(&ChunkStateWitness::new_dummy(10, 0, hash(b"prev")))
+                .unwrap();
+        assert_eq!(
+            signer.sign_chunk_state_witness(&witness).unwrap(),
+            in_memory_signer.sign_chunk_state_witness(&witness).unwrap()
+        );
+
+        // A block hash or approval must not be signed through requests which
+        // sign the given bytes as they are, bypassing slashing protection.
+        let block_hash = hash(b"block");
+        let approval = Approval::get_data_for_sig(&ApprovalInner::Endorsement(block_hash), 11);
+        for bytes in [block_hash.as_ref(), approval.as_slice()] {
+            let witness = EncodedChunkStateWitness::from_boxed_slice(bytes.into());
+            assert!(signer.sign_chunk_state_witness(&witness).is_err());
+            assert!(signer.sign_account_key_payload(bytes).is_err());
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use clap::Parser;
+use near_primitives::validator_signer::InMemoryValidatorSigner;
+use near_remote_signer::RemoteSignerServer;
+use std::path::PathBuf;
+
+/// Signing daemon holding the key of a validator configured with
+/// `remote_signer.socket_path`.
+#[derive(Parser)]
+struct Cli {
+    /// Unix socket to listen on.
+    #[clap(long)]
+    socket_path: PathBuf,
+    /// File with the validator key, in the format of `validator_key.json`.
+    #[clap(long)]
+    validator_key_file: PathBuf,
+    /// File recording the signed blocks, chunks and approvals.  Must be kept
+    /// across restarts of the daemon.
+    #[clap(long)]
+    slashing_protection_file: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let env_filter = near_o11y::EnvFilterBuilder::from_env().verbose(Some("")).finish().unwrap();
+    let _subscriber = near_o11y::default_subscriber(env_filter, &Default::default()).global();
+
+    let cli = Cli::parse();
+    let signer = InMemoryValidatorSigner::from_file(&cli.validator_key_file)?;
+    tracing::info!(target: "remote_signer", validator_id = %signer.validator_id(), socket_path = %cli.socket_path.display(), "Starting remote signer");
+    let server =
+        RemoteSignerServer::spawn(&cli.socket_path, signer, &cli.slashing_protection_file)?;
+    server.join();
+    Ok(())
+}
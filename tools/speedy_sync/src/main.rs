@@ -252,6 +252,8 @@ fn load_snapshot(load_cmd: LoadCmd) {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
+            shard_scheduler: Default::default(),
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -8,15 +8,19 @@ use near_chain::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
 use near_chain_configs::Genesis;
 use near_epoch_manager::{EpochManagerAdapter, EpochManagerHandle};
 use near_primitives::apply::ApplyChunkReason;
+use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::DelayedReceiptIndices;
+use near_primitives::serialize::dec_format;
 use near_primitives::transaction::{Action, ExecutionOutcomeWithId, ExecutionOutcomeWithProof};
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::types::{Balance, BlockHeight, Gas, ShardId, StateRoot};
+use near_primitives::views::ExecutionOutcomeView;
 use near_store::flat::{BlockInfo, FlatStateChanges, FlatStorageStatus};
 use near_store::{DBCol, Store};
 use nearcore::NightshadeRuntime;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::Write;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -105,6 +109,146 @@ fn old_outcomes(
         .collect()
 }
 
+/// Results of applying the chunk of a shard in a block, written as a line of
+/// the JSON report.
+///
+/// Except in the chained mode, every block is applied on top of the stored
+/// state of the previous block rather than on the state replayed for it, so a
+/// divergence shows up in the block which causes it only and isn't carried
+/// over to the following blocks.
+#[derive(serde::Serialize)]
+struct BlockReport {
+    height: BlockHeight,
+    block_hash: CryptoHash,
+    shard_id: ShardId,
+    chunk_present: bool,
+    /// State root the block was applied on: the stored one or, in the chained
+    /// mode, the one replayed for the previous block.
+    prev_state_root: StateRoot,
+    state_root: StateRoot,
+    outcome_root: CryptoHash,
+    gas_burnt: Gas,
+    #[serde(with = "dec_format")]
+    balance_burnt: Balance,
+    /// Outcomes of the transactions and receipts in the order they were
+    /// executed in, including their gas profiles.
+    outcomes: Vec<OutcomeReport>,
+    /// Differences with the results stored in the database, if any.
+    divergence: Option<Divergence>,
+}
+
+#[derive(serde::Serialize)]
+struct OutcomeReport {
+    id: CryptoHash,
+    outcome: ExecutionOutcomeView,
+}
+
+#[derive(serde::Serialize)]
+struct Divergence {
+    /// Fields of the `ChunkExtra` which differ from the stored one.
+    chunk_extra: Vec<FieldDiff>,
+    /// First outcome, in the order of execution, which differs from the
+    /// stored one.
+    first_divergent_outcome: Option<OutcomeDiff>,
+}
+
+#[derive(serde::Serialize)]
+struct FieldDiff {
+    field: &'static str,
+    stored: String,
+    replayed: String,
+}
+
+#[derive(serde::Serialize)]
+struct OutcomeDiff {
+    /// Hash of the transaction or id of the receipt.
+    id: CryptoHash,
+    is_transaction: bool,
+    /// `None` if the outcome isn't stored, or wasn't produced by the replay.
+    stored: Option<ExecutionOutcomeView>,
+    replayed: Option<ExecutionOutcomeView>,
+}
+
+/// Report of the replay of a range of blocks.  Blocks which results differ
+/// from the stored ones are recorded in the report instead of aborting the
+/// replay.
+struct ReplayReport<'a> {
+    file: Mutex<&'a mut File>,
+    divergent_heights: Mutex<Vec<BlockHeight>>,
+}
+
+impl<'a> ReplayReport<'a> {
+    fn new(file: &'a mut File) -> Self {
+        Self { file: Mutex::new(file), divergent_heights: Mutex::new(vec![]) }
+    }
+
+    fn add(&self, block_report: &BlockReport) {
+        if block_report.divergence.is_some() {
+            self.divergent_heights.lock().unwrap().push(block_report.height);
+        }
+        let mut file = self.file.lock().unwrap();
+        serde_json::to_writer(&mut **file, block_report).unwrap();
+        writeln!(file).unwrap();
+    }
+}
+
+fn diff_chunk_extra(stored: &ChunkExtra, replayed: &ChunkExtra) -> Vec<FieldDiff> {
+    let mut diffs = vec![];
+    let mut add = |field: &'static str, stored: String, replayed: String| {
+        if stored != replayed {
+            diffs.push(FieldDiff { field, stored, replayed });
+        }
+    };
+    add("state_root", stored.state_root().to_string(), replayed.state_root().to_string());
+    add("outcome_root", stored.outcome_root().to_string(), replayed.outcome_root().to_string());
+    add("gas_used", stored.gas_used().to_string(), replayed.gas_used().to_string());
+    add("gas_limit", stored.gas_limit().to_string(), replayed.gas_limit().to_string());
+    add("balance_burnt", stored.balance_burnt().to_string(), replayed.balance_burnt().to_string());
+    let proposals = |extra: &ChunkExtra| {
+        format!("{:?}", extra.validator_proposals().map(|p| p.into_v1()).collect::<Vec<_>>())
+    };
+    add("validator_proposals", proposals(stored), proposals(replayed));
+    diffs
+}
+
+/// Returns the first outcome which differs from the one stored for the block,
+/// or the first stored outcome the replay didn't produce.
+fn find_first_divergent_outcome(
+    chain_store: &ChainStore,
+    block_hash: &CryptoHash,
+    shard_id: ShardId,
+    outcomes: &[ExecutionOutcomeWithId],
+    tx_hashes: &HashSet<CryptoHash>,
+) -> Option<OutcomeDiff> {
+    for outcome in outcomes {
+        let stored = chain_store
+            .get_outcome_by_id_and_block_hash(&outcome.id, block_hash)
+            .unwrap()
+            .map(|stored| ExecutionOutcomeView::from(stored.outcome));
+        // The compute usage isn't stored, so it's compared through the views.
+        let replayed = ExecutionOutcomeView::from(outcome.outcome.clone());
+        if stored.as_ref() != Some(&replayed) {
+            return Some(OutcomeDiff {
+                id: outcome.id,
+                is_transaction: tx_hashes.contains(&outcome.id),
+                stored,
+                replayed: Some(replayed),
+            });
+        }
+    }
+    let replayed_ids = outcomes.iter().map(|outcome| outcome.id).collect::<HashSet<_>>();
+    let stored_ids =
+        chain_store.get_outcomes_by_block_hash_and_shard_id(block_hash, shard_id).unwrap();
+    let id = stored_ids.into_iter().find(|id| !replayed_ids.contains(id))?;
+    let stored = chain_store.get_outcome_by_id_and_block_hash(&id, block_hash).unwrap()?;
+    Some(OutcomeDiff {
+        id,
+        is_transaction: tx_hashes.contains(&id),
+        stored: Some(stored.outcome.into()),
+        replayed: None,
+    })
+}
+
 fn maybe_add_to_csv(csv_file_mutex: &Mutex<Option<&mut File>>, s: &str) {
     let mut csv_file = csv_file_mutex.lock().unwrap();
     if let Some(csv_file) = csv_file.as_mut() {
@@ -123,9 +267,11 @@ fn apply_block_from_range(
     progress_reporter: &ProgressReporter,
     verbose_output: bool,
     csv_file_mutex: &Mutex<Option<&mut File>>,
+    report: Option<&ReplayReport>,
     only_contracts: bool,
     storage: StorageSource,
-) {
+    replayed_prev_state_root: Option<StateRoot>,
+) -> Option<StateRoot> {
     // normally save_trie_changes depends on whether the node is
     // archival, but here we don't care, and can just set it to false
     // since we're not writing anything to the store anyway
@@ -135,7 +281,7 @@ fn apply_block_from_range(
         Err(_) => {
             // Skipping block because it's not available in ChainStore.
             progress_reporter.inc_and_report_progress(0);
-            return;
+            return None;
         }
     };
     let block = chain_store.get_block(&block_hash).unwrap();
@@ -145,7 +291,9 @@ fn apply_block_from_range(
     let mut prev_chunk_extra = None;
     let mut num_tx = 0;
     let mut num_receipt = 0;
+    let mut tx_hashes = HashSet::new();
     let chunk_present: bool;
+    let prev_state_root: StateRoot;
 
     let block_author = epoch_manager
         .get_block_producer(block.header().epoch_id(), block.header().height())
@@ -156,7 +304,7 @@ fn apply_block_from_range(
             println!("Skipping the genesis block #{}.", height);
         }
         progress_reporter.inc_and_report_progress(0);
-        return;
+        return None;
     } else if block.chunks()[shard_id as usize].height_included() == height {
         chunk_present = true;
         let res_existing_chunk_extra = chain_store.get_chunk_extra(&block_hash, &shard_uid);
@@ -192,7 +340,7 @@ fn apply_block_from_range(
                     ),
                 );
                 progress_reporter.inc_and_report_progress(0);
-                return;
+                return None;
             }
         };
 
@@ -218,6 +366,7 @@ fn apply_block_from_range(
 
         num_receipt = receipts.len();
         num_tx = chunk.transactions().len();
+        tx_hashes = chunk.transactions().iter().map(|tx| tx.get_hash()).collect();
         if only_contracts {
             let mut has_contracts = false;
             for tx in chunk.transactions() {
@@ -228,13 +377,14 @@ fn apply_block_from_range(
             }
             if !has_contracts {
                 progress_reporter.skipped.fetch_add(1, Ordering::Relaxed);
-                return;
+                return None;
             }
         }
 
+        prev_state_root = replayed_prev_state_root.unwrap_or(*chunk_inner.prev_state_root());
         runtime_adapter
             .apply_chunk(
-                storage.create_runtime_storage(*chunk_inner.prev_state_root()),
+                storage.create_runtime_storage(prev_state_root),
                 ApplyChunkReason::UpdateTrackedShard,
                 ApplyChunkShardContext {
                     shard_id,
@@ -257,10 +407,11 @@ fn apply_block_from_range(
         let chunk_extra =
             chain_store.get_chunk_extra(block.header().prev_hash(), &shard_uid).unwrap();
         prev_chunk_extra = Some(chunk_extra.clone());
+        prev_state_root = replayed_prev_state_root.unwrap_or(*chunk_extra.state_root());
 
         runtime_adapter
             .apply_chunk(
-                storage.create_runtime_storage(*chunk_extra.state_root()),
+                storage.create_runtime_storage(prev_state_root),
                 ApplyChunkReason::UpdateTrackedShard,
                 ApplyChunkShardContext {
                     shard_id,
@@ -299,12 +450,26 @@ fn apply_block_from_range(
     let delayed_indices =
         near_store::get::<DelayedReceiptIndices>(&state_update, &TrieKey::DelayedReceiptIndices);
 
+    let mut divergence = None;
     match existing_chunk_extra {
         Some(existing_chunk_extra) => {
             if verbose_output {
                 println!("block_height: {}, block_hash: {}\nchunk_extra: {:#?}\nexisting_chunk_extra: {:#?}\noutcomes: {:#?}", height, block_hash, chunk_extra, existing_chunk_extra, apply_result.outcomes);
             }
-            if !smart_equals(&existing_chunk_extra, &chunk_extra) {
+            if report.is_some() {
+                let chunk_extra_diff = diff_chunk_extra(&existing_chunk_extra, &chunk_extra);
+                let first_divergent_outcome = find_first_divergent_outcome(
+                    &chain_store,
+                    &block_hash,
+                    shard_id,
+                    &apply_result.outcomes,
+                    &tx_hashes,
+                );
+                if !chunk_extra_diff.is_empty() || first_divergent_outcome.is_some() {
+                    divergence =
+                        Some(Divergence { chunk_extra: chunk_extra_diff, first_divergent_outcome });
+                }
+            } else if !smart_equals(&existing_chunk_extra, &chunk_extra) {
                 panic!("Got a different ChunkExtra:\nblock_height: {}, block_hash: {}\nchunk_extra: {:#?}\nexisting_chunk_extra: {:#?}\nnew outcomes: {:#?}\n\nold outcomes: {:#?}\n", height, block_hash, chunk_extra, existing_chunk_extra, apply_result.outcomes, old_outcomes(store, &apply_result.outcomes));
             }
         }
@@ -333,6 +498,28 @@ fn apply_block_from_range(
             apply_result.trie_changes.state_changes().len(),
         ),
     );
+    if let Some(report) = report {
+        report.add(&BlockReport {
+            height,
+            block_hash,
+            shard_id,
+            chunk_present,
+            prev_state_root,
+            state_root: *chunk_extra.state_root(),
+            outcome_root: *chunk_extra.outcome_root(),
+            gas_burnt: apply_result.total_gas_burnt,
+            balance_burnt: apply_result.total_balance_burnt,
+            outcomes: apply_result
+                .outcomes
+                .iter()
+                .map(|outcome| OutcomeReport {
+                    id: outcome.id,
+                    outcome: outcome.outcome.clone().into(),
+                })
+                .collect(),
+            divergence,
+        });
+    }
     progress_reporter.inc_and_report_progress(apply_result.total_gas_burnt);
 
     if mode == ApplyRangeMode::Benchmarking {
@@ -362,6 +549,14 @@ fn apply_block_from_range(
         apply_result.trie_changes.insertions_into(&mut fake_store_update);
         apply_result.trie_changes.deletions_into(&mut fake_store_update);
     }
+    if mode == ApplyRangeMode::Chained {
+        // The next block is applied on the replayed state, so its trie nodes
+        // have to be in the store.
+        let mut store_update = store.store_update();
+        apply_result.trie_changes.insertions_into(&mut store_update);
+        store_update.commit().unwrap();
+    }
+    Some(apply_result.new_root)
 }
 
 pub fn apply_chain_range(
@@ -375,6 +570,7 @@ pub fn apply_chain_range(
     runtime_adapter: Arc<NightshadeRuntime>,
     verbose_output: bool,
     csv_file: Option<&mut File>,
+    report_file: Option<&mut File>,
     only_contracts: bool,
     storage: StorageSource,
 ) {
@@ -419,10 +615,18 @@ pub fn apply_chain_range(
             // head.
             (flat_head.height + 1, final_head.height)
         }
-        _ => (
-            start_height.unwrap_or_else(|| chain_store.tail().unwrap()),
-            end_height.unwrap_or_else(|| chain_store.head().unwrap().height),
-        ),
+        _ => {
+            if mode == ApplyRangeMode::Chained {
+                // Flat storage holds the stored state, not the replayed one,
+                // and every block has to be applied to keep the state chained.
+                assert!(!matches!(storage, StorageSource::FlatStorage));
+                assert!(!only_contracts);
+            }
+            (
+                start_height.unwrap_or_else(|| chain_store.tail().unwrap()),
+                end_height.unwrap_or_else(|| chain_store.head().unwrap().height),
+            )
+        }
     };
 
     println!(
@@ -434,6 +638,8 @@ pub fn apply_chain_range(
     let csv_file_mutex = Mutex::new(csv_file);
     maybe_add_to_csv(&csv_file_mutex, "Height,Hash,Author,#Tx,#Receipt,Timestamp,GasUsed,ChunkPresent,#ProcessedDelayedReceipts,#DelayedReceipts,#StateChanges");
 
+    let report = report_file.map(ReplayReport::new);
+
     let range = start_height..=end_height;
     let progress_reporter = ProgressReporter {
         cnt: AtomicU64::new(0),
@@ -444,7 +650,7 @@ pub fn apply_chain_range(
         non_empty_blocks: AtomicU64::new(0),
         tgas_burned: AtomicU64::new(0),
     };
-    let process_height = |height| {
+    let process_height = |height, replayed_prev_state_root| {
         apply_block_from_range(
             mode,
             height,
@@ -456,9 +662,11 @@ pub fn apply_chain_range(
             &progress_reporter,
             verbose_output,
             &csv_file_mutex,
+            report.as_ref(),
             only_contracts,
             storage,
-        );
+            replayed_prev_state_root,
+        )
     };
 
     match mode {
@@ -470,9 +678,21 @@ pub fn apply_chain_range(
                     "process_block_in_order",
                     height)
                 .entered();
-                process_height(height)
+                process_height(height, None);
             });
         }
+        ApplyRangeMode::Chained => {
+            let mut state_root = None;
+            for height in range {
+                let _span = tracing::debug_span!(
+                    target: "state_viewer",
+                    parent: &parent_span,
+                    "process_block_in_order",
+                    height)
+                .entered();
+                state_root = process_height(height, state_root).or(state_root);
+            }
+        }
         ApplyRangeMode::Parallel => {
             range.into_par_iter().for_each(|height| {
                 let _span = tracing::debug_span!(
@@ -481,15 +701,29 @@ pub fn apply_chain_range(
                 "process_block_in_parallel",
                 height)
                 .entered();
-                process_height(height)
+                process_height(height, None);
             });
         }
     }
 
-    println!(
-        "No differences found after applying chunks in the range {}..={} for shard_id {}",
-        start_height, end_height, shard_id
-    );
+    let mut divergent_heights =
+        report.map_or_else(Vec::new, |report| report.divergent_heights.into_inner().unwrap());
+    if divergent_heights.is_empty() {
+        println!(
+            "No differences found after applying chunks in the range {}..={} for shard_id {}",
+            start_height, end_height, shard_id
+        );
+    } else {
+        divergent_heights.sort();
+        println!(
+            "Found differences in {} blocks after applying chunks in the range {}..={} for shard_id {}, the first one at height {}",
+            divergent_heights.len(),
+            start_height,
+            end_height,
+            shard_id,
+            divergent_heights[0]
+        );
+    }
 }
 
 /**
@@ -640,9 +874,107 @@ mod test {
             runtime,
             true,
             None,
+            None,
+            false,
+            StorageSource::Trie,
+        );
+    }
+
+    #[test]
+    fn test_apply_chain_range_report() {
+        let epoch_length = 4;
+        let (store, genesis, mut env) = setup(epoch_length);
+        let genesis_hash = *env.clients[0].chain.genesis().hash();
+        let signer =
+            InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1").into();
+        let tx = SignedTransaction::stake(
+            1,
+            "test1".parse().unwrap(),
+            &signer,
+            TESTING_INIT_STAKE,
+            signer.public_key(),
+            genesis_hash,
+        );
+        let tx_hash = tx.get_hash();
+        assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+
+        safe_produce_blocks(&mut env, 1, epoch_length * 2 + 1, None);
+
+        initialize_genesis_state(store.clone(), &genesis, None);
+        let epoch_manager = EpochManager::new_arc_handle(store.clone(), &genesis.config);
+        let runtime = NightshadeRuntime::test(
+            Path::new("."),
+            store.clone(),
+            &genesis.config,
+            epoch_manager.clone(),
+        );
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        apply_chain_range(
+            ApplyRangeMode::Sequential,
+            store.clone(),
+            &genesis,
+            None,
+            None,
+            0,
+            epoch_manager.as_ref(),
+            runtime.clone(),
+            false,
+            None,
+            Some(file.as_file_mut()),
             false,
             StorageSource::Trie,
         );
+        let mut report = String::new();
+        file.as_file_mut().seek(SeekFrom::Start(0)).unwrap();
+        file.as_file_mut().read_to_string(&mut report).unwrap();
+        let blocks = report
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert!(!blocks.is_empty());
+        // Replaying with the same config gives the stored results.
+        assert!(blocks.iter().all(|block| block["divergence"].is_null()), "{:#?}", blocks);
+        // Blocks are applied on the stored state of their previous block,
+        // which is the replayed one as long as nothing diverges.
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[1]["prev_state_root"], pair[0]["state_root"]);
+        }
+        let tx_outcome = blocks
+            .iter()
+            .flat_map(|block| block["outcomes"].as_array().unwrap())
+            .find(|outcome| outcome["id"] == tx_hash.to_string())
+            .unwrap();
+        assert!(tx_outcome["outcome"]["gas_burnt"].as_u64().unwrap() > 0);
+
+        // The chained replay applies every block on the replayed state.
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        apply_chain_range(
+            ApplyRangeMode::Chained,
+            store,
+            &genesis,
+            None,
+            None,
+            0,
+            epoch_manager.as_ref(),
+            runtime,
+            false,
+            None,
+            Some(file.as_file_mut()),
+            false,
+            StorageSource::Trie,
+        );
+        let mut report = String::new();
+        file.as_file_mut().seek(SeekFrom::Start(0)).unwrap();
+        file.as_file_mut().read_to_string(&mut report).unwrap();
+        let chained_blocks = report
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(chained_blocks.len(), blocks.len());
+        assert!(chained_blocks.iter().all(|block| block["divergence"].is_null()));
+        for pair in chained_blocks.windows(2) {
+            assert_eq!(pair[1]["prev_state_root"], pair[0]["state_root"]);
+        }
     }
 
     #[test]
@@ -684,6 +1016,7 @@ mod test {
             runtime,
             true,
             Some(file.as_file_mut()),
+            None,
             false,
             StorageSource::Trie,
         );
@@ -11,7 +11,7 @@ use near_primitives::account::id::AccountId;
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::trie_key::col;
-use near_primitives::types::{BlockHeight, ShardId, StateRoot};
+use near_primitives::types::{BlockHeight, ProtocolVersion, ShardId, StateRoot};
 use near_primitives_core::types::EpochHeight;
 use near_store::{Mode, NodeStorage, Store, Temperature};
 use nearcore::{load_config, NearConfig};
@@ -255,6 +255,13 @@ pub enum ApplyRangeMode {
     /// `MoveFlatHeadCmd` and `MoveFlatHeadMode::Back`.
     /// Useful for benchmarking.
     Benchmarking,
+    /// Sequentially applies chunks, each one on the state replayed for the
+    /// previous one instead of the stored state, so differences are carried
+    /// over to the following blocks.  The trie nodes of the replayed states
+    /// are written to the database, which has to be opened in read-write
+    /// mode, so run it on a copy of the database.  Doesn't support flat
+    /// storage or `--only-contracts`.
+    Chained,
 }
 
 #[derive(clap::Parser)]
@@ -269,6 +276,22 @@ pub struct ApplyRangeCmd {
     verbose_output: bool,
     #[clap(long, value_parser)]
     csv_file: Option<PathBuf>,
+    /// Write the state roots, outcomes and gas profiles resulting from
+    /// applying each block to this file, as JSON lines, along with their
+    /// differences with the stored results.  Blocks with different results
+    /// are recorded instead of aborting the command.  Except in the `chained`
+    /// mode, each block is applied on the stored state of its previous block,
+    /// not on the replayed one, so differences aren't carried over to the
+    /// following blocks.
+    #[clap(long, value_parser)]
+    report_file: Option<PathBuf>,
+    /// Apply all blocks with the runtime config (fees, limits and wasm
+    /// config) of this protocol version instead of the one of the protocol
+    /// version of their epoch.  Only the parameters are replaced: protocol
+    /// features are still enabled according to the protocol version of the
+    /// epoch of each block.
+    #[clap(long)]
+    runtime_config_from_version: Option<ProtocolVersion>,
     #[clap(long)]
     only_contracts: bool,
     #[clap(long, default_value = "trie")]
@@ -286,6 +309,8 @@ impl ApplyRangeCmd {
             self.shard_id,
             self.verbose_output,
             self.csv_file,
+            self.report_file,
+            self.runtime_config_from_version,
             home_dir,
             near_config,
             store,
@@ -301,12 +326,20 @@ pub struct ApplyReceiptCmd {
     hash: String,
     #[clap(long, default_value = "trie")]
     storage: StorageSource,
+    /// Write the gas burnt by the functions of the contracts called while
+    /// applying the chunks of the receipt to this file, as folded stacks for
+    /// flamegraph tools like `inferno-flamegraph`.  Contracts are run with
+    /// a different VM to be profiled, so their gas may differ slightly from
+    /// the one burnt on chain.
+    #[clap(long, value_parser)]
+    function_profile_file: Option<PathBuf>,
 }
 
 impl ApplyReceiptCmd {
     pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
         let hash = CryptoHash::from_str(&self.hash).unwrap();
-        apply_receipt(home_dir, near_config, store, hash, self.storage).unwrap();
+        apply_receipt(home_dir, near_config, store, hash, self.storage, self.function_profile_file)
+            .unwrap();
     }
 }
 
@@ -316,12 +349,20 @@ pub struct ApplyTxCmd {
     hash: String,
     #[clap(long, default_value = "trie")]
     storage: StorageSource,
+    /// Write the gas burnt by the functions of the contracts called while
+    /// applying the chunks of the transaction to this file, as folded stacks for
+    /// flamegraph tools like `inferno-flamegraph`.  Contracts are run with
+    /// a different VM to be profiled, so their gas may differ slightly from
+    /// the one burnt on chain.
+    #[clap(long, value_parser)]
+    function_profile_file: Option<PathBuf>,
 }
 
 impl ApplyTxCmd {
     pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
         let hash = CryptoHash::from_str(&self.hash).unwrap();
-        apply_tx(home_dir, near_config, store, hash, self.storage).unwrap();
+        apply_tx(home_dir, near_config, store, hash, self.storage, self.function_profile_file)
+            .unwrap();
     }
 }
 
@@ -23,6 +23,7 @@ use near_chain_configs::GenesisChangeConfig;
 use near_epoch_manager::types::BlockHeaderInfo;
 use near_epoch_manager::EpochManagerHandle;
 use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_parameters::RuntimeConfigStore;
 use near_primitives::account::id::AccountId;
 use near_primitives::apply::ApplyChunkReason;
 use near_primitives::block::Block;
@@ -232,6 +233,8 @@ pub(crate) fn apply_range(
     shard_id: ShardId,
     verbose_output: bool,
     csv_file: Option<PathBuf>,
+    report_file: Option<PathBuf>,
+    runtime_config_from_version: Option<ProtocolVersion>,
     home_dir: &Path,
     near_config: NearConfig,
     store: Store,
@@ -239,13 +242,23 @@ pub(crate) fn apply_range(
     storage: StorageSource,
 ) {
     let mut csv_file = csv_file.map(|filename| std::fs::File::create(filename).unwrap());
-
+    let mut report_file = report_file.map(|filename| std::fs::File::create(filename).unwrap());
+
+    // Replaying with the runtime config of a single protocol version allows
+    // checking the effect of a config change on past blocks.  The protocol
+    // version of each epoch still decides which protocol features are enabled.
+    let runtime_config_store = runtime_config_from_version.map(|protocol_version| {
+        let config_store = RuntimeConfigStore::for_chain_id(&near_config.genesis.config.chain_id);
+        let runtime_config = config_store.get_config(protocol_version).as_ref().clone();
+        RuntimeConfigStore::with_one_config(runtime_config)
+    });
     let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
-    let runtime = NightshadeRuntime::from_config(
+    let runtime = NightshadeRuntime::from_config_with_runtime_config_store(
         home_dir,
         store.clone(),
         &near_config,
         epoch_manager.clone(),
+        runtime_config_store,
     )
     .expect("could not create the transaction runtime");
     apply_chain_range(
@@ -259,6 +272,7 @@ pub(crate) fn apply_range(
         runtime,
         verbose_output,
         csv_file.as_mut(),
+        report_file.as_mut(),
         only_contracts,
         storage,
     );
@@ -270,6 +284,7 @@ pub(crate) fn apply_receipt(
     store: Store,
     hash: CryptoHash,
     storage: StorageSource,
+    function_profile_file: Option<PathBuf>,
 ) -> anyhow::Result<()> {
     let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
     let runtime = NightshadeRuntime::from_config(
@@ -279,15 +294,17 @@ pub(crate) fn apply_receipt(
         epoch_manager.clone(),
     )
     .context("could not create the transaction runtime")?;
-    apply_chunk::apply_receipt(
-        near_config.genesis.config.genesis_height,
-        epoch_manager.as_ref(),
-        runtime.as_ref(),
-        store,
-        hash,
-        storage,
-    )
-    .map(|_| ())
+    let apply = || {
+        apply_chunk::apply_receipt(
+            near_config.genesis.config.genesis_height,
+            epoch_manager.as_ref(),
+            runtime.as_ref(),
+            store,
+            hash,
+            storage,
+        )
+    };
+    with_function_profile(function_profile_file, apply).map(|_| ())
 }
 
 pub(crate) fn apply_tx(
@@ -296,6 +313,7 @@ pub(crate) fn apply_tx(
     store: Store,
     hash: CryptoHash,
     storage: StorageSource,
+    function_profile_file: Option<PathBuf>,
 ) -> anyhow::Result<()> {
     let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
     let runtime = NightshadeRuntime::from_config(
@@ -305,15 +323,34 @@ pub(crate) fn apply_tx(
         epoch_manager.clone(),
     )
     .context("could not create the transaction runtime")?;
-    apply_chunk::apply_tx(
-        near_config.genesis.config.genesis_height,
-        epoch_manager.as_ref(),
-        runtime.as_ref(),
-        store,
-        hash,
-        storage,
-    )
-    .map(|_| ())
+    let apply = || {
+        apply_chunk::apply_tx(
+            near_config.genesis.config.genesis_height,
+            epoch_manager.as_ref(),
+            runtime.as_ref(),
+            store,
+            hash,
+            storage,
+        )
+    };
+    with_function_profile(function_profile_file, apply).map(|_| ())
+}
+
+/// Runs `f`, writing the profile of the contract calls it made to
+/// `function_profile_file` if set.
+fn with_function_profile<T>(
+    function_profile_file: Option<PathBuf>,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let Some(path) = function_profile_file else {
+        return f();
+    };
+    let (result, profile) = node_runtime::profile_functions(f);
+    let result = result?;
+    fs::write(&path, profile.to_folded_stacks())
+        .with_context(|| format!("could not write the function profile to {}", path.display()))?;
+    println!("Wrote the profile of {} gas to {}", profile.total_gas(), path.display());
+    Ok(result)
 }
 
 pub(crate) fn dump_account_storage(
@@ -214,6 +214,10 @@ fn format_key_and_value<'a>(
                 Box::new(SignedTransaction::try_from_slice(&value).unwrap()),
             )
         }
+        DBCol::TransactionPool => (
+            Box::new(CryptoHash::try_from(key).unwrap()),
+            Box::new(SignedTransaction::try_from_slice(value).unwrap()),
+        ),
         DBCol::TrieChanges => (
             Box::new(get_block_shard_uid_rev(key).unwrap()),
             Box::new(TrieChanges::try_from_slice(value).unwrap()),